@@ -2,22 +2,74 @@
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::io::Read;
+use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
 use cid::Cid;
 use fvm_ipld_blockstore::{Blockstore, Buffered};
-use fvm_ipld_encoding::{CBOR, DAG_CBOR, IPLD_RAW};
+use fvm_ipld_encoding::DAG_CBOR;
 use fvm_shared::commcid::{FIL_COMMITMENT_SEALED, FIL_COMMITMENT_UNSEALED};
+use fvm_shared::IDENTITY_HASH;
+use rayon::prelude::*;
+use rayon::ThreadPool;
+
+use super::compression::Compression;
+use crate::machine::{shared_thread_pool, CodecRegistry, InlineCidLimits};
+
+/// Determines when the blocks written to a [`BufferedBlockstore`] reach the underlying blockstore.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WritePolicy {
+    /// Buffer all blocks in memory until the state is flushed, then write back only the blocks
+    /// reachable from the new root.
+    #[default]
+    WriteBack,
+    /// Write all blocks directly to the underlying blockstore, reachable or not.
+    WriteThrough,
+    /// Like [`WritePolicy::WriteBack`], but once more than `max_bytes` are buffered, the oldest
+    /// buffered blocks (along with any buffered blocks they link to) are written back early. This
+    /// bounds memory usage at the cost of possibly writing unreachable blocks.
+    ///
+    /// Blocks are written back in first-in, first-out order: the block buffered first goes first,
+    /// regardless of how recently it was read or written again.
+    SizeCapped { max_bytes: usize },
+}
+
+/// Statistics about the blocks buffered by a [`BufferedBlockstore`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferStats {
+    /// The number of blocks currently buffered (not yet written to the underlying blockstore).
+    pub dirty_blocks: usize,
+    /// The total size of the blocks currently buffered, in bytes.
+    pub dirty_bytes: usize,
+    /// The number of blocks written back early due to [`WritePolicy::SizeCapped`].
+    pub spilled_blocks: u64,
+    /// The number of blocks written back on flush.
+    pub flushed_blocks: u64,
+}
 
 /// Wrapper around `Blockstore` to limit and have control over when values are written.
 /// This type is not threadsafe and can only be used in synchronous contexts.
 #[derive(Debug)]
 pub struct BufferedBlockstore<BS> {
     base: BS,
+    policy: WritePolicy,
     write: RefCell<HashMap<Cid, Vec<u8>>>,
+    /// The buffered blocks, in the order they were buffered (only tracked for
+    /// [`WritePolicy::SizeCapped`]).
+    order: RefCell<VecDeque<Cid>>,
+    stats: Cell<BufferStats>,
+    /// The pool used to parse blocks for links when writing back (shared with other blockstores),
+    /// if any.
+    flush_pool: Option<Arc<ThreadPool>>,
+    /// The codecs and multihashes blocks may use.
+    codecs: CodecRegistry,
+    /// The limits on inline (identity-hashed) CIDs.
+    inline_cid_limits: InlineCidLimits,
+    /// How blocks written to the underlying blockstore are compressed, if at all.
+    compression: Option<Compression>,
 }
 
 impl<BS> BufferedBlockstore<BS>
@@ -25,15 +77,154 @@ where
     BS: Blockstore,
 {
     pub fn new(base: BS) -> Self {
+        Self::with_policy(base, WritePolicy::default())
+    }
+
+    /// Creates a buffered blockstore with the given [`WritePolicy`].
+    pub fn with_policy(base: BS, policy: WritePolicy) -> Self {
         Self {
             base,
+            policy,
             write: Default::default(),
+            order: Default::default(),
+            stats: Default::default(),
+            flush_pool: None,
+            codecs: CodecRegistry::default(),
+            inline_cid_limits: InlineCidLimits::default(),
+            compression: None,
         }
     }
 
+    /// Accepts blocks using the codecs and multihashes in the given registry when writing back,
+    /// instead of the default ones.
+    pub fn with_codecs(mut self, codecs: CodecRegistry) -> Self {
+        self.codecs = codecs;
+        self
+    }
+
+    /// Accepts inline CIDs within the given limits when writing back, instead of the default ones.
+    pub fn with_inline_cid_limits(mut self, limits: InlineCidLimits) -> Self {
+        self.inline_cid_limits = limits;
+        self
+    }
+
+    /// Parses the blocks being written back for links on a pool of up to `workers` threads.
+    /// Writes to the underlying blockstore are still made from the calling thread, and the set of
+    /// blocks written is the same as with a single worker.
+    pub fn with_flush_workers(mut self, workers: usize) -> Result<Self> {
+        self.flush_pool = if workers > 1 {
+            Some(shared_thread_pool("fvm-flush", workers)?)
+        } else {
+            None
+        };
+        Ok(self)
+    }
+
+    /// Compresses blocks written to the underlying blockstore, and decompresses blocks read from
+    /// it. Blocks keep the CIDs of their uncompressed contents.
+    ///
+    /// Blocks written with compression enabled must be read back with compression enabled and the
+    /// same [`max_size`](Compression::max_size) (or through [`decode_block`](super::decode_block)
+    /// for the default configuration).
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
     pub fn into_inner(self) -> BS {
         self.base
     }
+
+    /// Returns a reference to the underlying blockstore.
+    pub fn inner(&self) -> &BS {
+        &self.base
+    }
+
+    /// Returns statistics about the currently buffered blocks.
+    pub fn stats(&self) -> BufferStats {
+        self.stats.get()
+    }
+
+    /// Buffers the given blocks, applying the write policy.
+    fn buffer<I>(&self, blocks: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (Cid, Vec<u8>)>,
+    {
+        let mut stats = self.stats.get();
+        {
+            let mut write = self.write.borrow_mut();
+            let mut order = self.order.borrow_mut();
+            for (k, v) in blocks {
+                stats.dirty_bytes += v.len();
+                match write.insert(k, v) {
+                    Some(old) => stats.dirty_bytes -= old.len(),
+                    None => {
+                        stats.dirty_blocks += 1;
+                        if let WritePolicy::SizeCapped { .. } = self.policy {
+                            order.push_back(k);
+                        }
+                    }
+                }
+            }
+        }
+        self.stats.set(stats);
+
+        if let WritePolicy::SizeCapped { max_bytes } = self.policy {
+            while self.stats.get().dirty_bytes > max_bytes {
+                let Some(oldest) = self.order.borrow_mut().pop_front() else { break };
+                let spilled = self.write_back(&oldest)?;
+                let mut stats = self.stats.get();
+                stats.spilled_blocks += spilled as u64;
+                self.stats.set(stats);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes blocks to the underlying blockstore, compressing them if enabled.
+    fn put_base<D, I>(&self, blocks: I) -> Result<()>
+    where
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (Cid, D)>,
+    {
+        match &self.compression {
+            Some(compression) => {
+                let blocks = blocks
+                    .into_iter()
+                    .map(|(k, v)| Ok((k, compression.encode(&k, v.as_ref())?.into_owned())))
+                    .collect::<Result<Vec<_>>>()?;
+                self.base.put_many_keyed(blocks)
+            }
+            None => self.base.put_many_keyed(blocks),
+        }
+    }
+
+    /// Writes back the buffered blocks reachable from `root`, returning the number of blocks
+    /// written.
+    fn write_back(&self, root: &Cid) -> Result<usize> {
+        let (codecs, inline) = (&self.codecs, &self.inline_cid_limits);
+        let blocks = match &self.flush_pool {
+            Some(pool) => {
+                take_reachable_parallel(&mut self.write.borrow_mut(), root, codecs, inline, pool)?
+            }
+            None => take_reachable(&mut self.write.borrow_mut(), root, codecs, inline)?,
+        };
+        let count = blocks.len();
+        let bytes: usize = blocks.iter().map(|(_, v)| v.len()).sum();
+        self.put_base(blocks)?;
+
+        // Forget the blocks we just wrote back.
+        if count > 0 && !self.order.borrow().is_empty() {
+            let write = self.write.borrow();
+            self.order.borrow_mut().retain(|k| write.contains_key(k));
+        }
+
+        let mut stats = self.stats.get();
+        stats.dirty_blocks -= count;
+        stats.dirty_bytes -= bytes;
+        self.stats.set(stats);
+        Ok(count)
+    }
 }
 
 impl<BS> Buffered for BufferedBlockstore<BS>
@@ -44,8 +235,11 @@ where
     /// This will recursively traverse the cache and write all data connected by links to this
     /// root Cid, moving the reachable blocks from the write buffer to the backing store.
     fn flush(&self, root: &Cid) -> Result<()> {
-        self.base
-            .put_many_keyed(take_reachable(&mut self.write.borrow_mut(), root)?)
+        let flushed = self.write_back(root)?;
+        let mut stats = self.stats.get();
+        stats.flushed_blocks += flushed as u64;
+        self.stats.set(stats);
+        Ok(())
     }
 }
 
@@ -81,7 +275,7 @@ fn cbor_read_header_buf<B: Read>(br: &mut B) -> anyhow::Result<(u8, u64)> {
 /// Given a CBOR serialized IPLD buffer, read through all of it and return all the Links.
 /// This function is useful because it is quite a bit more fast than doing this recursively on a
 /// deserialized IPLD object.
-fn scan_for_links(mut buf: &[u8], out: &mut Vec<Cid>) -> Result<()> {
+pub(crate) fn scan_for_links(mut buf: &[u8], out: &mut Vec<Cid>) -> Result<()> {
     let mut remaining = 1;
     while remaining > 0 {
         let (maj, extra) = cbor_read_header_buf(&mut buf)?;
@@ -135,12 +329,37 @@ fn scan_for_links(mut buf: &[u8], out: &mut Vec<Cid>) -> Result<()> {
     Ok(())
 }
 
-/// Moves the IPLD DAG under `root` from the cache to the base store.
-fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(Cid, Vec<u8>)>> {
-    const BLAKE2B_256: u64 = 0xb220;
-    const BLAKE2B_LEN: u8 = 32;
-    const IDENTITY: u64 = 0x0;
+/// Checks that we support the codec and hash function of a CID reachable from the state root, and
+/// that inline CIDs are within the limits.
+fn check_cid(k: &Cid, codecs: &CodecRegistry, inline: &InlineCidLimits) -> Result<()> {
+    // Check the codec (piece commitments are skipped by our callers).
+    let codec = k.codec();
+    if !codecs.allows_codec(codec) {
+        return Err(anyhow!("cid {k} has unexpected codec ({codec})"));
+    }
+    // Check the hash construction, allowing identity hashes within the inline CID limits.
+    let (hash, length) = (k.hash().code(), k.hash().size());
+    if hash == IDENTITY_HASH {
+        if !inline.allows(codec, length.into()) {
+            return Err(anyhow!(
+                "cid {k} exceeds the inline cid limits (codec={codec}, len={length})"
+            ));
+        }
+    } else if !codecs.allows_multihash(hash, length.into()) {
+        return Err(anyhow!(
+            "cid {k} has unexpected multihash (code={hash}, len={length})"
+        ));
+    }
+    Ok(())
+}
 
+/// Moves the IPLD DAG under `root` from the cache to the base store.
+fn take_reachable(
+    cache: &mut HashMap<Cid, Vec<u8>>,
+    root: &Cid,
+    codecs: &CodecRegistry,
+    inline: &InlineCidLimits,
+) -> Result<Vec<(Cid, Vec<u8>)>> {
     // Differences from lotus (vm.Copy):
     // 1. We assume that if we don't have a block in our buffer, it must already be in the client
     //    and don't check. This should only happen if the client is missing state.
@@ -151,27 +370,12 @@ fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(
     let mut result = Vec::new();
 
     while let Some(k) = stack.pop() {
-        // Check the codec.
-        match k.codec() {
-            // We ignore piece commitment CIDs.
-            FIL_COMMITMENT_UNSEALED | FIL_COMMITMENT_SEALED => continue,
-            // We allow raw, cbor, and dag cbor.
-            IPLD_RAW | DAG_CBOR | CBOR => (),
-            // Everything else is rejected.
-            codec => return Err(anyhow!("cid {k} has unexpected codec ({codec})")),
+        // We ignore piece commitment CIDs.
+        if matches!(k.codec(), FIL_COMMITMENT_UNSEALED | FIL_COMMITMENT_SEALED) {
+            continue;
         }
-        // Check the hash construction.
-        match (k.hash().code(), k.hash().size()) {
-            // Allow non-truncated blake2b-256 and identity hashes.
-            (BLAKE2B_256, BLAKE2B_LEN) | (IDENTITY, _) => (),
-            // Reject everything else.
-            (hash, length) => {
-                return Err(anyhow!(
-                    "cid {k} has unexpected multihash (code={hash}, len={length})"
-                ))
-            }
-        }
-        if k.hash().code() == IDENTITY {
+        check_cid(&k, codecs, inline)?;
+        if k.hash().code() == IDENTITY_HASH {
             if k.codec() == DAG_CBOR {
                 scan_for_links(k.hash().digest(), &mut stack)?;
             }
@@ -196,6 +400,56 @@ fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(
     Ok(result)
 }
 
+/// Like [`take_reachable`], but walks the DAG breadth-first, parsing each level's blocks for
+/// links on the given thread pool. Blocks are returned in a deterministic (breadth-first) order.
+fn take_reachable_parallel(
+    cache: &mut HashMap<Cid, Vec<u8>>,
+    root: &Cid,
+    codecs: &CodecRegistry,
+    inline: &InlineCidLimits,
+    pool: &ThreadPool,
+) -> Result<Vec<(Cid, Vec<u8>)>> {
+    let mut frontier = vec![*root];
+    let mut result = Vec::new();
+
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        let level_start = result.len();
+        for k in frontier {
+            if matches!(k.codec(), FIL_COMMITMENT_UNSEALED | FIL_COMMITMENT_SEALED) {
+                continue;
+            }
+            check_cid(&k, codecs, inline)?;
+            if k.hash().code() == IDENTITY_HASH {
+                if k.codec() == DAG_CBOR {
+                    scan_for_links(k.hash().digest(), &mut next)?;
+                }
+            } else if let Some(block) = cache.remove(&k) {
+                // Removing the block from the cache also makes sure we visit it only once.
+                result.push((k, block));
+            }
+        }
+
+        // Parse this level's blocks in parallel, preserving their order.
+        let links = pool.install(|| {
+            result[level_start..]
+                .par_iter()
+                .map(|(k, block)| {
+                    let mut links = Vec::new();
+                    if k.codec() == DAG_CBOR {
+                        scan_for_links(block, &mut links)?;
+                    }
+                    Ok(links)
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+        next.extend(links.into_iter().flatten());
+        frontier = next;
+    }
+
+    Ok(result)
+}
+
 impl<BS> Blockstore for BufferedBlockstore<BS>
 where
     BS: Blockstore,
@@ -204,13 +458,21 @@ where
         Ok(if let Some(data) = self.write.borrow().get(cid) {
             Some(data.clone())
         } else {
-            self.base.get(cid)?
+            match self.base.get(cid)? {
+                Some(data) => Some(match &self.compression {
+                    Some(compression) => compression.decode(cid, data),
+                    None => data,
+                }),
+                None => None,
+            }
         })
     }
 
     fn put_keyed(&self, cid: &Cid, buf: &[u8]) -> Result<()> {
-        self.write.borrow_mut().insert(*cid, Vec::from(buf));
-        Ok(())
+        if self.policy == WritePolicy::WriteThrough {
+            return self.put_base([(*cid, buf)]);
+        }
+        self.buffer([(*cid, Vec::from(buf))])
     }
 
     fn has(&self, k: &Cid) -> Result<bool> {
@@ -227,10 +489,10 @@ where
         D: AsRef<[u8]>,
         I: IntoIterator<Item = (Cid, D)>,
     {
-        self.write
-            .borrow_mut()
-            .extend(blocks.into_iter().map(|(k, v)| (k, v.as_ref().into())));
-        Ok(())
+        if self.policy == WritePolicy::WriteThrough {
+            return self.put_base(blocks);
+        }
+        self.buffer(blocks.into_iter().map(|(k, v)| (k, v.as_ref().into())))
     }
 }
 
@@ -238,7 +500,7 @@ where
 mod tests {
     use cid::multihash::{Code, Multihash};
     use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
-    use fvm_ipld_encoding::CborStore;
+    use fvm_ipld_encoding::{CborStore, CBOR, IPLD_RAW};
     use fvm_shared::{commcid, IDENTITY_HASH};
     use serde::{Deserialize, Serialize};
 
@@ -327,4 +589,144 @@ mod tests {
         assert_eq!(buf_store.get(&sealed_comm_cid).unwrap(), None);
         assert_eq!(mem.get_cbor::<u8>(&unconnected).unwrap(), None);
     }
+
+    #[test]
+    fn write_through() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::with_policy(&mem, WritePolicy::WriteThrough);
+
+        let cid = buf_store.put_cbor(&8u8, Code::Blake2b256).unwrap();
+        assert_eq!(mem.get_cbor::<u8>(&cid).unwrap(), Some(8));
+        assert_eq!(buf_store.stats(), BufferStats::default());
+    }
+
+    #[test]
+    fn size_capped_spill() {
+        let mem = MemoryBlockstore::default();
+        let buf_store =
+            BufferedBlockstore::with_policy(&mem, WritePolicy::SizeCapped { max_bytes: 64 });
+
+        let leaf = buf_store.put_cbor(&[1u8; 16], Code::Blake2b256).unwrap();
+        let mid = buf_store.put_cbor(&(leaf, 2u8), Code::Blake2b256).unwrap();
+        assert_eq!(mem.get(&leaf).unwrap(), None);
+        assert_eq!(buf_store.stats().dirty_blocks, 2);
+
+        // Pushes us over the cap, spilling the oldest block.
+        let big = buf_store.put_cbor(&[3u8; 40], Code::Blake2b256).unwrap();
+        let stats = buf_store.stats();
+        assert!(stats.dirty_bytes <= 64);
+        assert!(stats.spilled_blocks >= 1);
+        assert!(mem.has(&leaf).unwrap());
+
+        // A spilled parent must bring its buffered children along.
+        let root = buf_store.put_cbor(&(mid, big), Code::Blake2b256).unwrap();
+        buf_store.flush(&root).unwrap();
+        for c in [leaf, mid, big, root] {
+            assert!(mem.has(&c).unwrap());
+        }
+        assert_eq!(buf_store.stats().dirty_blocks, 0);
+        assert_eq!(buf_store.stats().dirty_bytes, 0);
+    }
+
+    #[test]
+    fn size_capped_order() {
+        let mem = MemoryBlockstore::default();
+        let buf_store =
+            BufferedBlockstore::with_policy(&mem, WritePolicy::SizeCapped { max_bytes: 1 << 20 });
+
+        // Writing the same block again doesn't queue it again.
+        let leaf = buf_store.put_cbor(&1u8, Code::Blake2b256).unwrap();
+        for _ in 0..10 {
+            buf_store.put_cbor(&1u8, Code::Blake2b256).unwrap();
+        }
+        let other = buf_store.put_cbor(&2u8, Code::Blake2b256).unwrap();
+        assert_eq!(buf_store.order.borrow().len(), 2);
+
+        // Blocks written back on flush are forgotten.
+        buf_store.flush(&leaf).unwrap();
+        assert_eq!(*buf_store.order.borrow(), [other]);
+        buf_store.flush(&other).unwrap();
+        assert!(buf_store.order.borrow().is_empty());
+    }
+
+    #[test]
+    fn parallel_flush() {
+        let serial = MemoryBlockstore::default();
+        let parallel = MemoryBlockstore::default();
+        let serial_store = BufferedBlockstore::new(&serial);
+        let parallel_store = BufferedBlockstore::new(&parallel)
+            .with_flush_workers(4)
+            .unwrap();
+
+        let mut roots = Vec::new();
+        for store in [&serial_store, &parallel_store] {
+            let leaves: Vec<Cid> = (0u8..16)
+                .map(|i| store.put_cbor(&i, Code::Blake2b256).unwrap())
+                .collect();
+            let mid = store.put_cbor(&leaves[..8], Code::Blake2b256).unwrap();
+            // Leaves are shared between levels.
+            let root = store
+                .put_cbor(&(mid, &leaves[4..]), Code::Blake2b256)
+                .unwrap();
+            store.put_cbor(&"unreachable", Code::Blake2b256).unwrap();
+            store.flush(&root).unwrap();
+            roots.push(root);
+        }
+
+        assert_eq!(roots[0], roots[1]);
+        assert_eq!(serial_store.stats(), parallel_store.stats());
+        assert_eq!(parallel_store.stats().flushed_blocks, 18);
+        assert_eq!(parallel_store.stats().dirty_blocks, 1);
+    }
+
+    #[test]
+    fn compressed_write_back() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::new(&mem).with_compression(Compression::default());
+
+        let value = vec![5u8; 1024];
+        let leaf = buf_store.put_cbor(&value, Code::Blake2b256).unwrap();
+        let root = buf_store.put_cbor(&(leaf, 1u8), Code::Blake2b256).unwrap();
+        buf_store.flush(&root).unwrap();
+
+        // The leaf is stored compressed under its original CID, and read back transparently.
+        let stored = mem.get(&leaf).unwrap().unwrap();
+        assert!(stored.len() < value.len());
+        assert_eq!(buf_store.get_cbor::<Vec<u8>>(&leaf).unwrap(), Some(value));
+        // Small blocks are stored as-is.
+        assert_eq!(mem.get_cbor::<(Cid, u8)>(&root).unwrap(), Some((leaf, 1)));
+    }
+
+    #[test]
+    fn inline_cid_limits() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::new(&mem).with_inline_cid_limits(InlineCidLimits {
+            max_payload: 8,
+            codecs: vec![IPLD_RAW],
+            linkable: false,
+        });
+        let inline = |codec, len| {
+            Cid::new_v1(
+                codec,
+                Multihash::wrap(IDENTITY_HASH, &vec![1u8; len]).unwrap(),
+            )
+        };
+
+        let root = buf_store
+            .put_cbor(&inline(IPLD_RAW, 8), Code::Blake2b256)
+            .unwrap();
+        buf_store.flush(&root).unwrap();
+
+        // Too large.
+        let root = buf_store
+            .put_cbor(&inline(IPLD_RAW, 9), Code::Blake2b256)
+            .unwrap();
+        buf_store.flush(&root).unwrap_err();
+
+        // Disallowed codec.
+        let root = buf_store
+            .put_cbor(&inline(CBOR, 1), Code::Blake2b256)
+            .unwrap();
+        buf_store.flush(&root).unwrap_err();
+    }
 }
@@ -0,0 +1,174 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::cell::{Cell, RefCell};
+use std::num::NonZeroUsize;
+
+use anyhow::Result;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use lru::LruCache;
+
+/// Hit/miss counters of a [`CachingBlockstore`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of reads served from the cache.
+    pub hits: u64,
+    /// The number of reads that went to the underlying blockstore.
+    pub misses: u64,
+    /// The number of blocks evicted to make room for others.
+    pub evictions: u64,
+}
+
+/// Wrapper around a (typically slow, "cold") `Blockstore` keeping the most recently used blocks in
+/// memory. Writes go through to the underlying blockstore.
+///
+/// To monitor the cache of a machine's blockstore, wrap the blockstore before constructing the
+/// machine and query the stats with `machine.blockstore().inner().stats()`.
+///
+/// This type is not threadsafe and can only be used in synchronous contexts.
+#[derive(Debug)]
+pub struct CachingBlockstore<BS> {
+    base: BS,
+    cache: RefCell<LruCache<Cid, Vec<u8>>>,
+    stats: Cell<CacheStats>,
+}
+
+impl<BS> CachingBlockstore<BS>
+where
+    BS: Blockstore,
+{
+    /// Creates a cache holding up to `capacity` blocks.
+    pub fn new(base: BS, capacity: NonZeroUsize) -> Self {
+        Self {
+            base,
+            cache: RefCell::new(LruCache::new(capacity)),
+            stats: Default::default(),
+        }
+    }
+
+    pub fn into_inner(self) -> BS {
+        self.base
+    }
+
+    /// Returns the cache's hit/miss counters.
+    pub fn stats(&self) -> CacheStats {
+        self.stats.get()
+    }
+
+    /// Resets the cache's hit/miss counters.
+    pub fn reset_stats(&self) {
+        self.stats.take();
+    }
+
+    /// Drops the given block from the cache (e.g., because it was removed from the underlying
+    /// blockstore by some other means).
+    pub fn invalidate(&self, k: &Cid) {
+        self.cache.borrow_mut().pop(k);
+    }
+
+    /// Drops all blocks from the cache.
+    pub fn clear(&self) {
+        self.cache.borrow_mut().clear();
+    }
+
+    fn insert(&self, k: Cid, block: Vec<u8>) {
+        if let Some((evicted, _)) = self.cache.borrow_mut().push(k, block) {
+            if evicted != k {
+                self.update_stats(|s| s.evictions += 1);
+            }
+        }
+    }
+
+    fn update_stats(&self, f: impl FnOnce(&mut CacheStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
+    }
+}
+
+impl<BS> Blockstore for CachingBlockstore<BS>
+where
+    BS: Blockstore,
+{
+    fn get(&self, k: &Cid) -> Result<Option<Vec<u8>>> {
+        if let Some(block) = self.cache.borrow_mut().get(k) {
+            self.update_stats(|s| s.hits += 1);
+            return Ok(Some(block.clone()));
+        }
+        self.update_stats(|s| s.misses += 1);
+        let block = self.base.get(k)?;
+        if let Some(block) = &block {
+            self.insert(*k, block.clone());
+        }
+        Ok(block)
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> Result<()> {
+        self.base.put_keyed(k, block)?;
+        self.insert(*k, block.to_vec());
+        Ok(())
+    }
+
+    fn has(&self, k: &Cid) -> Result<bool> {
+        if self.cache.borrow().contains(k) {
+            return Ok(true);
+        }
+        self.base.has(k)
+    }
+
+    fn put_many_keyed<D, I>(&self, blocks: I) -> Result<()>
+    where
+        Self: Sized,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (Cid, D)>,
+    {
+        let blocks: Vec<_> = blocks.into_iter().collect();
+        self.base
+            .put_many_keyed(blocks.iter().map(|(k, v)| (*k, v.as_ref())))?;
+        for (k, v) in blocks {
+            self.insert(k, v.as_ref().to_vec());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cid::multihash::Code;
+    use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+    use fvm_ipld_encoding::CborStore;
+
+    use super::*;
+
+    #[test]
+    fn hits_and_evictions() {
+        let mem = MemoryBlockstore::default();
+        let a = mem.put_cbor(&"a", Code::Blake2b256).unwrap();
+        let b = mem.put_cbor(&"b", Code::Blake2b256).unwrap();
+        let cache = CachingBlockstore::new(&mem, NonZeroUsize::new(1).unwrap());
+
+        assert_eq!(cache.get_cbor::<String>(&a).unwrap().unwrap(), "a");
+        assert_eq!(cache.get_cbor::<String>(&a).unwrap().unwrap(), "a");
+        assert_eq!(cache.get_cbor::<String>(&b).unwrap().unwrap(), "b");
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                evictions: 1
+            }
+        );
+
+        cache.invalidate(&b);
+        cache.get(&b).unwrap();
+        assert_eq!(cache.stats().misses, 3);
+
+        // Writes go through.
+        let c = cache.put_cbor(&"c", Code::Blake2b256).unwrap();
+        assert!(mem.has(&c).unwrap());
+        cache.reset_stats();
+        cache.get(&c).unwrap();
+        assert_eq!(cache.stats().hits, 1);
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::borrow::Cow;
+use std::io::Read;
+
+use anyhow::Result;
+use cid::Cid;
+use multihash::MultihashDigest;
+
+use crate::kernel::SupportedHashes;
+
+/// Prefixes zstd-compressed blocks. `0xff` can't start a CBOR item, so DAG-CBOR blocks never
+/// collide with this prefix.
+const COMPRESSED_PREFIX: &[u8] = b"\xffFVMz";
+
+/// Prefixes uncompressed blocks that would otherwise be mistaken for framed blocks.
+const ESCAPED_PREFIX: &[u8] = b"\xffFVMr";
+
+/// Configures the compression of blocks written to the underlying blockstore by a
+/// [`BufferedBlockstore`](super::BufferedBlockstore).
+///
+/// Compressed blocks are stored with a self-describing frame, under the CID of the uncompressed
+/// block, so compression never affects CIDs (or state roots). Blocks are transparently
+/// decompressed when read back through a buffered blockstore with compression enabled, or with
+/// [`decode_block`].
+///
+/// The underlying blockstore may also hold blocks this store never framed (e.g., raw blocks
+/// written by the node, or blocks written before compression was enabled), and those may start
+/// with a frame prefix by chance. A framed block is therefore only decoded if the decoded data
+/// matches the block's CID, and returned as-is otherwise. Blocks whose CIDs use a hash function
+/// the FVM doesn't support are never framed, as they can't be checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    /// The zstd compression level.
+    pub level: i32,
+    /// Blocks smaller than this are stored uncompressed.
+    pub min_size: usize,
+    /// Blocks are stored uncompressed unless compressing them saves at least this many bytes.
+    pub min_savings: usize,
+    /// Blocks larger than this are stored uncompressed, and compressed blocks are never
+    /// decompressed beyond this size.
+    pub max_size: usize,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            level: zstd::DEFAULT_COMPRESSION_LEVEL,
+            min_size: 256,
+            min_savings: 32,
+            max_size: 1 << 20,
+        }
+    }
+}
+
+impl Compression {
+    /// Frames a block for storage, compressing it if that's worthwhile.
+    pub(super) fn encode<'a>(&self, cid: &Cid, block: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        if SupportedHashes::try_from(cid.hash().code()).is_err() {
+            return Ok(Cow::Borrowed(block));
+        }
+        if block.len() >= self.min_size && block.len() <= self.max_size {
+            let compressed = zstd::bulk::compress(block, self.level)?;
+            if compressed.len() + COMPRESSED_PREFIX.len() + self.min_savings <= block.len() {
+                let mut framed = Vec::with_capacity(COMPRESSED_PREFIX.len() + compressed.len());
+                framed.extend_from_slice(COMPRESSED_PREFIX);
+                framed.extend_from_slice(&compressed);
+                return Ok(Cow::Owned(framed));
+            }
+        }
+        if is_framed(block) {
+            let mut escaped = Vec::with_capacity(ESCAPED_PREFIX.len() + block.len());
+            escaped.extend_from_slice(ESCAPED_PREFIX);
+            escaped.extend_from_slice(block);
+            return Ok(Cow::Owned(escaped));
+        }
+        Ok(Cow::Borrowed(block))
+    }
+
+    /// Returns the original contents of a block stored with this compression configuration.
+    pub(super) fn decode(&self, cid: &Cid, data: Vec<u8>) -> Vec<u8> {
+        let decoded = if let Some(compressed) = data.strip_prefix(COMPRESSED_PREFIX) {
+            decompress(compressed, self.max_size)
+        } else if let Some(escaped) = data.strip_prefix(ESCAPED_PREFIX) {
+            Some(escaped.to_vec())
+        } else {
+            None
+        };
+        match decoded {
+            Some(decoded) if matches_cid(cid, &decoded) => decoded,
+            _ => data,
+        }
+    }
+}
+
+fn is_framed(data: &[u8]) -> bool {
+    data.starts_with(COMPRESSED_PREFIX) || data.starts_with(ESCAPED_PREFIX)
+}
+
+/// Decompresses a zstd frame, failing if it's invalid or decompresses to more than `max_size`
+/// bytes.
+fn decompress(compressed: &[u8], max_size: usize) -> Option<Vec<u8>> {
+    let decoder = zstd::stream::Decoder::new(compressed).ok()?;
+    let mut out = Vec::new();
+    decoder
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut out)
+        .ok()?;
+    (out.len() <= max_size).then_some(out)
+}
+
+/// Returns true if `data` hashes to the (possibly truncated) digest of `cid`.
+fn matches_cid(cid: &Cid, data: &[u8]) -> bool {
+    let Ok(code) = SupportedHashes::try_from(cid.hash().code()) else {
+        return false;
+    };
+    let hash = code.digest(data);
+    let expected = cid.hash().digest();
+    hash.digest().get(..expected.len()) == Some(expected)
+}
+
+/// Returns the original contents of a block stored by a buffered blockstore with the default
+/// compression configuration. Blocks that weren't framed by a buffered blockstore (e.g., blocks
+/// written without compression) are returned as-is.
+pub fn decode_block(cid: &Cid, data: Vec<u8>) -> Vec<u8> {
+    Compression::default().decode(cid, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use cid::Cid;
+    use fvm_ipld_encoding::IPLD_RAW;
+    use multihash::{Code, MultihashDigest};
+
+    use super::{decode_block, Compression, COMPRESSED_PREFIX, ESCAPED_PREFIX};
+
+    fn cid_of(data: &[u8]) -> Cid {
+        Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(data))
+    }
+
+    #[test]
+    fn framing() {
+        let c = Compression::default();
+
+        let small = b"small".to_vec();
+        assert_eq!(&*c.encode(&cid_of(&small), &small).unwrap(), &small[..]);
+
+        let large = vec![7u8; 4096];
+        let cid = cid_of(&large);
+        let encoded = c.encode(&cid, &large).unwrap();
+        assert!(encoded.starts_with(COMPRESSED_PREFIX));
+        assert!(encoded.len() < large.len());
+        assert_eq!(decode_block(&cid, encoded.into_owned()), large);
+
+        // Incompressible blocks that look framed are escaped.
+        let tricky = [COMPRESSED_PREFIX, b"data"].concat();
+        let cid = cid_of(&tricky);
+        let encoded = c.encode(&cid, &tricky).unwrap();
+        assert!(encoded.starts_with(ESCAPED_PREFIX));
+        assert_eq!(decode_block(&cid, encoded.into_owned()), tricky);
+    }
+
+    #[test]
+    fn foreign_blocks() {
+        let c = Compression::default();
+
+        // Raw blocks written by someone else are returned as-is, even if they look framed.
+        let compressed = c.encode(&cid_of(&[7u8; 4096]), &[7u8; 4096]).unwrap();
+        let escaped = [ESCAPED_PREFIX, ESCAPED_PREFIX, b"data"].concat();
+        let garbage = [COMPRESSED_PREFIX, b"not zstd"].concat();
+        for raw in [compressed.into_owned(), escaped, garbage] {
+            assert_eq!(decode_block(&cid_of(&raw), raw.clone()), raw);
+        }
+
+        // Blocks with hashes we can't check are never framed.
+        let large = vec![7u8; 4096];
+        let cid = Cid::new_v1(IPLD_RAW, Code::Sha3_256.digest(&large));
+        assert_eq!(&*c.encode(&cid, &large).unwrap(), &large[..]);
+    }
+
+    #[test]
+    fn decompression_is_bounded() {
+        let small = Compression {
+            max_size: 1024,
+            ..Compression::default()
+        };
+
+        // Blocks above the limit are stored uncompressed.
+        let large = vec![7u8; 4096];
+        let cid = cid_of(&large);
+        assert_eq!(&*small.encode(&cid, &large).unwrap(), &large[..]);
+
+        // And never decompressed beyond it.
+        let encoded = Compression::default().encode(&cid, &large).unwrap();
+        assert_eq!(small.decode(&cid, encoded.to_vec()), encoded.to_vec());
+    }
+}
@@ -1,9 +1,14 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-//! Private blockstores for use in the FVM.
+//! Blockstores used by the FVM, and blockstore wrappers for embedders.
 
 mod buffered;
+mod caching;
+mod compression;
 mod discard;
 
-pub use buffered::BufferedBlockstore;
+pub(crate) use buffered::scan_for_links;
+pub use buffered::{BufferStats, BufferedBlockstore, WritePolicy};
+pub use caching::{CacheStats, CachingBlockstore};
+pub use compression::{decode_block, Compression};
 pub(crate) use discard::DiscardBlockstore;
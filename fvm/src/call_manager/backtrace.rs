@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::fmt::Display;
 
+use cid::Cid;
 use fvm_shared::address::Address;
 use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::ActorID;
@@ -78,6 +79,11 @@ impl Backtrace {
 pub struct Frame {
     /// The actor that exited with this code.
     pub source: ActorID,
+    /// The code CID of the actor.
+    pub code_cid: Cid,
+    /// The human-readable name of the actor's code, if the machine's
+    /// [`ActorNameResolver`](crate::machine::ActorNameResolver) knows it.
+    pub actor_name: Option<String>,
     /// The entrypoint that was invoked.
     pub entrypoint: Entrypoint,
     /// The exit code.
@@ -88,13 +94,14 @@ pub struct Frame {
 
 impl Display for Frame {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Address::new_id(self.source))?;
+        if let Some(name) = &self.actor_name {
+            write!(f, " [{}]", name)?;
+        }
         write!(
             f,
-            "{} (method {}) -- {} ({})",
-            Address::new_id(self.source),
-            self.entrypoint,
-            &self.message,
-            self.code,
+            " (method {}) -- {} ({})",
+            self.entrypoint, &self.message, self.code,
         )
     }
 }
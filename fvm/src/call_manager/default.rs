@@ -1,6 +1,7 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::rc::Rc;
+use std::time::Instant;
 
 use anyhow::{anyhow, Context};
 use cid::Cid;
@@ -11,16 +12,15 @@ use fvm_shared::address::{Address, Payload};
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::event::StampedEvent;
-use fvm_shared::sys::BlockId;
+use fvm_shared::sys::{BlockId, ReentrancyPolicy};
 use fvm_shared::{ActorID, METHOD_SEND};
 use num_traits::Zero;
 
 use super::state_access_tracker::{ActorAccessState, StateAccessTracker};
-use super::{Backtrace, CallManager, Entrypoint, InvocationResult, NO_DATA_BLOCK_ID};
+use super::{Backtrace, CallManager, Entrypoint, InvocationResult, SystemEvent, NO_DATA_BLOCK_ID};
 use crate::blockstore::DiscardBlockstore;
 use crate::call_manager::backtrace::Frame;
-use crate::call_manager::FinishRet;
-use crate::eam_actor::EAM_ACTOR_ID;
+use crate::call_manager::{DebugOutput, FinishRet};
 use crate::engine::Engine;
 use crate::gas::{Gas, GasTracker};
 use crate::kernel::{
@@ -34,6 +34,14 @@ use crate::syscalls::{charge_for_exec, update_gas_available};
 use crate::trace::{ExecutionEvent, ExecutionTrace};
 use crate::{syscall_error, system_actor};
 
+/// The minimum amount of host stack that must remain before entering a new call frame. This must
+/// cover the Wasm stack of the callee (bounded by wasmtime's `max_wasm_stack`, 4MiB) plus the host
+/// frames between two nested sends (kernel, syscall bindings, and wasmtime trampolines).
+const STACK_RED_ZONE: usize = 6 << 20;
+
+/// The size of each additional host stack segment allocated when a call chain runs out of stack.
+const STACK_SEGMENT_SIZE: usize = 32 << 20;
+
 /// The default [`CallManager`] implementation.
 #[repr(transparent)]
 pub struct DefaultCallManager<M: Machine>(Option<Box<InnerDefaultCallManager<M>>>);
@@ -75,8 +83,14 @@ pub struct InnerDefaultCallManager<M: Machine> {
     limits: M::Limiter,
     /// Accumulator for events emitted in this call stack.
     events: EventsAccumulator,
+    /// The logs and artifacts emitted by actors in this call stack.
+    debug_output: Vec<DebugOutput>,
     /// The actor call stack (ActorID and entrypoint name tuple).
     actor_call_stack: Vec<(ActorID, &'static str)>,
+    /// The reentrancy policy of each frame on the actor call stack.
+    reentrancy_policies: Vec<ReentrancyPolicy>,
+    /// When to interrupt actor code, if the network config sets an execution timeout.
+    execution_deadline: Option<Instant>,
 }
 
 #[doc(hidden)]
@@ -113,8 +127,17 @@ where
         gas_premium: TokenAmount,
     ) -> Self {
         let limits = machine.new_limiter();
+        let execution_deadline = machine
+            .context()
+            .execution_timeout
+            .map(|timeout| Instant::now() + timeout);
         let gas_tracker =
             GasTracker::new(Gas::new(gas_limit), Gas::zero(), machine.context().tracing);
+        let gas_tracker = if machine.context().syscall_census {
+            gas_tracker.with_syscall_census()
+        } else {
+            gas_tracker
+        };
 
         let state_access_tracker =
             StateAccessTracker::new(&machine.context().price_list.preloaded_actors);
@@ -160,8 +183,11 @@ where
             invocation_count: 0,
             limits,
             events: Default::default(),
+            debug_output: Vec::new(),
             state_access_tracker,
             actor_call_stack: vec![],
+            reentrancy_policies: vec![],
+            execution_deadline,
         })))
     }
 
@@ -267,10 +293,12 @@ where
             gas_tracker,
             mut exec_trace,
             events,
+            debug_output,
             ..
         } = *self.0.take().expect("call manager is poisoned");
 
         let gas_used = gas_tracker.gas_used().round_up();
+        let syscall_counts = gas_tracker.take_syscall_counts();
 
         // Finalize any trace events, if we're tracing.
         if machine.context().tracing {
@@ -293,6 +321,8 @@ where
                 exec_trace,
                 events,
                 events_root,
+                syscall_counts,
+                debug_output,
             }),
             machine,
         )
@@ -334,6 +364,16 @@ where
         &self.actor_call_stack
     }
 
+    fn set_reentrancy_policy(&mut self, policy: ReentrancyPolicy) -> Result<()> {
+        let current = self
+            .reentrancy_policies
+            .last_mut()
+            .context("no actor is executing")
+            .or_fatal()?;
+        *current = policy;
+        Ok(())
+    }
+
     fn next_actor_address(&self) -> Address {
         // Base the next address on the address specified as the message origin. This lets us use,
         // e.g., an f2 address even if we can't look it up anywhere.
@@ -405,6 +445,10 @@ where
         };
         self.set_actor(actor_id, actor)?;
         self.num_actors_created += 1;
+        self.emit_system_event(SystemEvent::ActorCreated {
+            actor: actor_id,
+            code: code_id,
+        });
         Ok(())
     }
 
@@ -412,11 +456,29 @@ where
         self.events.append_event(evt)
     }
 
+    fn emit_system_event(&mut self, evt: SystemEvent) {
+        if self.context().system_events {
+            self.events.append_system_event(evt.into())
+        }
+    }
+
+    fn append_debug_output(&mut self, output: DebugOutput) {
+        self.debug_output.push(output)
+    }
+
+    fn events_payload(&self) -> usize {
+        self.events.payload
+    }
+
     // Helper for creating actors. This really doesn't belong on this trait.
     fn invocation_count(&self) -> u64 {
         self.invocation_count
     }
 
+    fn call_depth(&self) -> u32 {
+        self.call_stack_depth
+    }
+
     /// Resolve an address and charge for it.
     fn resolve_address(&self, address: &Address) -> Result<Option<ActorID>> {
         if let Ok(id) = address.id() {
@@ -426,6 +488,13 @@ where
             self.gas_tracker
                 .apply_charge(self.price_list().on_resolve_address())?;
         }
+        // Only the managers of f4 namespaces assign f4 addresses, so addresses in namespaces
+        // without a manager can't resolve.
+        if let Payload::Delegated(da) = address.payload() {
+            if self.machine.address_manager(da.namespace()).is_none() {
+                return Ok(None);
+            }
+        }
         let id = self.state_tree().lookup_id(address)?;
         if id.is_some() {
             self.state_access_tracker.record_lookup_address(address);
@@ -471,6 +540,7 @@ where
         }
         self.state_tree_mut().delete_actor(id);
         self.state_access_tracker.record_actor_update(id);
+        self.emit_system_event(SystemEvent::ActorDeleted { actor: id });
         Ok(())
     }
 
@@ -538,7 +608,12 @@ where
 
         // Now we actually set the actor state, charging for reads/writes as necessary and recording
         // the fact that the actor has been updated.
+        let code = act.code;
         self.set_actor(addr_id, act)?;
+        self.emit_system_event(SystemEvent::ActorCreated {
+            actor: addr_id,
+            code,
+        });
         Ok(addr_id)
     }
 
@@ -622,7 +697,9 @@ where
                 }
                 // Validate that there's an actor at the target ID (we don't care what is there,
                 // just that something is there).
-                Payload::Delegated(da) if da.namespace() == EAM_ACTOR_ID => {
+                Payload::Delegated(da)
+                    if self.machine.address_manager(da.namespace()).is_some() =>
+                {
                     if read_only {
                         return Err(syscall_error!(ReadOnly; "cannot auto-create account {to} in read-only calls").into());
                     }
@@ -635,10 +712,43 @@ where
             },
         };
 
+        // Refuse to re-enter actors that have forbidden it. Plain sends don't run any code, so
+        // they're always allowed.
+        if !entrypoint.invokes(METHOD_SEND)
+            && self
+                .actor_call_stack
+                .iter()
+                .zip(&self.reentrancy_policies)
+                .any(|(&(id, _), &policy)| id == to && policy == ReentrancyPolicy::Forbid)
+        {
+            return Err(syscall_error!(Forbidden; "actor {} may not be re-entered", to).into());
+        }
+
+        #[cfg(feature = "tracing")]
+        let (span, gas_before) = (
+            tracing::debug_span!(
+                "call",
+                from,
+                to,
+                method = entrypoint.method_num(),
+                gas_used = tracing::field::Empty,
+            )
+            .entered(),
+            self.gas_tracker.gas_used(),
+        );
+
         self.actor_call_stack.push((to, entrypoint.func_name()));
+        self.reentrancy_policies.push(ReentrancyPolicy::Allow);
         let res = self.call_actor_resolved::<K>(from, to, entrypoint, params, value, read_only);
+        self.reentrancy_policies.pop();
         self.actor_call_stack.pop();
 
+        #[cfg(feature = "tracing")]
+        span.record(
+            "gas_used",
+            (self.gas_tracker.gas_used() - gas_before).round_up(),
+        );
+
         res
     }
 
@@ -715,6 +825,7 @@ where
         log::trace!("calling {} -> {}::{}", from, to, entrypoint);
         self.map_mut(|cm| {
             let engine = cm.engine.clone(); // reference the RC.
+            let execution_deadline = cm.execution_deadline;
 
             // Make the kernel.
             let kernel = K::new(
@@ -729,6 +840,9 @@ where
 
             // Make a store.
             let mut store = engine.new_store(kernel);
+            if let Some(deadline) = execution_deadline {
+                engine.set_execution_deadline(&mut store, deadline);
+            }
 
             // From this point on, there are no more syscall errors, only aborts.
             let result: std::result::Result<BlockId, Abort> = (|| {
@@ -852,8 +966,14 @@ where
                             cm.backtrace.begin(err);
                         }
 
+                        let actor_name = cm
+                            .machine
+                            .actor_name_resolver()
+                            .and_then(|r| r.actor_name(&state.code));
                         cm.backtrace.push_frame(Frame {
                             source: to,
+                            code_cid: state.code,
+                            actor_name,
                             entrypoint,
                             message,
                             code,
@@ -914,6 +1034,11 @@ where
 
     /// Check that we're not violating the call stack depth, then envelope a call
     /// with an increase/decrease of the depth to make sure none of them are missed.
+    ///
+    /// Nested sends recurse on the host stack. To make sure a chain of calls up to the maximum call
+    /// depth can't overflow the host stack (regardless of the stack size of the executing thread),
+    /// we switch to a freshly allocated stack segment whenever the remaining stack drops below
+    /// [`STACK_RED_ZONE`].
     fn with_stack_frame<F, V>(&mut self, f: F) -> Result<V>
     where
         F: FnOnce(&mut Self) -> Result<V>,
@@ -927,12 +1052,9 @@ where
         }
 
         self.call_stack_depth += 1;
-        let res =
-        <<<DefaultCallManager<M> as CallManager>::Machine as Machine>::Limiter>::with_stack_frame(
-            self,
-            |s| s.limiter_mut(),
-            f,
-        );
+        let res = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+            M::Limiter::with_stack_frame(self, |s| s.limiter_mut(), f)
+        });
         self.call_stack_depth -= 1;
         res
     }
@@ -945,7 +1067,10 @@ where
 /// normally).
 pub struct EventsAccumulator {
     events: Vec<StampedEvent>,
-    idxs: Vec<usize>,
+    /// The number of events and the payload at the start of each layer.
+    idxs: Vec<(usize, usize)>,
+    /// The total size of the keys and values of the accumulated events, excluding system events.
+    payload: usize,
 }
 impl Default for EventsAccumulator {
     fn default() -> Self {
@@ -954,10 +1079,20 @@ impl Default for EventsAccumulator {
         Self {
             events: Vec::with_capacity(128),
             idxs: Vec::with_capacity(8),
+            payload: 0,
         }
     }
 }
 
+/// Returns the total size of the keys and values of an event.
+fn event_payload(evt: &StampedEvent) -> usize {
+    evt.event
+        .entries
+        .iter()
+        .map(|e| e.key.len() + e.value.len())
+        .sum()
+}
+
 pub(crate) struct Events {
     root: Option<Cid>,
     events: Vec<StampedEvent>,
@@ -965,21 +1100,28 @@ pub(crate) struct Events {
 
 impl EventsAccumulator {
     fn append_event(&mut self, evt: StampedEvent) {
+        self.payload += event_payload(&evt);
+        self.events.push(evt)
+    }
+
+    /// Appends a system event, without counting it towards the payload.
+    fn append_system_event(&mut self, evt: StampedEvent) {
         self.events.push(evt)
     }
 
     fn begin_transaction(&mut self) {
-        self.idxs.push(self.events.len());
+        self.idxs.push((self.events.len(), self.payload));
     }
 
     fn end_transaction(&mut self, revert: bool) -> Result<()> {
-        let idx = self.idxs.pop().ok_or_else(|| {
+        let (idx, payload) = self.idxs.pop().ok_or_else(|| {
             ExecutionError::Fatal(anyhow!(
                 "no index in the event accumulator when ending a transaction"
             ))
         })?;
         if revert {
             self.events.truncate(idx);
+            self.payload = payload;
         }
         Ok(())
     }
@@ -5,11 +5,12 @@ use fvm_ipld_encoding::{to_vec, CBOR};
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
+use fvm_shared::sys::ReentrancyPolicy;
 use fvm_shared::upgrade::UpgradeInfo;
-use fvm_shared::{ActorID, MethodNum};
+use fvm_shared::{ActorID, MethodNum, IPLD_RAW};
 
 use crate::engine::Engine;
-use crate::gas::{Gas, GasCharge, GasTimer, GasTracker, PriceList};
+use crate::gas::{Gas, GasCharge, GasTimer, GasTracker, PriceList, SyscallCounts};
 use crate::kernel::{self, BlockRegistry, ClassifyResult, Context, Result};
 use crate::machine::{Machine, MachineContext};
 use crate::state_tree::ActorState;
@@ -22,8 +23,9 @@ pub use backtrace::Backtrace;
 mod default;
 
 pub use default::DefaultCallManager;
-use fvm_shared::event::StampedEvent;
+use fvm_shared::event::{ActorEvent, Entry, Flags, StampedEvent};
 
+use crate::system_actor::SYSTEM_ACTOR_ID;
 use crate::trace::ExecutionTrace;
 
 /// BlockID representing nil parameters or return data.
@@ -122,6 +124,10 @@ pub trait CallManager: 'static {
     // returns the actor call stack
     fn get_call_stack(&self) -> &[(ActorID, &'static str)];
 
+    /// Sets whether the currently executing actor may be re-entered (directly or transitively)
+    /// before its current invocation returns. The policy is dropped when the invocation returns.
+    fn set_reentrancy_policy(&mut self, policy: ReentrancyPolicy) -> Result<()>;
+
     /// Resolve an address into an actor ID, charging gas as appropriate.
     fn resolve_address(&self, address: &Address) -> Result<Option<ActorID>>;
 
@@ -144,6 +150,9 @@ pub trait CallManager: 'static {
     /// Gets the total invocations done on this call stack.
     fn invocation_count(&self) -> u64;
 
+    /// Returns the current call stack depth. This is 1 while executing the top-level message.
+    fn call_depth(&self) -> u32;
+
     /// Returns the current price list.
     fn price_list(&self) -> &PriceList {
         self.machine().context().price_list
@@ -174,6 +183,24 @@ pub trait CallManager: 'static {
 
     /// Appends an event to the event accumulator.
     fn append_event(&mut self, evt: StampedEvent);
+
+    /// Records the output of an actor's debugging syscalls, returned in
+    /// [`FinishRet::debug_output`].
+    fn append_debug_output(&mut self, output: DebugOutput);
+
+    /// Appends an actor lifecycle event to the event accumulator, if
+    /// [system events](crate::machine::NetworkConfig::system_events) are enabled.
+    ///
+    /// System events are free: they're emitted by the FVM (not by actors), so they're neither
+    /// charged gas nor counted towards the
+    /// [per-message payload limit](crate::machine::EventLimits::max_message_payload). Their size
+    /// is bounded by the actor creations, deletions, and upgrades (already charged for) that
+    /// trigger them.
+    fn emit_system_event(&mut self, evt: SystemEvent);
+
+    /// Returns the total size of the keys and values of the events emitted so far by the current
+    /// message (excluding events discarded due to aborts).
+    fn events_payload(&self) -> usize;
 }
 
 /// The result of calling actor's entrypoint
@@ -201,6 +228,61 @@ pub struct FinishRet {
     pub exec_trace: ExecutionTrace,
     pub events: Vec<StampedEvent>,
     pub events_root: Option<Cid>,
+    /// The syscalls made, if the syscall census is enabled.
+    pub syscall_counts: Option<SyscallCounts>,
+    /// The logs and artifacts emitted by actors, if actor debugging is enabled.
+    pub debug_output: Vec<DebugOutput>,
+}
+
+/// The output of an actor's debugging syscalls, collected when
+/// [actor debugging](crate::machine::NetworkConfig::actor_debugging) is enabled.
+///
+/// Output is kept even if the call that emitted it is reverted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DebugOutput {
+    /// A message logged with `debug::log`.
+    Log { actor: ActorID, message: String },
+    /// An artifact stored with `debug::store_artifact`.
+    Artifact {
+        actor: ActorID,
+        name: String,
+        data: Vec<u8>,
+    },
+}
+
+/// An actor lifecycle event, emitted by the system actor when
+/// [system events](crate::machine::NetworkConfig::system_events) are enabled.
+///
+/// These events consist of indexed raw-bytes entries: a `type` (`actor-created`, `actor-deleted`,
+/// or `code-upgraded`), the `actor` (as a big-endian ID), and, where applicable, the actor's new
+/// `code` CID.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SystemEvent {
+    ActorCreated { actor: ActorID, code: Cid },
+    ActorDeleted { actor: ActorID },
+    CodeUpgraded { actor: ActorID, code: Cid },
+}
+
+impl From<SystemEvent> for StampedEvent {
+    fn from(evt: SystemEvent) -> Self {
+        let entry = |key: &str, value: Vec<u8>| Entry {
+            flags: Flags::FLAG_INDEXED_ALL,
+            key: key.into(),
+            codec: IPLD_RAW,
+            value,
+        };
+        let (typ, actor, code) = match evt {
+            SystemEvent::ActorCreated { actor, code } => ("actor-created", actor, Some(code)),
+            SystemEvent::ActorDeleted { actor } => ("actor-deleted", actor, None),
+            SystemEvent::CodeUpgraded { actor, code } => ("code-upgraded", actor, Some(code)),
+        };
+        let mut entries = vec![
+            entry("type", typ.as_bytes().to_vec()),
+            entry("actor", actor.to_be_bytes().to_vec()),
+        ];
+        entries.extend(code.map(|code| entry("code", code.to_bytes())));
+        StampedEvent::new(SYSTEM_ACTOR_ID, ActorEvent { entries })
+    }
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -0,0 +1,345 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! This module contains the types and functions to read the cron actor's state. It does not
+//! contain the logic of the cron actor: that lives on-chain as a WASM actor.
+//!
+//! It's intended for node operators and tooling that need to inspect the tasks registered with
+//! cron, or to anticipate the cost of the next cron tick (e.g., at the end of heavy deadlines)
+//! with [`simulate_tick`].
+//!
+//! ## Version compatibility
+//!
+//! This module only handles the state layout used by builtin-actors v9 onwards.
+
+use anyhow::Context;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::tuple::*;
+use fvm_ipld_encoding::CborStore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::message::Message;
+use fvm_shared::{ActorID, MethodNum, BLOCK_GAS_LIMIT};
+use num_traits::Zero;
+
+use crate::call_manager::Entrypoint;
+use crate::executor::{ApplyKind, DefaultExecutor};
+use crate::gas::Gas;
+use crate::kernel::{ClassifyResult, Result};
+use crate::state_tree::{ActorState, StateTree};
+use crate::system_actor::SYSTEM_ACTOR_ID;
+use crate::trace::{ExecutionEvent, ExecutionTrace};
+use crate::Kernel;
+
+pub const CRON_ACTOR_ID: ActorID = 3;
+
+/// The cron actor method invoked (by the system actor) at the end of every epoch.
+pub const EPOCH_TICK_METHOD: MethodNum = 2;
+
+/// A task registered with cron: the method `method_num` of `receiver` is invoked every epoch.
+#[derive(Serialize_tuple, Deserialize_tuple, Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The actor to call (an ID address).
+    pub receiver: Address,
+    /// The method number to call on the actor.
+    pub method_num: MethodNum,
+}
+
+#[derive(Serialize_tuple, Deserialize_tuple, Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    /// The registered tasks, in invocation order.
+    pub entries: Vec<Entry>,
+}
+
+impl State {
+    /// Loads the cron actor state from the supplied state tree.
+    pub fn load<B>(state_tree: &StateTree<B>) -> Result<(Self, ActorState)>
+    where
+        B: Blockstore,
+    {
+        let cron_act = state_tree
+            .get_actor(CRON_ACTOR_ID)?
+            .context("cron actor address could not be resolved")
+            .or_fatal()?;
+
+        let state = state_tree
+            .store()
+            .get_cbor(&cron_act.state)
+            .or_fatal()?
+            .context("cron actor state not found")
+            .or_fatal()?;
+
+        Ok((state, cron_act))
+    }
+
+    /// Loads the cron actor state from the state tree rooted at `root`.
+    pub fn load_from_root<B>(store: B, root: &Cid) -> Result<(Self, ActorState)>
+    where
+        B: Blockstore,
+    {
+        Self::load(&StateTree::new_from_root(store, root)?)
+    }
+}
+
+/// The outcome of a simulated cron tick.
+#[derive(Clone, Debug)]
+pub struct TickEstimate {
+    /// The exit code of the tick.
+    pub exit_code: ExitCode,
+    /// The total gas used by the tick.
+    pub gas_used: u64,
+    /// The calls made by the cron actor, in order. Only populated if tracing is enabled (see
+    /// [`MachineContext::tracing`][crate::machine::MachineContext::tracing]).
+    pub calls: Vec<TickCall>,
+}
+
+/// A call made by the cron actor during a simulated tick.
+#[derive(Clone, Debug)]
+pub struct TickCall {
+    /// The called actor.
+    pub to: Address,
+    /// The called method.
+    pub method_num: MethodNum,
+    /// The gas used by the call, including any nested calls.
+    pub gas_used: u64,
+    /// The exit code of the call, or `None` if it failed to be dispatched.
+    pub exit_code: Option<ExitCode>,
+}
+
+/// Simulates the cron tick at the end of the current epoch, estimating the gas used by each task.
+/// The tick's effects on the state tree are discarded.
+pub fn simulate_tick<K>(executor: &mut DefaultExecutor<K>) -> anyhow::Result<TickEstimate>
+where
+    K: Kernel,
+{
+    let msg = Message {
+        version: 0,
+        from: Address::new_id(SYSTEM_ACTOR_ID),
+        to: Address::new_id(CRON_ACTOR_ID),
+        sequence: 0,
+        value: TokenAmount::default(),
+        method_num: EPOCH_TICK_METHOD,
+        params: Default::default(),
+        gas_limit: BLOCK_GAS_LIMIT * 10000,
+        gas_fee_cap: TokenAmount::default(),
+        gas_premium: TokenAmount::default(),
+    };
+    let (ret, _) =
+        executor.execute_message_tentatively(msg, ApplyKind::Implicit, 0, |_, _| false)?;
+
+    Ok(TickEstimate {
+        exit_code: ret.msg_receipt.exit_code,
+        gas_used: ret.msg_receipt.gas_used,
+        calls: tick_calls(ret.exec_trace),
+    })
+}
+
+/// Extracts the calls made by the cron actor from the trace of a tick.
+fn tick_calls(trace: ExecutionTrace) -> Vec<TickCall> {
+    // The call stack: the first frame is the cron actor itself, and the second is a task.
+    let mut calls = Vec::new();
+    let mut depth = 0;
+    let mut task_gas = Gas::zero();
+    let mut after_call = false;
+    let mut duplicate_error = false;
+    for event in trace {
+        let is_call = matches!(event, ExecutionEvent::Call { .. });
+        match event {
+            ExecutionEvent::Call {
+                to,
+                entrypoint: Entrypoint::Invoke(method_num),
+                ..
+            } => {
+                depth += 1;
+                if depth == 2 {
+                    task_gas = Gas::zero();
+                    calls.push(TickCall {
+                        to,
+                        method_num,
+                        gas_used: 0,
+                        exit_code: None,
+                    });
+                }
+            }
+            ExecutionEvent::Call { .. } => depth += 1,
+            ExecutionEvent::GasCharge(charge) if depth >= 2 => task_gas += charge.total(),
+            ExecutionEvent::CallReturn(exit_code, _) => {
+                if depth == 2 {
+                    if let Some(call) = calls.last_mut() {
+                        call.exit_code = Some(exit_code);
+                    }
+                }
+                depth -= 1;
+            }
+            // A call refused for exceeding the maximum call depth is reported twice: once when the
+            // stack frame is refused, and again as the result of the call.
+            ExecutionEvent::CallError(_) if duplicate_error => duplicate_error = false,
+            ExecutionEvent::CallError(err) => {
+                duplicate_error = after_call && err.1 == ErrorNumber::LimitExceeded;
+                depth -= 1;
+            }
+            _ => {}
+        }
+        after_call = is_call;
+        if depth >= 2 {
+            if let Some(call) = calls.last_mut() {
+                call.gas_used = task_gas.round_up();
+            }
+        }
+    }
+    calls
+}
+
+#[cfg(test)]
+mod test {
+    use cid::multihash::Code;
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::CborStore;
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::error::{ErrorNumber, ExitCode};
+    use fvm_shared::state::StateTreeVersion;
+
+    use super::{tick_calls, Entry, State, CRON_ACTOR_ID};
+    use crate::call_manager::Entrypoint;
+    use crate::gas::{Gas, GasCharge};
+    use crate::kernel::SyscallError;
+    use crate::state_tree::{ActorState, StateTree};
+    use crate::trace::ExecutionEvent;
+
+    fn call(to: u64, method: u64) -> ExecutionEvent {
+        ExecutionEvent::Call {
+            from: CRON_ACTOR_ID,
+            to: Address::new_id(to),
+            entrypoint: Entrypoint::Invoke(method),
+            params: None,
+            value: TokenAmount::default(),
+            gas_limit: u64::MAX,
+            read_only: false,
+        }
+    }
+
+    fn gas(amount: u64) -> ExecutionEvent {
+        ExecutionEvent::GasCharge(GasCharge::new("test", Gas::new(amount), Gas::zero()))
+    }
+
+    fn ret() -> ExecutionEvent {
+        ExecutionEvent::CallReturn(ExitCode::OK, None)
+    }
+
+    fn err(number: ErrorNumber) -> ExecutionEvent {
+        ExecutionEvent::CallError(SyscallError::new(number, "test"))
+    }
+
+    fn summary(trace: Vec<ExecutionEvent>) -> Vec<(Address, u64, u64, Option<ExitCode>)> {
+        tick_calls(trace)
+            .into_iter()
+            .map(|c| (c.to, c.method_num, c.gas_used, c.exit_code))
+            .collect()
+    }
+
+    #[test]
+    fn loads_state() {
+        let mut tree = StateTree::new(MemoryBlockstore::default(), StateTreeVersion::V5).unwrap();
+        let state = State {
+            entries: vec![
+                Entry {
+                    receiver: Address::new_id(4),
+                    method_num: 5,
+                },
+                Entry {
+                    receiver: Address::new_id(1000),
+                    method_num: 7,
+                },
+            ],
+        };
+        let head = tree.store().put_cbor(&state, Code::Blake2b256).unwrap();
+        let code = tree.store().put_cbor(&"cron", Code::Blake2b256).unwrap();
+        tree.set_actor(
+            CRON_ACTOR_ID,
+            ActorState::new(code, head, TokenAmount::default(), 0, None),
+        );
+
+        let (loaded, actor) = State::load(&tree).unwrap();
+        assert_eq!(loaded, state);
+        assert_eq!(actor.state, head);
+
+        let root = tree.flush().unwrap();
+        let (loaded, _) = State::load_from_root(tree.store(), &root).unwrap();
+        assert_eq!(loaded, state);
+    }
+
+    #[test]
+    fn attributes_gas_to_tasks() {
+        let trace = vec![
+            call(CRON_ACTOR_ID, 2),
+            gas(1),
+            call(4, 5),
+            gas(10),
+            // Nested calls count toward the task.
+            call(100, 1),
+            gas(20),
+            ret(),
+            ExecutionEvent::CallReturn(ExitCode::USR_ILLEGAL_STATE, None),
+            gas(2),
+            call(1000, 7),
+            gas(30),
+            err(ErrorNumber::NotFound),
+            ret(),
+        ];
+        assert_eq!(
+            summary(trace),
+            vec![
+                (Address::new_id(4), 5, 30, Some(ExitCode::USR_ILLEGAL_STATE)),
+                (Address::new_id(1000), 7, 30, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_depth_errors_once() {
+        // The first task hits the call depth limit when calling another actor. The call manager
+        // reports that error twice, which must not end the task (or the tick) early.
+        let trace = vec![
+            call(CRON_ACTOR_ID, 2),
+            call(4, 5),
+            gas(10),
+            call(100, 1),
+            err(ErrorNumber::LimitExceeded),
+            err(ErrorNumber::LimitExceeded),
+            gas(5),
+            ret(),
+            call(1000, 7),
+            gas(30),
+            ret(),
+            ret(),
+        ];
+        assert_eq!(
+            summary(trace),
+            vec![
+                (Address::new_id(4), 5, 15, Some(ExitCode::OK)),
+                (Address::new_id(1000), 7, 30, Some(ExitCode::OK)),
+            ]
+        );
+
+        // Tasks refused outright are still reported, without an exit code.
+        let trace = vec![
+            call(CRON_ACTOR_ID, 2),
+            call(4, 5),
+            err(ErrorNumber::LimitExceeded),
+            err(ErrorNumber::LimitExceeded),
+            call(1000, 7),
+            err(ErrorNumber::LimitExceeded),
+            err(ErrorNumber::LimitExceeded),
+            ret(),
+        ];
+        assert_eq!(
+            summary(trace),
+            vec![
+                (Address::new_id(4), 5, 0, None),
+                (Address::new_id(1000), 7, 0, None),
+            ]
+        );
+    }
+}
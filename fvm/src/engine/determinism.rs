@@ -0,0 +1,112 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use anyhow::{anyhow, Context};
+use wasmparser::{Validator, WasmFeatures};
+
+/// A way an engine could execute actor code nondeterministically, found by [`audit_determinism`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Nondeterminism {
+    #[error("NaN canonicalization is disabled")]
+    NanCanonicalizationDisabled,
+    #[error("the threads proposal is enabled")]
+    Threads,
+    #[error("the relaxed SIMD proposal is enabled")]
+    RelaxedSimd,
+}
+
+/// Returns NaN with payload `1`, plus zero, as bits. Hardware propagates the payload, while
+/// canonicalization replaces the result with the canonical NaN.
+const NAN_PROBE: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, 0x03,
+    0x02, 0x01, 0x00, 0x07, 0x09, 0x01, 0x05, 0x70, 0x72, 0x6f, 0x62, 0x65, 0x00, 0x00, 0x0a, 0x12,
+    0x01, 0x10, 0x00, 0x41, 0x81, 0x80, 0x80, 0xfe, 0x07, 0xbe, 0x43, 0x00, 0x00, 0x00, 0x00, 0x92,
+    0xbc, 0x0b,
+];
+
+/// The canonical (32 bit) NaN.
+const CANONICAL_NAN: i32 = 0x7fc00000;
+
+/// Declares a shared memory.
+const THREADS_PROBE: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x05, 0x04, 0x01, 0x03, 0x01, 0x01,
+];
+
+/// Uses `i32x4.relaxed_trunc_f32x4_s`, whose result is platform-dependent.
+const RELAXED_SIMD_PROBE: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00, 0x03, 0x02,
+    0x01, 0x00, 0x0a, 0x1a, 0x01, 0x18, 0x00, 0xfd, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfd, 0x81, 0x02, 0x1a, 0x0b,
+];
+
+/// Checks a wasmtime engine for settings that could make actor execution nondeterministic, by
+/// compiling (and running) probe modules. The engine is deterministic if nothing is returned.
+///
+/// Wasmtime doesn't expose its configuration, so this checks the engine's behavior instead.
+pub fn audit_determinism(engine: &wasmtime::Engine) -> anyhow::Result<Vec<Nondeterminism>> {
+    let mut found = Vec::new();
+
+    if wasmtime::Module::new(engine, THREADS_PROBE).is_ok() {
+        found.push(Nondeterminism::Threads);
+    }
+    if wasmtime::Module::new(engine, RELAXED_SIMD_PROBE).is_ok() {
+        found.push(Nondeterminism::RelaxedSimd);
+    }
+
+    let module = wasmtime::Module::new(engine, NAN_PROBE).context("failed to compile NaN probe")?;
+    let mut store = wasmtime::Store::new(engine, ());
+    // In case epoch interruption is enabled.
+    store.set_epoch_deadline(super::epoch::NO_DEADLINE);
+    let instance = wasmtime::Instance::new(&mut store, &module, &[])
+        .context("failed to instantiate NaN probe")?;
+    let nan = instance
+        .get_typed_func::<(), i32>(&mut store, "probe")?
+        .call(&mut store, ())
+        .context("failed to run NaN probe")?;
+    if nan != CANONICAL_NAN {
+        found.push(Nondeterminism::NanCanonicalizationDisabled);
+    }
+
+    Ok(found)
+}
+
+/// Rejects modules using the threads or relaxed SIMD proposals.
+pub(super) fn check_module(wasm: &[u8]) -> anyhow::Result<()> {
+    let features = WasmFeatures {
+        threads: false,
+        relaxed_simd: false,
+        ..Default::default()
+    };
+    Validator::new_with_features(features)
+        .validate_all(wasm)
+        .map(|_| ())
+        .map_err(|e| anyhow!("module may execute nondeterministically: {}", e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{audit_determinism, check_module, Nondeterminism, RELAXED_SIMD_PROBE};
+
+    #[test]
+    fn audit() {
+        let mut c = wasmtime::Config::new();
+        c.cranelift_nan_canonicalization(true);
+        c.wasm_threads(false);
+        c.wasm_relaxed_simd(false);
+        let engine = wasmtime::Engine::new(&c).unwrap();
+        assert_eq!(audit_determinism(&engine).unwrap(), vec![]);
+
+        c.cranelift_nan_canonicalization(false);
+        c.wasm_threads(true);
+        c.wasm_relaxed_simd(true);
+        let engine = wasmtime::Engine::new(&c).unwrap();
+        let found = audit_determinism(&engine).unwrap();
+        assert!(found.contains(&Nondeterminism::Threads));
+        assert!(found.contains(&Nondeterminism::RelaxedSimd));
+    }
+
+    #[test]
+    fn modules() {
+        assert!(check_module(b"\0asm\x01\0\0\0").is_ok());
+        assert!(check_module(RELAXED_SIMD_PROBE).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use cid::Cid;
+
+/// The length of the checksum appended to each cached artifact.
+const CHECKSUM_LEN: usize = 32;
+
+/// An on-disk cache of compiled actor modules (see [`Engine::export_compiled`]), so restarts
+/// don't have to recompile actor code.
+///
+/// Artifacts are keyed by code CID and [engine fingerprint] (which covers the FVM version, the
+/// compiler, and any consensus-affecting engine configuration). Each artifact is stored with a
+/// checksum, and corrupt artifacts are discarded.
+///
+/// [`Engine::export_compiled`]: super::Engine::export_compiled
+/// [engine fingerprint]: super::Engine::artifact_fingerprint
+#[derive(Clone, Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Opens (creating, if necessary) a cache in the specified directory.
+    ///
+    /// # Safety
+    ///
+    /// Cached artifacts are loaded as native code. The directory must only be writable by
+    /// trusted parties.
+    pub unsafe fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(DiskCache { dir })
+    }
+
+    fn path(&self, k: &Cid, fingerprint: &[u8; 32]) -> PathBuf {
+        let fingerprint: String = fingerprint.iter().map(|b| format!("{b:02x}")).collect();
+        self.dir.join(format!("{k}-{fingerprint}.bin"))
+    }
+
+    fn checksum(data: &[u8]) -> blake2b_simd::Hash {
+        blake2b_simd::Params::new()
+            .hash_length(CHECKSUM_LEN)
+            .hash(data)
+    }
+
+    /// Loads a cached artifact, if present and intact.
+    pub(super) fn load(&self, k: &Cid, fingerprint: &[u8; 32]) -> Option<Vec<u8>> {
+        let path = self.path(k, fingerprint);
+        let mut data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                log::warn!("failed to read cached module {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        let valid = data.len() >= CHECKSUM_LEN && {
+            let (artifact, checksum) = data.split_at(data.len() - CHECKSUM_LEN);
+            Self::checksum(artifact).as_bytes() == checksum
+        };
+        if !valid {
+            log::warn!("discarding corrupt cached module {}", path.display());
+            self.remove(k, fingerprint);
+            return None;
+        }
+        data.truncate(data.len() - CHECKSUM_LEN);
+        Some(data)
+    }
+
+    /// Stores an artifact, replacing any existing artifact atomically.
+    pub(super) fn store(&self, k: &Cid, fingerprint: &[u8; 32], artifact: &[u8]) -> io::Result<()> {
+        let path = self.path(k, fingerprint);
+        let tmp = path.with_extension(format!("tmp-{}", std::process::id()));
+        let mut data = Vec::with_capacity(artifact.len() + CHECKSUM_LEN);
+        data.extend_from_slice(artifact);
+        data.extend_from_slice(Self::checksum(artifact).as_bytes());
+        fs::write(&tmp, data)?;
+        if let Err(e) = fs::rename(&tmp, &path) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Removes an artifact, if present.
+    pub(super) fn remove(&self, k: &Cid, fingerprint: &[u8; 32]) {
+        let _ = fs::remove_file(self.path(k, fingerprint));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cid::Cid;
+    use fvm_ipld_encoding::IPLD_RAW;
+    use multihash::{Code, MultihashDigest};
+
+    use super::DiskCache;
+
+    #[test]
+    fn store_load_verify() {
+        let dir = std::env::temp_dir().join(format!("fvm-disk-cache-{}", std::process::id()));
+        let cache = unsafe { DiskCache::open(&dir) }.unwrap();
+        let k = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"code"));
+        let fingerprint = [1u8; 32];
+
+        assert_eq!(cache.load(&k, &fingerprint), None);
+        cache.store(&k, &fingerprint, b"artifact").unwrap();
+        assert_eq!(
+            cache.load(&k, &fingerprint).as_deref(),
+            Some(&b"artifact"[..])
+        );
+
+        // Artifacts are keyed by fingerprint.
+        assert_eq!(cache.load(&k, &[2u8; 32]), None);
+
+        // Corrupt artifacts are discarded.
+        let path = cache.path(&k, &fingerprint);
+        let mut data = std::fs::read(&path).unwrap();
+        data[0] ^= 1;
+        std::fs::write(&path, data).unwrap();
+        assert_eq!(cache.load(&k, &fingerprint), None);
+        assert!(!path.exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
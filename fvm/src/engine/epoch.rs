@@ -0,0 +1,70 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the engine's epoch is incremented. Execution deadlines are enforced at this
+/// granularity.
+pub(super) const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// The epoch deadline of stores without an execution deadline. This is far enough in the future
+/// to never be reached, without overflowing when added to the current epoch.
+pub(super) const NO_DEADLINE: u64 = u64::MAX >> 1;
+
+/// Increments an engine's epoch every [`EPOCH_TICK`] on a background thread, interrupting Wasm
+/// execution in stores whose epoch deadline has passed. The thread exits when the ticker is
+/// dropped.
+pub(super) struct EpochTicker {
+    stop: Arc<AtomicBool>,
+}
+
+impl EpochTicker {
+    pub fn start(engine: wasmtime::Engine) -> std::io::Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        thread::Builder::new()
+            .name("fvm-epoch-ticker".into())
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        thread::sleep(EPOCH_TICK);
+                        engine.increment_epoch();
+                    }
+                }
+            })?;
+        Ok(EpochTicker { stop })
+    }
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Returns the number of epoch ticks until the given deadline (at least one, so execution can
+/// always start).
+pub(super) fn ticks_until(deadline: Instant) -> u64 {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    let tick = EPOCH_TICK.as_nanos();
+    let ticks = (remaining.as_nanos() + tick - 1) / tick;
+    ticks.clamp(1, NO_DEADLINE as u128) as u64
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{ticks_until, EPOCH_TICK};
+
+    #[test]
+    fn deadline_ticks() {
+        // Passed deadlines still allow execution to start.
+        assert_eq!(ticks_until(Instant::now()), 1);
+        // Deadlines are rounded up to the next tick.
+        let ticks = ticks_until(Instant::now() + 10 * EPOCH_TICK + Duration::from_nanos(1));
+        assert!((10..=11).contains(&ticks), "{ticks}");
+    }
+}
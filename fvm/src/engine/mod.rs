@@ -2,13 +2,19 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 mod concurrency;
+mod determinism;
+mod disk_cache;
+mod epoch;
 mod instance_pool;
+mod validate;
 
 use std::any::{Any, TypeId};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::ops::Deref;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use anyhow::{anyhow, Context};
 use cid::Cid;
@@ -18,30 +24,46 @@ use fvm_wasm_instrument::gas_metering::GAS_COUNTER_NAME;
 use num_traits::Zero;
 use wasmtime::OptLevel::Speed;
 use wasmtime::{
-    Global, GlobalType, InstanceAllocationStrategy, Linker, Memory, MemoryType, Module, Mutability,
-    Val, ValType,
+    ExternType, Global, GlobalType, InstanceAllocationStrategy, Linker, Memory, MemoryType, Module,
+    Mutability, Val, ValType,
 };
 
 use crate::gas::{Gas, GasTimer, WasmGasPrices};
 use crate::machine::limiter::MemoryLimiter;
 use crate::machine::{Machine, NetworkConfig};
+use crate::syscalls::bind::bind_unsupported;
 use crate::syscalls::error::Abort;
 use crate::syscalls::{
     charge_for_exec, charge_for_init, record_init_time, update_gas_available, InvocationData,
+    SYSCALL_MODULES,
 };
 use crate::Kernel;
 
 use self::concurrency::EngineConcurrency;
+pub use self::determinism::{audit_determinism, Nondeterminism};
+pub use self::disk_cache::DiskCache;
+use self::epoch::EpochTicker;
 use self::instance_pool::InstancePool;
+pub use self::validate::{
+    wasm_validate, Diagnostic, DiagnosticKind, ValidationConfig, MAX_ACTOR_CODE_SIZE,
+};
 
 /// The expected max stack depth used to determine the number of instances needed for a given
 /// concurrency level.
 const EXPECTED_MAX_STACK_DEPTH: u32 = 20;
 
+/// The prefix of compiled artifacts exported with [`Engine::export_compiled`].
+const ARTIFACT_MAGIC: &[u8] = b"fvm-wasm-artifact-v1";
+
+/// The maximum number of shared library modules (see [`NetworkConfig::add_shared_module`]).
+pub const MAX_SHARED_MODULES: usize = 4;
+
 /// Container managing engines with different consensus-affecting configurations.
 pub struct MultiEngine {
     engines: Mutex<HashMap<EngineConfig, EnginePool>>,
     concurrency: u32,
+    disk_cache: Option<DiskCache>,
+    strict_determinism: bool,
 }
 
 /// The proper way of getting this struct is to convert from `NetworkConfig`
@@ -53,9 +75,23 @@ pub struct EngineConfig {
     pub concurrency: u32,
     pub wasm_prices: &'static WasmGasPrices,
     pub actor_redirect: Vec<(Cid, Cid)>,
+    pub shared_modules: Vec<(String, Cid)>,
+    /// Whether Wasm execution can be interrupted at a deadline (see
+    /// [`NetworkConfig::execution_timeout`]).
+    pub epoch_interruption: bool,
+    /// Whether to capture Wasm stack traces when actors trap, to include them in the message's
+    /// backtrace (see [`NetworkConfig::actor_debugging`]).
+    pub wasm_backtraces: bool,
+    /// Whether to link stubs for unknown syscall imports (see
+    /// [`NetworkConfig::lazy_syscall_linking`]).
+    pub lazy_syscall_linking: bool,
+    /// Whether to link the `actor::create_and_invoke` syscall (see
+    /// [`NetworkConfig::actor_factories`]).
+    pub actor_factories: bool,
 }
 
 impl EngineConfig {
+    /// The number of stores (actor invocations) that may be live at once.
     fn instance_pool_size(&self) -> u32 {
         std::cmp::min(
             // Allocate at least one full call depth worth of stack, plus some per concurrent call
@@ -63,9 +99,14 @@ impl EngineConfig {
             self.max_call_depth + EXPECTED_MAX_STACK_DEPTH * self.concurrency,
             // Most machines simply can't handle any more than 48k instances (fails to allocate
             // address space).
-            48 * 1024,
+            48 * 1024 / self.instances_per_store(),
         )
     }
+
+    /// The maximum number of instances per store: the actor, plus any shared modules.
+    fn instances_per_store(&self) -> u32 {
+        1 + self.shared_modules.len() as u32
+    }
 }
 
 impl From<&NetworkConfig> for EngineConfig {
@@ -76,6 +117,11 @@ impl From<&NetworkConfig> for EngineConfig {
             max_inst_memory_bytes: nc.max_inst_memory_bytes,
             wasm_prices: &nc.price_list.wasm_rules,
             actor_redirect: nc.actor_redirect.clone(),
+            shared_modules: nc.shared_modules.clone(),
+            epoch_interruption: nc.execution_timeout.is_some(),
+            wasm_backtraces: nc.actor_debugging,
+            lazy_syscall_linking: nc.lazy_syscall_linking,
+            actor_factories: nc.actor_factories,
             concurrency: 1,
         }
     }
@@ -89,9 +135,27 @@ impl MultiEngine {
         MultiEngine {
             engines: Mutex::new(HashMap::new()),
             concurrency,
+            disk_cache: None,
+            strict_determinism: false,
+        }
+    }
+
+    /// Like [`MultiEngine::new`], but all engines are created in strict determinism mode (see
+    /// [`EnginePool::new_strict`]). This protects against engine settings (e.g., in a fork) that
+    /// could cause consensus failures.
+    pub fn new_strict(concurrency: u32) -> MultiEngine {
+        MultiEngine {
+            strict_determinism: true,
+            ..Self::new(concurrency)
         }
     }
 
+    /// Persist compiled actor code in the given on-disk cache, shared by all engines.
+    pub fn with_disk_cache(mut self, disk_cache: DiskCache) -> Self {
+        self.disk_cache = Some(disk_cache);
+        self
+    }
+
     pub fn get(&self, nc: &NetworkConfig) -> anyhow::Result<EnginePool> {
         let mut engines = self
             .engines
@@ -103,7 +167,12 @@ impl MultiEngine {
 
         let pool = match engines.entry(ec.clone()) {
             Occupied(entry) => entry.into_mut(),
-            Vacant(entry) => entry.insert(EnginePool::new_default(ec)?),
+            Vacant(entry) => entry.insert(EnginePool::build(
+                &wasmtime_config(&ec)?,
+                ec,
+                self.disk_cache.clone(),
+                self.strict_determinism,
+            )?),
         };
 
         Ok(pool.clone())
@@ -121,7 +190,23 @@ fn wasmtime_config(ec: &EngineConfig) -> anyhow::Result<wasmtime::Config> {
         return Err(anyhow!("concurrency limit must not be 0"));
     }
 
-    let instance_count = ec.instance_pool_size();
+    if ec.shared_modules.len() > MAX_SHARED_MODULES {
+        return Err(anyhow!(
+            "at most {MAX_SHARED_MODULES} shared modules may be registered"
+        ));
+    }
+
+    if let Some((name, _)) = ec
+        .shared_modules
+        .iter()
+        .find(|(name, _)| SYSCALL_MODULES.contains(&name.as_str()))
+    {
+        return Err(anyhow!(
+            "shared module name {name} is reserved for syscalls"
+        ));
+    }
+
+    let instance_count = ec.instance_pool_size() * ec.instances_per_store();
     let instance_memory_maximum_size = ec.max_inst_memory_bytes;
     if instance_memory_maximum_size % wasmtime_environ::WASM_PAGE_SIZE as u64 != 0 {
         return Err(anyhow!(
@@ -195,7 +280,8 @@ fn wasmtime_config(ec: &EngineConfig) -> anyhow::Result<wasmtime::Config> {
 
     // Execution cost accouting is done through wasm instrumentation,
     c.consume_fuel(false);
-    c.epoch_interruption(false);
+    // but embedders may bound wall-clock execution time as a safety net.
+    c.epoch_interruption(ec.epoch_interruption);
 
     // Disable debug-related things, wasm-instrument doesn't fix debug info
     // yet, so those aren't useful, just add overhead
@@ -203,7 +289,9 @@ fn wasmtime_config(ec: &EngineConfig) -> anyhow::Result<wasmtime::Config> {
     c.generate_address_map(false);
     c.cranelift_debug_verifier(false);
     c.native_unwind_info(false);
-    c.wasm_backtrace(false);
+    // Only capture Wasm backtraces (symbolicated with the module's name section, if any) when
+    // debugging actors, they make traps more expensive.
+    c.wasm_backtrace(ec.wasm_backtraces);
     c.wasm_reference_types(false);
 
     // Reiterate some defaults
@@ -220,6 +308,19 @@ fn wasmtime_config(ec: &EngineConfig) -> anyhow::Result<wasmtime::Config> {
     Ok(c)
 }
 
+/// Feeds [`Hash`](std::hash::Hash) implementations into a fingerprint.
+struct FingerprintHasher<'a>(&'a mut blake2b_simd::State);
+
+impl std::hash::Hasher for FingerprintHasher<'_> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        unreachable!("fingerprints are finalized separately")
+    }
+}
+
 #[derive(Clone)]
 struct ModuleRecord {
     module: Module,
@@ -246,6 +347,14 @@ struct EngineInner {
     config: EngineConfig,
 
     actor_redirect: HashMap<Cid, Cid>,
+
+    disk_cache: Option<DiskCache>,
+
+    /// Reject actor code using nondeterministic Wasm features.
+    strict_determinism: bool,
+
+    /// Advances the engine's epoch, if execution can be interrupted.
+    _epoch_ticker: Option<EpochTicker>,
 }
 
 /// EnginePool represents a limited pool of engines.
@@ -268,8 +377,49 @@ impl EnginePool {
 
     /// Create a new Engine from a wasmtime config.
     pub fn new(c: &wasmtime::Config, ec: EngineConfig) -> anyhow::Result<Self> {
+        Self::new_with_disk_cache(c, ec, None)
+    }
+
+    /// Create a new Engine from a wasmtime config, persisting compiled actor code in the given
+    /// on-disk cache (if any).
+    pub fn new_with_disk_cache(
+        c: &wasmtime::Config,
+        ec: EngineConfig,
+        disk_cache: Option<DiskCache>,
+    ) -> anyhow::Result<Self> {
+        Self::build(c, ec, disk_cache, false)
+    }
+
+    /// Create a new Engine from a wasmtime config in strict determinism mode: fails if the config
+    /// could make actor execution nondeterministic (see [`audit_determinism`]), and rejects actor
+    /// code using nondeterministic Wasm features (threads and relaxed SIMD).
+    pub fn new_strict(
+        c: &wasmtime::Config,
+        ec: EngineConfig,
+        disk_cache: Option<DiskCache>,
+    ) -> anyhow::Result<Self> {
+        Self::build(c, ec, disk_cache, true)
+    }
+
+    fn build(
+        c: &wasmtime::Config,
+        ec: EngineConfig,
+        disk_cache: Option<DiskCache>,
+        strict_determinism: bool,
+    ) -> anyhow::Result<Self> {
         let engine = wasmtime::Engine::new(c)?;
 
+        if strict_determinism {
+            let found = audit_determinism(&engine)?;
+            if !found.is_empty() {
+                let found: Vec<_> = found.iter().map(ToString::to_string).collect();
+                return Err(anyhow!(
+                    "engine may execute actors nondeterministically: {}",
+                    found.join(", ")
+                ));
+            }
+        }
+
         let mut dummy_store = wasmtime::Store::new(&engine, ());
         let gg_type = GlobalType::new(ValType::I64, Mutability::Var);
         let dummy_gg = Global::new(&mut dummy_store, gg_type, Val::I64(0))
@@ -280,6 +430,12 @@ impl EnginePool {
 
         let actor_redirect = ec.actor_redirect.iter().cloned().collect();
 
+        let epoch_ticker = if ec.epoch_interruption {
+            Some(EpochTicker::start(engine.clone()).context("failed to start epoch ticker")?)
+        } else {
+            None
+        };
+
         Ok(EnginePool(Arc::new(EngineInner {
             concurrency_limit: EngineConcurrency::new(ec.concurrency),
             instance_limit: InstancePool::new(ec.instance_pool_size(), ec.max_call_depth),
@@ -290,6 +446,9 @@ impl EnginePool {
             instance_cache: Mutex::new(HashMap::new()),
             config: ec,
             actor_redirect,
+            disk_cache,
+            strict_determinism,
+            _epoch_ticker: epoch_ticker,
         })))
     }
 }
@@ -392,7 +551,7 @@ impl Engine {
         let size = match cache.get(k) {
             Some(item) => item.size,
             None => {
-                let m = self.load_raw(wasm)?;
+                let m = self.load_cached_or_raw(k, wasm)?;
                 let s = m.size;
                 cache.insert(*k, m);
                 s
@@ -401,11 +560,82 @@ impl Engine {
         Ok(size)
     }
 
+    /// Loads a module from the on-disk cache if it's there, or compiles it (and caches it on disk)
+    /// otherwise.
+    fn load_cached_or_raw(&self, k: &Cid, wasm: &[u8]) -> anyhow::Result<ModuleRecord> {
+        if let Some(m) = self.load_from_disk(k) {
+            return Ok(m);
+        }
+        let m = self.load_raw(wasm)?;
+        self.store_to_disk(k, &m);
+        Ok(m)
+    }
+
+    /// Loads a module compiled by a previous run from the on-disk cache, if any.
+    fn load_from_disk(&self, k: &Cid) -> Option<ModuleRecord> {
+        let disk_cache = self.inner.disk_cache.as_ref()?;
+        let fingerprint = self.artifact_fingerprint();
+        let artifact = disk_cache.load(k, &fingerprint)?;
+        // SAFETY: the disk cache may only be written to by trusted parties (see
+        // `DiskCache::open`), and we've checked the artifact's integrity.
+        match unsafe { self.decode_artifact(k, &artifact) } {
+            Ok(record) => Some(record),
+            Err(e) => {
+                log::warn!("discarding unusable cached module for {k}: {e:#}");
+                disk_cache.remove(k, &fingerprint);
+                None
+            }
+        }
+    }
+
+    /// Stores a freshly compiled module in the on-disk cache, if any. Failures are logged, not
+    /// returned, as the cache is only an optimization.
+    fn store_to_disk(&self, k: &Cid, record: &ModuleRecord) {
+        let Some(disk_cache) = &self.inner.disk_cache else {
+            return;
+        };
+        let res = self.encode_artifact(k, record).and_then(|artifact| {
+            disk_cache
+                .store(k, &self.artifact_fingerprint(), &artifact)
+                .map_err(Into::into)
+        });
+        if let Err(e) = res {
+            log::warn!("failed to cache compiled module for {k}: {e:#}");
+        }
+    }
+
+    /// Validates and loads actor code installed at runtime (i.e., not part of the builtin actors
+    /// bundle), and prepares it for execution. The code is always validated against this engine's
+    /// configuration, even if it's already loaded (e.g., by an engine with a different config).
+    ///
+    /// Returns the original byte code size.
+    pub fn install_actor_code(&self, k: &Cid, wasm: &[u8]) -> anyhow::Result<usize> {
+        let config = ValidationConfig::for_actors(&self.inner.config.shared_modules);
+        let diagnostics = wasm_validate(wasm, &config);
+        if !diagnostics.is_empty() {
+            let problems: Vec<_> = diagnostics.iter().map(|d| d.to_string()).collect();
+            return Err(anyhow!("invalid actor code: {}", problems.join("; ")));
+        }
+        if let Some(item) = self
+            .inner
+            .module_cache
+            .lock()
+            .expect("module_cache poisoned")
+            .get(self.with_redirect(k))
+        {
+            return Ok(item.size);
+        }
+        self.prepare_wasm_bytecode(k, wasm)
+    }
+
     fn load_raw(&self, raw_wasm: &[u8]) -> anyhow::Result<ModuleRecord> {
         // First make sure that non-instrumented wasm is valid
         Module::validate(&self.inner.engine, raw_wasm)
             .map_err(anyhow::Error::msg)
             .with_context(|| "failed to validate actor wasm")?;
+        if self.inner.strict_determinism {
+            determinism::check_module(raw_wasm)?;
+        }
 
         // Note: when adding debug mode support (with recorded syscall replay) don't instrument to
         // avoid breaking debug info
@@ -470,12 +700,140 @@ impl Engine {
         Ok(module)
     }
 
+    /// Returns a fingerprint of the settings affecting how this engine compiles actor code (the FVM
+    /// version, the instrumentation and memory settings, and the compiler's version and settings).
+    /// Compiled artifacts can only be imported into engines with the same fingerprint.
+    pub fn artifact_fingerprint(&self) -> [u8; 32] {
+        let EngineConfig {
+            max_wasm_stack,
+            max_inst_memory_bytes,
+            wasm_prices,
+            ..
+        } = &self.inner.config;
+        let mut state = blake2b_simd::Params::new().hash_length(32).to_state();
+        state.update(concat!("fvm-", env!("CARGO_PKG_VERSION"), ";").as_bytes());
+        // Hash the canonical CBOR encoding of the settings, not their `Debug` output.
+        state.update(
+            &fvm_ipld_encoding::to_vec(&(max_wasm_stack, max_inst_memory_bytes, wasm_prices))
+                .expect("engine settings are serializable"),
+        );
+        self.inner
+            .engine
+            .precompile_compatibility_hash()
+            .hash(&mut FingerprintHasher(&mut state));
+        state
+            .finalize()
+            .as_bytes()
+            .try_into()
+            .expect("hash length is 32 bytes")
+    }
+
+    /// Exports the compiled module for the given actor code, so it can be imported into identical
+    /// engines on other hosts with [`Engine::import_compiled`]. Returns `None` if the code hasn't
+    /// been loaded into this engine.
+    pub fn export_compiled(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        let k = self.with_redirect(k);
+        let Some(record) = self
+            .inner
+            .module_cache
+            .lock()
+            .expect("module_cache poisoned")
+            .get(k)
+            .cloned()
+        else {
+            return Ok(None);
+        };
+        self.encode_artifact(k, &record).map(Some)
+    }
+
+    fn encode_artifact(&self, k: &Cid, record: &ModuleRecord) -> anyhow::Result<Vec<u8>> {
+        let compiled = record.module.serialize()?;
+        let code = k.to_bytes();
+        let mut artifact =
+            Vec::with_capacity(ARTIFACT_MAGIC.len() + 32 + 4 + code.len() + 8 + compiled.len());
+        artifact.extend_from_slice(ARTIFACT_MAGIC);
+        artifact.extend_from_slice(&self.artifact_fingerprint());
+        artifact.extend_from_slice(&(code.len() as u32).to_be_bytes());
+        artifact.extend_from_slice(&code);
+        artifact.extend_from_slice(&(record.size as u64).to_be_bytes());
+        artifact.extend_from_slice(&compiled);
+        Ok(artifact)
+    }
+
+    /// Imports a compiled module exported with [`Engine::export_compiled`], skipping compilation
+    /// when the actor code is first used. Fails if the artifact was exported by an engine with a
+    /// different [fingerprint](Engine::artifact_fingerprint) or wasmtime configuration, or for
+    /// different code. Does nothing if the code has already been loaded.
+    ///
+    /// # Safety
+    ///
+    /// The artifact is native code, and is executed as-is: it must come from a trusted host. See
+    /// [`wasmtime::Module::deserialize`] for more information.
+    pub unsafe fn import_compiled(&self, k: &Cid, artifact: &[u8]) -> anyhow::Result<()> {
+        let k = self.with_redirect(k);
+        let mut cache = self
+            .inner
+            .module_cache
+            .lock()
+            .expect("module_cache poisoned");
+        if let Vacant(v) = cache.entry(*k) {
+            v.insert(self.decode_artifact(k, artifact)?);
+        }
+        Ok(())
+    }
+
+    /// Decodes and loads an artifact produced by [`Engine::encode_artifact`].
+    ///
+    /// # Safety
+    ///
+    /// See [`Engine::import_compiled`].
+    unsafe fn decode_artifact(&self, k: &Cid, artifact: &[u8]) -> anyhow::Result<ModuleRecord> {
+        fn split(data: &[u8], n: usize) -> anyhow::Result<(&[u8], &[u8])> {
+            if data.len() < n {
+                return Err(anyhow!("truncated compiled artifact"));
+            }
+            Ok(data.split_at(n))
+        }
+
+        let (magic, rest) = split(artifact, ARTIFACT_MAGIC.len())?;
+        if magic != ARTIFACT_MAGIC {
+            return Err(anyhow!("not a compiled artifact"));
+        }
+        let (fingerprint, rest) = split(rest, 32)?;
+        if fingerprint != self.artifact_fingerprint() {
+            return Err(anyhow!(
+                "compiled artifact was exported by an incompatible engine"
+            ));
+        }
+        let (code_len, rest) = split(rest, 4)?;
+        let code_len = u32::from_be_bytes(code_len.try_into().unwrap()) as usize;
+        let (code, rest) = split(rest, code_len)?;
+        if code != k.to_bytes() {
+            return Err(anyhow!("compiled artifact is not for code {k}"));
+        }
+        let (size, compiled) = split(rest, 8)?;
+        let size = u64::from_be_bytes(size.try_into().unwrap()) as usize;
+
+        let module = Module::deserialize(&self.inner.engine, compiled)
+            .context("failed to load compiled artifact")?;
+        Ok(ModuleRecord { module, size })
+    }
+
     /// Lookup a loaded wasmtime module.
     pub fn get_module(
         &self,
         blockstore: &impl Blockstore,
         k: &Cid,
     ) -> anyhow::Result<Option<Module>> {
+        Ok(self.lookup_module(blockstore, k)?.map(|(module, _)| module))
+    }
+
+    /// Like [`Engine::get_module`], but also returns whether the module was already cached.
+    fn lookup_module(
+        &self,
+        blockstore: &impl Blockstore,
+        k: &Cid,
+    ) -> anyhow::Result<Option<(Module, bool)>> {
         let k = self.with_redirect(k);
         match self
             .inner
@@ -484,17 +842,20 @@ impl Engine {
             .expect("module_cache poisoned")
             .entry(*k)
         {
-            Occupied(v) => Ok(Some(v.get().module.clone())),
+            Occupied(v) => Ok(Some((v.get().module.clone(), true))),
             Vacant(v) => blockstore
                 .get(k)
                 .context("failed to lookup wasm module in blockstore")?
-                .map(|raw_wasm| Ok(v.insert(self.load_raw(&raw_wasm)?).module.clone()))
+                .map(|raw_wasm| {
+                    let m = self.load_cached_or_raw(k, &raw_wasm)?;
+                    Ok((v.insert(m).module.clone(), false))
+                })
                 .transpose(),
         }
     }
 
-    /// Lookup and instantiate a loaded wasmtime module with the given store. This will cache the
-    /// linker, syscalls, etc.
+    /// Lookup and instantiate a loaded wasmtime module with the given store, along with any shared
+    /// modules it imports. This will cache the linker, syscalls, etc.
     ///
     /// This returns an `Abort` as it may need to execute initialization code, charge gas, etc.
     pub fn instantiate<K: Kernel>(
@@ -502,7 +863,21 @@ impl Engine {
         store: &mut wasmtime::Store<InvocationData<K>>,
         k: &Cid,
     ) -> Result<Option<wasmtime::Instance>, Abort> {
-        let k = self.with_redirect(k);
+        let start = Instant::now();
+        let (module, shared) = {
+            let machine = store.data().kernel.machine();
+            let blockstore = machine.blockstore();
+            let Some((module, cached)) = self.lookup_module(blockstore, k).map_err(Abort::Fatal)?
+            else {
+                return Ok(None);
+            };
+            machine.metrics().module_cache_lookup(cached);
+            let shared = self
+                .shared_modules_for(blockstore, &module)
+                .map_err(Abort::Fatal)?;
+            (module, shared)
+        };
+
         let mut instance_cache = self.inner.instance_cache.lock().expect("cache poisoned");
 
         let type_id = TypeId::of::<K>();
@@ -534,70 +909,89 @@ impl Engine {
             .context("failed to define gas counter")
             .map_err(Abort::Fatal)?;
 
-        let mut module_cache = self
-            .inner
-            .module_cache
-            .lock()
-            .expect("module_cache poisoned");
-
-        let instantiate = |store: &mut wasmtime::Store<InvocationData<K>>, module| {
-            // Before we instantiate the module, we should make sure the user has sufficient gas to
-            // pay for the minimum memory requirements. The module instrumentation in `inject` only
-            // adds code to charge for _growing_ the memory, but not for the amount made accessible
-            // initially. The limits are checked by wasmtime during instantiation, though.
-            let t = charge_for_init(store, module).map_err(Abort::from_error_as_fatal)?;
-
-            // Pre-instantiate to catch any linker errors. These are considered fatal as it means
-            // the wasm module wasn't properly validated.
-            let pre_instance = cache
+        // Instantiate the shared modules first (in registration order) so the actor, and later
+        // shared modules, can link against them. Definitions left over from previous stores are
+        // always shadowed before they're used.
+        for (name, shared_module) in &shared {
+            // The linker allows shadowing, so a shared module named after a syscall namespace
+            // would replace the syscalls.
+            if SYSCALL_MODULES.contains(name) {
+                return Err(Abort::Fatal(anyhow!(
+                    "shared module name {name} is reserved for syscalls"
+                )));
+            }
+            let instance = instantiate_module(&cache.linker, store, shared_module)?;
+            cache
                 .linker
-                .instantiate_pre(module)
-                .context("failed to link actor module")?;
-
-            // Update the gas _just_ in case.
-            update_gas_available(store)?;
-            let res = pre_instance.instantiate(&mut *store);
-            charge_for_exec(store)?;
-
-            let inst = res.map_err(|e| {
-                // We can't really tell what type of error happened, so we have to assume that we
-                // either ran out of memory or trapped. Given that we've already type-checked the
-                // module, this is the most likely case anyways. That or there'a a bug in the FVM.
-                Abort::Exit(
-                    ExitCode::SYS_ILLEGAL_INSTRUCTION,
-                    format!("failed to instantiate module: {e}"),
-                    0,
-                )
-            })?;
-
-            // Record the time it took for the linker to instantiate the module.
-            // This should also include everything that happens above in this method.
-            // Note that this does _not_ contain the time it took the load the Wasm file,
-            // which could have been cached already.
-            record_init_time(store, t);
+                .instance(&mut *store, name, instance)
+                .context("failed to define shared module")
+                .map_err(Abort::Fatal)?;
+        }
 
-            Ok(Some(inst))
+        // Stubs for unsupported syscalls are bound in a copy of the linker, as their signatures
+        // are chosen by the module importing them.
+        let stubbed = if self.inner.config.lazy_syscall_linking {
+            link_unsupported_syscalls(&cache.linker, store, &module, &shared)
+                .map_err(Abort::Fatal)?
+        } else {
+            None
         };
+        let linker = stubbed.as_ref().unwrap_or(&cache.linker);
 
-        match module_cache.entry(*k) {
-            Occupied(v) => instantiate(store, &v.get().module),
-            Vacant(v) => match store
-                .data()
-                .kernel
-                .machine()
-                .blockstore()
-                .get(k)
-                .context("failed to lookup wasm module in blockstore")
-                .map_err(Abort::Fatal)?
-            {
-                Some(raw_wasm) => instantiate(
-                    store,
-                    &v.insert(self.load_raw(&raw_wasm).map_err(Abort::Fatal)?)
-                        .module,
-                ),
-                None => Ok(None),
-            },
+        let instance = instantiate_module(linker, store, &module)?;
+        store
+            .data()
+            .kernel
+            .machine()
+            .metrics()
+            .actor_instantiated(start.elapsed());
+        Ok(Some(instance))
+    }
+
+    /// Returns the shared modules (transitively) imported by the given module, in registration
+    /// order.
+    fn shared_modules_for<'a>(
+        &'a self,
+        blockstore: &impl Blockstore,
+        module: &Module,
+    ) -> anyhow::Result<Vec<(&'a str, Module)>> {
+        let shared_modules = &self.inner.config.shared_modules;
+        if shared_modules.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Shared modules may only import shared modules registered before them, so a single
+        // backwards pass finds all dependencies.
+        let mut imported: HashSet<String> =
+            module.imports().map(|i| i.module().to_owned()).collect();
+        let mut result = Vec::new();
+        for (i, (name, code)) in shared_modules.iter().enumerate().rev() {
+            if !imported.contains(name) {
+                continue;
+            }
+            let shared_module = self
+                .get_module(blockstore, code)?
+                .with_context(|| format!("shared module {name} ({code}) not found"))?;
+            // Syscalls operate on the memory of the actor's instance, not the shared module's, so
+            // shared modules may only import the gas counter and earlier shared modules.
+            if let Some(import) = shared_module.imports().find(|import| {
+                !(import.module() == "gas" && import.name() == GAS_COUNTER_NAME)
+                    && !shared_modules[..i]
+                        .iter()
+                        .any(|(earlier, _)| earlier == import.module())
+            }) {
+                return Err(anyhow!(
+                    "shared module {name} imports {}.{}, but may only import shared modules \
+                     registered before it",
+                    import.module(),
+                    import.name()
+                ));
+            }
+            imported.extend(shared_module.imports().map(|i| i.module().to_owned()));
+            result.push((name.as_str(), shared_module));
         }
+        result.reverse();
+        Ok(result)
     }
 
     /// Construct a new wasmtime "store" from the given kernel.
@@ -634,9 +1028,14 @@ impl Engine {
             .expect("failed to create available_gas global");
         store.data_mut().avail_gas_global = gg;
 
+        if self.inner.config.epoch_interruption {
+            store.set_epoch_deadline(epoch::NO_DEADLINE);
+        }
+
         store.limiter(move |data| {
             // Keep the reservation alive as long as the limiter is alive. The limiter limits the
-            // store to one instance and one memory, which is covered by the reservation.
+            // store to one instance and one memory per module, which is covered by the
+            // reservation.
             let _ = &reservation;
 
             // SAFETY: This is safe because WasmtimeLimiter is `repr(transparent)`.
@@ -657,6 +1056,92 @@ impl Engine {
 
         store
     }
+
+    /// Interrupts execution in the given store (with a fatal error) once the deadline passes. Has
+    /// no effect unless the engine was configured with an execution timeout.
+    pub fn set_execution_deadline<T>(&self, store: &mut wasmtime::Store<T>, deadline: Instant) {
+        if self.inner.config.epoch_interruption {
+            store.set_epoch_deadline(epoch::ticks_until(deadline));
+        }
+    }
+}
+
+/// Returns a copy of the linker with stubs for the syscalls imported by the actor module that the
+/// kernel doesn't provide (see [`NetworkConfig::lazy_syscall_linking`]), or `None` if the kernel
+/// provides them all. Only functions imported from a syscall module with a syscall's signature
+/// (returning an error number) are stubbed. Other imports, e.g., from unknown modules, are left
+/// alone and fail to link as usual.
+fn link_unsupported_syscalls<K: Kernel>(
+    linker: &wasmtime::Linker<InvocationData<K>>,
+    store: &mut wasmtime::Store<InvocationData<K>>,
+    module: &Module,
+    shared: &[(&str, Module)],
+) -> anyhow::Result<Option<wasmtime::Linker<InvocationData<K>>>> {
+    let mut stubbed: Option<wasmtime::Linker<InvocationData<K>>> = None;
+    for import in module.imports() {
+        let ExternType::Func(ty) = import.ty() else {
+            continue;
+        };
+        let Some(syscall_module) = SYSCALL_MODULES
+            .iter()
+            .find(|name| **name == import.module())
+        else {
+            continue;
+        };
+        if shared.iter().any(|(name, _)| *name == import.module())
+            || linker.get_by_import(&mut *store, &import).is_some()
+            || !ty.results().eq([ValType::I32])
+        {
+            continue;
+        }
+        let with_stubs = stubbed.get_or_insert_with(|| linker.clone());
+        bind_unsupported(with_stubs, syscall_module, import.name(), ty)?;
+    }
+    Ok(stubbed)
+}
+
+/// Instantiates a module with the given linker, charging for its initial memory and for the
+/// execution of its start function (if any).
+fn instantiate_module<K: Kernel>(
+    linker: &wasmtime::Linker<InvocationData<K>>,
+    store: &mut wasmtime::Store<InvocationData<K>>,
+    module: &Module,
+) -> Result<wasmtime::Instance, Abort> {
+    // Before we instantiate the module, we should make sure the user has sufficient gas to
+    // pay for the minimum memory requirements. The module instrumentation in `inject` only
+    // adds code to charge for _growing_ the memory, but not for the amount made accessible
+    // initially. The limits are checked by wasmtime during instantiation, though.
+    let t = charge_for_init(store, module).map_err(Abort::from_error_as_fatal)?;
+
+    // Pre-instantiate to catch any linker errors. These are considered fatal as it means
+    // the wasm module wasn't properly validated.
+    let pre_instance = linker
+        .instantiate_pre(module)
+        .context("failed to link actor module")?;
+
+    // Update the gas _just_ in case.
+    update_gas_available(store)?;
+    let res = pre_instance.instantiate(&mut *store);
+    charge_for_exec(store)?;
+
+    let inst = res.map_err(|e| {
+        // We can't really tell what type of error happened, so we have to assume that we
+        // either ran out of memory or trapped. Given that we've already type-checked the
+        // module, this is the most likely case anyways. That or there'a a bug in the FVM.
+        Abort::Exit(
+            ExitCode::SYS_ILLEGAL_INSTRUCTION,
+            format!("failed to instantiate module: {e}"),
+            0,
+        )
+    })?;
+
+    // Record the time it took for the linker to instantiate the module.
+    // This should also include everything that happens above in this method.
+    // Note that this does _not_ contain the time it took the load the Wasm file,
+    // which could have been cached already.
+    record_init_time(store, t);
+
+    Ok(inst)
 }
 
 #[repr(transparent)]
@@ -688,14 +1173,15 @@ impl<L: MemoryLimiter> wasmtime::ResourceLimiter for WasmtimeLimiter<L> {
         Ok(self.0.grow_instance_table(current, desired))
     }
 
-    // The FVM allows one instance & one memory per store/kernel (for now).
+    // The FVM allows one instance & one memory per store/kernel, plus one of each per shared
+    // module.
 
     fn instances(&self) -> usize {
-        1
+        1 + MAX_SHARED_MODULES
     }
 
     fn memories(&self) -> usize {
-        1
+        1 + MAX_SHARED_MODULES
     }
 }
 
@@ -758,4 +1244,160 @@ mod tests {
         assert!(limits.table_growing(2, 4, None).unwrap());
         assert_eq!(limits.0.memory, 5 * 8);
     }
+
+    #[test]
+    fn compiled_artifact_roundtrip() {
+        use cid::Cid;
+        use fvm_ipld_encoding::IPLD_RAW;
+        use fvm_shared::version::NetworkVersion;
+        use multihash::{Code, MultihashDigest};
+
+        use crate::engine::{EngineConfig, EnginePool};
+        use crate::gas::price_list_by_network_version;
+
+        let pool = |max_wasm_stack| {
+            EnginePool::new_default(EngineConfig {
+                max_call_depth: 1,
+                max_wasm_stack,
+                max_inst_memory_bytes: 1 << 20,
+                concurrency: 1,
+                wasm_prices: &price_list_by_network_version(NetworkVersion::V21).wasm_rules,
+                actor_redirect: vec![],
+                shared_modules: vec![],
+                epoch_interruption: false,
+                wasm_backtraces: false,
+                lazy_syscall_linking: false,
+                actor_factories: false,
+            })
+            .unwrap()
+        };
+
+        let wasm = b"\0asm\x01\0\0\0";
+        let k = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(wasm));
+
+        let source = pool(2048).acquire();
+        assert!(source.export_compiled(&k).unwrap().is_none());
+        let size = source.prepare_wasm_bytecode(&k, wasm).unwrap();
+        let artifact = source.export_compiled(&k).unwrap().unwrap();
+
+        let other = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"other"));
+        let target = pool(2048).acquire();
+        unsafe {
+            assert!(target.import_compiled(&other, &artifact).is_err());
+            assert!(target.import_compiled(&k, &artifact[..40]).is_err());
+            target.import_compiled(&k, &artifact).unwrap();
+        }
+        assert_eq!(target.prepare_wasm_bytecode(&k, &[]).unwrap(), size);
+
+        // Engines instrumenting code differently have different fingerprints.
+        let incompatible = pool(1024).acquire();
+        assert_ne!(
+            incompatible.artifact_fingerprint(),
+            source.artifact_fingerprint()
+        );
+        unsafe {
+            assert!(incompatible.import_compiled(&k, &artifact).is_err());
+        }
+    }
+
+    #[test]
+    fn lazy_load_uses_disk_cache() {
+        use cid::Cid;
+        use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+        use fvm_ipld_encoding::IPLD_RAW;
+        use fvm_shared::version::NetworkVersion;
+        use multihash::{Code, MultihashDigest};
+
+        use crate::engine::{wasmtime_config, DiskCache, EngineConfig, EnginePool};
+        use crate::machine::NetworkConfig;
+
+        let dir = std::env::temp_dir().join(format!("fvm-lazy-load-{}", std::process::id()));
+        let cache = unsafe { DiskCache::open(&dir) }.unwrap();
+        let ec: EngineConfig = (&NetworkConfig::new(NetworkVersion::V21)).into();
+        let pool = || {
+            EnginePool::new_with_disk_cache(
+                &wasmtime_config(&ec).unwrap(),
+                ec.clone(),
+                Some(cache.clone()),
+            )
+            .unwrap()
+        };
+
+        let wasm = b"\0asm\x01\0\0\0";
+        let k = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(wasm));
+        let bs = MemoryBlockstore::default();
+        bs.put_keyed(&k, wasm).unwrap();
+
+        // Loading the module on demand compiles it and caches it on disk...
+        let engine = pool().acquire();
+        assert!(engine.get_module(&bs, &k).unwrap().is_some());
+        let fingerprint = engine.artifact_fingerprint();
+        assert!(cache.load(&k, &fingerprint).is_some());
+
+        // ...which fresh engines can load it from.
+        assert!(pool().acquire().load_from_disk(&k).is_some());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn default_config_is_deterministic() {
+        use fvm_shared::version::NetworkVersion;
+
+        use crate::engine::{wasmtime_config, EngineConfig, EnginePool};
+        use crate::machine::NetworkConfig;
+
+        let ec: EngineConfig = (&NetworkConfig::new(NetworkVersion::V21)).into();
+        EnginePool::new_strict(&wasmtime_config(&ec).unwrap(), ec, None).unwrap();
+    }
+
+    #[test]
+    fn shared_module_pool_sizing() {
+        use cid::Cid;
+        use fvm_shared::version::NetworkVersion;
+
+        use crate::engine::{wasmtime_config, EngineConfig, EnginePool, MAX_SHARED_MODULES};
+        use crate::machine::NetworkConfig;
+
+        let mut nc = NetworkConfig::new(NetworkVersion::V21);
+        let ec: EngineConfig = (&nc).into();
+        assert_eq!(ec.instances_per_store(), 1);
+        let pool_size = ec.instance_pool_size();
+
+        // Every store reserves room for the actor and each shared module.
+        for i in 0..MAX_SHARED_MODULES {
+            nc.add_shared_module(format!("lib{i}"), Cid::default())
+                .unwrap();
+        }
+        let ec: EngineConfig = (&nc).into();
+        assert_eq!(ec.instances_per_store(), 1 + MAX_SHARED_MODULES as u32);
+        assert!(ec.instance_pool_size() <= pool_size);
+        assert!(ec.instance_pool_size() * ec.instances_per_store() <= 48 * 1024);
+        EnginePool::new_default(ec).unwrap();
+
+        nc.add_shared_module("one_too_many", Cid::default())
+            .unwrap();
+        let ec: EngineConfig = (&nc).into();
+        assert!(wasmtime_config(&ec).is_err());
+    }
+
+    #[test]
+    fn reserved_shared_module_names() {
+        use cid::Cid;
+        use fvm_shared::version::NetworkVersion;
+
+        use crate::engine::{wasmtime_config, EngineConfig};
+        use crate::machine::NetworkConfig;
+
+        let mut nc = NetworkConfig::new(NetworkVersion::V21);
+        for name in ["vm", "ipld", "self"] {
+            nc.add_shared_module(name, Cid::default()).unwrap_err();
+        }
+        assert!(nc.shared_modules.is_empty());
+
+        // Shared modules registered behind `add_shared_module`'s back are rejected by the engine.
+        nc.shared_modules.push(("send".into(), Cid::default()));
+        let ec: EngineConfig = (&nc).into();
+        assert!(wasmtime_config(&ec).is_err());
+    }
 }
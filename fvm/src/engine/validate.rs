@@ -0,0 +1,316 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::fmt::Display;
+
+use cid::Cid;
+use wasmparser::{ExternalKind, Parser, Payload, TypeRef, Validator, WasmFeatures};
+
+use crate::call_manager::INVOKE_FUNC_NAME;
+use crate::syscalls::SYSCALL_MODULES;
+
+/// The maximum size of actor code installed at runtime, in bytes.
+pub const MAX_ACTOR_CODE_SIZE: usize = 2 << 20;
+
+/// Rules checked by [`wasm_validate`].
+#[derive(Clone, Debug)]
+pub struct ValidationConfig {
+    /// The maximum size of the module, in bytes.
+    pub max_code_size: usize,
+    /// Deny floating point types and instructions.
+    pub deny_floats: bool,
+    /// Deny SIMD types and instructions.
+    pub deny_simd: bool,
+    /// Deny atomic instructions and shared memories.
+    pub deny_threads: bool,
+    /// The maximum number of functions the module may define.
+    pub max_functions: u32,
+    /// The maximum number of memories the module may declare (including imported memories).
+    pub max_memories: u32,
+    /// The modules functions may be imported from. Only functions may be imported.
+    pub allowed_imports: Vec<String>,
+    /// Require the module to export an `invoke` function and its `memory`, as actors must.
+    pub require_actor_exports: bool,
+}
+
+impl Default for ValidationConfig {
+    /// The rules for actor code installed at runtime.
+    fn default() -> Self {
+        Self {
+            max_code_size: MAX_ACTOR_CODE_SIZE,
+            deny_floats: true,
+            deny_simd: true,
+            deny_threads: true,
+            max_functions: 1 << 14,
+            max_memories: 1,
+            allowed_imports: SYSCALL_MODULES.iter().map(|&m| m.into()).collect(),
+            require_actor_exports: true,
+        }
+    }
+}
+
+impl ValidationConfig {
+    /// The rules for actor code installed at runtime, allowing imports from the given shared
+    /// library modules (see [`NetworkConfig::add_shared_module`]).
+    ///
+    /// [`NetworkConfig::add_shared_module`]: crate::machine::NetworkConfig::add_shared_module
+    pub fn for_actors(shared_modules: &[(String, Cid)]) -> Self {
+        let mut config = Self::default();
+        config
+            .allowed_imports
+            .extend(shared_modules.iter().map(|(name, _)| name.clone()));
+        config
+    }
+}
+
+/// A problem found by [`wasm_validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The offset of the problem within the module, if known.
+    pub offset: Option<usize>,
+    /// The problem.
+    pub kind: DiagnosticKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum DiagnosticKind {
+    #[error("module is {size} bytes, exceeding the maximum of {max} bytes")]
+    CodeTooLarge { size: usize, max: usize },
+    #[error("module is malformed: {0}")]
+    Malformed(String),
+    #[error("module uses a denied feature: {0}")]
+    DeniedFeature(String),
+    #[error("module defines {count} functions, exceeding the maximum of {max}")]
+    TooManyFunctions { count: u32, max: u32 },
+    #[error("module declares {count} memories, exceeding the maximum of {max}")]
+    TooManyMemories { count: u32, max: u32 },
+    #[error("module imports a non-function {module}.{name}")]
+    NonFunctionImport { module: String, name: String },
+    #[error("module imports {module}.{name} from a module that isn't allowed")]
+    DeniedImport { module: String, name: String },
+    #[error("module doesn't export {0}")]
+    MissingExport(&'static str),
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{} (at offset {:#x})", self.kind, offset),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Checks Wasm code against the given rules (e.g., before deploying it as an actor), returning
+/// every problem found. Code is valid if no problems are returned.
+///
+/// Malformed code is reported as a single [`DiagnosticKind::Malformed`] problem.
+pub fn wasm_validate(wasm: &[u8], config: &ValidationConfig) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut report = |offset, kind| diagnostics.push(Diagnostic { offset, kind });
+
+    if wasm.len() > config.max_code_size {
+        report(
+            None,
+            DiagnosticKind::CodeTooLarge {
+                size: wasm.len(),
+                max: config.max_code_size,
+            },
+        );
+    }
+
+    // The features the engine supports. Anything else is malformed as far as we're concerned.
+    let supported = WasmFeatures {
+        multi_memory: false,
+        memory64: false,
+        multi_value: false,
+        reference_types: false,
+        ..Default::default()
+    };
+    if let Err(e) = Validator::new_with_features(supported).validate_all(wasm) {
+        report(
+            Some(e.offset()),
+            DiagnosticKind::Malformed(e.message().into()),
+        );
+        return diagnostics;
+    }
+
+    let allowed = WasmFeatures {
+        floats: !config.deny_floats,
+        simd: supported.simd && !config.deny_simd,
+        relaxed_simd: supported.relaxed_simd && !config.deny_simd,
+        threads: supported.threads && !config.deny_threads,
+        ..supported
+    };
+    if let Err(e) = Validator::new_with_features(allowed).validate_all(wasm) {
+        report(
+            Some(e.offset()),
+            DiagnosticKind::DeniedFeature(e.message().into()),
+        );
+    }
+
+    let mut functions = 0u32;
+    let mut memories = 0u32;
+    let mut has_invoke = false;
+    let mut has_memory = false;
+    // The module has already been validated, so it parses.
+    for payload in Parser::new(0).parse_all(wasm).flatten() {
+        match payload {
+            Payload::ImportSection(reader) => {
+                for (offset, import) in reader.into_iter_with_offsets().flatten() {
+                    if matches!(import.ty, TypeRef::Memory(_)) {
+                        memories += 1;
+                    }
+                    let (module, name) = (import.module.to_owned(), import.name.to_owned());
+                    if !matches!(import.ty, TypeRef::Func(_)) {
+                        report(
+                            Some(offset),
+                            DiagnosticKind::NonFunctionImport { module, name },
+                        );
+                    } else if !config.allowed_imports.contains(&module) {
+                        report(Some(offset), DiagnosticKind::DeniedImport { module, name });
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => functions += reader.count(),
+            Payload::MemorySection(reader) => memories += reader.count(),
+            Payload::ExportSection(reader) => {
+                for export in reader.into_iter().flatten() {
+                    match export.kind {
+                        ExternalKind::Func if export.name == INVOKE_FUNC_NAME => has_invoke = true,
+                        ExternalKind::Memory if export.name == "memory" => has_memory = true,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if functions > config.max_functions {
+        report(
+            None,
+            DiagnosticKind::TooManyFunctions {
+                count: functions,
+                max: config.max_functions,
+            },
+        );
+    }
+    if memories > config.max_memories {
+        report(
+            None,
+            DiagnosticKind::TooManyMemories {
+                count: memories,
+                max: config.max_memories,
+            },
+        );
+    }
+    if config.require_actor_exports {
+        if !has_invoke {
+            report(None, DiagnosticKind::MissingExport("an invoke function"));
+        }
+        if !has_memory {
+            report(None, DiagnosticKind::MissingExport("its memory"));
+        }
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use super::{wasm_validate, DiagnosticKind, ValidationConfig};
+
+    /// Builds a module exporting `invoke` (with the given body) and its memory.
+    fn actor_module(body: &[u8]) -> Vec<u8> {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        // Types: (i32) -> i32
+        wasm.extend_from_slice(&[0x01, 0x06, 0x01, 0x60, 0x01, 0x7f, 0x01, 0x7f]);
+        // Functions
+        wasm.extend_from_slice(&[0x03, 0x02, 0x01, 0x00]);
+        // Memory
+        wasm.extend_from_slice(&[0x05, 0x03, 0x01, 0x00, 0x01]);
+        // Exports
+        wasm.extend_from_slice(&[0x07, 0x13, 0x02, 0x06]);
+        wasm.extend_from_slice(b"invoke");
+        wasm.extend_from_slice(&[0x00, 0x00, 0x06]);
+        wasm.extend_from_slice(b"memory");
+        wasm.extend_from_slice(&[0x02, 0x00]);
+        // Code
+        wasm.extend_from_slice(&[0x0a, body.len() as u8 + 2, 0x01, body.len() as u8]);
+        wasm.extend_from_slice(body);
+        wasm
+    }
+
+    fn kinds(wasm: &[u8], config: &ValidationConfig) -> Vec<DiagnosticKind> {
+        wasm_validate(wasm, config)
+            .into_iter()
+            .map(|d| d.kind)
+            .collect()
+    }
+
+    #[test]
+    fn valid_actor() {
+        // local.get 0
+        let wasm = actor_module(&[0x00, 0x20, 0x00, 0x0b]);
+        assert_eq!(kinds(&wasm, &Default::default()), vec![]);
+    }
+
+    #[test]
+    fn missing_exports() {
+        assert_eq!(
+            kinds(b"\0asm\x01\0\0\0", &Default::default()),
+            vec![
+                DiagnosticKind::MissingExport("an invoke function"),
+                DiagnosticKind::MissingExport("its memory"),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(
+            kinds(b"\0asm\x01\0\0\0\x01", &Default::default())[..],
+            [DiagnosticKind::Malformed(_)]
+        ));
+    }
+
+    #[test]
+    fn floats() {
+        // f32.const 0; drop; local.get 0
+        let wasm = actor_module(&[0x00, 0x43, 0x00, 0x00, 0x00, 0x00, 0x1a, 0x20, 0x00, 0x0b]);
+        assert!(matches!(
+            kinds(&wasm, &Default::default())[..],
+            [DiagnosticKind::DeniedFeature(_)]
+        ));
+
+        let config = ValidationConfig {
+            deny_floats: false,
+            ..Default::default()
+        };
+        assert_eq!(kinds(&wasm, &config), vec![]);
+    }
+
+    #[test]
+    fn limits() {
+        let wasm = actor_module(&[0x00, 0x20, 0x00, 0x0b]);
+        let config = ValidationConfig {
+            max_code_size: 8,
+            max_functions: 0,
+            max_memories: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            kinds(&wasm, &config),
+            vec![
+                DiagnosticKind::CodeTooLarge {
+                    size: wasm.len(),
+                    max: 8
+                },
+                DiagnosticKind::TooManyFunctions { count: 1, max: 0 },
+                DiagnosticKind::TooManyMemories { count: 1, max: 0 },
+            ]
+        );
+    }
+}
@@ -5,36 +5,78 @@ use std::result::Result as StdResult;
 
 use anyhow::{anyhow, Result};
 use cid::Cid;
-use fvm_ipld_encoding::{RawBytes, CBOR};
-use fvm_shared::address::Payload;
+use fvm_ipld_encoding::{to_vec, RawBytes, CBOR, DAG_CBOR};
+use fvm_shared::address::{Address, Payload};
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::event::StampedEvent;
-use fvm_shared::message::Message;
+use fvm_shared::message::{
+    AuthorizeGasSponsorshipParams, Message, AUTHORIZE_GAS_SPONSORSHIP_METHOD,
+};
 use fvm_shared::receipt::Receipt;
 use fvm_shared::{ActorID, IPLD_RAW, METHOD_SEND};
+use multihash::{Code, MultihashDigest};
 use num_traits::Zero;
 
-use super::{ApplyFailure, ApplyKind, ApplyRet, Executor};
-use crate::call_manager::{backtrace, Backtrace, CallManager, Entrypoint, InvocationResult};
+use super::{
+    ApplyFailure, ApplyKind, ApplyRet, BundleReport, Executor, FailureReason, GasEstimate,
+    GasEstimationConfig, GasSponsor, MessageEvents, MessageFailure, SenderChecks, SoftFailReport,
+    ValueTransferPolicy,
+};
+use crate::call_manager::{
+    backtrace, Backtrace, CallManager, DebugOutput, Entrypoint, InvocationResult,
+};
 use crate::eam_actor::EAM_ACTOR_ID;
 use crate::engine::EnginePool;
-use crate::gas::{Gas, GasCharge, GasOutputs};
+use crate::gas::{Gas, GasCharge, GasOutputs, SyscallCounts};
 use crate::kernel::{Block, ClassifyResult, Context as _, ExecutionError, Kernel};
-use crate::machine::{Machine, BURNT_FUNDS_ACTOR_ID, REWARD_ACTOR_ID};
+use crate::machine::{EventContext, Machine, BURNT_FUNDS_ACTOR_ID, REWARD_ACTOR_ID};
+use crate::state_tree::ActorChange;
 use crate::trace::ExecutionTrace;
 
 /// The default [`Executor`].
 ///
-/// # Warning
-///
-/// Message execution might run out of stack and crash (the entire process) if it doesn't have at
-/// least 64MiB of stack space. If you can't guarantee 64MiB of stack space, wrap this executor in
-/// a [`ThreadedExecutor`][super::ThreadedExecutor].
+/// Messages can be executed on any thread, regardless of its stack size: each call frame switches
+/// to a newly allocated stack segment when the remaining stack runs low, so deeply nested calls
+/// can't overflow the host stack.
 pub struct DefaultExecutor<K: Kernel> {
     engine_pool: EnginePool,
     // If the inner value is `None` it means the machine got poisoned and is unusable.
     machine: Option<<K::CallManager as CallManager>::Machine>,
+    // The number of explicit messages executed since events were last taken (used to index
+    // events).
+    message_count: u64,
+    // Events retained for indexing, grouped by message.
+    indexed_events: Vec<MessageEvents>,
+    // Caps on the value explicit messages may transfer, if any.
+    value_transfer_policy: Option<ValueTransferPolicy>,
+    // The checks made on the senders of explicit messages.
+    sender_checks: SenderChecks,
+    // The sum of the gas limits of the explicit messages applied since last taken.
+    gas_limit_total: u64,
+    // The number of open executor transactions, whose effects might still be dropped.
+    transaction_depth: usize,
+    // Messages applied inside executor transactions, whose events and metrics are only published
+    // once their effects are kept.
+    unpublished: Vec<AppliedMessage>,
+}
+
+// The events and metrics of an applied message, to be published to the machine's event sink and
+// metrics.
+struct AppliedMessage {
+    kind: ApplyKind,
+    exit_code: ExitCode,
+    gas_used: u64,
+    syscall_counts: Option<SyscallCounts>,
+    events: Option<(EventContext, Vec<StampedEvent>)>,
+}
+
+// The executor's bookkeeping at the start of a transaction, restored if its effects are dropped.
+struct Checkpoint {
+    message_count: u64,
+    indexed_events: usize,
+    gas_limit_total: u64,
+    unpublished: usize,
 }
 
 impl<K: Kernel> Deref for DefaultExecutor<K> {
@@ -64,13 +106,250 @@ where
         apply_kind: ApplyKind,
         raw_length: usize,
     ) -> anyhow::Result<ApplyRet> {
+        self.execute_message_with_sponsor(msg, apply_kind, raw_length, None)
+    }
+
+    /// Flush the state-tree to the underlying blockstore.
+    fn flush(&mut self) -> anyhow::Result<Cid> {
+        let k = (**self).flush()?;
+        Ok(k)
+    }
+}
+
+impl<K> DefaultExecutor<K>
+where
+    K: Kernel,
+{
+    /// Create a new [`DefaultExecutor`] for executing messages on the [`Machine`].
+    pub fn new(
+        engine_pool: EnginePool,
+        machine: <K::CallManager as CallManager>::Machine,
+    ) -> anyhow::Result<Self> {
+        // Skip preloading all builtin actors when testing.
+        #[cfg(not(any(test, feature = "testing")))]
+        {
+            // Preload any uncached modules.
+            // This interface works for now because we know all actor CIDs
+            // ahead of time, but with user-supplied code, we won't have that
+            // guarantee.
+            engine_pool.acquire().preload(
+                machine.blockstore(),
+                machine.builtin_actors().builtin_actor_codes().chain(
+                    machine
+                        .context()
+                        .shared_modules
+                        .iter()
+                        .map(|(_, code)| code),
+                ),
+            )?;
+        }
+        Ok(Self {
+            engine_pool,
+            machine: Some(machine),
+            message_count: 0,
+            indexed_events: Vec::new(),
+            value_transfer_policy: None,
+            sender_checks: SenderChecks::default(),
+            gas_limit_total: 0,
+            transaction_depth: 0,
+            unpublished: Vec::new(),
+        })
+    }
+
+    /// Enforces the given [`ValueTransferPolicy`] on all explicit messages. Messages transferring
+    /// more than allowed are rejected before execution with
+    /// [`ExitCode::SYS_SENDER_STATE_INVALID`] and an [`ApplyFailure::ValueTransferDenied`]
+    /// describing the violated cap. As with other invalid messages, the miner that included them is
+    /// penalized.
+    pub fn with_value_transfer_policy(mut self, policy: ValueTransferPolicy) -> Self {
+        self.value_transfer_policy = Some(policy);
+        self
+    }
+
+    /// Relaxes the checks made on the senders of explicit messages, for off-chain execution. See
+    /// [`SenderChecks`].
+    pub fn with_sender_checks(mut self, checks: SenderChecks) -> Self {
+        self.sender_checks = checks;
+        self
+    }
+
+    /// Executes an explicit message whose gas is paid for by a third party (the sponsor's
+    /// `payer`) instead of the sender, e.g., a dapp sponsoring its users' transactions.
+    ///
+    /// The sender is validated (and its nonce bumped) as usual, but the gas is deducted from, and
+    /// refunded to, the payer. Before the message is invoked, the payer's
+    /// [`AUTHORIZE_GAS_SPONSORSHIP_METHOD`] is called (read-only, from the sender) with the
+    /// message CID and the sponsor's approval data. The payer is only charged once this call
+    /// exits successfully, and the gas it used is part of the message's gas. Otherwise, the
+    /// message fails validation with [`ExitCode::SYS_SENDER_STATE_INVALID`]: the state is left
+    /// untouched (including the sender's nonce), nobody is charged for gas, and the miner is
+    /// penalized.
+    ///
+    /// The payer is supplied alongside the message rather than as a field of [`Message`], so that
+    /// the message's serialization (and CID) is unchanged.
+    ///
+    /// If the payer is the sender, this is equivalent to [`Executor::execute_message`].
+    pub fn execute_sponsored_message(
+        &mut self,
+        msg: Message,
+        sponsor: GasSponsor,
+        raw_length: usize,
+    ) -> anyhow::Result<ApplyRet> {
+        self.execute_message_with_sponsor(msg, ApplyKind::Explicit, raw_length, Some(sponsor))
+    }
+
+    fn execute_message_with_sponsor(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+        sponsor: Option<GasSponsor>,
+    ) -> anyhow::Result<ApplyRet> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "execute_message",
+            from = %msg.from,
+            to = %msg.to,
+            method = msg.method_num,
+            gas_limit = msg.gas_limit,
+            exit_code = tracing::field::Empty,
+            gas_used = tracing::field::Empty,
+        )
+        .entered();
+
+        let message_index = self.message_count;
+        if apply_kind == ApplyKind::Explicit {
+            self.message_count += 1;
+        }
+
+        let message_cid =
+            if self.context().index_events || sponsor.is_some() || self.event_sink().is_some() {
+                Some(message_cid(&msg)?)
+            } else {
+                None
+            };
+
+        let ret = self.apply_message(
+            msg,
+            apply_kind,
+            raw_length,
+            sponsor,
+            message_cid,
+            message_index,
+        )?;
+
+        #[cfg(feature = "tracing")]
+        {
+            span.record("exit_code", ret.msg_receipt.exit_code.value());
+            span.record("gas_used", ret.msg_receipt.gas_used);
+        }
+
+        let events = match (self.event_sink(), message_cid) {
+            (Some(_), Some(message_cid)) => Some((
+                EventContext {
+                    epoch: self.context().epoch,
+                    message_cid,
+                    message_index,
+                },
+                ret.events.clone(),
+            )),
+            _ => None,
+        };
+        self.publish(AppliedMessage {
+            kind: apply_kind,
+            exit_code: ret.msg_receipt.exit_code,
+            gas_used: ret.msg_receipt.gas_used,
+            syscall_counts: ret.syscall_counts.clone(),
+            events,
+        });
+        Ok(ret)
+    }
+
+    /// Publishes the events and metrics of an applied message, or holds them back until the
+    /// enclosing transaction ends if its effects might still be dropped.
+    fn publish(&mut self, message: AppliedMessage) {
+        if self.transaction_depth > 0 {
+            self.unpublished.push(message);
+            return;
+        }
+
+        let metrics = self.metrics();
+        metrics.message_applied(message.kind, message.exit_code, message.gas_used);
+        for (syscall, &count) in message.syscall_counts.iter().flatten() {
+            metrics.syscalls_called(syscall, count);
+        }
+        if let (Some(sink), Some((context, events))) = (self.event_sink(), &message.events) {
+            for event in events {
+                sink.push_event(context, event);
+            }
+        }
+    }
+
+    /// Begins a transaction: a state-tree transaction, along with the executor's own bookkeeping.
+    fn begin_transaction(&mut self) -> Checkpoint {
+        self.state_tree_mut().begin_transaction();
+        self.transaction_depth += 1;
+        Checkpoint {
+            message_count: self.message_count,
+            indexed_events: self.indexed_events.len(),
+            gas_limit_total: self.gas_limit_total,
+            unpublished: self.unpublished.len(),
+        }
+    }
+
+    /// Ends a transaction, either keeping its effects (publishing the events and metrics of its
+    /// messages, unless it's nested) or dropping them.
+    fn end_transaction(&mut self, checkpoint: Checkpoint, commit: bool) -> anyhow::Result<()> {
+        self.state_tree_mut().end_transaction(!commit)?;
+        self.transaction_depth -= 1;
+        if !commit {
+            self.message_count = checkpoint.message_count;
+            self.indexed_events.truncate(checkpoint.indexed_events);
+            self.gas_limit_total = checkpoint.gas_limit_total;
+            self.unpublished.truncate(checkpoint.unpublished);
+        } else if self.transaction_depth == 0 {
+            for message in std::mem::take(&mut self.unpublished) {
+                self.publish(message);
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn apply_message(
+        &mut self,
+        mut msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+        sponsor: Option<GasSponsor>,
+        message_cid: Option<Cid>,
+        message_index: u64,
+    ) -> anyhow::Result<ApplyRet> {
+        if apply_kind == ApplyKind::Explicit {
+            self.gas_limit_total = self.gas_limit_total.saturating_add(msg.gas_limit);
+        }
+
         // Validate if the message was correct, charge for it, and extract some preliminary data.
-        let (sender_id, gas_cost, inclusion_cost) =
-            match self.preflight_message(&msg, apply_kind, raw_length)? {
+        let payer = sponsor.as_ref().map(|s| &s.payer);
+        let (sender_id, payer_id, gas_cost, inclusion_cost) =
+            match self.preflight_message(&mut msg, apply_kind, raw_length, payer)? {
                 Ok(res) => res,
                 Err(apply_ret) => return Ok(apply_ret),
             };
 
+        // If someone else is paying for gas, they need to approve.
+        let approval = match (sponsor, message_cid) {
+            (Some(sponsor), Some(message)) if payer_id != sender_id => {
+                let params = to_vec(&AuthorizeGasSponsorshipParams {
+                    message,
+                    approval: sponsor.approval,
+                })
+                .or_fatal()?;
+                Some(Block::new(CBOR, params, Vec::new()))
+            }
+            _ => None,
+        };
+
         struct MachineExecRet {
             result: crate::kernel::error::Result<InvocationResult>,
             gas_used: u64,
@@ -78,6 +357,9 @@ where
             exec_trace: ExecutionTrace,
             events_root: Option<Cid>,
             events: Vec<StampedEvent>, // TODO consider removing if nothing in the client ends up using it.
+            syscall_counts: Option<SyscallCounts>,
+            debug_output: Vec<DebugOutput>,
+            sponsorship_rejected: bool,
         }
 
         // Pre-resolve the message receiver's address, if known.
@@ -141,19 +423,50 @@ where
                 )
             });
 
-            let result = cm.with_transaction(|cm| {
-                // Invoke the message. We charge for the return value internally if the call-stack depth
-                // is 1.
-                cm.call_actor::<K>(
+            let approved = match approval {
+                None => Ok(true),
+                Some(params) => match cm.call_actor::<K>(
                     sender_id,
-                    msg.to,
-                    Entrypoint::Invoke(msg.method_num),
-                    params,
-                    &msg.value,
+                    Address::new_id(payer_id),
+                    Entrypoint::Invoke(AUTHORIZE_GAS_SPONSORSHIP_METHOD),
+                    Some(params),
+                    &TokenAmount::zero(),
                     None,
-                    false,
-                )
-            });
+                    true,
+                ) {
+                    // Only charge the payer once it has approved.
+                    Ok(InvocationResult { exit_code, .. }) if exit_code.is_success() => {
+                        charge_payer(cm.machine_mut(), payer_id, &gas_cost).map(|_| true)
+                    }
+                    Ok(_) => Ok(false),
+                    // E.g., the payer doesn't exist or has no code.
+                    Err(ExecutionError::Syscall(_)) => Ok(false),
+                    // Fatal errors consume all the gas, which is settled as usual.
+                    Err(e) => charge_payer(cm.machine_mut(), payer_id, &gas_cost).and(Err(e)),
+                },
+            };
+
+            let sponsorship_rejected = matches!(approved, Ok(false));
+            let result = match approved {
+                Ok(true) => cm.with_transaction(|cm| {
+                    // Invoke the message. We charge for the return value internally if the
+                    // call-stack depth is 1.
+                    cm.call_actor::<K>(
+                        sender_id,
+                        msg.to,
+                        Entrypoint::Invoke(msg.method_num),
+                        params,
+                        &msg.value,
+                        None,
+                        false,
+                    )
+                }),
+                Ok(false) => Ok(InvocationResult {
+                    exit_code: ExitCode::SYS_SENDER_STATE_INVALID,
+                    value: None,
+                }),
+                Err(e) => Err(e),
+            };
 
             let (res, machine) = match cm.finish() {
                 (Ok(res), machine) => (res, machine),
@@ -168,6 +481,9 @@ where
                     exec_trace: res.exec_trace,
                     events_root: res.events_root,
                     events: res.events,
+                    syscall_counts: res.syscall_counts,
+                    debug_output: res.debug_output,
+                    sponsorship_rejected,
                 }),
                 machine,
             )
@@ -180,8 +496,29 @@ where
             exec_trace,
             events_root,
             events,
+            syscall_counts,
+            debug_output,
+            sponsorship_rejected,
         } = ret;
 
+        if sponsorship_rejected {
+            // Treat the message as if it had failed validation: undo the sender's nonce bump from
+            // preflight_message (the approval is read-only, so that's the only state change). Nobody
+            // paid for the message's gas (including the approval), so the miner is penalized as for
+            // any other message that shouldn't have been included.
+            self.state_tree_mut()
+                .mutate_actor(sender_id, |sender| {
+                    sender.sequence -= 1;
+                    Ok(())
+                })
+                .context("failed to lookup sender to revert its nonce")?;
+            return Ok(ApplyRet::prevalidation_fail(
+                ExitCode::SYS_SENDER_STATE_INVALID,
+                "Gas sponsorship not approved",
+                &self.context().base_fee * msg.gas_limit,
+            ));
+        }
+
         // Extract the exit code and build the result of the message application.
         let receipt = match res {
             Ok(InvocationResult { exit_code, value }) => {
@@ -258,15 +595,18 @@ where
             Some(ApplyFailure::MessageBacktrace(backtrace))
         };
 
-        match apply_kind {
+        let ret = match apply_kind {
             ApplyKind::Explicit => self.finish_message(
                 sender_id,
+                payer_id,
                 msg,
                 receipt,
                 failure_info,
                 gas_cost,
                 exec_trace,
                 events,
+                syscall_counts,
+                debug_output,
             ),
             ApplyKind::Implicit => Ok(ApplyRet {
                 msg_receipt: receipt,
@@ -277,44 +617,254 @@ where
                 refund: TokenAmount::zero(),
                 gas_refund: 0,
                 gas_burned: 0,
+                gas_payer: None,
                 failure_info,
                 exec_trace,
                 events,
+                syscall_counts,
+                debug_output,
             }),
+        }?;
+
+        if let (Some(message_cid), Some(events_root)) = (message_cid, ret.msg_receipt.events_root) {
+            self.indexed_events.push(MessageEvents {
+                message_cid,
+                message_index,
+                events_root,
+                events: ret.events.clone(),
+            });
         }
+
+        Ok(ret)
     }
 
-    /// Flush the state-tree to the underlying blockstore.
-    fn flush(&mut self) -> anyhow::Result<Cid> {
-        let k = (**self).flush()?;
-        Ok(k)
+    /// Takes the events emitted by all messages executed so far (typically, all messages in a
+    /// tipset), grouped by message and in execution order. Messages that emitted no events are
+    /// omitted.
+    ///
+    /// Events are only retained if [`MachineContext::index_events`][crate::machine::MachineContext::index_events]
+    /// is enabled; otherwise, this always returns an empty vector.
+    ///
+    /// Message indices restart from zero afterwards, for the next tipset.
+    pub fn take_tipset_events(&mut self) -> Vec<MessageEvents> {
+        self.message_count = 0;
+        std::mem::take(&mut self.indexed_events)
     }
-}
 
-impl<K> DefaultExecutor<K>
-where
-    K: Kernel,
-{
-    /// Create a new [`DefaultExecutor`] for executing messages on the [`Machine`].
-    pub fn new(
-        engine_pool: EnginePool,
-        machine: <K::CallManager as CallManager>::Machine,
-    ) -> anyhow::Result<Self> {
-        // Skip preloading all builtin actors when testing.
-        #[cfg(not(any(test, feature = "testing")))]
-        {
-            // Preload any uncached modules.
-            // This interface works for now because we know all actor CIDs
-            // ahead of time, but with user-supplied code, we won't have that
-            // guarantee.
-            engine_pool.acquire().preload(
-                machine.blockstore(),
-                machine.builtin_actors().builtin_actor_codes(),
-            )?;
+    /// Returns the sum of the gas limits of the explicit messages applied since the last call (or
+    /// since the executor was created), and resets it. Call this after applying the messages of
+    /// each block to get the gas in the block, for [`compute_next`](crate::gas::basefee::compute_next).
+    ///
+    /// Messages whose effects are dropped by [`DefaultExecutor::execute_message_tentatively`] or a
+    /// reverted [bundle](DefaultExecutor::execute_message_bundle) aren't counted.
+    pub fn take_gas_limit_total(&mut self) -> u64 {
+        std::mem::take(&mut self.gas_limit_total)
+    }
+
+    /// Executes a message as a "dry-run", then lets the caller decide whether to keep its effects.
+    ///
+    /// The message is executed inside a state-tree transaction. Once it completes, `decide` is
+    /// called with the result and the actors the message changed; its effects are committed if it
+    /// returns true and dropped otherwise. Returns the result of the message along with the
+    /// decision.
+    ///
+    /// Dropping a message's effects restores the state tree exactly, and the message isn't counted
+    /// when indexing events.
+    pub fn execute_message_tentatively<F>(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+        decide: F,
+    ) -> anyhow::Result<(ApplyRet, bool)>
+    where
+        F: FnOnce(&ApplyRet, &[ActorChange]) -> bool,
+    {
+        let checkpoint = self.begin_transaction();
+        let ret = self.execute_message(msg, apply_kind, raw_length);
+        if self.machine.is_none() {
+            // The machine was poisoned; there's nothing left to revert.
+            return Err(ret.err().unwrap_or_else(|| anyhow!("machine poisoned")));
         }
-        Ok(Self {
-            engine_pool,
-            machine: Some(machine),
+        let ret = match ret {
+            Ok(ret) => ret,
+            Err(e) => {
+                self.end_transaction(checkpoint, false)?;
+                return Err(e);
+            }
+        };
+
+        let changes = self.state_tree().transaction_changes()?;
+        let commit = decide(&ret, &changes);
+        self.end_transaction(checkpoint, commit)?;
+        Ok((ret, commit))
+    }
+
+    /// Applies a batch of messages in "soft-fail" mode, for analytics and simulation.
+    ///
+    /// Unlike consensus validation, where a fatal error aborts the whole tipset, a message failing
+    /// with a fatal error here has its effects discarded and is recorded in the returned report,
+    /// and execution continues with the next message. Messages exiting with non-zero exit codes
+    /// are applied as usual, and also recorded.
+    ///
+    /// This only returns an error if the machine is poisoned, in which case no further messages
+    /// can be applied.
+    pub fn execute_messages_soft_fail<I>(&mut self, msgs: I) -> anyhow::Result<SoftFailReport>
+    where
+        I: IntoIterator<Item = (Message, ApplyKind, usize)>,
+    {
+        let mut report = SoftFailReport::default();
+        for (index, (msg, apply_kind, raw_length)) in msgs.into_iter().enumerate() {
+            let checkpoint = self.begin_transaction();
+            let ret = self.execute_message(msg, apply_kind, raw_length);
+            if self.machine.is_none() {
+                return Err(ret
+                    .err()
+                    .unwrap_or_else(|| anyhow!("machine poisoned"))
+                    .context(format!("machine poisoned applying message {index}")));
+            }
+            self.end_transaction(checkpoint, ret.is_ok())?;
+            match ret {
+                Ok(ret) => {
+                    let exit_code = ret.msg_receipt.exit_code;
+                    if !exit_code.is_success() {
+                        report.failures.push(MessageFailure {
+                            index,
+                            reason: FailureReason::Exit(exit_code),
+                        });
+                    }
+                    report.applied.push((index, ret));
+                }
+                Err(e) => {
+                    log::warn!("soft-fail: message {index} failed: {e:#}");
+                    report.failures.push(MessageFailure {
+                        index,
+                        reason: FailureReason::Fatal(e),
+                    });
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Applies a bundle of messages atomically: either every message succeeds and all their
+    /// effects are kept, or the bundle stops at the first message that fails (exits with a
+    /// non-zero exit code or hits a fatal error) and the effects of the entire bundle are
+    /// discarded. This is meant for local devnets and tests exercising interactions that span
+    /// several top-level messages; it's not part of consensus.
+    ///
+    /// As with [`DefaultExecutor::execute_message_tentatively`], the messages of a reverted bundle
+    /// aren't counted when indexing events.
+    ///
+    /// This only returns an error if the machine is poisoned, in which case no further messages
+    /// can be applied.
+    pub fn execute_message_bundle<I>(&mut self, msgs: I) -> anyhow::Result<BundleReport>
+    where
+        I: IntoIterator<Item = (Message, ApplyKind, usize)>,
+    {
+        let mut report = BundleReport::default();
+        let checkpoint = self.begin_transaction();
+        for (index, (msg, apply_kind, raw_length)) in msgs.into_iter().enumerate() {
+            let ret = self.execute_message(msg, apply_kind, raw_length);
+            if self.machine.is_none() {
+                return Err(ret
+                    .err()
+                    .unwrap_or_else(|| anyhow!("machine poisoned"))
+                    .context(format!("machine poisoned applying message {index}")));
+            }
+            match ret {
+                Ok(ret) => {
+                    let exit_code = ret.msg_receipt.exit_code;
+                    report.applied.push(ret);
+                    if !exit_code.is_success() {
+                        report.failure = Some(MessageFailure {
+                            index,
+                            reason: FailureReason::Exit(exit_code),
+                        });
+                        break;
+                    }
+                }
+                Err(e) => {
+                    report.failure = Some(MessageFailure {
+                        index,
+                        reason: FailureReason::Fatal(e),
+                    });
+                    break;
+                }
+            }
+        }
+
+        let commit = report.committed();
+        self.end_transaction(checkpoint, commit)?;
+        Ok(report)
+    }
+
+    /// Estimates the gas limit of an explicit message, e.g., to back a node's gas estimation API.
+    ///
+    /// The message is first executed with the configured maximum gas limit, and the gas it used is
+    /// multiplied by the overestimation factor. If configured, the message is then re-executed with
+    /// the estimated limit to check that it fits. The message's own gas limit is ignored, and the
+    /// gas payer's balance isn't checked (see [`SenderChecks::balance`]). The effects of every
+    /// execution are dropped, as with [`DefaultExecutor::execute_message_tentatively`].
+    pub fn estimate_gas(
+        &mut self,
+        msg: Message,
+        raw_length: usize,
+        config: GasEstimationConfig,
+    ) -> anyhow::Result<GasEstimate> {
+        let checks = self.sender_checks;
+        self.sender_checks.balance = false;
+        let estimate = self.estimate_gas_unchecked(msg, raw_length, config);
+        self.sender_checks = checks;
+        estimate
+    }
+
+    fn estimate_gas_unchecked(
+        &mut self,
+        mut msg: Message,
+        raw_length: usize,
+        config: GasEstimationConfig,
+    ) -> anyhow::Result<GasEstimate> {
+        let overestimate = |gas: u64| {
+            ((gas as f64 * config.overestimation).ceil() as u64).min(config.max_gas_limit)
+        };
+
+        msg.gas_limit = config.max_gas_limit;
+        let (mut ret, _) = self.execute_message_tentatively(
+            msg.clone(),
+            ApplyKind::Explicit,
+            raw_length,
+            |_, _| false,
+        )?;
+        let gas_used = ret.msg_receipt.gas_used;
+        let mut gas_limit = overestimate(gas_used);
+
+        // There's nothing to verify if the message fails regardless of its gas limit.
+        if config.verify && ret.msg_receipt.exit_code.is_success() {
+            loop {
+                msg.gas_limit = gas_limit;
+                (ret, _) = self.execute_message_tentatively(
+                    msg.clone(),
+                    ApplyKind::Explicit,
+                    raw_length,
+                    |_, _| false,
+                )?;
+                if ret.msg_receipt.exit_code != ExitCode::SYS_OUT_OF_GAS {
+                    break;
+                }
+                // Stop once the limit can't be raised any further.
+                let next = overestimate(gas_limit);
+                if next <= gas_limit {
+                    break;
+                }
+                gas_limit = next;
+            }
+        }
+
+        Ok(GasEstimate {
+            gas_limit,
+            gas_used,
+            ret,
         })
     }
 
@@ -325,16 +875,17 @@ where
     }
 
     // TODO: The return type here is very strange because we have three cases:
-    //  1. Continue: Return sender ID, & gas.
+    //  1. Continue: Return sender ID, gas payer ID, & gas.
     //  2. Short-circuit: Return ApplyRet.
     //  3. Fail: Return an error.
     //  We could use custom types, but that would be even more annoying.
     fn preflight_message(
         &mut self,
-        msg: &Message,
+        msg: &mut Message,
         apply_kind: ApplyKind,
         raw_length: usize,
-    ) -> Result<StdResult<(ActorID, TokenAmount, GasCharge), ApplyRet>> {
+        gas_payer: Option<&Address>,
+    ) -> Result<StdResult<(ActorID, ActorID, TokenAmount, GasCharge), ApplyRet>> {
         msg.check().or_fatal()?;
 
         // TODO We don't like having price lists _inside_ the FVM, but passing
@@ -381,7 +932,12 @@ where
         };
 
         if apply_kind == ApplyKind::Implicit {
-            return Ok(Ok((sender_id, TokenAmount::zero(), inclusion_cost)));
+            return Ok(Ok((
+                sender_id,
+                sender_id,
+                TokenAmount::zero(),
+                inclusion_cost,
+            )));
         }
 
         let mut sender_state = match self
@@ -419,7 +975,7 @@ where
             sender_state.code = *self.builtin_actors().get_ethaccount_code();
         }
 
-        if !sender_is_valid {
+        if !sender_is_valid && self.sender_checks.account {
             return Ok(Err(ApplyRet::prevalidation_fail(
                 ExitCode::SYS_SENDER_INVALID,
                 "Send not from valid sender",
@@ -428,7 +984,7 @@ where
         };
 
         // Check sequence is correct
-        if msg.sequence != sender_state.sequence {
+        if msg.sequence != sender_state.sequence && self.sender_checks.nonce {
             return Ok(Err(ApplyRet::prevalidation_fail(
                 ExitCode::SYS_SENDER_STATE_INVALID,
                 format!(
@@ -439,39 +995,112 @@ where
             )));
         };
 
+        // Enforce the embedder's value transfer policy, if any.
+        if let Some(policy) = &self.value_transfer_policy {
+            let receiver_id = self
+                .state_tree()
+                .lookup_id(&msg.to)
+                .with_context(|| format!("failed to lookup actor {}", &msg.to))?;
+            if let Err(violation) = policy.check(sender_id, receiver_id, &msg.value) {
+                // Like any other invalid message, the miner is penalized for including it (so
+                // that rejected messages can't be included for free).
+                return Ok(Err(ApplyRet::rejected(
+                    ExitCode::SYS_SENDER_STATE_INVALID,
+                    ApplyFailure::ValueTransferDenied(violation),
+                    miner_penalty_amount,
+                )));
+            }
+        }
+
         sender_state.sequence += 1;
 
-        // Ensure from actor has enough balance to cover the gas cost of the message.
-        let gas_cost: TokenAmount = msg.gas_fee_cap.clone() * msg.gas_limit;
-        if sender_state.balance < gas_cost {
-            return Ok(Err(ApplyRet::prevalidation_fail(
-                ExitCode::SYS_SENDER_STATE_INVALID,
-                format!(
-                    "Actor balance less than needed: {} < {}",
-                    sender_state.balance, gas_cost
-                ),
-                miner_penalty_amount,
-            )));
+        // Resolve the gas payer, if someone other than the sender is paying.
+        let payer_id = match gas_payer {
+            None => sender_id,
+            Some(payer) => match self
+                .state_tree()
+                .lookup_id(payer)
+                .with_context(|| format!("failed to lookup gas payer {}", payer))?
+            {
+                Some(id) => id,
+                None => {
+                    return Ok(Err(ApplyRet::prevalidation_fail(
+                        ExitCode::SYS_SENDER_INVALID,
+                        "Gas payer invalid",
+                        miner_penalty_amount,
+                    )));
+                }
+            },
+        };
+
+        // Ensure the gas payer has enough balance to cover the gas cost of the message.
+        let payer_balance = if payer_id == sender_id {
+            sender_state.balance.clone()
+        } else {
+            match self
+                .state_tree()
+                .get_actor(payer_id)
+                .with_context(|| format!("failed to lookup gas payer {}", payer_id))?
+            {
+                Some(act) => act.balance,
+                None => {
+                    return Ok(Err(ApplyRet::prevalidation_fail(
+                        ExitCode::SYS_SENDER_INVALID,
+                        "Gas payer invalid",
+                        miner_penalty_amount,
+                    )));
+                }
+            }
+        };
+        let mut gas_cost: TokenAmount = msg.gas_fee_cap.clone() * msg.gas_limit;
+        if payer_balance < gas_cost {
+            if self.sender_checks.balance {
+                let payer = if payer_id == sender_id {
+                    "Actor"
+                } else {
+                    "Gas payer"
+                };
+                return Ok(Err(ApplyRet::prevalidation_fail(
+                    ExitCode::SYS_SENDER_STATE_INVALID,
+                    format!(
+                        "{} balance less than needed: {} < {}",
+                        payer, payer_balance, gas_cost
+                    ),
+                    miner_penalty_amount,
+                )));
+            }
+            // Relaxed: the gas is free. Rather than crediting the payer (and minting funds when
+            // unused gas is refunded), apply the message as if its fee cap and premium were zero.
+            msg.gas_fee_cap = TokenAmount::zero();
+            msg.gas_premium = TokenAmount::zero();
+            gas_cost = TokenAmount::zero();
         }
 
-        sender_state.deduct_funds(&gas_cost)?;
+        if payer_id == sender_id {
+            sender_state.deduct_funds(&gas_cost)?;
+        }
+        // Otherwise, the payer is only charged once it approves the sponsorship, see
+        // apply_message.
 
         // Update the actor in the state tree
         self.state_tree_mut().set_actor(sender_id, sender_state);
 
-        Ok(Ok((sender_id, gas_cost, inclusion_cost)))
+        Ok(Ok((sender_id, payer_id, gas_cost, inclusion_cost)))
     }
 
     #[allow(clippy::too_many_arguments)]
     fn finish_message(
         &mut self,
         sender_id: ActorID,
+        payer_id: ActorID,
         msg: Message,
         receipt: Receipt,
         failure_info: Option<ApplyFailure>,
         gas_cost: TokenAmount,
         exec_trace: ExecutionTrace,
         events: Vec<StampedEvent>,
+        syscall_counts: Option<SyscallCounts>,
+        debug_output: Vec<DebugOutput>,
     ) -> anyhow::Result<ApplyRet> {
         // NOTE: we don't support old network versions in the FVM, so we always burn.
         let GasOutputs {
@@ -510,8 +1139,8 @@ where
 
         transfer_to_actor(BURNT_FUNDS_ACTOR_ID, &over_estimation_burn)?;
 
-        // refund unused gas
-        transfer_to_actor(sender_id, &refund)?;
+        // refund unused gas to whoever paid for it
+        transfer_to_actor(payer_id, &refund)?;
 
         if (&base_fee_burn + &over_estimation_burn + &refund + &miner_tip) != gas_cost {
             // Sanity check. This could be a fatal error.
@@ -526,9 +1155,12 @@ where
             refund,
             gas_refund,
             gas_burned,
+            gas_payer: (payer_id != sender_id).then_some(payer_id),
             failure_info,
             exec_trace,
             events,
+            syscall_counts,
+            debug_output,
         })
     }
 
@@ -548,3 +1180,22 @@ where
         )
     }
 }
+
+/// Deducts the gas cost of a sponsored message from its payer, once the payer has approved.
+fn charge_payer<M: Machine>(
+    machine: &mut M,
+    payer_id: ActorID,
+    gas_cost: &TokenAmount,
+) -> crate::kernel::Result<()> {
+    // The balance was checked in preflight (or the gas made free, if the check is relaxed) and
+    // the approval is read-only, so it can't fall short.
+    machine
+        .state_tree_mut()
+        .mutate_actor(payer_id, |payer| payer.deduct_funds(gas_cost).or_fatal())
+}
+
+/// Computes the CID of an unsigned message.
+fn message_cid(msg: &Message) -> anyhow::Result<Cid> {
+    let bytes = to_vec(msg).map_err(|e| anyhow!("failed to serialize message: {e}"))?;
+    Ok(Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&bytes)))
+}
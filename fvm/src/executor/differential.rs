@@ -0,0 +1,141 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::fmt::{Debug, Display};
+
+use cid::Cid;
+use fvm_shared::message::Message;
+
+use super::{ApplyKind, ApplyRet, Executor};
+
+/// A difference between the results of the two executors of a [`DifferentialExecutor`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the message that diverged (in execution order), or `None` if the state roots
+    /// diverged on flush.
+    pub message_index: Option<usize>,
+    /// What diverged: the `result` (one executor failed), the `receipt` (including gas used), the
+    /// `fees`, the `events`, or the `state root`.
+    pub what: &'static str,
+    /// The primary executor's value, debug-formatted.
+    pub primary: String,
+    /// The secondary executor's value, debug-formatted.
+    pub secondary: String,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.message_index {
+            Some(idx) => write!(f, "message {idx}: ")?,
+            None => write!(f, "flush: ")?,
+        }
+        write!(
+            f,
+            "{} diverged: {} != {}",
+            self.what, self.primary, self.secondary
+        )
+    }
+}
+
+/// An executor applying every message to two executors (e.g., two FVM versions across a network
+/// upgrade, or the FVM and another implementation), recording any divergence in their results:
+/// receipts (exit code, return value, gas used, and events root), fees, events, and, on flush,
+/// state roots.
+///
+/// The two executors must start from the same state. The primary executor's results are returned,
+/// and the secondary's are only compared.
+pub struct DifferentialExecutor<P, S> {
+    primary: P,
+    secondary: S,
+    message_count: usize,
+    divergences: Vec<Divergence>,
+}
+
+impl<P, S> DifferentialExecutor<P, S> {
+    pub fn new(primary: P, secondary: S) -> Self {
+        DifferentialExecutor {
+            primary,
+            secondary,
+            message_count: 0,
+            divergences: Vec::new(),
+        }
+    }
+
+    /// Returns the divergences recorded so far.
+    pub fn divergences(&self) -> &[Divergence] {
+        &self.divergences
+    }
+
+    /// Returns and clears the divergences recorded so far.
+    pub fn take_divergences(&mut self) -> Vec<Divergence> {
+        std::mem::take(&mut self.divergences)
+    }
+
+    /// Returns the two executors.
+    pub fn into_inner(self) -> (P, S) {
+        (self.primary, self.secondary)
+    }
+
+    fn compare<T: PartialEq + Debug>(
+        &mut self,
+        message_index: Option<usize>,
+        what: &'static str,
+        primary: &T,
+        secondary: &T,
+    ) {
+        if primary != secondary {
+            self.divergences.push(Divergence {
+                message_index,
+                what,
+                primary: format!("{primary:?}"),
+                secondary: format!("{secondary:?}"),
+            });
+        }
+    }
+}
+
+impl<P, S> Executor for DifferentialExecutor<P, S>
+where
+    P: Executor,
+    S: Executor,
+{
+    type Kernel = P::Kernel;
+
+    fn execute_message(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> anyhow::Result<ApplyRet> {
+        let idx = Some(self.message_count);
+        self.message_count += 1;
+
+        let secondary = self
+            .secondary
+            .execute_message(msg.clone(), apply_kind, raw_length);
+        let primary = self.primary.execute_message(msg, apply_kind, raw_length);
+
+        match (&primary, &secondary) {
+            (Ok(p), Ok(s)) => {
+                self.compare(idx, "receipt", &p.msg_receipt, &s.msg_receipt);
+                self.compare(idx, "fees", &p.fees(), &s.fees());
+                self.compare(idx, "events", &p.events, &s.events);
+            }
+            // Both failed: there's nothing to compare, the error is returned.
+            (Err(_), Err(_)) => {}
+            (p, s) => self.compare(
+                idx,
+                "result",
+                &p.as_ref().map(|_| ()).map_err(|e| e.to_string()),
+                &s.as_ref().map(|_| ()).map_err(|e| e.to_string()),
+            ),
+        }
+        primary
+    }
+
+    fn flush(&mut self) -> anyhow::Result<Cid> {
+        let primary = self.primary.flush()?;
+        let secondary = self.secondary.flush()?;
+        self.compare(None, "state root", &primary, &secondary);
+        Ok(primary)
+    }
+}
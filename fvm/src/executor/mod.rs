@@ -1,22 +1,28 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 mod default;
+mod differential;
 mod threaded;
 
+use std::collections::HashMap;
 use std::fmt::Display;
 
 use cid::Cid;
 pub use default::DefaultExecutor;
+pub use differential::{DifferentialExecutor, Divergence};
 use fvm_ipld_encoding::RawBytes;
+use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::event::StampedEvent;
 use fvm_shared::message::Message;
 use fvm_shared::receipt::Receipt;
+use fvm_shared::{ActorID, BLOCK_GAS_LIMIT};
 use num_traits::Zero;
 pub use threaded::ThreadedExecutor;
 
-use crate::call_manager::Backtrace;
+use crate::call_manager::{Backtrace, DebugOutput};
+use crate::gas::SyscallCounts;
 use crate::trace::ExecutionTrace;
 use crate::Kernel;
 
@@ -46,6 +52,121 @@ pub trait Executor {
     fn flush(&mut self) -> anyhow::Result<Cid>;
 }
 
+/// A third party paying for the gas of a message. See
+/// [`DefaultExecutor::execute_sponsored_message`].
+#[derive(Clone, Debug)]
+pub struct GasSponsor {
+    /// The actor paying for gas.
+    pub payer: Address,
+    /// Opaque approval data passed to the payer's
+    /// [`AUTHORIZE_GAS_SPONSORSHIP_METHOD`][fvm_shared::message::AUTHORIZE_GAS_SPONSORSHIP_METHOD]
+    /// (e.g., its signature over the message CID).
+    pub approval: RawBytes,
+}
+
+/// Per-actor ceilings on the value a single explicit message may transfer, configured by the
+/// embedder (e.g., for compliance-constrained private networks). See
+/// [`DefaultExecutor::with_value_transfer_policy`].
+///
+/// Implicit messages are never subject to this policy.
+#[derive(Clone, Debug, Default)]
+pub struct ValueTransferPolicy {
+    /// The maximum value a message from each sender may transfer.
+    pub sender_caps: HashMap<ActorID, TokenAmount>,
+    /// The maximum value a message to each receiver may transfer.
+    pub receiver_caps: HashMap<ActorID, TokenAmount>,
+}
+
+impl ValueTransferPolicy {
+    /// Caps the value any single message from `sender` may transfer.
+    pub fn cap_sender(mut self, sender: ActorID, cap: TokenAmount) -> Self {
+        self.sender_caps.insert(sender, cap);
+        self
+    }
+
+    /// Caps the value any single message to `receiver` may transfer.
+    pub fn cap_receiver(mut self, receiver: ActorID, cap: TokenAmount) -> Self {
+        self.receiver_caps.insert(receiver, cap);
+        self
+    }
+
+    /// Checks a transfer of `value` from `sender` to `receiver` (if the receiver exists) against
+    /// the configured caps.
+    pub fn check(
+        &self,
+        sender: ActorID,
+        receiver: Option<ActorID>,
+        value: &TokenAmount,
+    ) -> Result<(), ValueTransferViolation> {
+        if let Some(cap) = self.sender_caps.get(&sender).filter(|cap| value > *cap) {
+            return Err(ValueTransferViolation::SenderCapExceeded {
+                sender,
+                value: value.clone(),
+                cap: cap.clone(),
+            });
+        }
+        if let Some((receiver, cap)) = receiver
+            .and_then(|id| Some((id, self.receiver_caps.get(&id)?)))
+            .filter(|(_, cap)| value > *cap)
+        {
+            return Err(ValueTransferViolation::ReceiverCapExceeded {
+                receiver,
+                value: value.clone(),
+                cap: cap.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The checks made on the sender of an explicit message before it's applied, configured by the
+/// embedder. See [`DefaultExecutor::with_sender_checks`]. All checks are enabled by default.
+///
+/// Relaxing checks is only meant for off-chain execution, e.g., to estimate the gas of a message
+/// before its sender is funded, or to simulate pending messages in a message pool out of order.
+/// Messages applied with relaxed checks may not be valid on chain. The sender must always exist,
+/// and implicit messages are never checked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SenderChecks {
+    /// Check that the sender is an account (or Ethereum account) actor. Otherwise, any actor may
+    /// send messages.
+    pub account: bool,
+    /// Check that the message's sequence is the sender's next nonce. Otherwise, the sender's nonce
+    /// is bumped regardless of the message's sequence.
+    pub nonce: bool,
+    /// Check that the gas payer's balance covers the message's gas limit at its fee cap.
+    /// Otherwise, a message whose payer can't cover it is applied as if its fee cap and premium
+    /// were zero: its gas is free, and the payer's balance is left untouched.
+    pub balance: bool,
+}
+
+impl Default for SenderChecks {
+    fn default() -> Self {
+        SenderChecks {
+            account: true,
+            nonce: true,
+            balance: true,
+        }
+    }
+}
+
+/// A message rejected by a [`ValueTransferPolicy`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ValueTransferViolation {
+    #[error("value {value} exceeds the cap of {cap} for sender {sender}")]
+    SenderCapExceeded {
+        sender: ActorID,
+        value: TokenAmount,
+        cap: TokenAmount,
+    },
+    #[error("value {value} exceeds the cap of {cap} for receiver {receiver}")]
+    ReceiverCapExceeded {
+        receiver: ActorID,
+        value: TokenAmount,
+        cap: TokenAmount,
+    },
+}
+
 /// A description of some failure encountered when applying a message.
 #[derive(Debug, Clone)]
 pub enum ApplyFailure {
@@ -53,6 +174,8 @@ pub enum ApplyFailure {
     MessageBacktrace(Backtrace),
     /// A message describing a pre-validation failure.
     PreValidation(String),
+    /// The message was rejected by the executor's [`ValueTransferPolicy`].
+    ValueTransferDenied(ValueTransferViolation),
 }
 
 impl Display for ApplyFailure {
@@ -65,6 +188,9 @@ impl Display for ApplyFailure {
             ApplyFailure::PreValidation(msg) => {
                 writeln!(f, "pre-validation failed: {}", msg)?;
             }
+            ApplyFailure::ValueTransferDenied(violation) => {
+                writeln!(f, "value transfer denied: {}", violation)?;
+            }
         }
         Ok(())
     }
@@ -86,6 +212,9 @@ pub struct ApplyRet {
     pub refund: TokenAmount,
     pub gas_refund: u64,
     pub gas_burned: u64,
+    /// The actor that paid for the message's gas (and received the refund), if other than the
+    /// sender. See [`DefaultExecutor::execute_sponsored_message`].
+    pub gas_payer: Option<ActorID>,
 
     /// Additional failure information for debugging, if any.
     pub failure_info: Option<ApplyFailure>,
@@ -93,6 +222,12 @@ pub struct ApplyRet {
     pub exec_trace: ExecutionTrace,
     /// Events generated while applying the message.
     pub events: Vec<StampedEvent>,
+    /// The number of calls to each syscall made while applying the message, if the
+    /// [syscall census](crate::machine::MachineContext::syscall_census) is enabled.
+    pub syscall_counts: Option<SyscallCounts>,
+    /// The logs and artifacts emitted by actors while applying the message, if
+    /// [actor debugging](crate::machine::NetworkConfig::actor_debugging) is enabled.
+    pub debug_output: Vec<DebugOutput>,
 }
 
 impl ApplyRet {
@@ -101,6 +236,19 @@ impl ApplyRet {
         code: ExitCode,
         message: impl Into<String>,
         miner_penalty: TokenAmount,
+    ) -> ApplyRet {
+        Self::rejected(
+            code,
+            ApplyFailure::PreValidation(message.into()),
+            miner_penalty,
+        )
+    }
+
+    /// Returns the result of a message rejected before execution for the given reason.
+    pub(crate) fn rejected(
+        code: ExitCode,
+        failure: ApplyFailure,
+        miner_penalty: TokenAmount,
     ) -> ApplyRet {
         ApplyRet {
             msg_receipt: Receipt {
@@ -116,11 +264,169 @@ impl ApplyRet {
             refund: TokenAmount::zero(),
             gas_refund: 0,
             gas_burned: 0,
-            failure_info: Some(ApplyFailure::PreValidation(message.into())),
+            gas_payer: None,
+            failure_info: Some(failure),
             exec_trace: vec![],
             events: vec![],
+            syscall_counts: None,
+            debug_output: vec![],
         }
     }
+
+    /// The fees paid for the message, broken down by destination.
+    pub fn fees(&self) -> MessageFees {
+        MessageFees {
+            base_fee_burn: self.base_fee_burn.clone(),
+            over_estimation_burn: self.over_estimation_burn.clone(),
+            miner_tip: self.miner_tip.clone(),
+            refund: self.refund.clone(),
+            miner_penalty: self.penalty.clone(),
+        }
+    }
+
+    /// The root of the AMT of events emitted by the message, if any, as recorded in the receipt.
+    pub fn events_root(&self) -> Option<Cid> {
+        self.msg_receipt.events_root
+    }
+}
+
+/// The fees paid for a message (see [`ApplyRet::fees`]).
+///
+/// The gas payer locks `gas_limit * gas_fee_cap` up-front. That's split between the base fee burn,
+/// the over-estimation burn, the miner tip, and the refund returned to the payer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MessageFees {
+    /// Burnt for the gas used, at the base fee (capped at the fee cap).
+    pub base_fee_burn: TokenAmount,
+    /// Burnt for over-estimating the gas limit.
+    pub over_estimation_burn: TokenAmount,
+    /// Paid to the miner that included the message.
+    pub miner_tip: TokenAmount,
+    /// Returned to the gas payer.
+    pub refund: TokenAmount,
+    /// Charged to the miner that included the message, not to the gas payer (e.g., for messages
+    /// failing validation, or for a base fee above the fee cap).
+    pub miner_penalty: TokenAmount,
+}
+
+impl MessageFees {
+    /// The total amount burnt by the gas payer.
+    pub fn total_burned(&self) -> TokenAmount {
+        &self.base_fee_burn + &self.over_estimation_burn
+    }
+
+    /// The total amount the gas payer paid for the message (burns and miner tip), net of the
+    /// refund.
+    pub fn total_paid(&self) -> TokenAmount {
+        self.total_burned() + &self.miner_tip
+    }
+}
+
+/// The events emitted by a single message, as retained by the executor when event indexing is
+/// enabled (see [`MachineContext::index_events`](crate::machine::MachineContext::index_events)).
+///
+/// This is laid out for direct storage in an events index: events are keyed by the CID of the
+/// message that emitted them, and by the message's position in the tipset.
+#[derive(Clone, Debug)]
+pub struct MessageEvents {
+    /// The CID of the (unsigned) message that emitted these events.
+    ///
+    /// NOTE: Secp256k1-signed messages are identified on-chain by the CID of the _signed_ message.
+    /// Embedders indexing such messages should re-key these entries accordingly.
+    pub message_cid: Cid,
+    /// The index of the message among the explicit messages of the tipset (counting every
+    /// explicit message whose effects were kept, including those that emitted no events).
+    /// Implicit messages (e.g., cron) aren't counted: their index is the number of explicit
+    /// messages applied before them.
+    pub message_index: u64,
+    /// The root of the events AMT, as recorded in the message receipt.
+    pub events_root: Cid,
+    /// The events, in emission order, stamped with the ID of the emitting actor.
+    pub events: Vec<StampedEvent>,
+}
+
+/// The outcome of applying a batch of messages in "soft-fail" mode (see
+/// [`DefaultExecutor::execute_messages_soft_fail`]).
+#[derive(Debug, Default)]
+pub struct SoftFailReport {
+    /// The results of the messages that were applied (whether or not they succeeded), keyed by
+    /// their index in the batch.
+    pub applied: Vec<(usize, ApplyRet)>,
+    /// The messages that failed, in batch order.
+    pub failures: Vec<MessageFailure>,
+}
+
+/// The outcome of applying an atomic bundle of messages (see
+/// [`DefaultExecutor::execute_message_bundle`]).
+#[derive(Debug, Default)]
+pub struct BundleReport {
+    /// The results of the messages that were applied, in bundle order. If the bundle was reverted,
+    /// this includes the message that failed (unless it failed with a fatal error).
+    pub applied: Vec<ApplyRet>,
+    /// The first message that failed, if any, in which case none of the bundle's effects were
+    /// kept.
+    pub failure: Option<MessageFailure>,
+}
+
+impl BundleReport {
+    /// Returns true if every message in the bundle succeeded and its effects were kept.
+    pub fn committed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// How [`DefaultExecutor::estimate_gas`] estimates the gas limit of a message.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GasEstimationConfig {
+    /// The factor the gas used is multiplied by to get the estimated gas limit, leaving some
+    /// headroom for state changes between estimation and inclusion.
+    pub overestimation: f64,
+    /// The gas limit the message is first executed with, and the highest limit ever estimated.
+    pub max_gas_limit: u64,
+    /// Whether to re-execute the message with the estimated gas limit to check that it fits,
+    /// raising the limit by the overestimation factor until it does (or reaches the maximum).
+    pub verify: bool,
+}
+
+impl Default for GasEstimationConfig {
+    fn default() -> Self {
+        GasEstimationConfig {
+            overestimation: 1.25,
+            max_gas_limit: BLOCK_GAS_LIMIT,
+            verify: true,
+        }
+    }
+}
+
+/// The outcome of [`DefaultExecutor::estimate_gas`].
+#[derive(Clone, Debug)]
+pub struct GasEstimate {
+    /// The estimated gas limit.
+    pub gas_limit: u64,
+    /// The gas used by the message when executed with the maximum gas limit.
+    pub gas_used: u64,
+    /// The result of the last execution of the message: with the estimated gas limit if the
+    /// estimate was verified, otherwise with the maximum gas limit. Check its exit code: a message
+    /// that fails regardless of its gas limit still gets an estimate.
+    pub ret: ApplyRet,
+}
+
+/// A message that failed while applying a batch in "soft-fail" mode, or an atomic bundle.
+#[derive(Debug)]
+pub struct MessageFailure {
+    /// The index of the message in the batch.
+    pub index: usize,
+    /// Why the message failed.
+    pub reason: FailureReason,
+}
+
+/// The reason a message in a batch failed.
+#[derive(Debug)]
+pub enum FailureReason {
+    /// The message was applied, but exited with a non-zero exit code.
+    Exit(ExitCode),
+    /// The message couldn't be applied due to a fatal error. Its effects were discarded.
+    Fatal(anyhow::Error),
 }
 
 /// The kind of message being applied:
@@ -134,3 +440,74 @@ pub enum ApplyKind {
     Explicit,
     Implicit,
 }
+
+#[cfg(test)]
+mod tests {
+    use fvm_shared::econ::TokenAmount;
+
+    use super::{ApplyRet, MessageFees, ValueTransferPolicy, ValueTransferViolation};
+
+    #[test]
+    fn value_transfer_policy() {
+        let policy = ValueTransferPolicy::default()
+            .cap_sender(100, TokenAmount::from_atto(10))
+            .cap_receiver(200, TokenAmount::from_atto(5));
+
+        // Uncapped actors may transfer anything.
+        assert!(policy
+            .check(101, Some(201), &TokenAmount::from_atto(1000))
+            .is_ok());
+        assert!(policy
+            .check(101, None, &TokenAmount::from_atto(1000))
+            .is_ok());
+
+        // Caps are inclusive.
+        assert!(policy
+            .check(100, Some(201), &TokenAmount::from_atto(10))
+            .is_ok());
+        assert_eq!(
+            policy.check(100, Some(201), &TokenAmount::from_atto(11)),
+            Err(ValueTransferViolation::SenderCapExceeded {
+                sender: 100,
+                value: TokenAmount::from_atto(11),
+                cap: TokenAmount::from_atto(10),
+            })
+        );
+        assert_eq!(
+            policy.check(100, Some(200), &TokenAmount::from_atto(6)),
+            Err(ValueTransferViolation::ReceiverCapExceeded {
+                receiver: 200,
+                value: TokenAmount::from_atto(6),
+                cap: TokenAmount::from_atto(5),
+            })
+        );
+    }
+
+    #[test]
+    fn message_fees() {
+        let mut ret = ApplyRet::prevalidation_fail(
+            fvm_shared::error::ExitCode::OK,
+            "",
+            TokenAmount::from_atto(7),
+        );
+        ret.base_fee_burn = TokenAmount::from_atto(100);
+        ret.over_estimation_burn = TokenAmount::from_atto(10);
+        ret.miner_tip = TokenAmount::from_atto(5);
+        ret.refund = TokenAmount::from_atto(20);
+
+        let fees = ret.fees();
+        assert_eq!(
+            fees,
+            MessageFees {
+                base_fee_burn: TokenAmount::from_atto(100),
+                over_estimation_burn: TokenAmount::from_atto(10),
+                miner_tip: TokenAmount::from_atto(5),
+                refund: TokenAmount::from_atto(20),
+                miner_penalty: TokenAmount::from_atto(7),
+            }
+        );
+        assert_eq!(fees.total_burned(), TokenAmount::from_atto(110));
+        assert_eq!(fees.total_paid(), TokenAmount::from_atto(115));
+        assert_eq!(ret.events_root(), None);
+    }
+}
@@ -21,8 +21,13 @@ lazy_static! {
     );
 }
 
-/// An executor that executes messages on a separate thread with a 64MiB stack. If you can guarantee
-/// at least 64MiB of stack space, you don't need this executor.
+/// An executor that executes messages on a separate thread with a 64MiB stack.
+///
+/// This isn't needed to avoid overflowing the host stack: executors allocate additional stack
+/// segments on demand when the current stack runs low (see [`DefaultExecutor`]). It only avoids
+/// the cost of allocating those segments when executing on threads with small stacks.
+///
+/// [`DefaultExecutor`]: super::DefaultExecutor
 pub struct ThreadedExecutor<E>(pub E);
 
 impl<E> Executor for ThreadedExecutor<E>
@@ -0,0 +1,161 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use cid::Cid;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::consensus::ConsensusFault;
+use fvm_shared::randomness::Randomness;
+
+use super::{Chain, Consensus, Externs, ProofsBackend, Rand};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum RandomnessKind {
+    Chain,
+    Beacon,
+    BeaconBytes,
+}
+
+/// Wraps a node's externs, caching randomness lookups for the lifetime of the wrapper.
+///
+/// Randomness at a given epoch never changes, but actors frequently request it from the same few
+/// epochs, and each lookup may be an expensive round-trip to the node (or to a drand client).
+/// Wrap the externs passed to a machine with this to make each distinct lookup only once per
+/// machine. Errors are not cached.
+///
+/// All other externs are forwarded as is.
+pub struct CachingRand<E> {
+    inner: E,
+    cache: Mutex<BTreeMap<(RandomnessKind, ChainEpoch), Vec<u8>>>,
+}
+
+impl<E> CachingRand<E> {
+    pub fn new(inner: E) -> Self {
+        CachingRand {
+            inner,
+            cache: Mutex::default(),
+        }
+    }
+
+    /// Returns the wrapped externs.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    fn get_or_fetch(
+        &self,
+        kind: RandomnessKind,
+        round: ChainEpoch,
+        fetch: impl FnOnce() -> anyhow::Result<Vec<u8>>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let key = (kind, round);
+        if let Some(rand) = self.cache.lock().unwrap().get(&key) {
+            return Ok(rand.clone());
+        }
+        // Don't hold the lock while fetching: the fetch may be slow.
+        let rand = fetch()?;
+        self.cache.lock().unwrap().insert(key, rand.clone());
+        Ok(rand)
+    }
+}
+
+impl<E: Rand> Rand for CachingRand<E> {
+    fn get_chain_randomness(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+        let rand = self.get_or_fetch(RandomnessKind::Chain, round, || {
+            self.inner.get_chain_randomness(round).map(Vec::from)
+        })?;
+        Ok(rand
+            .try_into()
+            .expect("cached chain randomness is 32 bytes"))
+    }
+
+    fn get_beacon_randomness(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+        let rand = self.get_or_fetch(RandomnessKind::Beacon, round, || {
+            self.inner.get_beacon_randomness(round).map(Vec::from)
+        })?;
+        Ok(rand
+            .try_into()
+            .expect("cached beacon randomness is 32 bytes"))
+    }
+
+    fn get_beacon_randomness_bytes(&self, round: ChainEpoch) -> anyhow::Result<Randomness> {
+        self.get_or_fetch(RandomnessKind::BeaconBytes, round, || {
+            self.inner.get_beacon_randomness_bytes(round).map(|r| r.0)
+        })
+        .map(Randomness)
+    }
+}
+
+impl<E: Consensus> Consensus for CachingRand<E> {
+    fn verify_consensus_fault(
+        &self,
+        h1: &[u8],
+        h2: &[u8],
+        extra: &[u8],
+    ) -> anyhow::Result<(Option<ConsensusFault>, i64)> {
+        self.inner.verify_consensus_fault(h1, h2, extra)
+    }
+}
+
+impl<E: Chain> Chain for CachingRand<E> {
+    fn get_tipset_cid(&self, epoch: ChainEpoch) -> anyhow::Result<Cid> {
+        self.inner.get_tipset_cid(epoch)
+    }
+}
+
+impl<E: Externs> Externs for CachingRand<E> {
+    fn proofs_backend(&self) -> &dyn ProofsBackend {
+        self.inner.proofs_backend()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use fvm_shared::clock::ChainEpoch;
+
+    use super::CachingRand;
+    use crate::externs::Rand;
+
+    #[derive(Default)]
+    struct CountingRand {
+        calls: Cell<usize>,
+    }
+
+    impl Rand for CountingRand {
+        fn get_chain_randomness(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+            self.calls.set(self.calls.get() + 1);
+            if round < 0 {
+                return Err(anyhow::anyhow!("negative epoch"));
+            }
+            Ok([round as u8; 32])
+        }
+
+        fn get_beacon_randomness(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+            self.calls.set(self.calls.get() + 1);
+            Ok([!(round as u8); 32])
+        }
+    }
+
+    #[test]
+    fn caches_randomness_by_kind_and_epoch() {
+        let rand = CachingRand::new(CountingRand::default());
+
+        assert_eq!(rand.get_chain_randomness(1).unwrap(), [1; 32]);
+        assert_eq!(rand.get_chain_randomness(1).unwrap(), [1; 32]);
+        assert_eq!(rand.inner.calls.get(), 1);
+
+        // Different epochs and kinds are cached separately.
+        assert_eq!(rand.get_chain_randomness(2).unwrap(), [2; 32]);
+        assert_eq!(rand.get_beacon_randomness(1).unwrap(), [!1; 32]);
+        assert_eq!(rand.get_beacon_randomness(1).unwrap(), [!1; 32]);
+        assert_eq!(rand.inner.calls.get(), 3);
+
+        // Errors aren't cached.
+        assert!(rand.get_chain_randomness(-1).is_err());
+        assert!(rand.get_chain_randomness(-1).is_err());
+        assert_eq!(rand.inner.calls.get(), 5);
+    }
+}
@@ -3,10 +3,72 @@
 //! This module contains the logic to invoke the node by traversing Boundary A.
 
 use cid::Cid;
+use futures::future::BoxFuture;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::consensus::ConsensusFault;
+use fvm_shared::randomness::Randomness;
+use fvm_shared::sector::{
+    AggregateSealVerifyProofAndInfos, ReplicaUpdateInfo, SealVerifyInfo, WindowPoStVerifyInfo,
+};
 
-pub trait Externs: Rand + Consensus + Chain {}
+use crate::kernel::filecoin::SyncProofsBackend;
+use crate::kernel::Result;
+
+mod cache;
+mod replay;
+
+pub use cache::CachingRand;
+pub use replay::{ExternLookup, RecordingExterns, ReplayLog, ReplayingExterns};
+
+pub trait Externs: Rand + Consensus + Chain {
+    /// Returns the backend seal and PoSt proofs are verified with.
+    ///
+    /// Defaults to [`SyncProofsBackend`], which verifies proofs in-process when the kernel awaits
+    /// them, on the calling thread (or the machine's
+    /// [proof pool](crate::machine::Machine::proof_pool)).
+    fn proofs_backend(&self) -> &dyn ProofsBackend {
+        &SyncProofsBackend
+    }
+}
+
+/// Verifies seal and PoSt proofs on behalf of the kernel, e.g., by dispatching them to a dedicated
+/// thread pool or an external verification service.
+///
+/// Each method returns a future resolving to the verification result. The kernel always observes
+/// results in input order, so execution stays deterministic regardless of where or when the work
+/// completes.
+///
+/// The kernel waits on these futures with a minimal blocking executor
+/// ([`futures::executor::block_on`]): single proofs on the executor thread, batches on the threads
+/// of the machine's [proof pool](crate::machine::Machine::proof_pool) (with bounded concurrency).
+/// The futures are therefore not polled from within any particular async runtime, and each one
+/// occupies its thread until it completes. A backend built on an async runtime (e.g., tokio)
+/// should spawn the work onto that runtime and return a future that merely waits for the result
+/// (e.g., on a oneshot channel).
+///
+/// Verification is consensus-critical: a backend must return the same results as
+/// [`SyncProofsBackend`]. Specifically, it must return `Ok(false)` or an `IllegalArgument` syscall
+/// error for invalid proofs, and a fatal error if it fails to produce a result (e.g., because the
+/// service is unreachable). Batch verification aborts the message on any other error.
+pub trait ProofsBackend: Send + Sync {
+    /// Verifies a seal proof.
+    fn verify_seal<'a>(&'a self, info: &'a SealVerifyInfo) -> BoxFuture<'a, Result<bool>>;
+
+    /// Verifies a window proof of spacetime.
+    fn verify_post<'a>(&'a self, info: &'a WindowPoStVerifyInfo) -> BoxFuture<'a, Result<bool>>;
+
+    /// Verifies an aggregated batch of seal proofs.
+    fn verify_aggregate_seals<'a>(
+        &'a self,
+        aggregate: &'a AggregateSealVerifyProofAndInfos,
+    ) -> BoxFuture<'a, Result<bool>>;
+
+    /// Verifies a replica update (snap deal) proof.
+    fn verify_replica_update<'a>(
+        &'a self,
+        replica: &'a ReplicaUpdateInfo,
+    ) -> BoxFuture<'a, Result<bool>>;
+}
 
 /// Consensus related methods.
 pub trait Consensus {
@@ -28,6 +90,16 @@ pub trait Rand {
     /// Gets 32 bytes of randomness for ChainRand paramaterized by the DomainSeparationTag,
     /// ChainEpoch, Entropy from the latest beacon entry.
     fn get_beacon_randomness(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]>;
+
+    /// Gets the randomness from the latest beacon entry at the given round, at its native length
+    /// (see [`beacon_randomness_length`](fvm_shared::randomness::beacon_randomness_length)).
+    ///
+    /// Defaults to the 32 bytes returned by [`Rand::get_beacon_randomness`]. Nodes only need to
+    /// implement this once the beacon returns randomness of a different length.
+    fn get_beacon_randomness_bytes(&self, round: ChainEpoch) -> anyhow::Result<Randomness> {
+        self.get_beacon_randomness(round)
+            .map(|rand| Randomness(rand.to_vec()))
+    }
 }
 
 /// Chain information provider.
@@ -0,0 +1,318 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context as _};
+use cid::Cid;
+use fvm_ipld_encoding::strict_bytes;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::consensus::{ConsensusFault, ConsensusFaultType};
+use fvm_shared::randomness::Randomness;
+use num_traits::FromPrimitive;
+use serde::{Deserialize, Serialize};
+
+use super::{Chain, Consensus, Externs, ProofsBackend, Rand};
+
+/// A single extern lookup made during execution, along with its result.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExternLookup {
+    ChainRandomness {
+        epoch: ChainEpoch,
+        #[serde(with = "strict_bytes")]
+        randomness: [u8; 32],
+    },
+    BeaconRandomness {
+        epoch: ChainEpoch,
+        #[serde(with = "strict_bytes")]
+        randomness: [u8; 32],
+    },
+    BeaconRandomnessBytes {
+        epoch: ChainEpoch,
+        #[serde(with = "strict_bytes")]
+        randomness: Vec<u8>,
+    },
+    TipsetCid {
+        epoch: ChainEpoch,
+        cid: Cid,
+    },
+    ConsensusFault {
+        #[serde(with = "strict_bytes")]
+        h1: Vec<u8>,
+        #[serde(with = "strict_bytes")]
+        h2: Vec<u8>,
+        #[serde(with = "strict_bytes")]
+        extra: Vec<u8>,
+        /// The fault's target, epoch, and type, if a fault was found.
+        fault: Option<(Address, ChainEpoch, u8)>,
+        gas_used: i64,
+    },
+}
+
+/// The extern lookups made while executing messages, in the order they were made. Recorded with
+/// [`RecordingExterns`] and replayed with [`ReplayingExterns`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ReplayLog(pub Vec<ExternLookup>);
+
+impl ReplayLog {
+    /// Decodes a log from CBOR.
+    pub fn from_cbor(bytes: &[u8]) -> anyhow::Result<Self> {
+        fvm_ipld_encoding::from_slice(bytes).context("failed to decode replay log")
+    }
+
+    /// Encodes the log as CBOR.
+    pub fn to_cbor(&self) -> anyhow::Result<Vec<u8>> {
+        fvm_ipld_encoding::to_vec(self).context("failed to encode replay log")
+    }
+
+    fn find<T>(&self, f: impl FnMut(&ExternLookup) -> Option<T>) -> Option<T> {
+        self.0.iter().find_map(f)
+    }
+}
+
+/// Wraps a node's externs, recording every lookup into a [`ReplayLog`].
+///
+/// Record the externs of a machine executing a failing message (e.g., on a mainnet node) to later
+/// reproduce the execution exactly, without the node, with [`ReplayingExterns`]. Failed lookups
+/// aren't recorded.
+pub struct RecordingExterns<E> {
+    inner: E,
+    log: Mutex<ReplayLog>,
+}
+
+impl<E> RecordingExterns<E> {
+    pub fn new(inner: E) -> Self {
+        RecordingExterns {
+            inner,
+            log: Mutex::default(),
+        }
+    }
+
+    /// Returns the lookups recorded so far.
+    pub fn log(&self) -> ReplayLog {
+        self.log.lock().unwrap().clone()
+    }
+
+    fn record<T>(
+        &self,
+        res: anyhow::Result<T>,
+        lookup: impl FnOnce(&T) -> ExternLookup,
+    ) -> anyhow::Result<T> {
+        if let Ok(v) = &res {
+            self.log.lock().unwrap().0.push(lookup(v));
+        }
+        res
+    }
+}
+
+impl<E: Rand> Rand for RecordingExterns<E> {
+    fn get_chain_randomness(&self, epoch: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+        self.record(self.inner.get_chain_randomness(epoch), |r| {
+            ExternLookup::ChainRandomness {
+                epoch,
+                randomness: *r,
+            }
+        })
+    }
+
+    fn get_beacon_randomness(&self, epoch: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+        self.record(self.inner.get_beacon_randomness(epoch), |r| {
+            ExternLookup::BeaconRandomness {
+                epoch,
+                randomness: *r,
+            }
+        })
+    }
+
+    fn get_beacon_randomness_bytes(&self, epoch: ChainEpoch) -> anyhow::Result<Randomness> {
+        self.record(self.inner.get_beacon_randomness_bytes(epoch), |r| {
+            ExternLookup::BeaconRandomnessBytes {
+                epoch,
+                randomness: r.0.clone(),
+            }
+        })
+    }
+}
+
+impl<E: Consensus> Consensus for RecordingExterns<E> {
+    fn verify_consensus_fault(
+        &self,
+        h1: &[u8],
+        h2: &[u8],
+        extra: &[u8],
+    ) -> anyhow::Result<(Option<ConsensusFault>, i64)> {
+        self.record(
+            self.inner.verify_consensus_fault(h1, h2, extra),
+            |(fault, gas_used)| ExternLookup::ConsensusFault {
+                h1: h1.to_vec(),
+                h2: h2.to_vec(),
+                extra: extra.to_vec(),
+                fault: fault
+                    .as_ref()
+                    .map(|f| (f.target, f.epoch, f.fault_type as u8)),
+                gas_used: *gas_used,
+            },
+        )
+    }
+}
+
+impl<E: Chain> Chain for RecordingExterns<E> {
+    fn get_tipset_cid(&self, epoch: ChainEpoch) -> anyhow::Result<Cid> {
+        self.record(self.inner.get_tipset_cid(epoch), |cid| {
+            ExternLookup::TipsetCid { epoch, cid: *cid }
+        })
+    }
+}
+
+impl<E: Externs> Externs for RecordingExterns<E> {
+    fn proofs_backend(&self) -> &dyn ProofsBackend {
+        self.inner.proofs_backend()
+    }
+}
+
+/// Externs answering lookups from a [`ReplayLog`], to reproduce a recorded execution exactly.
+///
+/// Lookups that weren't recorded fail.
+pub struct ReplayingExterns {
+    log: ReplayLog,
+}
+
+impl ReplayingExterns {
+    pub fn new(log: ReplayLog) -> Self {
+        ReplayingExterns { log }
+    }
+}
+
+impl Rand for ReplayingExterns {
+    fn get_chain_randomness(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+        self.log
+            .find(|l| match l {
+                ExternLookup::ChainRandomness { epoch, randomness } if *epoch == round => {
+                    Some(*randomness)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| anyhow!("no chain randomness recorded for epoch {round}"))
+    }
+
+    fn get_beacon_randomness(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+        self.log
+            .find(|l| match l {
+                ExternLookup::BeaconRandomness { epoch, randomness } if *epoch == round => {
+                    Some(*randomness)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| anyhow!("no beacon randomness recorded for epoch {round}"))
+    }
+
+    fn get_beacon_randomness_bytes(&self, round: ChainEpoch) -> anyhow::Result<Randomness> {
+        self.log
+            .find(|l| match l {
+                ExternLookup::BeaconRandomnessBytes { epoch, randomness } if *epoch == round => {
+                    Some(Randomness(randomness.clone()))
+                }
+                _ => None,
+            })
+            .ok_or_else(|| anyhow!("no beacon randomness recorded for epoch {round}"))
+    }
+}
+
+impl Consensus for ReplayingExterns {
+    fn verify_consensus_fault(
+        &self,
+        h1: &[u8],
+        h2: &[u8],
+        extra: &[u8],
+    ) -> anyhow::Result<(Option<ConsensusFault>, i64)> {
+        let (fault, gas_used) = self
+            .log
+            .find(|l| match l {
+                ExternLookup::ConsensusFault {
+                    h1: rh1,
+                    h2: rh2,
+                    extra: rextra,
+                    fault,
+                    gas_used,
+                } if rh1 == h1 && rh2 == h2 && rextra == extra => Some((*fault, *gas_used)),
+                _ => None,
+            })
+            .context("no consensus fault check recorded for these block headers")?;
+        let fault = fault
+            .map(|(target, epoch, fault_type)| {
+                Ok::<_, anyhow::Error>(ConsensusFault {
+                    target,
+                    epoch,
+                    fault_type: ConsensusFaultType::from_u8(fault_type)
+                        .with_context(|| format!("invalid recorded fault type {fault_type}"))?,
+                })
+            })
+            .transpose()?;
+        Ok((fault, gas_used))
+    }
+}
+
+impl Chain for ReplayingExterns {
+    fn get_tipset_cid(&self, round: ChainEpoch) -> anyhow::Result<Cid> {
+        self.log
+            .find(|l| match l {
+                ExternLookup::TipsetCid { epoch, cid } if *epoch == round => Some(*cid),
+                _ => None,
+            })
+            .ok_or_else(|| anyhow!("no tipset CID recorded for epoch {round}"))
+    }
+}
+
+impl Externs for ReplayingExterns {}
+
+#[cfg(test)]
+mod tests {
+    use cid::Cid;
+    use fvm_shared::clock::ChainEpoch;
+    use fvm_shared::IDENTITY_HASH;
+    use multihash::Multihash;
+
+    use super::{RecordingExterns, ReplayLog, ReplayingExterns};
+    use crate::externs::{Chain, Rand};
+
+    struct EpochExterns;
+
+    impl Rand for EpochExterns {
+        fn get_chain_randomness(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+            Ok([round as u8; 32])
+        }
+
+        fn get_beacon_randomness(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+            Ok([!(round as u8); 32])
+        }
+    }
+
+    impl Chain for EpochExterns {
+        fn get_tipset_cid(&self, round: ChainEpoch) -> anyhow::Result<Cid> {
+            let digest = Multihash::wrap(IDENTITY_HASH, &round.to_be_bytes())?;
+            Ok(Cid::new_v1(fvm_ipld_encoding::DAG_CBOR, digest))
+        }
+    }
+
+    #[test]
+    fn replays_recorded_lookups() {
+        let recorder = RecordingExterns::new(EpochExterns);
+        let chain = recorder.get_chain_randomness(1).unwrap();
+        let beacon = recorder.get_beacon_randomness_bytes(2).unwrap();
+        let cid = recorder.get_tipset_cid(3).unwrap();
+
+        let log = ReplayLog::from_cbor(&recorder.log().to_cbor().unwrap()).unwrap();
+        assert_eq!(log, recorder.log());
+        assert_eq!(log.0.len(), 3);
+
+        let replayer = ReplayingExterns::new(log);
+        assert_eq!(replayer.get_chain_randomness(1).unwrap(), chain);
+        assert_eq!(replayer.get_beacon_randomness_bytes(2).unwrap(), beacon);
+        assert_eq!(replayer.get_tipset_cid(3).unwrap(), cid);
+
+        // Lookups that weren't recorded fail.
+        assert!(replayer.get_chain_randomness(2).is_err());
+        assert!(replayer.get_beacon_randomness(2).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! The Filecoin base fee market: the base fee of each tipset is adjusted from its parent's by up to
+//! 12.5%, depending on how far the gas in the parent's blocks was from the target.
+use fvm_shared::bigint::{BigInt, Integer};
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::BLOCK_GAS_LIMIT;
+
+/// The gas each block targets: half the block gas limit.
+pub const BLOCK_GAS_TARGET: u64 = BLOCK_GAS_LIMIT / 2;
+
+/// The base fee changes by at most `1 / BASE_FEE_MAX_CHANGE_DENOM` (12.5%) between tipsets.
+pub const BASE_FEE_MAX_CHANGE_DENOM: i64 = 8;
+
+/// The base fee never drops below this many attoFIL.
+pub const MINIMUM_BASE_FEE: i64 = 100;
+
+/// Computes the base fee of the next tipset, given the base fee of the parent tipset, the gas in
+/// the parent's blocks (on average, per block), and the gas each block targets (usually
+/// [`BLOCK_GAS_TARGET`]).
+///
+/// On Filecoin, the gas in a block is the sum of the gas _limits_ of its messages (counting
+/// messages included in several blocks once), not the gas they used. The default executor sums
+/// the gas limits of the explicit messages it applies, see
+/// [`DefaultExecutor::take_gas_limit_total`](crate::executor::DefaultExecutor::take_gas_limit_total).
+///
+/// This matches the reference implementation exactly, including its rounding: the change is
+/// rounded towards negative infinity.
+pub fn compute_next(parent_base_fee: &TokenAmount, gas_used: u64, target: u64) -> TokenAmount {
+    let target = BigInt::from(target);
+    let delta = (BigInt::from(gas_used) - &target).clamp(-target.clone(), target.clone());
+    let change = (parent_base_fee.atto() * delta)
+        .div_floor(&target)
+        .div_floor(&BigInt::from(BASE_FEE_MAX_CHANGE_DENOM));
+    let next = parent_base_fee.atto() + change;
+    TokenAmount::from_atto(next.max(BigInt::from(MINIMUM_BASE_FEE)))
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_shared::econ::TokenAmount;
+
+    use super::{compute_next, BLOCK_GAS_TARGET, MINIMUM_BASE_FEE};
+
+    #[test]
+    fn next_base_fee() {
+        let base_fee = TokenAmount::from_atto(100_000_000);
+
+        // On target, the base fee doesn't change.
+        assert_eq!(
+            compute_next(&base_fee, BLOCK_GAS_TARGET, BLOCK_GAS_TARGET),
+            base_fee
+        );
+
+        // The change is capped at 12.5% either way.
+        assert_eq!(
+            compute_next(&base_fee, 4 * BLOCK_GAS_TARGET, BLOCK_GAS_TARGET),
+            TokenAmount::from_atto(112_500_000)
+        );
+        assert_eq!(
+            compute_next(&base_fee, 0, BLOCK_GAS_TARGET),
+            TokenAmount::from_atto(87_500_000)
+        );
+
+        // Decreases are rounded towards negative infinity.
+        assert_eq!(
+            compute_next(
+                &TokenAmount::from_atto(1001),
+                BLOCK_GAS_TARGET - 1,
+                BLOCK_GAS_TARGET
+            ),
+            TokenAmount::from_atto(1000)
+        );
+
+        // The base fee never drops below the minimum.
+        assert_eq!(
+            compute_next(
+                &TokenAmount::from_atto(MINIMUM_BASE_FEE),
+                0,
+                BLOCK_GAS_TARGET
+            ),
+            TokenAmount::from_atto(MINIMUM_BASE_FEE)
+        );
+    }
+}
@@ -3,18 +3,23 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display};
 use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
 
 use anyhow::Context;
 use num_traits::Zero;
+use serde::{Deserialize, Serialize};
 
 pub use self::charge::GasCharge;
 pub(crate) use self::outputs::GasOutputs;
-pub use self::price_list::{price_list_by_network_version, PriceList, WasmGasPrices};
+pub use self::price_list::{
+    price_list_by_network_version, PriceList, PriceSchedule, WasmGasPrices,
+};
 pub use self::timer::{GasDuration, GasInstant, GasTimer};
 use crate::kernel::{ClassifyResult, ExecutionError, Result};
 
+pub mod basefee;
 mod charge;
 mod outputs;
 mod price_list;
@@ -22,13 +27,17 @@ mod timer;
 
 pub const MILLIGAS_PRECISION: u64 = 1000;
 
+/// The number of calls to each syscall, keyed by `module::name`.
+pub type SyscallCounts = BTreeMap<String, u64>;
+
 /// A typesafe representation of gas (internally stored as milligas).
 ///
 /// - All math operations are _saturating_ and never overflow.
 /// - Enforces correct units by making it impossible to, e.g., get gas squared (by multiplying gas
 ///   by gas).
 /// - Makes it harder to confuse gas and milligas.
-#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default)]
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Gas(u64 /* milligas */);
 
 impl Debug for Gas {
@@ -169,6 +178,7 @@ pub struct GasTracker {
     gas_used: Cell<Gas>,
     gas_snapshots: Vec<GasSnapshot>,
     trace: Option<RefCell<Vec<GasCharge>>>,
+    syscalls: Option<RefCell<BTreeMap<(&'static str, &'static str), u64>>>,
 }
 
 impl GasTracker {
@@ -186,9 +196,34 @@ impl GasTracker {
             gas_used: Cell::new(gas_used),
             gas_snapshots: Vec::new(),
             trace: enable_tracing.then_some(Default::default()),
+            syscalls: None,
+        }
+    }
+
+    /// Enables counting syscalls with [`GasTracker::record_syscall`].
+    pub fn with_syscall_census(mut self) -> Self {
+        self.syscalls = Some(Default::default());
+        self
+    }
+
+    /// Records a call to the given syscall, if counting syscalls.
+    pub fn record_syscall(&self, module: &'static str, name: &'static str) {
+        if let Some(syscalls) = &self.syscalls {
+            *syscalls.borrow_mut().entry((module, name)).or_default() += 1;
         }
     }
 
+    /// Takes the syscall counts recorded so far, if counting syscalls.
+    pub fn take_syscall_counts(&self) -> Option<SyscallCounts> {
+        self.syscalls.as_ref().map(|syscalls| {
+            syscalls
+                .take()
+                .into_iter()
+                .map(|((module, name), count)| (format!("{module}::{name}"), count))
+                .collect()
+        })
+    }
+
     fn charge_gas_inner(&self, to_use: Gas) -> Result<()> {
         // The gas type uses saturating math.
         let gas_used = self.gas_used.get() + to_use;
@@ -308,6 +343,22 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn syscall_census() {
+        let t = GasTracker::new(Gas::new(20), Gas::zero(), false);
+        t.record_syscall("ipld", "block_open");
+        assert_eq!(t.take_syscall_counts(), None);
+
+        let t = t.with_syscall_census();
+        t.record_syscall("ipld", "block_open");
+        t.record_syscall("ipld", "block_open");
+        t.record_syscall("vm", "exit");
+        let counts = t.take_syscall_counts().unwrap();
+        assert_eq!(counts["ipld::block_open"], 2);
+        assert_eq!(counts["vm::exit"], 1);
+        assert!(t.take_syscall_counts().unwrap().is_empty());
+    }
+
     #[test]
     fn milligas_to_gas_round() {
         assert_eq!(milligas_to_gas(100, false), 0);
@@ -2,7 +2,7 @@
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::Mul;
 
 use anyhow::Context;
@@ -18,6 +18,7 @@ use fvm_shared::ActorID;
 use fvm_wasm_instrument::gas_metering::{InstructionCost, Operator, Rules};
 use lazy_static::lazy_static;
 use num_traits::Zero;
+use serde::{Deserialize, Serialize};
 
 use super::GasCharge;
 use crate::gas::Gas;
@@ -293,6 +294,11 @@ lazy_static! {
             scale: Gas::new(1400),
         },
 
+        // No separate charges for indexed and value bytes in this network version (covered by the
+        // per-byte memory and hashing charges). See DRAGON_PRICES.
+        event_per_indexed_byte: Zero::zero(),
+        event_per_value_byte: Zero::zero(),
+
         utf8_validation: ScalingCost {
             flat: Gas::new(500),
             scale: Gas::new(16),
@@ -305,10 +311,25 @@ lazy_static! {
         ipld_cbor_scan_per_field: Gas::new(35),
         ipld_link_tracked: Gas::new(300),
         ipld_link_checked: Gas::new(300),
+        ipld_inline_cid_per_byte: Zero::zero(),
+    };
+
+    #[cfg_attr(not(feature = "nv22-dev"), allow(dead_code))]
+    static ref DRAGON_PRICES: PriceList = PriceList {
+        // Charge for the bytes clients index (indexed keys and values) and retain (all values)
+        // on top of the per-event charges, so actors can't bloat client databases for free.
+        event_per_indexed_byte: Gas::new(32),
+        event_per_value_byte: Gas::new(8),
+
+        // Inline CID payloads are copied into (and parsed out of) every block linking to them,
+        // bypassing the per-block storage and hashing charges.
+        ipld_inline_cid_per_byte: Gas::new(10),
+
+        ..WATERMELON_PRICES.clone()
     };
 }
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct ScalingCost {
     pub flat: Gas,
     pub scale: Gas,
@@ -340,10 +361,11 @@ impl ScalingCost {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub(crate) struct StepCost(Vec<Step>);
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct Step {
     start: u64,
     cost: Gas,
@@ -362,7 +384,12 @@ impl StepCost {
 
 /// Provides prices for operations in the VM.
 /// All costs are in milligas.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Price lists can be (de)serialized, so price changes can be shipped as data (see
+/// [`PriceSchedule`]). Prices keyed by signature type, hash function, or proof type are encoded as
+/// lists of `(key, price)` pairs, sorted by the key's numeric code, so the encoding is canonical.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PriceList {
     /// Gas cost charged to the originator of an on-chain message (regardless of
     /// whether it succeeds or fails in application) is given by:
@@ -399,11 +426,13 @@ pub struct PriceList {
     pub(crate) actor_create_storage: Gas,
 
     /// Gas cost for verifying a cryptographic signature.
+    #[serde(with = "enum_map")]
     pub(crate) sig_cost: HashMap<SignatureType, ScalingCost>,
 
     /// Gas cost for recovering secp256k1 signer public key
     pub(crate) secp256k1_recover_cost: Gas,
 
+    #[serde(with = "enum_map")]
     pub(crate) hashing_cost: HashMap<SupportedHashes, ScalingCost>,
 
     /// Gas cost for walking up the chain.
@@ -412,9 +441,12 @@ pub struct PriceList {
 
     pub(crate) compute_unsealed_sector_cid_base: Gas,
     pub(crate) verify_seal_base: Gas,
+    #[serde(with = "enum_map")]
     pub(crate) verify_aggregate_seal_per: HashMap<RegisteredSealProof, Gas>,
+    #[serde(with = "enum_map")]
     pub(crate) verify_aggregate_seal_steps: HashMap<RegisteredSealProof, StepCost>,
 
+    #[serde(with = "enum_map")]
     pub(crate) verify_post_lookup: HashMap<RegisteredPoStProof, ScalingCost>,
     pub(crate) verify_consensus_fault: Gas,
     pub(crate) verify_replica_update: Gas,
@@ -450,6 +482,12 @@ pub struct PriceList {
     /// Gas cost to validate an ActorEvent as soon as it's received from the actor, and prior
     /// to it being parsed.
     pub(crate) event_per_entry: ScalingCost,
+    /// Gas cost per byte of an event's indexed keys and values (on top of the per-byte charges
+    /// for all event data), covering the cost of indexing them.
+    pub(crate) event_per_indexed_byte: Gas,
+    /// Gas cost per byte of an event's values (on top of the per-byte charges for all event
+    /// data), covering the cost of retaining them.
+    pub(crate) event_per_value_byte: Gas,
 
     /// Gas cost of doing lookups in the builtin actor mappings.
     pub(crate) builtin_actor_manifest_lookup: Gas,
@@ -479,9 +517,14 @@ pub struct PriceList {
 
     /// Gas cost for checking if CID is reachable.
     pub(crate) ipld_link_checked: Gas,
+
+    /// Gas cost per byte of identity-hashed ("inline") CID payload encountered when parsing
+    /// blocks, on top of the per-CID cost.
+    pub(crate) ipld_inline_cid_per_byte: Gas,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct WasmGasPrices {
     /// The default gas cost for instructions.
     pub(crate) instruction_default: Gas,
@@ -507,6 +550,20 @@ pub struct WasmGasPrices {
 }
 
 impl PriceList {
+    /// Returns a hash of every price in this list. Nodes configured with different price lists will
+    /// charge different amounts of gas (and will therefore diverge), so this is included in the
+    /// [machine fingerprint](crate::machine::Machine::fingerprint).
+    pub fn fingerprint(&self) -> [u8; 32] {
+        // Maps are encoded sorted by key, so the encoding is canonical.
+        let encoded = fvm_ipld_encoding::to_vec(self).expect("price lists are serializable");
+        blake2b_simd::Params::new()
+            .hash_length(32)
+            .hash(&encoded)
+            .as_bytes()
+            .try_into()
+            .expect("hash length is 32 bytes")
+    }
+
     /// Returns the gas required for storing a message of a given size in the chain, plus the cost
     /// of updating the sending actor's nonce and balance in the state-tree.
     #[inline]
@@ -751,6 +808,17 @@ impl PriceList {
         )
     }
 
+    /// Returns the base gas required for opening an inline (identity-hashed) object. Unlike
+    /// [`PriceList::on_block_open_base`], this doesn't include the cost of a blockstore lookup.
+    #[inline]
+    pub fn on_block_open_inline(&self, data_size: usize) -> GasCharge {
+        GasCharge::new(
+            "OnBlockOpenInline",
+            self.ipld_link_checked + self.ipld_inline_cid_per_byte * data_size,
+            Zero::zero(),
+        )
+    }
+
     /// Returns the gas required for reading a loaded object.
     #[inline]
     pub fn on_block_read(&self, data_size: usize) -> GasCharge {
@@ -798,6 +866,21 @@ impl PriceList {
         GasCharge::new("OnBlockLink", initial_compute, deferred_compute + storage)
     }
 
+    /// Returns the gas required for inlining an object into an identity-hashed CID. Inline
+    /// objects aren't written to the state blockstore, so there's no storage charge.
+    #[inline]
+    pub fn on_block_link_inline(&self, data_size: usize) -> GasCharge {
+        let memcpy = self.block_memcpy.apply(data_size);
+        let alloc = self.block_allocate.apply(data_size);
+        let inline = self.ipld_inline_cid_per_byte * data_size;
+
+        GasCharge::new(
+            "OnBlockLinkInline",
+            memcpy + alloc + inline + self.ipld_link_tracked,
+            Zero::zero(),
+        )
+    }
+
     /// Returns the gas required for storing an object.
     #[inline]
     pub fn on_block_stat(&self) -> GasCharge {
@@ -900,6 +983,19 @@ impl PriceList {
         GasCharge::new("OnMessageContext", self.message_context, Zero::zero())
     }
 
+    /// Returns the gas required for reading the actor call stack, given its depth.
+    #[inline]
+    pub fn on_call_stack(&self, depth: usize) -> GasCharge {
+        GasCharge::new(
+            "OnCallStack",
+            self.message_context
+                + self
+                    .block_memcpy
+                    .apply(depth * std::mem::size_of::<ActorID>()),
+            Zero::zero(),
+        )
+    }
+
     /// Returns the gas required for installing an actor.
     pub fn on_install_actor(&self, wasm_size: usize) -> GasCharge {
         GasCharge::new(
@@ -928,10 +1024,18 @@ impl PriceList {
     }
 
     #[inline]
-    pub fn on_actor_event(&self, entries: usize, keysize: usize, valuesize: usize) -> GasCharge {
+    pub fn on_actor_event(
+        &self,
+        entries: usize,
+        keysize: usize,
+        valuesize: usize,
+        indexed_size: usize,
+    ) -> GasCharge {
         // Here we estimate per-event overhead given the constraints on event values.
 
         let validate_entries = self.event_per_entry.apply(entries);
+        let indexing = self.event_per_indexed_byte * indexed_size;
+        let retention = self.event_per_value_byte * valuesize;
         let validate_utf8 = self.utf8_validation.apply(keysize);
 
         // Estimate the size, saturating at max-u64. Given how we calculate gas, this will saturate
@@ -953,8 +1057,8 @@ impl PriceList {
             // Charge for validation/storing/serializing events.
             mem * 2u32 + validate_entries + validate_utf8,
             // Charge for forming the AMT and returning the events to the client.
-            // one copy into the AMT, one copy to the client.
-            hash + mem,
+            // one copy into the AMT, one copy to the client. Plus indexing and retention.
+            hash + mem + indexing + retention,
         )
     }
 
@@ -969,12 +1073,163 @@ impl PriceList {
     }
 }
 
+/// Price lists by network version, to use instead of the built-in ones (see
+/// [`NetworkConfig::price_schedule`](crate::machine::NetworkConfig::price_schedule)). This lets
+/// price changes be shipped (and tested against fixtures) as data, rather than as code.
+///
+/// Schedules are encoded as lists of `(network version, price list)` pairs, sorted by network
+/// version.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "Vec<(NetworkVersion, PriceList)>",
+    into = "Vec<(NetworkVersion, PriceList)>"
+)]
+pub struct PriceSchedule(BTreeMap<NetworkVersion, PriceList>);
+
+impl PriceSchedule {
+    /// Decodes a CBOR-encoded schedule.
+    pub fn from_cbor(bytes: &[u8]) -> anyhow::Result<Self> {
+        fvm_ipld_encoding::from_slice(bytes).context("failed to decode price schedule")
+    }
+
+    /// Encodes the schedule as CBOR.
+    pub fn to_cbor(&self) -> anyhow::Result<Vec<u8>> {
+        fvm_ipld_encoding::to_vec(self).context("failed to encode price schedule")
+    }
+
+    /// Sets the price list for a network version, replacing any previous one.
+    pub fn insert(&mut self, network_version: NetworkVersion, price_list: PriceList) -> &mut Self {
+        self.0.insert(network_version, price_list);
+        self
+    }
+
+    /// Returns the price list for a network version, if the schedule has one.
+    pub fn get(&self, network_version: NetworkVersion) -> Option<&PriceList> {
+        self.0.get(&network_version)
+    }
+
+    /// Iterates over the price lists in the schedule, by network version.
+    pub fn iter(&self) -> impl Iterator<Item = (NetworkVersion, &PriceList)> {
+        self.0.iter().map(|(nv, pl)| (*nv, pl))
+    }
+}
+
+impl TryFrom<Vec<(NetworkVersion, PriceList)>> for PriceSchedule {
+    type Error = String;
+
+    fn try_from(entries: Vec<(NetworkVersion, PriceList)>) -> Result<Self, Self::Error> {
+        let mut schedule = BTreeMap::new();
+        for (nv, price_list) in entries {
+            if schedule.insert(nv, price_list).is_some() {
+                return Err(format!("duplicate price list for network version {nv}"));
+            }
+        }
+        Ok(Self(schedule))
+    }
+}
+
+impl From<PriceSchedule> for Vec<(NetworkVersion, PriceList)> {
+    fn from(schedule: PriceSchedule) -> Self {
+        schedule.0.into_iter().collect()
+    }
+}
+
+/// (De)serializes price maps keyed by enums as lists of `(code, price)` pairs sorted by code, so
+/// they have a canonical encoding (and don't need string keys).
+mod enum_map {
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    use fvm_shared::crypto::signature::SignatureType;
+    use fvm_shared::sector::{RegisteredPoStProof, RegisteredSealProof};
+    use num_traits::FromPrimitive;
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::kernel::SupportedHashes;
+
+    /// An enum with a stable numeric code.
+    pub trait Key: Copy + Eq + Hash {
+        fn code(self) -> i64;
+        fn from_code(code: i64) -> Option<Self>;
+    }
+
+    impl Key for SignatureType {
+        fn code(self) -> i64 {
+            self as i64
+        }
+
+        fn from_code(code: i64) -> Option<Self> {
+            SignatureType::from_i64(code)
+        }
+    }
+
+    impl Key for SupportedHashes {
+        fn code(self) -> i64 {
+            u64::from(&self) as i64
+        }
+
+        fn from_code(code: i64) -> Option<Self> {
+            SupportedHashes::try_from(code as u64).ok()
+        }
+    }
+
+    impl Key for RegisteredSealProof {
+        fn code(self) -> i64 {
+            self.into()
+        }
+
+        fn from_code(code: i64) -> Option<Self> {
+            Some(code.into())
+        }
+    }
+
+    impl Key for RegisteredPoStProof {
+        fn code(self) -> i64 {
+            self.into()
+        }
+
+        fn from_code(code: i64) -> Option<Self> {
+            Some(code.into())
+        }
+    }
+
+    pub fn serialize<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Key,
+        V: Serialize,
+        S: Serializer,
+    {
+        let mut entries: Vec<_> = map.iter().map(|(k, v)| (k.code(), v)).collect();
+        entries.sort_by_key(|(code, _)| *code);
+        entries.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        K: Key,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let entries: Vec<(i64, V)> = Vec::deserialize(deserializer)?;
+        let mut map = HashMap::with_capacity(entries.len());
+        for (code, value) in entries {
+            let key = K::from_code(code)
+                .ok_or_else(|| D::Error::custom(format!("unknown price key {code}")))?;
+            if map.insert(key, value).is_some() {
+                return Err(D::Error::custom(format!("duplicate price key {code}")));
+            }
+        }
+        Ok(map)
+    }
+}
+
 /// Returns gas price list by NetworkVersion for gas consumption.
 pub fn price_list_by_network_version(network_version: NetworkVersion) -> &'static PriceList {
     match network_version {
         NetworkVersion::V21 => &WATERMELON_PRICES,
         #[cfg(feature = "nv22-dev")]
-        _ if network_version == NetworkVersion::V22 => &WATERMELON_PRICES,
+        _ if network_version == NetworkVersion::V22 => &DRAGON_PRICES,
         _ => panic!("network version {nv} not supported", nv = network_version),
     }
 }
@@ -1337,3 +1592,78 @@ fn test_step_cost_zero() {
     assert_eq!(costs.lookup(0), Gas::new(1));
     assert_eq!(costs.lookup(10), Gas::new(1));
 }
+
+#[cfg(test)]
+mod tests {
+    use fvm_shared::version::NetworkVersion;
+
+    use super::{price_list_by_network_version, PriceSchedule, DRAGON_PRICES, WATERMELON_PRICES};
+
+    #[test]
+    fn price_schedule_round_trip() {
+        let nv = NetworkVersion::V21;
+        let mut price_list = price_list_by_network_version(nv).clone();
+        price_list.send_invoke_method = price_list.send_invoke_method * 2u64;
+
+        let mut schedule = PriceSchedule::default();
+        schedule.insert(nv, price_list.clone());
+        let bytes = schedule.to_cbor().unwrap();
+        let decoded = PriceSchedule::from_cbor(&bytes).unwrap();
+        assert_eq!(decoded, schedule);
+        assert_eq!(
+            decoded.get(nv).unwrap().fingerprint(),
+            price_list.fingerprint()
+        );
+        // The encoding is canonical.
+        assert_eq!(decoded.to_cbor().unwrap(), bytes);
+
+        // Each network version may only have one price list.
+        let duplicated =
+            fvm_ipld_encoding::to_vec(&vec![(nv, price_list.clone()), (nv, price_list)]).unwrap();
+        assert!(PriceSchedule::from_cbor(&duplicated).is_err());
+    }
+
+    #[test]
+    fn fingerprint_covers_prices() {
+        let prices = WATERMELON_PRICES.clone();
+        assert_eq!(prices.fingerprint(), WATERMELON_PRICES.fingerprint());
+        assert_ne!(DRAGON_PRICES.fingerprint(), WATERMELON_PRICES.fingerprint());
+
+        let mut changed = prices.clone();
+        changed.wasm_rules.call = changed.wasm_rules.call * 2u64;
+        assert_ne!(changed.fingerprint(), prices.fingerprint());
+
+        let mut changed = prices.clone();
+        changed.preloaded_actors.push(1000);
+        assert_ne!(changed.fingerprint(), prices.fingerprint());
+    }
+
+    #[test]
+    fn event_gas_grows_with_payload() {
+        let gas = |values: usize, indexed: usize| {
+            DRAGON_PRICES.on_actor_event(1, 4, values, indexed).total()
+        };
+        assert!(gas(100, 0) > gas(10, 0));
+        assert!(gas(100, 100) > gas(100, 0));
+
+        // On top of the memory and hashing charges, every value byte and indexed byte is charged.
+        let base = WATERMELON_PRICES.on_actor_event(1, 4, 100, 50).total();
+        assert_eq!(
+            gas(100, 50) - base,
+            DRAGON_PRICES.event_per_value_byte * 100 + DRAGON_PRICES.event_per_indexed_byte * 50
+        );
+    }
+
+    #[test]
+    fn inline_cid_gas_grows_with_payload() {
+        let base = WATERMELON_PRICES.on_block_link_inline(64).total();
+        assert_eq!(
+            DRAGON_PRICES.on_block_link_inline(64).total() - base,
+            DRAGON_PRICES.ipld_inline_cid_per_byte * 64
+        );
+        assert!(
+            DRAGON_PRICES.on_block_open_inline(64).total()
+                > DRAGON_PRICES.on_block_open_inline(1).total()
+        );
+    }
+}
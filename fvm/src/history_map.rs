@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::borrow::Borrow;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 
 /// A map with an "undo" history. All changes to this map are recorded in the history and can be "reverted" by calling `rollback`. Specifically:
@@ -100,6 +100,20 @@ where
         self.history.len()
     }
 
+    /// Returns every key modified since the specified point in history, along with the value it had
+    /// at that point (`None` if it wasn't in the map). Keys are returned in the order in which they
+    /// were first modified.
+    pub fn changed_since(&self, height: usize) -> Vec<(&K, Option<&V>)> {
+        let mut seen = HashSet::new();
+        self.history
+            .get(height..)
+            .unwrap_or_default()
+            .iter()
+            .filter(|(k, _)| seen.insert(k))
+            .map(|(k, v)| (k, v.as_ref()))
+            .collect()
+    }
+
     /// Discards all undo history.
     pub fn discard_history(&mut self) {
         self.history.clear();
@@ -184,4 +198,22 @@ mod test {
         assert_eq!(map.history_len(), 0);
         assert_eq!(map.get(&1), None);
     }
+
+    #[test]
+    fn changed_since() {
+        let mut map = HistoryMap::<i32, &'static str>::default();
+        map.insert(1, "foo");
+        let height = map.history_len();
+        assert!(map.changed_since(height).is_empty());
+
+        map.insert(2, "bar");
+        map.insert(1, "baz");
+        map.insert(1, "qux");
+        assert_eq!(
+            map.changed_since(height),
+            vec![(&2, None), (&1, Some(&"foo"))]
+        );
+        assert_eq!(map.changed_since(0), vec![(&1, None), (&2, None)]);
+        assert!(map.changed_since(10).is_empty());
+    }
 }
@@ -7,29 +7,39 @@ use num_traits::Zero;
 
 use crate::gas::{Gas, GasTimer, GasTracker, PriceList};
 use crate::kernel::{ExecutionError, Result};
+use crate::machine::{CodecRegistry, InlineCidLimits};
 use crate::syscall_error;
 
 mod cbor;
 
 struct LinkVisitor<'a> {
     pub price_list: &'a PriceList,
+    inline_limits: &'a InlineCidLimits,
+    codecs: &'a CodecRegistry,
     gas_available: Gas,
     gas_remaining: Gas,
     links: Vec<Cid>,
 }
 
-/// Codecs allowed by the IPLD subsystem.
-pub const ALLOWED_CODECS: &[u64] = &[CBOR, DAG_CBOR, IPLD_RAW];
 /// Codecs ignored by the IPLD subsystem.
 pub const IGNORED_CODECS: &[u64] = &[FIL_COMMITMENT_UNSEALED, FIL_COMMITMENT_SEALED];
 
-// TODO: Deduplicate
-const BLAKE2B_256: u64 = 0xb220;
+/// Codecs the IPLD subsystem can scan for links: DAG-CBOR blocks are parsed, while CBOR and raw
+/// blocks can't contain links. Blocks of any other codec can't be supported, as we wouldn't be
+/// able to track the blocks they link to.
+pub const SCANNABLE_CODECS: &[u64] = &[DAG_CBOR, CBOR, IPLD_RAW];
 
 impl<'a> LinkVisitor<'a> {
-    pub fn new(price_list: &'a PriceList, gas_available: Gas) -> Self {
+    pub fn new(
+        price_list: &'a PriceList,
+        inline_limits: &'a InlineCidLimits,
+        codecs: &'a CodecRegistry,
+        gas_available: Gas,
+    ) -> Self {
         Self {
             price_list,
+            inline_limits,
+            codecs,
             gas_available,
             gas_remaining: gas_available,
             links: Vec::new(),
@@ -76,7 +86,7 @@ impl<'a> LinkVisitor<'a> {
             return Ok(());
         }
 
-        if !ALLOWED_CODECS.contains(&codec) {
+        if !self.codecs.allows_codec(codec) {
             // NOTE: We could get away without doing this here _except_ for
             // identity-hash CIDs. Because, unfortunately, those _don't_ go through the
             // `ipld::block_create` API.
@@ -89,14 +99,33 @@ impl<'a> LinkVisitor<'a> {
         }
 
         if cid.hash().code() == fvm_shared::IDENTITY_HASH {
+            let payload = cid.hash().digest();
+            if payload.len() > self.inline_limits.max_payload {
+                return Err(syscall_error!(
+                    LimitExceeded; "block links to inline CID with {} byte payload (max {})",
+                    payload.len(), self.inline_limits.max_payload
+                )
+                .into());
+            }
+            if !self.inline_limits.codecs.contains(&codec) {
+                return Err(syscall_error!(
+                    NotFound; "block links to inline CID with forbidden codec {codec}"
+                )
+                .into());
+            }
+            self.charge_gas(self.price_list.ipld_inline_cid_per_byte * payload.len())?;
+
             // TODO: Test max recursion depth. Each level should take 6-7 bytes
             // leaving at most 11 (likely less) recursive calls (max of a 64
             // byte digest). We need to make sure this isn't going to be a
             // problem, or rewrite this to be non-recursive.
-            return scan_for_links_inner(self, cid.codec(), cid.hash().digest());
+            return scan_for_links_inner(self, codec, payload);
         }
 
-        if cid.hash().code() != BLAKE2B_256 || cid.hash().size() != 32 {
+        if !self
+            .codecs
+            .allows_multihash(cid.hash().code(), cid.hash().size().into())
+        {
             return Err(syscall_error!(
                 NotFound; "block links to CID with forbidden multihash type (code: {}, len: {})",
                 cid.hash().code(), cid.hash().size()
@@ -114,8 +143,11 @@ impl<'a> LinkVisitor<'a> {
 fn scan_for_links_inner(visitor: &mut LinkVisitor, codec: u64, data: &[u8]) -> Result<()> {
     match codec {
         DAG_CBOR => cbor::scan_for_reachable_links(visitor, data),
-        IPLD_RAW | CBOR => Ok(()),
-        codec => Err(syscall_error!(IllegalCodec; "codec {} not allowed", codec).into()),
+        CBOR | IPLD_RAW => Ok(()),
+        // The codec registry refuses codecs we can't scan, so this shouldn't happen.
+        _ => Err(
+            syscall_error!(IllegalCodec; "can't scan blocks with codec {codec} for links").into(),
+        ),
     }
 }
 
@@ -124,10 +156,17 @@ pub fn scan_for_reachable_links(
     codec: u64,
     data: &[u8],
     price_list: &PriceList,
+    inline_limits: &InlineCidLimits,
+    codecs: &CodecRegistry,
     gas_tracker: &GasTracker,
 ) -> Result<Vec<Cid>> {
     let start = GasTimer::start();
-    let mut visitor = LinkVisitor::new(price_list, gas_tracker.gas_available());
+    let mut visitor = LinkVisitor::new(
+        price_list,
+        inline_limits,
+        codecs,
+        gas_tracker.gas_available(),
+    );
     let ret = scan_for_links_inner(&mut visitor, codec, data);
     let t = gas_tracker.charge_gas("OnScanIpldLinks", visitor.gas_used())?;
     let ret = ret.map(|_| visitor.finish());
@@ -140,6 +179,7 @@ mod test {
     use crate::gas::{price_list_by_network_version, Gas, GasTracker};
 
     use crate::kernel::{ExecutionError, Result};
+    use crate::machine::{CodecRegistry, InlineCidLimits};
     use cid::Cid;
     use fvm_ipld_encoding::{CBOR, DAG_CBOR, IPLD_RAW};
     use fvm_shared::commcid::FIL_COMMITMENT_UNSEALED;
@@ -163,7 +203,14 @@ mod test {
         let expected_gas = price_list.ipld_cbor_scan_per_field * cbor_field_count
             + price_list.ipld_cbor_scan_per_cid * cbor_link_count;
         let tracker = GasTracker::new(expected_gas, Gas::zero(), false);
-        let res = super::scan_for_reachable_links(codec, data, &price_list, &tracker);
+        let res = super::scan_for_reachable_links(
+            codec,
+            data,
+            &price_list,
+            &InlineCidLimits::default(),
+            &CodecRegistry::for_network_version(NetworkVersion::V21),
+            &tracker,
+        );
         assert!(
             tracker.gas_available().is_zero(),
             "expected to run out of gas"
@@ -219,6 +266,35 @@ mod test {
         );
     }
 
+    #[test]
+    fn inline_cid_limits() {
+        let inline_cid = Cid::new_v1(IPLD_RAW, Multihash::wrap(0, &[0u8; 16]).unwrap());
+        let data = fvm_ipld_encoding::to_vec(&Test(0, inline_cid, 1)).unwrap();
+        let price_list = price_list_by_network_version(NetworkVersion::V21);
+        let codecs = CodecRegistry::for_network_version(NetworkVersion::V21);
+        let scan = |limits: &InlineCidLimits| {
+            let tracker = GasTracker::new(Gas::new(1_000_000), Gas::zero(), false);
+            super::scan_for_reachable_links(DAG_CBOR, &data, price_list, limits, &codecs, &tracker)
+        };
+
+        assert!(scan(&InlineCidLimits::default()).unwrap().is_empty());
+
+        let too_small = InlineCidLimits {
+            max_payload: 15,
+            ..Default::default()
+        };
+        assert!(matches!(
+            scan(&too_small).unwrap_err(),
+            ExecutionError::Syscall(e) if e.1 == fvm_shared::error::ErrorNumber::LimitExceeded
+        ));
+
+        let no_raw = InlineCidLimits {
+            codecs: vec![DAG_CBOR],
+            ..Default::default()
+        };
+        assert!(scan(&no_raw).is_err());
+    }
+
     #[test]
     fn ignores_pieces() {
         let test_cid = Cid::new_v1(
@@ -1,10 +1,10 @@
-use std::collections::HashSet;
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::rc::Rc;
 
 use cid::Cid;
 use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_shared::collections::DeterministicSet;
 
 use super::Result;
 use crate::syscall_error;
@@ -14,7 +14,7 @@ use crate::syscall_error;
 #[derive(Default)]
 pub struct BlockRegistry {
     blocks: Vec<Block>,
-    reachable: HashSet<Cid>,
+    reachable: DeterministicSet<Cid>,
 }
 
 /// Blocks in the block registry are addressed by an ordinal, starting from 1 (`FIRST_ID`).
@@ -7,35 +7,36 @@ use std::path::PathBuf;
 use anyhow::{anyhow, Context as _};
 use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
-use fvm_ipld_encoding::IPLD_RAW;
 use fvm_shared::address::Payload;
 use fvm_shared::crypto::signature;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ErrorNumber;
 use fvm_shared::event::{ActorEvent, Entry, Flags};
+use fvm_shared::randomness::beacon_randomness_length;
 use fvm_shared::sys::out::vm::ContextFlags;
 use fvm_shared::upgrade::UpgradeInfo;
-use fvm_shared::ActorID;
+use fvm_shared::{ActorID, IDENTITY_HASH, IPLD_RAW, METHOD_CONSTRUCTOR};
 use multihash::MultihashDigest;
+use num_traits::{FromPrimitive, Zero};
 
 use super::blocks::{Block, BlockRegistry};
 use super::error::Result;
 use super::hash::SupportedHashes;
 use super::*;
 use crate::call_manager::{
-    CallManager, Entrypoint, InvocationResult, INVOKE_FUNC_NAME, NO_DATA_BLOCK_ID,
-    UPGRADE_FUNC_NAME,
+    CallManager, DebugOutput, Entrypoint, InvocationResult, SystemEvent, INVOKE_FUNC_NAME,
+    NO_DATA_BLOCK_ID, UPGRADE_FUNC_NAME,
 };
 use crate::externs::{Chain, Rand};
-use crate::gas::GasTimer;
+use crate::gas::{GasCharge, GasInstant, GasTimer};
 use crate::init_actor::INIT_ACTOR_ID;
 use crate::machine::{MachineContext, NetworkConfig, BURNT_FUNDS_ACTOR_ID};
 use crate::state_tree::ActorState;
 use crate::{ipld, syscall_error};
 
-const BLAKE2B_256: u64 = 0xb220;
 const ENV_ARTIFACT_DIR: &str = "FVM_STORE_ARTIFACT_DIR";
 const MAX_ARTIFACT_NAME_LEN: usize = 256;
+const MAX_METRIC_NAME_LEN: usize = 256;
 
 #[cfg(feature = "testing")]
 const TEST_ACTOR_ALLOWED_TO_CALL_CREATE_ACTOR: ActorID = 98;
@@ -128,6 +129,7 @@ where
         }
 
         // Send.
+        let gas_before = self.call_manager.gas_tracker().gas_used();
         let result = self.call_manager.with_transaction(|cm| {
             cm.call_actor::<K>(
                 from,
@@ -139,6 +141,7 @@ where
                 read_only,
             )
         })?;
+        let gas_used = self.call_manager.gas_tracker().gas_used() - gas_before;
 
         // Store result and return.
         Ok(match result {
@@ -160,6 +163,7 @@ where
                     block_id,
                     block_stat,
                     exit_code,
+                    gas_used,
                 }
             }
             InvocationResult {
@@ -169,6 +173,7 @@ where
                 block_id: NO_DATA_BLOCK_ID,
                 block_stat: BlockStat { codec: 0, size: 0 },
                 exit_code,
+                gas_used,
             },
         })
     }
@@ -215,6 +220,7 @@ where
             return Err(syscall_error!(LimitExceeded; "cannot store return block").into());
         }
 
+        let gas_before = self.call_manager.gas_tracker().gas_used();
         let result = self.call_manager.with_transaction(|cm| {
             let state = cm
                 .get_actor(self.actor_id)?
@@ -236,6 +242,10 @@ where
                     None,
                 ),
             )?;
+            cm.emit_system_event(SystemEvent::CodeUpgraded {
+                actor: self.actor_id,
+                code: new_code_cid,
+            });
 
             // run the upgrade entrypoint
             let result = cm.call_actor::<K>(
@@ -261,21 +271,258 @@ where
                     block_id,
                     block_stat,
                     exit_code,
+                    gas_used: self.call_manager.gas_tracker().gas_used() - gas_before,
                 })
             }
             Err(err) => Err(err),
         }
     }
+
+    fn create_and_invoke<K: Kernel<CallManager = C>>(
+        &mut self,
+        code_cid: Cid,
+        actor_id: ActorID,
+        delegated_address: Option<Address>,
+        params_id: BlockId,
+        value: &TokenAmount,
+    ) -> Result<CallResult> {
+        if !self.call_manager.context().actor_factories {
+            return Err(syscall_error!(Forbidden; "actor factories are disabled").into());
+        }
+        if self.read_only {
+            return Err(
+                syscall_error!(ReadOnly; "create_and_invoke cannot be called while read-only")
+                    .into(),
+            );
+        }
+        // Only the init actor allocates actor IDs. Other factories let us assign one.
+        let assign_id = self.actor_id != INIT_ACTOR_ID;
+        if assign_id && actor_id != 0 {
+            return Err(syscall_error!(
+                Forbidden; "only the init actor may choose the new actor's ID, not {}", self.actor_id
+            )
+            .into());
+        }
+        self.check_delegated_address(delegated_address.as_ref())?;
+
+        // Load parameters.
+        let params = if params_id == NO_DATA_BLOCK_ID {
+            None
+        } else {
+            Some(self.blocks.get(params_id)?.clone())
+        };
+
+        // Make sure we can actually store the return block.
+        if self.blocks.is_full() {
+            return Err(syscall_error!(LimitExceeded; "cannot store return block").into());
+        }
+
+        // Create the actor and invoke its constructor in a single transaction so the actor is
+        // rolled back if the constructor aborts.
+        let from = self.actor_id;
+        let gas_before = self.call_manager.gas_tracker().gas_used();
+        let result = self.call_manager.with_transaction(|cm| {
+            let actor_id = if assign_id {
+                // Register the next actor address, as the init actor would.
+                let gas = cm.price_list().address_assignment + cm.price_list().address_lookup;
+                cm.charge_gas(GasCharge::new("OnAssignActorId", Gas::zero(), gas))?;
+                let address = cm.next_actor_address();
+                cm.machine_mut()
+                    .state_tree_mut()
+                    .register_new_address(&address)?
+            } else {
+                actor_id
+            };
+            cm.create_actor(code_cid, actor_id, delegated_address)?;
+            cm.call_actor::<K>(
+                from,
+                Address::new_id(actor_id),
+                Entrypoint::Invoke(METHOD_CONSTRUCTOR),
+                params,
+                value,
+                None,
+                false,
+            )
+        })?;
+        let gas_used = self.call_manager.gas_tracker().gas_used() - gas_before;
+
+        let InvocationResult { exit_code, value } = result;
+        let (block_stat, block_id) = match value {
+            None => (BlockStat { codec: 0, size: 0 }, NO_DATA_BLOCK_ID),
+            Some(block) => (
+                block.stat(),
+                self.blocks
+                    .put_reachable(block)
+                    .or_fatal()
+                    .context("failed to store a valid return value")?,
+            ),
+        };
+        Ok(CallResult {
+            block_id,
+            block_stat,
+            exit_code,
+            gas_used,
+        })
+    }
 }
 
 impl<C> DefaultKernel<C>
 where
     C: CallManager,
 {
+    /// Checks that the current actor may create new actors.
+    fn check_create_actor(&self, op: &str) -> Result<()> {
+        let is_allowed_to_create_actor = self.actor_id == INIT_ACTOR_ID;
+
+        #[cfg(feature = "testing")]
+        let is_allowed_to_create_actor =
+            is_allowed_to_create_actor || self.actor_id == TEST_ACTOR_ALLOWED_TO_CALL_CREATE_ACTOR;
+
+        if !is_allowed_to_create_actor {
+            return Err(syscall_error!(
+                Forbidden,
+                "{} is restricted to InitActor. Called by {}",
+                op,
+                self.actor_id
+            )
+            .into());
+        }
+
+        if self.read_only {
+            return Err(syscall_error!(ReadOnly, "{} cannot be called while read-only", op).into());
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the new actor may be assigned the given delegated address: f4 addresses may
+    /// only be assigned in namespaces with a manager, on behalf of (called by) the manager actor.
+    fn check_delegated_address(&self, delegated_address: Option<&Address>) -> Result<()> {
+        let Some(Payload::Delegated(da)) = delegated_address.map(Address::payload) else {
+            return Ok(());
+        };
+        let namespace = da.namespace();
+        if self
+            .call_manager
+            .machine()
+            .address_manager(namespace)
+            .is_none()
+        {
+            return Err(
+                syscall_error!(Forbidden; "no address manager for f4 namespace {namespace}").into(),
+            );
+        }
+        if self.caller != namespace {
+            return Err(syscall_error!(
+                Forbidden;
+                "f4 addresses in namespace {namespace} can only be assigned on behalf of actor {namespace}, not {}",
+                self.caller
+            )
+            .into());
+        }
+        Ok(())
+    }
+
     /// Returns `Some(actor_state)` or `None` if this actor has been deleted.
     fn get_self(&self) -> Result<Option<ActorState>> {
         self.call_manager.get_actor(self.actor_id)
     }
+
+    /// Charges for, validates, and compiles actor code installed at runtime. We charge for the
+    /// whole code up-front, before compiling it.
+    fn install_wasm(&mut self, k: &Cid, wasm: &[u8], start: GasInstant) -> Result<()> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_install_actor(wasm.len()))?;
+
+        self.call_manager
+            .engine()
+            .install_actor_code(k, wasm)
+            .context("failed to install actor")
+            .or_illegal_argument()?;
+
+        t.stop_with(start);
+        Ok(())
+    }
+
+    /// Opens an inline (identity-hashed) CID. The block's data is the CID's digest, so this
+    /// doesn't touch the blockstore, and the CID itself needn't be reachable (but its children
+    /// must be).
+    fn block_open_inline(&mut self, cid: &Cid) -> Result<(BlockId, BlockStat)> {
+        let data = cid.hash().digest();
+        if !self
+            .call_manager
+            .context()
+            .inline_cid_limits
+            .allows(cid.codec(), data.len())
+        {
+            return Err(syscall_error!(NotFound; "inline CID not allowed: {cid}").into());
+        }
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_block_open_inline(data.len()),
+        )?;
+        t.stop();
+
+        // This can fail because we can run out of gas.
+        let children = ipld::scan_for_reachable_links(
+            cid.codec(),
+            data,
+            self.call_manager.price_list(),
+            &self.call_manager.context().inline_cid_limits,
+            &self.call_manager.context().codecs,
+            self.call_manager.gas_tracker(),
+        )?;
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_block_open(data.len(), children.len()),
+        )?;
+
+        let block = Block::new(cid.codec(), data, children);
+        let stat = block.stat();
+        let id = self.blocks.put_check_reachable(block)?;
+        t.stop();
+        Ok((id, stat))
+    }
+
+    /// Inlines a block into an identity-hashed CID. The block isn't written to the blockstore.
+    fn block_link_inline(&mut self, id: BlockId, hash_len: u32) -> Result<Cid> {
+        let start = GasTimer::start();
+        let block = self.blocks.get(id)?;
+        let limits = &self.call_manager.context().inline_cid_limits;
+        if !limits.codecs.contains(&block.codec()) {
+            return Err(syscall_error!(
+                IllegalCid; "blocks with codec {} may not be inlined", block.codec()
+            )
+            .into());
+        }
+        if block.size() as usize > limits.max_payload {
+            return Err(syscall_error!(
+                LimitExceeded; "blocks larger than {} bytes may not be inlined", limits.max_payload
+            )
+            .into());
+        }
+        if hash_len != block.size() {
+            return Err(syscall_error!(IllegalCid; "invalid hash length: {}", hash_len).into());
+        }
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_block_link_inline(block.size() as usize),
+        )?;
+
+        let hash = multihash::Multihash::wrap(IDENTITY_HASH, block.data())
+            .map_err(|_| syscall_error!(IllegalCid; "block too large to inline"))?;
+        let k = Cid::new_v1(block.codec(), hash);
+
+        t.stop_with(start);
+        Ok(k)
+    }
 }
 
 impl<C> SelfOps for DefaultKernel<C>
@@ -374,6 +621,12 @@ where
     C: CallManager,
 {
     fn block_open(&mut self, cid: &Cid) -> Result<(BlockId, BlockStat)> {
+        if cid.hash().code() == IDENTITY_HASH
+            && self.call_manager.context().inline_cid_limits.linkable
+        {
+            return self.block_open_inline(cid);
+        }
+
         let t = self
             .call_manager
             .charge_gas(self.call_manager.price_list().on_block_open_base())?;
@@ -393,12 +646,15 @@ where
             .or_fatal()?;
 
         t.stop();
+        self.call_manager.machine().metrics().block_read(data.len());
 
         // This can fail because we can run out of gas.
         let children = ipld::scan_for_reachable_links(
             cid.codec(),
             &data,
             self.call_manager.price_list(),
+            &self.call_manager.context().inline_cid_limits,
+            &self.call_manager.context().codecs,
             self.call_manager.gas_tracker(),
         )?;
 
@@ -416,11 +672,15 @@ where
     }
 
     fn block_create(&mut self, codec: u64, data: &[u8]) -> Result<BlockId> {
-        if data.len() > self.machine().context().max_block_size {
-            return Err(syscall_error!(LimitExceeded; "blocks may not be larger than 1MiB").into());
+        let max_block_size = self.machine().context().max_block_size;
+        if data.len() > max_block_size {
+            return Err(syscall_error!(
+                LimitExceeded; "blocks may not be larger than {max_block_size} bytes"
+            )
+            .into());
         }
 
-        if !ipld::ALLOWED_CODECS.contains(&codec) {
+        if !self.call_manager.context().codecs.allows_codec(codec) {
             return Err(syscall_error!(IllegalCodec; "codec {} not allowed", codec).into());
         }
 
@@ -428,9 +688,20 @@ where
             codec,
             data,
             self.call_manager.price_list(),
+            &self.call_manager.context().inline_cid_limits,
+            &self.call_manager.context().codecs,
             self.call_manager.gas_tracker(),
         )?;
 
+        let max_block_links = self.machine().context().max_block_links;
+        if children.len() > max_block_links {
+            return Err(syscall_error!(
+                LimitExceeded; "blocks may not contain more than {max_block_links} links"
+            )
+            .into());
+        }
+
+        // Charges for checking that each link is reachable.
         let t = self.call_manager.charge_gas(
             self.call_manager
                 .price_list()
@@ -443,8 +714,19 @@ where
     }
 
     fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
-        if hash_fun != BLAKE2B_256 || hash_len != 32 {
-            return Err(syscall_error!(IllegalCid; "cids must be 32-byte blake2b").into());
+        if hash_fun == IDENTITY_HASH && self.call_manager.context().inline_cid_limits.linkable {
+            return self.block_link_inline(id, hash_len);
+        }
+        if !self
+            .call_manager
+            .context()
+            .codecs
+            .allows_multihash(hash_fun, hash_len)
+        {
+            return Err(syscall_error!(
+                IllegalCid; "multihash {:#x} with length {} not allowed", hash_fun, hash_len
+            )
+            .into());
         }
         let start = GasTimer::start();
         let block = self.blocks.get(id)?;
@@ -468,6 +750,10 @@ where
             // TODO: This is really "super fatal". It means we failed to store state, and should
             // probably abort the entire block.
             .or_fatal()?;
+        self.call_manager
+            .machine()
+            .metrics()
+            .block_written(block.data().len());
         self.blocks.mark_reachable(&k);
 
         t.stop_with(start);
@@ -552,6 +838,22 @@ where
         t.stop();
         Ok(ctx)
     }
+
+    fn call_depth(&self) -> Result<u32> {
+        Ok(self.call_manager.call_depth())
+    }
+
+    fn call_stack(&self) -> Result<Vec<ActorID>> {
+        let stack = self.call_manager.get_call_stack();
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_call_stack(stack.len()))?;
+        t.record(Ok(stack.iter().map(|&(id, _)| id).collect()))
+    }
+
+    fn set_reentrancy(&mut self, policy: ReentrancyPolicy) -> Result<()> {
+        self.call_manager.set_reentrancy_policy(policy)
+    }
 }
 
 impl<C> CircSupplyOps for DefaultKernel<C>
@@ -583,10 +885,28 @@ where
                 .on_verify_signature(sig_type, plaintext.len()),
         )?;
 
-        // We only support key addresses (f1/f3). This change does not require a FIP, because no
-        // actors invoke this method with non-key addresses.
+        // We support key addresses (f1/f3), and f4 addresses in namespaces the machine has a
+        // signature verifier for. This change does not require a FIP, because no actors invoke
+        // this method with other addresses.
         let signing_addr = match signer.payload() {
             Payload::BLS(_) | Payload::Secp256k1(_) => *signer,
+            Payload::Delegated(da) => {
+                let namespace = da.namespace();
+                let verifier = self
+                    .call_manager
+                    .machine()
+                    .signature_verifier(namespace)
+                    .ok_or_else(|| {
+                        syscall_error!(IllegalArgument; "no signature verifier for namespace {namespace}")
+                    })?;
+                // Like the built-in signature types, a panicking verifier fails the syscall.
+                return t.record(catch_and_log_panic(
+                    "verifying delegated signature",
+                    panic::AssertUnwindSafe(|| {
+                        Ok(verifier.verify(sig_type, signature, da.subaddress(), plaintext))
+                    }),
+                ));
+            }
             // Not a key address.
             _ => {
                 return Err(syscall_error!(IllegalArgument; "address protocol {} not supported", signer.protocol()).into());
@@ -656,6 +976,35 @@ where
     fn price_list(&self) -> &PriceList {
         self.call_manager.price_list()
     }
+
+    fn price_of(&self, op: PricedOperation, size: usize, param: u64) -> Result<Gas> {
+        let price_list = self.call_manager.price_list();
+        let charge = match op {
+            PricedOperation::BlockCreate => price_list.on_block_create(size, 0),
+            PricedOperation::BlockRead => price_list.on_block_read(size),
+            PricedOperation::Hash => {
+                let hasher = SupportedHashes::try_from(param).map_err(
+                    |_| syscall_error!(IllegalArgument; "unsupported hash code {param}"),
+                )?;
+                price_list.on_hashing(hasher, size)
+            }
+            PricedOperation::VerifySignature => {
+                let sig_type = u32::try_from(param)
+                    .ok()
+                    .and_then(SignatureType::from_u32)
+                    .ok_or_else(
+                        || syscall_error!(IllegalArgument; "unknown signature type {param}"),
+                    )?;
+                price_list.on_verify_signature(sig_type, size)
+            }
+            PricedOperation::ValueTransfer => price_list.on_value_transfer(),
+        };
+        Ok(charge.total())
+    }
+
+    fn record_syscall(&self, module: &'static str, name: &'static str) {
+        self.call_manager.gas_tracker().record_syscall(module, name)
+    }
 }
 
 impl<C> NetworkOps for DefaultKernel<C>
@@ -715,6 +1064,31 @@ where
 
         self.call_manager.externs().get_tipset_cid(epoch).or_fatal()
     }
+
+    fn machine_fingerprint(&self) -> Result<[u8; 32]> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_network_context())?;
+        let fingerprint = self.call_manager.machine().fingerprint();
+        t.stop();
+        Ok(fingerprint)
+    }
+
+    fn epoch_timing(&self) -> Result<EpochTiming> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_network_context())?;
+        let NetworkConfig {
+            epoch_duration_seconds,
+            blocks_per_epoch,
+            ..
+        } = self.call_manager.context().network;
+        t.stop();
+        Ok(EpochTiming {
+            epoch_duration_seconds,
+            blocks_per_epoch,
+        })
+    }
 }
 
 impl<C> RandomnessOps for DefaultKernel<C>
@@ -767,6 +1141,37 @@ where
                 .or_illegal_argument(),
         )
     }
+
+    fn get_randomness_from_beacon_bytes(&self, rand_epoch: ChainEpoch) -> Result<Randomness> {
+        let lookback = self
+            .call_manager
+            .context()
+            .epoch
+            .checked_sub(rand_epoch)
+            .ok_or_else(|| syscall_error!(IllegalArgument; "randomness epoch {} is in the future", rand_epoch))?;
+
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_get_randomness(lookback))?;
+
+        let randomness = self
+            .call_manager
+            .externs()
+            .get_beacon_randomness_bytes(rand_epoch)
+            .or_illegal_argument()?;
+
+        // The length is part of the network's rules, so a mismatch is a bug in the node.
+        let expected = beacon_randomness_length(self.call_manager.context().network_version);
+        if randomness.0.len() != expected {
+            return Err(anyhow!(
+                "expected {} bytes of beacon randomness, got {}",
+                expected,
+                randomness.0.len()
+            ))
+            .or_fatal();
+        }
+        t.record(Ok(randomness))
+    }
 }
 
 impl<C> ActorOps for DefaultKernel<C>
@@ -806,26 +1211,8 @@ where
         actor_id: ActorID,
         delegated_address: Option<Address>,
     ) -> Result<()> {
-        let is_allowed_to_create_actor = self.actor_id == INIT_ACTOR_ID;
-
-        #[cfg(feature = "testing")]
-        let is_allowed_to_create_actor =
-            is_allowed_to_create_actor || self.actor_id == TEST_ACTOR_ALLOWED_TO_CALL_CREATE_ACTOR;
-
-        if !is_allowed_to_create_actor {
-            return Err(syscall_error!(
-                Forbidden,
-                "create_actor is restricted to InitActor. Called by {}",
-                self.actor_id
-            )
-            .into());
-        }
-
-        if self.read_only {
-            return Err(
-                syscall_error!(ReadOnly, "create_actor cannot be called while read-only").into(),
-            );
-        }
+        self.check_create_actor("create_actor")?;
+        self.check_delegated_address(delegated_address.as_ref())?;
 
         self.call_manager
             .create_actor(code_id, actor_id, delegated_address)
@@ -833,19 +1220,38 @@ where
 
     fn install_actor(&mut self, code_id: Cid) -> Result<()> {
         let start = GasTimer::start();
-        let size = self
+        let wasm = self
             .call_manager
-            .engine()
-            .preload(self.call_manager.blockstore(), &[code_id])
-            .context("failed to install actor")
-            .or_illegal_argument()?;
+            .blockstore()
+            .get(&code_id)
+            .or_fatal()?
+            .ok_or_else(|| syscall_error!(NotFound; "no code with CID {}", code_id))?;
 
-        let t = self
-            .call_manager
-            .charge_gas(self.call_manager.price_list().on_install_actor(size))?;
-        t.stop_with(start);
+        self.install_wasm(&code_id, &wasm, start)
+    }
 
-        Ok(())
+    fn install_actor_code(&mut self, code_id: BlockId) -> Result<Cid> {
+        let start = GasTimer::start();
+        let block = self.blocks.get(code_id)?.clone();
+        if block.codec() != IPLD_RAW {
+            return Err(
+                syscall_error!(IllegalCodec; "actor code must be IPLD_RAW, not {}", block.codec())
+                    .into(),
+            );
+        }
+        let k = Cid::new_v1(IPLD_RAW, SupportedHashes::Blake2b256.digest(block.data()));
+
+        self.install_wasm(&k, block.data(), start)?;
+
+        self.call_manager
+            .blockstore()
+            .put_keyed(&k, block.data())
+            .or_fatal()?;
+        self.blocks.mark_reachable(&k);
+        // The code isn't reachable from the state root, so make sure it survives the next flush.
+        self.call_manager.machine_mut().retain_block(k);
+
+        Ok(k)
     }
 
     fn get_builtin_actor_type(&self, code_cid: &Cid) -> Result<u32> {
@@ -904,15 +1310,21 @@ impl<C> DebugOps for DefaultKernel<C>
 where
     C: CallManager,
 {
-    fn log(&self, msg: String) {
-        println!("{}", msg)
+    fn log(&mut self, msg: String) {
+        println!("{}", msg);
+        if self.debug_enabled() {
+            self.call_manager.append_debug_output(DebugOutput::Log {
+                actor: self.actor_id,
+                message: msg,
+            });
+        }
     }
 
     fn debug_enabled(&self) -> bool {
         self.call_manager.context().actor_debugging
     }
 
-    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()> {
+    fn store_artifact(&mut self, name: &str, data: &[u8]) -> Result<()> {
         // Ensure well formed artifact name
         {
             if name.len() > MAX_ARTIFACT_NAME_LEN {
@@ -955,10 +1367,29 @@ where
             }
         } else {
             log::error!(
-                "store_artifact was ignored, env var {} was not set",
+                "store_artifact was not written to disk, env var {} was not set",
                 ENV_ARTIFACT_DIR
             )
         }
+
+        if self.debug_enabled() {
+            self.call_manager
+                .append_debug_output(DebugOutput::Artifact {
+                    actor: self.actor_id,
+                    name: name.to_owned(),
+                    data: data.to_vec(),
+                });
+        }
+        Ok(())
+    }
+
+    fn record_metric(&self, name: &str, kind: MetricKind, value: i64) -> Result<()> {
+        if name.is_empty() || name.len() > MAX_METRIC_NAME_LEN {
+            return Err(syscall_error!(IllegalArgument; "metric name must be 1 to {} bytes", MAX_METRIC_NAME_LEN).into());
+        }
+        if let Some(sink) = self.call_manager.machine().metric_sink() {
+            sink.record_metric(self.actor_id, name, kind, value);
+        }
         Ok(())
     }
 }
@@ -984,28 +1415,46 @@ where
         event_keys: &[u8],
         event_values: &[u8],
     ) -> Result<()> {
-        const MAX_NR_ENTRIES: usize = 255;
-        const MAX_KEY_LEN: usize = 31;
-        const MAX_TOTAL_VALUES_LEN: usize = 8 << 10;
-
         if self.read_only {
             return Err(syscall_error!(ReadOnly; "cannot emit events while read-only").into());
         }
 
+        // The indexed keys and values, as declared by the headers. We validate the headers below,
+        // but charge up-front (saturating on bogus lengths).
+        let indexed_size = event_headers.iter().fold(0usize, |size, header| {
+            let flags = header.flags;
+            let mut size = size;
+            if flags.contains(Flags::FLAG_INDEXED_KEY) {
+                size = size.saturating_add(header.key_len as usize);
+            }
+            if flags.contains(Flags::FLAG_INDEXED_VALUE) {
+                size = size.saturating_add(header.val_len as usize);
+            }
+            size
+        });
+
         let t = self
             .call_manager
             .charge_gas(self.call_manager.price_list().on_actor_event(
                 event_headers.len(),
                 event_keys.len(),
                 event_values.len(),
+                indexed_size,
             ))?;
 
-        if event_headers.len() > MAX_NR_ENTRIES {
-            return Err(syscall_error!(LimitExceeded; "event exceeded max entries: {} > {MAX_NR_ENTRIES}", event_headers.len()).into());
+        let limits = &self.call_manager.context().event_limits;
+
+        let payload = self.call_manager.events_payload() + event_keys.len() + event_values.len();
+        if payload > limits.max_message_payload {
+            return Err(syscall_error!(LimitExceeded; "events exceeded the max payload per message: {} > {}", payload, limits.max_message_payload).into());
+        }
+
+        if event_headers.len() > limits.max_entries {
+            return Err(syscall_error!(LimitExceeded; "event exceeded max entries: {} > {}", event_headers.len(), limits.max_entries).into());
         }
 
-        if event_values.len() > MAX_TOTAL_VALUES_LEN {
-            return Err(syscall_error!(LimitExceeded; "total event value lengths exceeded the max size: {} > {MAX_TOTAL_VALUES_LEN}", event_values.len()).into());
+        if event_values.len() > limits.max_total_values_len {
+            return Err(syscall_error!(LimitExceeded; "total event value lengths exceeded the max size: {} > {}", event_values.len(), limits.max_total_values_len).into());
         }
 
         // We validate utf8 all at once for better performance.
@@ -1015,26 +1464,34 @@ where
 
         let mut key_offset: usize = 0;
         let mut val_offset: usize = 0;
+        let mut indexed_entries: usize = 0;
 
         let mut entries: Vec<Entry> = Vec::with_capacity(event_headers.len());
         for header in event_headers {
             // make sure that the fixed parsed values are within bounds before we do any allocation
             let flags = header.flags;
-            if Flags::from_bits(flags.bits()).is_none() {
+            if !Flags::from_bits(flags.bits()).map_or(false, |f| limits.flags.contains(f)) {
                 return Err(
                     syscall_error!(IllegalArgument; "event flags are invalid: {}", flags.bits())
                         .into(),
                 );
             }
 
-            if header.key_len > MAX_KEY_LEN as u32 {
+            if flags.intersects(Flags::FLAG_INDEXED_ALL) {
+                indexed_entries += 1;
+                if indexed_entries > limits.max_indexed_entries {
+                    return Err(syscall_error!(LimitExceeded; "event exceeded max indexed entries: {}", limits.max_indexed_entries).into());
+                }
+            }
+
+            if header.key_len > limits.max_key_len as u32 {
                 let tmp = header.key_len;
-                return Err(syscall_error!(LimitExceeded; "event key exceeded max size: {} > {MAX_KEY_LEN}", tmp).into());
+                return Err(syscall_error!(LimitExceeded; "event key exceeded max size: {} > {}", tmp, limits.max_key_len).into());
             }
 
             // We check this here purely to detect/prevent integer overflows below. That's why we
             // return IllegalArgument, not LimitExceeded.
-            if header.val_len > MAX_TOTAL_VALUES_LEN as u32 {
+            if header.val_len > limits.max_total_values_len as u32 {
                 return Err(
                     syscall_error!(IllegalArgument; "event entry value out of range").into(),
                 );
@@ -1051,12 +1508,11 @@ where
                 .context("event entry value out of range")
                 .or_illegal_argument()?;
 
-            // Check the codec. We currently only allow IPLD_RAW.
-            if header.codec != IPLD_RAW {
-                let tmp = header.codec;
+            // Check the codec.
+            let codec = header.codec;
+            if !limits.codecs.contains(&codec) {
                 return Err(
-                    syscall_error!(IllegalCodec; "event codec must be IPLD_RAW, was: {}", tmp)
-                        .into(),
+                    syscall_error!(IllegalCodec; "event codec not allowed: {}", codec).into(),
                 );
             }
 
@@ -1,5 +1,33 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+//! Errors raised while executing a message.
+//!
+//! The kernel, syscalls, and call manager all return [`ExecutionError`]s, which fall into three
+//! classes:
+//!
+//! - [`ExecutionError::Syscall`]: the actor made an invalid request (bad arguments, missing
+//!   actor, insufficient funds, etc.). The syscall fails with the error's [`ErrorNumber`] and the
+//!   actor decides what to do; execution continues.
+//! - [`ExecutionError::OutOfGas`]: the message ran out of gas. This aborts the running actor and,
+//!   as gas is shared by the whole call stack, every caller up to the message's receiver. The
+//!   message fails with `SYS_OUT_OF_GAS`.
+//! - [`ExecutionError::Fatal`]: something went wrong in the node or the FVM itself (e.g., a
+//!   missing state block, a bug). This aborts the whole message without a receipt, so it must
+//!   never be triggered by an actor.
+//!
+//! Conversion rules:
+//!
+//! - Errors are classified where they're raised, with [`syscall_error!`](crate::syscall_error),
+//!   [`ClassifyResult::or_error`] (and [`ClassifyResult::or_illegal_argument`]), or
+//!   [`ClassifyResult::or_fatal`]. The only implicit conversion _into_ an [`ExecutionError`] is
+//!   from a [`SyscallError`].
+//! - [`Context`] adds context without changing the class (and error number).
+//! - An [`ExecutionError`] converts into an [`anyhow::Error`] (losing its class and error
+//!   number). This is only meant for reporting an error as fatal.
+//! - At the Wasm boundary, syscall errors are returned to the actor as error numbers, and out of
+//!   gas and fatal errors become the equivalent `Abort`s (unwinding the actor's Wasm stack). An
+//!   abort with an exit code (or out of gas) becomes an exit code in the call manager, while a
+//!   fatal abort becomes a fatal error again.
 use std::fmt::Display;
 
 use derive_more::Display;
@@ -194,3 +222,64 @@ fn test_syscall_error_formatting() {
         "msg"
     );
 }
+
+#[test]
+fn test_classify_result() {
+    let err: std::result::Result<(), &str> = Err("bad");
+    match err.or_error(ErrorNumber::NotFound) {
+        Err(ExecutionError::Syscall(SyscallError(msg, ErrorNumber::NotFound))) => {
+            assert_eq!(msg, "bad")
+        }
+        other => panic!("expected a syscall error, got {other:?}"),
+    }
+    assert!(matches!(
+        err.or_illegal_argument(),
+        Err(ExecutionError::Syscall(SyscallError(
+            _,
+            ErrorNumber::IllegalArgument
+        )))
+    ));
+    assert!(matches!(
+        Err::<(), _>(anyhow::anyhow!("bad")).or_fatal(),
+        Err(ExecutionError::Fatal(_))
+    ));
+    assert_eq!(Ok::<_, &str>(1).or_illegal_argument().unwrap(), 1);
+}
+
+#[test]
+fn test_error_classes() {
+    let syscall = || ExecutionError::from(syscall_error!(Forbidden; "nope"));
+    let fatal = || ExecutionError::Fatal(anyhow::anyhow!("broken"));
+
+    assert!(!syscall().is_fatal());
+    assert!(!ExecutionError::OutOfGas.is_fatal());
+    assert!(fatal().is_fatal());
+
+    // Context preserves the class and error number.
+    match syscall().context("sending") {
+        ExecutionError::Syscall(SyscallError(msg, ErrorNumber::Forbidden)) => {
+            assert_eq!(msg, "sending: nope")
+        }
+        other => panic!("expected a syscall error, got {other:?}"),
+    }
+    assert!(matches!(
+        ExecutionError::OutOfGas.context("sending"),
+        ExecutionError::OutOfGas
+    ));
+    match fatal().with_context(|| "sending") {
+        ExecutionError::Fatal(err) => assert_eq!(format!("{err:#}"), "sending: broken"),
+        other => panic!("expected a fatal error, got {other:?}"),
+    }
+    assert!(matches!(
+        Err::<(), _>(ExecutionError::OutOfGas).context("sending"),
+        Err(ExecutionError::OutOfGas)
+    ));
+
+    // Conversions into anyhow are lossy.
+    assert_eq!(anyhow::Error::from(syscall()).to_string(), "nope");
+    assert_eq!(
+        anyhow::Error::from(ExecutionError::OutOfGas).to_string(),
+        "out of gas"
+    );
+    assert_eq!(anyhow::Error::from(fatal()).to_string(), "broken");
+}
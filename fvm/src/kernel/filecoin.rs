@@ -7,21 +7,23 @@ use std::panic::{self, UnwindSafe};
 use ambassador::Delegate;
 use filecoin_proofs_api::{self as proofs, ProverId, PublicReplicaInfo, SectorId};
 
+use futures::executor::block_on;
+use futures::future::BoxFuture;
+use futures::FutureExt;
 use fvm_ipld_encoding::bytes_32;
 use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ErrorNumber;
 use fvm_shared::piece::{zero_piece_commitment, PaddedPieceSize};
 use fvm_shared::sector::{RegisteredPoStProof, SectorInfo};
 use fvm_shared::{commcid, ActorID};
 use lazy_static::lazy_static;
-use rayon::iter::{
-    IndexedParallelIterator, IntoParallelRefIterator, ParallelDrainRange, ParallelIterator,
-};
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
 use super::blocks::BlockRegistry;
 use super::error::Result;
 use super::*;
 use crate::call_manager::CallManager;
-use crate::externs::Consensus;
+use crate::externs::{Consensus, Externs, ProofsBackend};
 use crate::*;
 
 lazy_static! {
@@ -40,6 +42,11 @@ pub trait FilecoinKernel: Kernel {
     /// Verifies a window proof of spacetime.
     fn verify_post(&self, verify_info: &WindowPoStVerifyInfo) -> Result<bool>;
 
+    /// Verifies a batch of window proofs of spacetime, returning one result per proof (in input
+    /// order). Gas is charged for every proof, as if verified individually, before any proof is
+    /// verified. Malformed proofs are reported as invalid.
+    fn batch_verify_posts(&self, infos: &[WindowPoStVerifyInfo]) -> Result<Vec<bool>>;
+
     /// Verifies that two block headers provide proof of a consensus fault:
     /// - both headers mined by the same actor
     /// - headers are different
@@ -119,9 +126,37 @@ where
             .charge_gas(self.0.call_manager.price_list().on_verify_post(verify_info))?;
 
         // This is especially important to catch as, otherwise, a bad "post" could be undisputable.
-        t.record(catch_and_log_panic("verifying post", || {
-            verify_post(verify_info)
-        }))
+        let backend = self.0.call_manager.externs().proofs_backend();
+        t.record(catch_and_log_panic(
+            "verifying post",
+            panic::AssertUnwindSafe(|| block_on(backend.verify_post(verify_info))),
+        ))
+    }
+
+    fn batch_verify_posts(&self, infos: &[WindowPoStVerifyInfo]) -> Result<Vec<bool>> {
+        let mut items = Vec::with_capacity(infos.len());
+        for info in infos {
+            let t = self
+                .0
+                .call_manager
+                .charge_gas(self.0.call_manager.price_list().on_verify_post(info))?;
+            items.push((info, t));
+        }
+        let backend = self.0.call_manager.externs().proofs_backend();
+        let results = self.verify_batch(items, |(info, timer)| {
+            let start = GasTimer::start();
+            let res = catch_and_log_panic(
+                "verifying post in batch",
+                panic::AssertUnwindSafe(|| block_on(backend.verify_post(info))),
+            );
+            timer.stop_with(start);
+            res
+        });
+        results
+            .into_iter()
+            .zip(infos)
+            .map(|(res, info)| batch_result("post", info.prover, res))
+            .collect()
     }
 
     fn verify_consensus_fault(
@@ -163,42 +198,21 @@ where
             items.push((vi, t));
         }
         log::debug!("batch verify seals start");
-        let out = items.par_drain(..)
-            .with_min_len(vis.len() / *NUM_CPUS)
-            .map(|(seal, timer)| {
-                let start = GasTimer::start();
-                let verify_seal_result = std::panic::catch_unwind(|| verify_seal(seal));
-                let ok = match verify_seal_result {
-                    Ok(res) => {
-                        match res {
-                            Ok(correct) => {
-                                if !correct {
-                                    log::debug!(
-                                        "seal verify in batch failed (miner: {}) (err: Invalid Seal proof)",
-                                        seal.sector_id.miner
-                                    );
-                                }
-                                correct // all ok
-                            }
-                            Err(err) => {
-                                log::debug!(
-                                    "seal verify in batch failed (miner: {}) (err: {})",
-                                    seal.sector_id.miner,
-                                    err
-                                );
-                                false
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        log::error!("seal verify internal fail (miner: {}) (err: {:?})", seal.sector_id.miner, e);
-                        false
-                    }
-                };
-                timer.stop_with(start);
-                ok
-            })
-            .collect();
+        let backend = self.0.call_manager.externs().proofs_backend();
+        let results = self.verify_batch(items, |(seal, timer)| {
+            let start = GasTimer::start();
+            let res = catch_and_log_panic(
+                "verifying seal in batch",
+                panic::AssertUnwindSafe(|| block_on(backend.verify_seal(seal))),
+            );
+            timer.stop_with(start);
+            res
+        });
+        let out = results
+            .into_iter()
+            .zip(vis)
+            .map(|(res, seal)| batch_result("seal", seal.sector_id.miner, res))
+            .collect::<Result<Vec<bool>>>()?;
         log::debug!("batch verify seals end");
         Ok(out)
     }
@@ -210,8 +224,13 @@ where
                 .price_list()
                 .on_verify_aggregate_seals(aggregate),
         )?;
-        t.record(catch_and_log_panic("verifying aggregate seals", || {
-            verify_aggregate_seals(aggregate)
+        let pool = self.0.call_manager.machine().proof_pool();
+        let backend = self.0.call_manager.externs().proofs_backend();
+        t.record(in_proof_pool(pool, || {
+            catch_and_log_panic(
+                "verifying aggregate seals",
+                panic::AssertUnwindSafe(|| block_on(backend.verify_aggregate_seals(aggregate))),
+            )
         }))
     }
 
@@ -222,9 +241,41 @@ where
                 .price_list()
                 .on_verify_replica_update(replica),
         )?;
-        t.record(catch_and_log_panic("verifying replica update", || {
-            verify_replica_update(replica)
-        }))
+        let backend = self.0.call_manager.externs().proofs_backend();
+        t.record(catch_and_log_panic(
+            "verifying replica update",
+            panic::AssertUnwindSafe(|| block_on(backend.verify_replica_update(replica))),
+        ))
+    }
+}
+
+impl<C> DefaultFilecoinKernel<DefaultKernel<C>>
+where
+    C: CallManager,
+{
+    /// Applies `f` to each proof of a batch on the machine's proof pool, returning the results in
+    /// order. The batch is split into at most
+    /// [`proof_parallelism_per_message`](crate::machine::MachineContext::proof_parallelism_per_message)
+    /// chunks, each verified sequentially, so one message never verifies more proofs concurrently.
+    fn verify_batch<T, R, F>(&self, items: Vec<T>, f: F) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(T) -> R + Sync,
+    {
+        let max_parallelism = match self.0.call_manager.context().proof_parallelism_per_message {
+            0 => *NUM_CPUS,
+            n => n,
+        };
+        let chunks = split_batch(items, max_parallelism);
+        let pool = self.0.call_manager.machine().proof_pool();
+        let results: Vec<Vec<R>> = in_proof_pool(pool, || {
+            chunks
+                .into_par_iter()
+                .map(|chunk| chunk.into_iter().map(&f).collect())
+                .collect()
+        });
+        results.into_iter().flatten().collect()
     }
 }
 
@@ -266,6 +317,18 @@ where
         self.0.upgrade_actor::<Self>(new_code_cid, params_id)
     }
 
+    fn create_and_invoke<K: Kernel<CallManager = Self::CallManager>>(
+        &mut self,
+        code_cid: Cid,
+        actor_id: ActorID,
+        delegated_address: Option<Address>,
+        params_id: BlockId,
+        value: &TokenAmount,
+    ) -> Result<CallResult> {
+        self.0
+            .create_and_invoke::<Self>(code_cid, actor_id, delegated_address, params_id, value)
+    }
+
     fn new(
         mgr: C,
         blocks: BlockRegistry,
@@ -287,6 +350,92 @@ where
     }
 }
 
+/// The default [`ProofsBackend`]: verifies proofs in-process, on the thread awaiting the result.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SyncProofsBackend;
+
+impl ProofsBackend for SyncProofsBackend {
+    fn verify_seal<'a>(&'a self, info: &'a SealVerifyInfo) -> BoxFuture<'a, Result<bool>> {
+        async move { verify_seal(info) }.boxed()
+    }
+
+    fn verify_post<'a>(&'a self, info: &'a WindowPoStVerifyInfo) -> BoxFuture<'a, Result<bool>> {
+        async move { verify_post(info) }.boxed()
+    }
+
+    fn verify_aggregate_seals<'a>(
+        &'a self,
+        aggregate: &'a AggregateSealVerifyProofAndInfos,
+    ) -> BoxFuture<'a, Result<bool>> {
+        async move { verify_aggregate_seals(aggregate) }.boxed()
+    }
+
+    fn verify_replica_update<'a>(
+        &'a self,
+        replica: &'a ReplicaUpdateInfo,
+    ) -> BoxFuture<'a, Result<bool>> {
+        async move { verify_replica_update(replica) }.boxed()
+    }
+}
+
+/// Runs `f` on the machine's proof verification pool, if it has one.
+fn in_proof_pool<T, F>(pool: Option<&rayon::ThreadPool>, f: F) -> T
+where
+    T: Send,
+    F: FnOnce() -> T + Send,
+{
+    match pool {
+        Some(pool) => pool.install(f),
+        None => f(),
+    }
+}
+
+/// Splits `items` into at most `max_chunks` chunks of (nearly) equal size, preserving order.
+fn split_batch<T>(items: Vec<T>, max_chunks: usize) -> Vec<Vec<T>> {
+    let chunk_len = ((items.len() + max_chunks - 1) / max_chunks).max(1);
+    let mut items = items.into_iter().peekable();
+    let mut chunks = Vec::new();
+    while items.peek().is_some() {
+        chunks.push(items.by_ref().take(chunk_len).collect());
+    }
+    chunks
+}
+
+/// Interprets the result of verifying one proof of a batch.
+///
+/// Invalid proofs (`Ok(false)` or an `IllegalArgument` error) are reported as invalid. Fatal
+/// errors abort the batch, as do any other errors, which the backend shouldn't return: reporting
+/// a proof as invalid because the backend failed to verify it would fork the node off the chain.
+fn batch_result(kind: &str, miner: ActorID, res: Result<bool>) -> Result<bool> {
+    match res {
+        Ok(true) => Ok(true),
+        Ok(false) => {
+            log::debug!(
+                "{} verify in batch failed (miner: {}) (err: Invalid proof)",
+                kind,
+                miner
+            );
+            Ok(false)
+        }
+        Err(ExecutionError::Syscall(SyscallError(msg, ErrorNumber::IllegalArgument))) => {
+            log::debug!(
+                "{} verify in batch failed (miner: {}) (err: {})",
+                kind,
+                miner,
+                msg
+            );
+            Ok(false)
+        }
+        Err(e) if e.is_fatal() => Err(e),
+        Err(e) => Err(ExecutionError::Fatal(anyhow::anyhow!(
+            "proofs backend failed to verify {} (miner: {}): {}",
+            kind,
+            miner,
+            e
+        ))),
+    }
+}
+
 fn catch_and_log_panic<F: FnOnce() -> Result<R> + UnwindSafe, R>(context: &str, f: F) -> Result<R> {
     match panic::catch_unwind(f) {
         Ok(v) => v,
@@ -16,9 +16,9 @@ use fvm_shared::sector::{
     AggregateSealVerifyProofAndInfos, RegisteredSealProof, ReplicaUpdateInfo, SealVerifyInfo,
     WindowPoStVerifyInfo,
 };
-use fvm_shared::sys::out::network::NetworkContext;
+use fvm_shared::sys::out::network::{EpochTiming, NetworkContext};
 use fvm_shared::sys::out::vm::MessageContext;
-use fvm_shared::sys::SendFlags;
+use fvm_shared::sys::{MetricKind, PricedOperation, ReentrancyPolicy, SendFlags};
 use fvm_shared::{ActorID, MethodNum};
 
 mod blocks;
@@ -46,6 +46,9 @@ pub struct CallResult {
     pub block_id: BlockId,
     pub block_stat: BlockStat,
     pub exit_code: ExitCode,
+    /// The gas consumed by the call: the callee's execution, and the cost of the call itself
+    /// (e.g., the value transfer).
+    pub gas_used: Gas,
 }
 
 /// The "kernel" implements the FVM interface as presented to the actors. It:
@@ -124,6 +127,26 @@ pub trait Kernel:
         new_code_cid: Cid,
         params_id: BlockId,
     ) -> Result<CallResult>;
+
+    /// Creates an actor (see [`ActorOps::create_actor`]) and invokes its constructor with the
+    /// supplied parameters and value as a single atomic operation. If the constructor exits with
+    /// a non-zero exit code, the actor's creation is rolled back along with the constructor's
+    /// state changes.
+    ///
+    /// Unlike [`ActorOps::create_actor`], any actor may call this (e.g., user-deployed factories),
+    /// provided [`NetworkConfig::actor_factories`](crate::machine::NetworkConfig::actor_factories)
+    /// is enabled. Only the init actor allocates actor IDs: other callers must pass an `actor_id`
+    /// of 0, and the new actor is registered under the [next actor address](ActorOps::next_actor_address)
+    /// instead. It's part of the Kernel trait (rather than [`ActorOps`]) for the same reason as
+    /// [`Kernel::send`].
+    fn create_and_invoke<K: Kernel<CallManager = Self::CallManager>>(
+        &mut self,
+        code_cid: Cid,
+        actor_id: ActorID,
+        delegated_address: Option<Address>,
+        params_id: BlockId,
+        value: &TokenAmount,
+    ) -> Result<CallResult>;
 }
 
 pub trait SyscallHandler<K: Kernel>: Sized {
@@ -138,6 +161,12 @@ pub trait NetworkOps {
 
     /// The CID of the tipset at the specified epoch.
     fn tipset_cid(&self, epoch: ChainEpoch) -> Result<Cid>;
+
+    /// The fingerprint of the machine's consensus-relevant configuration.
+    fn machine_fingerprint(&self) -> Result<[u8; 32]>;
+
+    /// The network's epoch duration and expected blocks per epoch.
+    fn epoch_timing(&self) -> Result<EpochTiming>;
 }
 
 /// Accessors to query attributes of the incoming message.
@@ -145,6 +174,19 @@ pub trait NetworkOps {
 pub trait MessageOps {
     /// Message information.
     fn msg_context(&self) -> Result<MessageContext>;
+
+    /// The depth of the current invocation on the call stack, starting at 1 for the top-level
+    /// message. Sends fail with `LimitExceeded` once this reaches the network's maximum call depth.
+    fn call_depth(&self) -> Result<u32>;
+
+    /// The IDs of the actors on the call stack, starting with the receiver of the top-level message
+    /// and ending with the current actor. The origin of the message isn't included (see
+    /// [`MessageContext::origin`]).
+    fn call_stack(&self) -> Result<Vec<ActorID>>;
+
+    /// Sets whether the current actor may be re-entered before the current invocation returns.
+    /// Calls that would re-enter it fail with `Forbidden`.
+    fn set_reentrancy(&mut self, policy: ReentrancyPolicy) -> Result<()>;
 }
 
 /// The IPLD subset of the kernel.
@@ -152,21 +194,32 @@ pub trait MessageOps {
 pub trait IpldBlockOps {
     /// Open a block.
     ///
-    /// This method will fail if the requested block isn't reachable.
+    /// This method will fail if the requested block isn't reachable. If inline CIDs are linkable
+    /// (see [`InlineCidLimits`](crate::machine::InlineCidLimits)), identity-hashed CIDs are opened
+    /// without touching the blockstore and needn't be reachable, but their links must be.
     fn block_open(&mut self, cid: &Cid) -> Result<(BlockId, BlockStat)>;
 
     /// Create a new block.
     ///
-    /// This method will fail if the block is too large (SPEC_AUDIT), the codec is not allowed
-    /// (SPEC_AUDIT), the block references unreachable blocks, or the block contains too many links
-    /// (SPEC_AUDIT).
+    /// The block is parsed with the given codec to find its links, each of which must point to a
+    /// reachable block (only DAG-CBOR blocks can contain links). This method will fail if the block
+    /// is larger than
+    /// [`NetworkConfig::max_block_size`](crate::machine::NetworkConfig::max_block_size), the codec
+    /// is not allowed by [`NetworkConfig::codecs`](crate::machine::NetworkConfig::codecs), the
+    /// block references unreachable blocks, or the block contains more than
+    /// [`NetworkConfig::max_block_links`](crate::machine::NetworkConfig::max_block_links) links.
     fn block_create(&mut self, codec: u64, data: &[u8]) -> Result<BlockId>;
 
     /// Computes a CID for a block.
     ///
     /// This is the only way to add a new block to the "reachable" set.
     ///
-    /// This method will fail if the block handle is invalid.
+    /// This method will fail if the block handle is invalid, or if the multihash isn't allowed by
+    /// [`NetworkConfig::codecs`](crate::machine::NetworkConfig::codecs).
+    ///
+    /// If inline CIDs are linkable (see [`InlineCidLimits`](crate::machine::InlineCidLimits)), the
+    /// identity hash may be used to inline small blocks into the CID instead. The hash length must
+    /// then be the block's size.
     fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid>;
 
     /// Read data from a block.
@@ -229,8 +282,14 @@ pub trait ActorOps {
         delegated_address: Option<Address>,
     ) -> Result<()>;
 
+    /// Validates, compiles, and caches the actor code stored under `code_cid`, making it
+    /// available for actor creation. See [`crate::engine::EnginePool::install_actor_code`].
     fn install_actor(&mut self, code_cid: Cid) -> Result<()>;
 
+    /// Installs the actor code in the specified (IPLD_RAW) block, like
+    /// [`ActorOps::install_actor`], storing it in the blockstore and returning its CID.
+    fn install_actor_code(&mut self, code_id: BlockId) -> Result<Cid>;
+
     /// Returns the actor's "type" (if builitin) or 0 (if not).
     fn get_builtin_actor_type(&self, code_cid: &Cid) -> Result<u32>;
 
@@ -270,12 +329,23 @@ pub trait GasOps {
 
     /// Returns the currently active gas price list.
     fn price_list(&self) -> &PriceList;
+
+    /// Returns the gas an operation of the given size would currently cost, per the active price
+    /// list. See [`PricedOperation`] for the meaning of `param`.
+    fn price_of(&self, op: PricedOperation, size: usize, param: u64) -> Result<Gas>;
+
+    /// Records a call to the given syscall, for the
+    /// [syscall census](crate::machine::MachineContext::syscall_census).
+    fn record_syscall(&self, module: &'static str, name: &'static str);
 }
 
 /// Cryptographic primitives provided by the kernel.
 #[delegatable_trait]
 pub trait CryptoOps {
     /// Verifies that a signature is valid for an address and plaintext.
+    ///
+    /// Signers must have key (f1/f3) addresses, or f4 addresses in a namespace the machine has a
+    /// [`SignatureVerifier`](crate::machine::SignatureVerifier) for.
     fn verify_signature(
         &self,
         sig_type: SignatureType,
@@ -314,20 +384,30 @@ pub trait RandomnessOps {
     /// This randomness is not tied to any fork of the chain, and is unbiasable.
     fn get_randomness_from_beacon(&self, rand_epoch: ChainEpoch)
         -> Result<[u8; RANDOMNESS_LENGTH]>;
+
+    /// Like [`RandomnessOps::get_randomness_from_beacon`], but returns the beacon randomness at
+    /// the length used by the current network version, which may differ from 32 bytes.
+    fn get_randomness_from_beacon_bytes(&self, rand_epoch: ChainEpoch) -> Result<Randomness>;
 }
 
 /// Debugging APIs.
 #[delegatable_trait]
 pub trait DebugOps {
-    /// Log a message.
-    fn log(&self, msg: String);
+    /// Log a message. When actor debugging is enabled, the message is also returned in
+    /// [`ApplyRet::debug_output`](crate::executor::ApplyRet::debug_output).
+    fn log(&mut self, msg: String);
 
     /// Returns whether debug mode is enabled.
     fn debug_enabled(&self) -> bool;
 
-    /// Store an artifact.
+    /// Store an artifact. When actor debugging is enabled, the artifact is also returned in
+    /// [`ApplyRet::debug_output`](crate::executor::ApplyRet::debug_output).
     /// Returns error on malformed name, returns Ok and logs the error on system/os errors.
-    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()>;
+    fn store_artifact(&mut self, name: &str, data: &[u8]) -> Result<()>;
+
+    /// Report a metric to the host's [`MetricSink`](crate::machine::MetricSink), if any.
+    /// Returns error on malformed name.
+    fn record_metric(&self, name: &str, kind: MetricKind, value: i64) -> Result<()>;
 }
 
 /// Track and limit memory expansion.
@@ -345,7 +425,8 @@ pub trait LimiterOps {
 /// Eventing APIs.
 #[delegatable_trait]
 pub trait EventOps {
-    /// Records an event emitted throughout execution.
+    /// Records an event emitted throughout execution. The event must conform to the network's
+    /// [`EventLimits`](crate::machine::EventLimits).
     fn emit_event(
         &mut self,
         event_headers: &[fvm_shared::sys::EventEntry],
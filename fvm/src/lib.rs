@@ -7,14 +7,32 @@
 //!
 //! This package emits logs using the log façade. Configure the logging backend
 //! of your choice during the initialization of the consuming application.
+//!
+//! ## Tracing
+//!
+//! With the `tracing` feature, this package also emits [tracing](https://docs.rs/tracing) spans:
+//!
+//! - `execute_message` (info): each message applied by the default executor, with its sender,
+//!   receiver, method, gas limit, and, once applied, its exit code and the gas it used.
+//! - `call` (debug): each call frame, with the caller and receiver IDs, the method, and the gas
+//!   the call used.
+//! - `syscall` (trace): each syscall, with its module (category) and name.
+//!
+//! Export them to OpenTelemetry (e.g., with `tracing-opentelemetry`) to inspect slow messages.
+
+// Hash maps and sets iterate in a nondeterministic order, so consensus-critical modules must use
+// `fvm_shared::collections` instead (enforced with `disallowed_types`, see clippy.toml).
+#![allow(clippy::disallowed_types)]
 
 pub use kernel::default::DefaultKernel;
 pub use kernel::Kernel;
 
+#[deny(clippy::disallowed_types)]
 pub mod call_manager;
 pub mod engine;
 pub mod executor;
 pub mod externs;
+#[deny(clippy::disallowed_types)]
 pub mod kernel;
 pub mod machine;
 pub mod syscalls;
@@ -22,7 +40,7 @@ pub mod syscalls;
 pub mod gas;
 pub mod state_tree;
 
-mod blockstore;
+pub mod blockstore;
 
 #[cfg(not(feature = "testing"))]
 mod account_actor;
@@ -38,9 +56,12 @@ pub mod init_actor;
 #[cfg(feature = "testing")]
 pub mod system_actor;
 
+pub mod cron_actor;
 mod eam_actor;
 mod history_map;
 mod ipld;
+pub mod power_actor;
+pub mod reward_actor;
 pub mod trace;
 
 #[cfg(test)]
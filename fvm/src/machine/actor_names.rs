@@ -0,0 +1,22 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use cid::Cid;
+
+/// Maps actor code CIDs to human-readable names (e.g., "storageminer"), to label the frames of
+/// [call backtraces](crate::call_manager::Backtrace) of failed messages. Register it with
+/// [`DefaultMachine::with_actor_name_resolver`](super::DefaultMachine::with_actor_name_resolver).
+///
+/// Names are purely informational, and never affect execution.
+pub trait ActorNameResolver: Send + Sync + 'static {
+    /// Returns the name of the actor with the given code CID, if known.
+    fn actor_name(&self, code: &Cid) -> Option<String>;
+}
+
+impl<F> ActorNameResolver for F
+where
+    F: Fn(&Cid) -> Option<String> + Send + Sync + 'static,
+{
+    fn actor_name(&self, code: &Cid) -> Option<String> {
+        self(code)
+    }
+}
@@ -0,0 +1,55 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use multihash::MultihashDigest;
+
+use crate::kernel::SupportedHashes;
+
+/// Describes a namespace of delegated (f4) addresses, managed by the actor whose ID is the
+/// namespace. Register it with
+/// [`DefaultMachine::with_address_manager`](super::DefaultMachine::with_address_manager).
+///
+/// Only namespaces with a registered manager are usable: the FVM only lets the manager actor
+/// assign addresses in its namespace to new actors (through the init actor), only resolves
+/// addresses in registered namespaces, and only creates placeholder actors on sends to unassigned
+/// addresses in registered namespaces.
+///
+/// This is consensus-critical: every node on a network must register the same managers, for the
+/// same namespaces.
+pub trait AddressManager: Send + Sync + 'static {
+    /// Derives the subaddress the manager actor assigns to the actor it creates from `seed`, so
+    /// clients can predict the address of an actor before creating it. What the seed is depends on
+    /// the manager.
+    fn derive_subaddress(&self, seed: &[u8]) -> Vec<u8>;
+}
+
+/// The Ethereum address manager's namespace: subaddresses are Ethereum addresses, the last 20
+/// bytes of the keccak256 digest of the seed. The seed is what the Ethereum address manager hashes
+/// to assign addresses: the RLP-encoded creator address and nonce (`CREATE`), or `0xff`, the
+/// creator address, the salt, and the keccak256 digest of the init code (`CREATE2`).
+#[derive(Copy, Clone, Debug, Default)]
+pub struct EthAddressManager;
+
+impl AddressManager for EthAddressManager {
+    fn derive_subaddress(&self, seed: &[u8]) -> Vec<u8> {
+        SupportedHashes::Keccak256.digest(seed).digest()[12..].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AddressManager, EthAddressManager};
+
+    #[test]
+    fn eth_manager_derives_create_addresses() {
+        // rlp([0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0, 0])
+        let seed = [
+            0xd6, 0x94, 0x6a, 0xc7, 0xea, 0x33, 0xf8, 0x83, 0x1e, 0xa9, 0xdc, 0xc5, 0x33, 0x93,
+            0xaa, 0xa8, 0x8b, 0x25, 0xa7, 0x85, 0xdb, 0xf0, 0x80,
+        ];
+        let expected = [
+            0xcd, 0x23, 0x4a, 0x47, 0x1b, 0x72, 0xba, 0x2f, 0x1c, 0xcf, 0x0a, 0x70, 0xfc, 0xab,
+            0xa6, 0x48, 0xa5, 0xee, 0xcd, 0x8d,
+        ];
+        assert_eq!(EthAddressManager.derive_subaddress(&seed), expected);
+    }
+}
@@ -1,8 +1,12 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use cid::Cid;
+use fvm_shared::ActorID;
 
-use super::{Machine, MachineContext, Manifest};
+use super::{
+    ActorNameResolver, AddressManager, EventSink, Machine, MachineContext, Manifest, MetricSink,
+    Metrics, SignatureVerifier,
+};
 use crate::kernel::Result;
 use crate::state_tree::StateTree;
 
@@ -48,6 +52,11 @@ impl<M: Machine> Machine for Box<M> {
         (**self).flush()
     }
 
+    #[inline(always)]
+    fn retain_block(&mut self, k: Cid) {
+        (**self).retain_block(k)
+    }
+
     #[inline(always)]
     fn into_store(self) -> Self::Blockstore {
         (*self).into_store()
@@ -58,8 +67,48 @@ impl<M: Machine> Machine for Box<M> {
         (**self).machine_id()
     }
 
+    #[inline(always)]
+    fn fingerprint(&self) -> [u8; 32] {
+        (**self).fingerprint()
+    }
+
     #[inline(always)]
     fn new_limiter(&self) -> Self::Limiter {
         (**self).new_limiter()
     }
+
+    #[inline(always)]
+    fn proof_pool(&self) -> Option<&rayon::ThreadPool> {
+        (**self).proof_pool()
+    }
+
+    #[inline(always)]
+    fn event_sink(&self) -> Option<&dyn EventSink> {
+        (**self).event_sink()
+    }
+
+    #[inline(always)]
+    fn metric_sink(&self) -> Option<&dyn MetricSink> {
+        (**self).metric_sink()
+    }
+
+    #[inline(always)]
+    fn metrics(&self) -> &dyn Metrics {
+        (**self).metrics()
+    }
+
+    #[inline(always)]
+    fn signature_verifier(&self, namespace: ActorID) -> Option<&dyn SignatureVerifier> {
+        (**self).signature_verifier(namespace)
+    }
+
+    #[inline(always)]
+    fn address_manager(&self, namespace: ActorID) -> Option<&dyn AddressManager> {
+        (**self).address_manager(namespace)
+    }
+
+    #[inline(always)]
+    fn actor_name_resolver(&self) -> Option<&dyn ActorNameResolver> {
+        (**self).actor_name_resolver()
+    }
 }
@@ -0,0 +1,219 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Loading of builtin-actors bundles (CARs rooted at a versioned actor manifest), including
+//! multiple bundles for different network versions (e.g., for upgrade testing).
+use std::io::Read;
+
+use anyhow::{anyhow, Context as _};
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore;
+use fvm_shared::collections::DeterministicMap;
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::IDENTITY_HASH;
+
+use super::{car, Manifest, NetworkConfig};
+
+/// A builtin-actors bundle loaded into a blockstore.
+pub struct Bundle {
+    root: Cid,
+    version: u32,
+    actors: Vec<(String, Cid)>,
+    manifest: Manifest,
+}
+
+impl Bundle {
+    /// Loads the bundle rooted at `root` (a `(manifest version, manifest CID)` tuple) from the
+    /// blockstore, checking that the code of every actor in the manifest is present.
+    pub fn load<BS: Blockstore>(store: &BS, root: &Cid) -> anyhow::Result<Self> {
+        let (version, manifest_cid): (u32, Cid) = store
+            .get_cbor(root)?
+            .with_context(|| format!("cannot find bundle root {root}"))?;
+        let actors = Manifest::load_entries(store, &manifest_cid, version)
+            .with_context(|| format!("failed to load manifest of bundle {root}"))?;
+        for (name, code) in &actors {
+            if code.hash().code() != IDENTITY_HASH && !store.has(code)? {
+                return Err(anyhow!(
+                    "bundle {root} is missing the code of the {name} actor ({code})"
+                ));
+            }
+        }
+        let manifest = Manifest::new(actors.iter().map(|(name, code)| (name.as_str(), *code)))
+            .with_context(|| format!("invalid manifest in bundle {root}"))?;
+        Ok(Self {
+            root: *root,
+            version,
+            actors,
+            manifest,
+        })
+    }
+
+    /// Imports a bundle CAR (with a single root) into the blockstore, and loads it.
+    pub fn load_car<BS, R>(store: &BS, reader: R) -> anyhow::Result<Self>
+    where
+        BS: Blockstore,
+        R: Read + Send + Unpin,
+    {
+        match &*car::import_car(store, reader)? {
+            [root] => Self::load(store, root),
+            roots => Err(anyhow!(
+                "expected one root CID in bundle, got {}",
+                roots.len()
+            )),
+        }
+    }
+
+    /// Returns the bundle's root CID. This is the CID to pass to
+    /// [`NetworkConfig::override_actors`].
+    pub fn root(&self) -> &Cid {
+        &self.root
+    }
+
+    /// Returns the version of the bundle's manifest.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the bundle's actor manifest.
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Returns the code CID of the named actor, if it's in the bundle.
+    pub fn code_by_name(&self, name: &str) -> Option<&Cid> {
+        self.actors
+            .iter()
+            .find_map(|(n, code)| (n == name).then_some(code))
+    }
+
+    /// Returns the (name, code CID) pairs of the actors in the bundle, in manifest order.
+    pub fn actors(&self) -> impl Iterator<Item = (&str, &Cid)> {
+        self.actors.iter().map(|(name, code)| (name.as_str(), code))
+    }
+
+    /// Checks that the network config uses this bundle, if it overrides the builtin actors.
+    pub fn verify(&self, config: &NetworkConfig) -> anyhow::Result<()> {
+        match config.builtin_actors_override {
+            Some(expected) if expected != self.root => Err(anyhow!(
+                "bundle root {} doesn't match the configured builtin actors {expected}",
+                self.root
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Builtin-actors bundles for multiple network versions, loaded into the same blockstore.
+#[derive(Default)]
+pub struct Bundles {
+    by_version: DeterministicMap<NetworkVersion, Bundle>,
+}
+
+impl Bundles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the bundle for a network version, returning the bundle previously registered
+    /// for it (if any).
+    pub fn insert(&mut self, nv: NetworkVersion, bundle: Bundle) -> Option<Bundle> {
+        self.by_version.insert(nv, bundle)
+    }
+
+    /// Imports a bundle CAR into the blockstore, and registers it for a network version.
+    pub fn load_car<BS, R>(
+        &mut self,
+        store: &BS,
+        nv: NetworkVersion,
+        reader: R,
+    ) -> anyhow::Result<&Bundle>
+    where
+        BS: Blockstore,
+        R: Read + Send + Unpin,
+    {
+        let bundle = Bundle::load_car(store, reader)
+            .with_context(|| format!("failed to load bundle for network version {nv}"))?;
+        self.by_version.insert(nv, bundle);
+        Ok(&self.by_version[&nv])
+    }
+
+    /// Returns the bundle registered for a network version.
+    pub fn get(&self, nv: NetworkVersion) -> Option<&Bundle> {
+        self.by_version.get(&nv)
+    }
+
+    /// Returns the registered bundles, by network version.
+    pub fn iter(&self) -> impl Iterator<Item = (NetworkVersion, &Bundle)> {
+        self.by_version.iter().map(|(nv, bundle)| (*nv, bundle))
+    }
+
+    /// Configures the network config to use the bundle registered for its network version.
+    pub fn configure(&self, config: &mut NetworkConfig) -> anyhow::Result<()> {
+        let nv = config.network_version;
+        let bundle = self
+            .get(nv)
+            .ok_or_else(|| anyhow!("no bundle registered for network version {nv}"))?;
+        config.override_actors(*bundle.root());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::CborStore;
+    use fvm_shared::version::NetworkVersion;
+    use multihash::Code;
+
+    use super::{Bundle, Bundles};
+    use crate::machine::car::export_car;
+    use crate::machine::{Manifest, NetworkConfig};
+
+    fn bundle_car() -> (Vec<u8>, cid::Cid) {
+        let src = MemoryBlockstore::default();
+        let manifest = src
+            .put_cbor(&Manifest::DUMMY_CODES, Code::Blake2b256)
+            .unwrap();
+        let root = src.put_cbor(&(1u32, manifest), Code::Blake2b256).unwrap();
+        let mut car = Vec::new();
+        export_car(&src, &root, &mut car).unwrap();
+        (car, root)
+    }
+
+    #[test]
+    fn load_and_configure() {
+        let (car, root) = bundle_car();
+        let store = MemoryBlockstore::default();
+        let bundle = Bundle::load_car(&store, car.as_slice()).unwrap();
+        assert_eq!(bundle.root(), &root);
+        assert_eq!(bundle.version(), 1);
+        assert_eq!(
+            bundle.code_by_name("account"),
+            Some(bundle.manifest().get_account_code())
+        );
+
+        let mut bundles = Bundles::new();
+        bundles.insert(NetworkVersion::V21, bundle);
+        let mut config = NetworkConfig::new(NetworkVersion::V21);
+        bundles.configure(&mut config).unwrap();
+        assert_eq!(config.builtin_actors_override, Some(root));
+        bundles
+            .get(NetworkVersion::V21)
+            .unwrap()
+            .verify(&config)
+            .unwrap();
+
+        let mut config = NetworkConfig::new(NetworkVersion::V20);
+        bundles.configure(&mut config).unwrap_err();
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let store = MemoryBlockstore::default();
+        let manifest = store
+            .put_cbor(&Manifest::DUMMY_CODES, Code::Blake2b256)
+            .unwrap();
+        let root = store.put_cbor(&(2u32, manifest), Code::Blake2b256).unwrap();
+        assert!(Bundle::load(&store, &root).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Import/export of machine state as CARv1 streams, for snapshotting and restoring state (e.g., on
+//! devnets or in test fixtures) without a full node.
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, Context as _};
+use cid::Cid;
+use futures::executor::block_on;
+use futures::io::AllowStdIo;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_car::CarHeader;
+use fvm_ipld_encoding::DAG_CBOR;
+use fvm_shared::commcid::{FIL_COMMITMENT_SEALED, FIL_COMMITMENT_UNSEALED};
+use fvm_shared::IDENTITY_HASH;
+
+use crate::blockstore::scan_for_links;
+
+/// Writes every block reachable from `root` to `writer` as a CARv1 stream with `root` as its only
+/// root.
+///
+/// Identity-hashed CIDs are inlined in their parents and are therefore not written, and piece
+/// commitments are not followed. Fails if any other reachable block is missing from the store.
+pub fn export_car<BS, W>(store: &BS, root: &Cid, writer: W) -> anyhow::Result<()>
+where
+    BS: Blockstore,
+    W: Write + Send + Unpin,
+{
+    export_car_roots(store, &[*root], writer)
+}
+
+/// Like [`export_car`], but writes every block reachable from any of `roots`, with all of them as
+/// the CAR's roots. Blocks shared between roots are written once.
+pub fn export_car_roots<BS, W>(store: &BS, roots: &[Cid], writer: W) -> anyhow::Result<()>
+where
+    BS: Blockstore,
+    W: Write + Send + Unpin,
+{
+    let mut walker = DagWalker {
+        store,
+        // Walk the roots in order.
+        stack: roots.iter().rev().copied().collect(),
+        seen: HashSet::new(),
+        error: None,
+    };
+    let mut writer = AllowStdIo::new(writer);
+    block_on(
+        CarHeader::new(roots.to_vec(), 1)
+            .write_stream_async(&mut writer, &mut futures::stream::iter(&mut walker)),
+    )
+    .context("failed to write car")?;
+    match walker.error {
+        Some(e) => {
+            let roots: Vec<_> = roots.iter().map(Cid::to_string).collect();
+            Err(e.context(format!("failed to export state under {}", roots.join(", "))))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Reads a CARv1 stream into `store`, validating every block against its CID. Returns the CAR's
+/// roots.
+pub fn import_car<BS, R>(store: &BS, reader: R) -> anyhow::Result<Vec<Cid>>
+where
+    BS: Blockstore,
+    R: Read + Send + Unpin,
+{
+    block_on(fvm_ipld_car::load_car(store, AllowStdIo::new(reader))).context("failed to load car")
+}
+
+/// Lazily walks an IPLD DAG depth-first, yielding each block once. Iteration stops at the first
+/// error, which is recorded in `error`.
+struct DagWalker<'a, BS> {
+    store: &'a BS,
+    stack: Vec<Cid>,
+    seen: HashSet<Cid>,
+    error: Option<anyhow::Error>,
+}
+
+impl<BS: Blockstore> DagWalker<'_, BS> {
+    fn visit(&mut self, k: Cid) -> anyhow::Result<Option<(Cid, Vec<u8>)>> {
+        if !self.seen.insert(k) {
+            return Ok(None);
+        }
+        // We don't follow piece commitment CIDs.
+        if matches!(k.codec(), FIL_COMMITMENT_UNSEALED | FIL_COMMITMENT_SEALED) {
+            return Ok(None);
+        }
+        if k.hash().code() == IDENTITY_HASH {
+            if k.codec() == DAG_CBOR {
+                scan_for_links(k.hash().digest(), &mut self.stack)?;
+            }
+            return Ok(None);
+        }
+        let block = self
+            .store
+            .get(&k)?
+            .ok_or_else(|| anyhow!("missing block {k}"))?;
+        // At the moment, only DAG_CBOR can link to other blocks.
+        if k.codec() == DAG_CBOR {
+            scan_for_links(&block, &mut self.stack)?;
+        }
+        Ok(Some((k, block)))
+    }
+}
+
+impl<BS: Blockstore> Iterator for DagWalker<'_, BS> {
+    type Item = (Cid, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        while let Some(k) = self.stack.pop() {
+            match self.visit(k) {
+                Ok(Some(block)) => return Some(block),
+                Ok(None) => continue,
+                Err(e) => {
+                    self.error = Some(e);
+                    return None;
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+    use fvm_ipld_encoding::CborStore;
+    use multihash::Code;
+
+    use super::*;
+
+    #[test]
+    fn export_import_roundtrip() {
+        let src = MemoryBlockstore::default();
+        let leaf = src.put_cbor(&"leaf", Code::Blake2b256).unwrap();
+        let root = src.put_cbor(&(leaf, leaf, 1u8), Code::Blake2b256).unwrap();
+        // Not reachable from the root, so it shouldn't be exported.
+        let orphan = src.put_cbor(&"orphan", Code::Blake2b256).unwrap();
+
+        let mut car = Vec::new();
+        export_car(&src, &root, &mut car).unwrap();
+
+        let dst = MemoryBlockstore::default();
+        assert_eq!(import_car(&dst, car.as_slice()).unwrap(), vec![root]);
+        assert_eq!(dst.get(&root).unwrap(), src.get(&root).unwrap());
+        assert_eq!(dst.get(&leaf).unwrap(), src.get(&leaf).unwrap());
+        assert!(!dst.has(&orphan).unwrap());
+    }
+
+    #[test]
+    fn export_multiple_roots() {
+        let src = MemoryBlockstore::default();
+        let leaf = src.put_cbor(&"leaf", Code::Blake2b256).unwrap();
+        let pre = src.put_cbor(&(leaf, 1u8), Code::Blake2b256).unwrap();
+        let post = src.put_cbor(&(leaf, 2u8), Code::Blake2b256).unwrap();
+
+        let mut car = Vec::new();
+        export_car_roots(&src, &[pre, post], &mut car).unwrap();
+
+        let dst = MemoryBlockstore::default();
+        assert_eq!(import_car(&dst, car.as_slice()).unwrap(), vec![pre, post]);
+        for k in [pre, post, leaf] {
+            assert_eq!(dst.get(&k).unwrap(), src.get(&k).unwrap());
+        }
+    }
+
+    #[test]
+    fn export_missing_block() {
+        let src = MemoryBlockstore::default();
+        let missing = MemoryBlockstore::default()
+            .put_cbor(&"missing", Code::Blake2b256)
+            .unwrap();
+        let root = src.put_cbor(&(missing,), Code::Blake2b256).unwrap();
+        export_car(&src, &root, Vec::new()).unwrap_err();
+    }
+}
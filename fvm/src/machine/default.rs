@@ -1,17 +1,24 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::HashMap;
 use std::ops::RangeInclusive;
+use std::sync::Arc;
 
 use anyhow::{anyhow, Context as _};
 use cid::Cid;
 use fvm_ipld_blockstore::{Block, Blockstore, Buffered};
 use fvm_ipld_encoding::{to_vec, CborStore, DAG_CBOR};
 use fvm_shared::version::NetworkVersion;
+use fvm_shared::ActorID;
 use log::debug;
 use multihash::Code::Blake2b256;
 
-use super::{Machine, MachineContext};
+use super::{
+    shared_thread_pool, ActorNameResolver, AddressManager, EthAddressManager, EventSink, Machine,
+    MachineContext, MetricSink, Metrics, NoopMetrics, SignatureVerifier,
+};
 use crate::blockstore::BufferedBlockstore;
+use crate::eam_actor::EAM_ACTOR_ID;
 use crate::externs::Externs;
 use crate::kernel::{ClassifyResult, Result};
 use crate::machine::limiter::DefaultMemoryLimiter;
@@ -42,6 +49,24 @@ pub struct DefaultMachine<B, E> {
     /// Somewhat unique ID of the machine consisting of (epoch, randomness)
     /// randomness is generated with `initial_state_root`
     id: String,
+    /// The [machine fingerprint](Machine::fingerprint), computed once on construction.
+    fingerprint: [u8; 32],
+    /// The thread pool dedicated to verifying proofs (shared with other machines), if configured.
+    proof_pool: Option<Arc<rayon::ThreadPool>>,
+    /// The sink events are pushed to, if any.
+    event_sink: Option<Box<dyn EventSink>>,
+    /// The sink metrics are pushed to, if any.
+    metric_sink: Option<Box<dyn MetricSink>>,
+    /// The receiver of the metrics collected by the FVM.
+    metrics: Box<dyn Metrics>,
+    /// The verifiers of signatures by f4 addresses, by namespace.
+    signature_verifiers: HashMap<ActorID, Box<dyn SignatureVerifier>>,
+    /// The managers of f4 address namespaces, by namespace.
+    address_managers: HashMap<ActorID, Box<dyn AddressManager>>,
+    /// The resolver naming actors in call backtraces, if any.
+    actor_name_resolver: Option<Box<dyn ActorNameResolver>>,
+    /// Blocks to write back on the next flush, even if they aren't reachable from the state root.
+    retained: Vec<Cid>,
 }
 
 impl<B, E> DefaultMachine<B, E>
@@ -68,13 +93,16 @@ where
 
         debug!(
             "initializing a new machine, epoch={}, base_fee={}, nv={:?}, root={}",
-            context.epoch, &context.base_fee, context.network_version, context.initial_state_root
+            context.epoch,
+            &context.base_fee,
+            context.effective_network_version(),
+            context.initial_state_root
         );
 
-        if !SUPPORTED_VERSIONS.contains(&context.network_version) {
+        if !SUPPORTED_VERSIONS.contains(&context.effective_network_version()) {
             return Err(anyhow!(
                 "unsupported network version: {}",
-                context.network_version
+                context.effective_network_version()
             ));
         }
 
@@ -89,11 +117,18 @@ where
             ));
         }
 
+        // Switch to the network version in effect at this epoch, migrating the state if needed.
+        let mut context = context.clone();
+        context.apply_upgrades(&blockstore)?;
+
         put_empty_blocks(&blockstore)?;
 
         // Create a new state tree from the supplied root.
         let state_tree = {
-            let bstore = BufferedBlockstore::new(blockstore);
+            let bstore = BufferedBlockstore::with_policy(blockstore, context.write_policy)
+                .with_flush_workers(context.flush_workers)?
+                .with_codecs(context.codecs.clone())
+                .with_inline_cid_limits(context.inline_cid_limits.clone());
             StateTree::new_from_root(bstore, &context.initial_state_root)?
         };
 
@@ -117,6 +152,13 @@ where
         // 16 bytes is random _enough_
         let randomness: [u8; 16] = rand::random();
 
+        let fingerprint = super::fingerprint(&context.network, &builtin_actors);
+
+        let proof_pool = match context.proof_threads {
+            0 => None,
+            threads => Some(shared_thread_pool("fvm-proofs", threads)?),
+        };
+
         Ok(DefaultMachine {
             context: context.clone(),
             externs,
@@ -127,8 +169,68 @@ where
                 context.epoch,
                 cid::multibase::encode(cid::multibase::Base::Base32Lower, randomness)
             ),
+            fingerprint,
+            proof_pool,
+            event_sink: None,
+            metric_sink: None,
+            metrics: Box::new(NoopMetrics),
+            signature_verifiers: HashMap::new(),
+            address_managers: HashMap::from([(
+                EAM_ACTOR_ID,
+                Box::new(EthAddressManager) as Box<dyn AddressManager>,
+            )]),
+            actor_name_resolver: None,
+            retained: Vec::new(),
         })
     }
+
+    /// Pushes the events emitted by actors to the given sink. See [`EventSink`].
+    pub fn with_event_sink(mut self, sink: impl EventSink) -> Self {
+        self.event_sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Pushes the metrics reported by actors to the given sink. See [`MetricSink`].
+    pub fn with_metric_sink(mut self, sink: impl MetricSink) -> Self {
+        self.metric_sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Reports the metrics collected by the FVM to the given receiver. See [`Metrics`].
+    pub fn with_metrics(mut self, metrics: impl Metrics) -> Self {
+        self.metrics = Box::new(metrics);
+        self
+    }
+
+    /// Verifies signatures by f4 addresses in the given namespace with the given verifier. See
+    /// [`SignatureVerifier`].
+    pub fn with_signature_verifier(
+        mut self,
+        namespace: ActorID,
+        verifier: impl SignatureVerifier,
+    ) -> Self {
+        self.signature_verifiers
+            .insert(namespace, Box::new(verifier));
+        self
+    }
+
+    /// Manages f4 addresses in the given namespace with the given manager, replacing any manager
+    /// already registered for it. The Ethereum address manager's namespace is managed by
+    /// [`EthAddressManager`] unless replaced. See [`AddressManager`].
+    pub fn with_address_manager(
+        mut self,
+        namespace: ActorID,
+        manager: impl AddressManager,
+    ) -> Self {
+        self.address_managers.insert(namespace, Box::new(manager));
+        self
+    }
+
+    /// Names the actors in call backtraces with the given resolver. See [`ActorNameResolver`].
+    pub fn with_actor_name_resolver(mut self, resolver: impl ActorNameResolver) -> Self {
+        self.actor_name_resolver = Some(Box::new(resolver));
+        self
+    }
 }
 
 impl<B, E> Machine for DefaultMachine<B, E>
@@ -172,9 +274,16 @@ where
     fn flush(&mut self) -> Result<Cid> {
         let root = self.state_tree_mut().flush()?;
         self.blockstore().flush(&root).or_fatal()?;
+        for k in std::mem::take(&mut self.retained) {
+            self.blockstore().flush(&k).or_fatal()?;
+        }
         Ok(root)
     }
 
+    fn retain_block(&mut self, k: Cid) {
+        self.retained.push(k);
+    }
+
     fn into_store(self) -> Self::Blockstore {
         self.state_tree.into_store()
     }
@@ -183,9 +292,41 @@ where
         &self.id
     }
 
+    fn fingerprint(&self) -> [u8; 32] {
+        self.fingerprint
+    }
+
     fn new_limiter(&self) -> Self::Limiter {
         DefaultMemoryLimiter::for_network(&self.context().network)
     }
+
+    fn proof_pool(&self) -> Option<&rayon::ThreadPool> {
+        self.proof_pool.as_deref()
+    }
+
+    fn event_sink(&self) -> Option<&dyn EventSink> {
+        self.event_sink.as_deref()
+    }
+
+    fn metric_sink(&self) -> Option<&dyn MetricSink> {
+        self.metric_sink.as_deref()
+    }
+
+    fn metrics(&self) -> &dyn Metrics {
+        &*self.metrics
+    }
+
+    fn signature_verifier(&self, namespace: ActorID) -> Option<&dyn SignatureVerifier> {
+        self.signature_verifiers.get(&namespace).map(|v| &**v)
+    }
+
+    fn address_manager(&self, namespace: ActorID) -> Option<&dyn AddressManager> {
+        self.address_managers.get(&namespace).map(|m| &**m)
+    }
+
+    fn actor_name_resolver(&self) -> Option<&dyn ActorNameResolver> {
+        self.actor_name_resolver.as_deref()
+    }
 }
 
 // Helper method that puts certain "empty" types in the blockstore.
@@ -0,0 +1,31 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use cid::Cid;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::event::StampedEvent;
+
+/// The context in which an event pushed to an [`EventSink`] was emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventContext {
+    /// The epoch in which the event was emitted.
+    pub epoch: ChainEpoch,
+    /// The CID of the (unsigned) message that emitted the event.
+    pub message_cid: Cid,
+    /// The index of the message among the explicit messages of the tipset (see
+    /// [`MessageEvents::message_index`](crate::executor::MessageEvents::message_index)).
+    pub message_index: u64,
+}
+
+/// A subscriber to the events emitted by actors, for indexers embedding the FVM. Register it with
+/// [`DefaultMachine::with_event_sink`](super::DefaultMachine::with_event_sink).
+///
+/// Events are pushed synchronously, in emission order, as soon as the emitting message has been
+/// applied. Events emitted by calls that were later aborted (and are therefore not committed to
+/// the message's receipt) are never pushed. Neither are the events of messages whose effects the
+/// executor drops (e.g., rejected tentative messages, gas estimation runs, or reverted bundles):
+/// the events of messages applied this way are only pushed once their effects are kept.
+pub trait EventSink: Send + Sync + 'static {
+    /// Called with each event emitted by a message. The emitter's actor ID is recorded in the
+    /// event itself.
+    fn push_event(&self, context: &EventContext, event: &StampedEvent);
+}
@@ -43,6 +43,7 @@ pub trait MemoryLimiter: Sized {
 /// across all Wasm instances.
 pub struct DefaultMemoryLimiter {
     max_memory_bytes: usize,
+    max_inst_memory_bytes: usize,
     curr_memory_bytes: usize,
 }
 
@@ -50,12 +51,25 @@ impl DefaultMemoryLimiter {
     pub fn new(max_memory_bytes: usize) -> Self {
         Self {
             max_memory_bytes,
+            max_inst_memory_bytes: usize::MAX,
             curr_memory_bytes: 0,
         }
     }
 
+    /// Limit the memory of each individual Wasm instance to `max_inst_memory_bytes`, in addition
+    /// to the limit on the total memory used by the call stack.
+    ///
+    /// Memory growth is charged gas separately (see
+    /// [`PriceList::grow_memory_gas`](crate::gas::PriceList::grow_memory_gas)), and the limit on
+    /// the whole call stack is [`NetworkConfig::max_memory_bytes`].
+    pub fn with_max_instance_memory(mut self, max_inst_memory_bytes: usize) -> Self {
+        self.max_inst_memory_bytes = max_inst_memory_bytes;
+        self
+    }
+
     pub fn for_network(config: &NetworkConfig) -> Self {
         Self::new(config.max_memory_bytes as usize)
+            .with_max_instance_memory(config.max_inst_memory_bytes as usize)
     }
 }
 
@@ -75,6 +89,15 @@ impl MemoryLimiter for DefaultMemoryLimiter {
         true
     }
 
+    fn grow_instance_memory(&mut self, from: usize, to: usize) -> bool {
+        // Wasmtime also enforces this limit (it's the size of each slot in the pooling allocator),
+        // but we check it here as well so the limit doesn't depend on the engine configuration.
+        if to > self.max_inst_memory_bytes {
+            return false;
+        }
+        self.grow_memory(to.saturating_sub(from))
+    }
+
     fn with_stack_frame<T, G, F, R>(t: &mut T, g: G, f: F) -> R
     where
         G: Fn(&mut T) -> &mut Self,
@@ -123,6 +146,26 @@ mod tests {
         assert_eq!(limits.memory_used(), 1);
     }
 
+    #[test]
+    fn instance_memory() {
+        let mut limits = DefaultMemoryLimiter::new(10).with_max_instance_memory(4);
+        assert!(limits.grow_instance_memory(0, 4)); // Ok, just at the instance limit.
+        assert!(!limits.grow_instance_memory(4, 5)); // Fail, over the instance limit.
+        assert_eq!(limits.memory_used(), 4);
+
+        // Each instance is limited independently, but the total is still limited.
+        DefaultMemoryLimiter::with_stack_frame(
+            &mut limits,
+            |x| x,
+            |limits| {
+                assert!(limits.grow_instance_memory(0, 4));
+                assert!(!limits.grow_instance_memory(0, 3)); // Fail, 4+4+3 would be over 10.
+                assert!(limits.grow_instance_memory(0, 2));
+            },
+        );
+        assert_eq!(limits.memory_used(), 4);
+    }
+
     #[test]
     fn table() {
         let mut limits = DefaultMemoryLimiter::new(10);
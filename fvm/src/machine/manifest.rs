@@ -71,18 +71,24 @@ impl Manifest {
 
     /// Load a manifest from the blockstore.
     pub fn load<B: Blockstore>(bs: &B, root_cid: &Cid, ver: u32) -> anyhow::Result<Manifest> {
+        Manifest::new(Self::load_entries(bs, root_cid, ver)?)
+    }
+
+    /// Load the (actor name, code CID) entries of a manifest from the blockstore, in manifest
+    /// order.
+    pub(crate) fn load_entries<B: Blockstore>(
+        bs: &B,
+        root_cid: &Cid,
+        ver: u32,
+    ) -> anyhow::Result<Vec<(String, Cid)>> {
         if ver != 1 {
             return Err(anyhow!("unsupported manifest version {}", ver));
         }
 
-        let vec: Vec<(String, Cid)> = match bs.get_cbor(root_cid)? {
-            Some(vec) => vec,
-            None => {
-                return Err(anyhow!("cannot find manifest root cid {}", root_cid));
-            }
-        };
-
-        Manifest::new(vec)
+        match bs.get_cbor(root_cid)? {
+            Some(vec) => Ok(vec),
+            None => Err(anyhow!("cannot find manifest root cid {}", root_cid)),
+        }
     }
 
     /// Construct a new manifest from actor name/cid tuples.
@@ -0,0 +1,17 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use fvm_shared::sys::MetricKind;
+use fvm_shared::ActorID;
+
+/// A receiver of the metrics reported by actors through the `debug::metric` syscall, for
+/// instrumenting experimental actors on devnets. Register it with
+/// [`DefaultMachine::with_metric_sink`](super::DefaultMachine::with_metric_sink).
+///
+/// Metrics are only reported when [actor debugging](super::NetworkConfig::actor_debugging) is
+/// enabled, and are pushed as soon as they're reported, even if the reporting call is later
+/// aborted.
+pub trait MetricSink: Send + Sync + 'static {
+    /// Called with each metric reported by an actor. Counters should be incremented by `value`,
+    /// while gauges should be set to `value`.
+    fn record_metric(&self, actor: ActorID, name: &str, kind: MetricKind, value: i64);
+}
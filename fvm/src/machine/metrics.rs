@@ -0,0 +1,219 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::time::Duration;
+
+use fvm_shared::error::ExitCode;
+
+use crate::executor::ApplyKind;
+
+/// A receiver of the metrics the FVM collects about its own execution, for monitoring nodes.
+/// Register it with [`DefaultMachine::with_metrics`](super::DefaultMachine::with_metrics).
+///
+/// Unlike a [`MetricSink`](super::MetricSink), which receives the metrics reported by actors,
+/// this receives metrics collected by the FVM itself. Every method does nothing by default, so
+/// implementations only need to handle the metrics they're interested in. Metrics are not
+/// consensus-critical.
+///
+/// Message metrics only cover messages whose effects are kept, as with
+/// [`EventSink`](super::EventSink). Resource metrics (blocks and instantiations) cover all the work
+/// done, including by messages whose effects are later dropped.
+pub trait Metrics: Send + Sync + 'static {
+    /// Called after each message is applied, whether or not it succeeded, with its exit code and
+    /// the gas it used.
+    fn message_applied(&self, _kind: ApplyKind, _exit_code: ExitCode, _gas_used: u64) {}
+
+    /// Called after each message is applied with the number of calls it made to each syscall
+    /// (named `module::name`). Syscalls are only counted when the
+    /// [syscall census](super::MachineContext::syscall_census) is enabled.
+    fn syscalls_called(&self, _syscall: &str, _count: u64) {}
+
+    /// Called when an actor reads a block of the given size from the blockstore.
+    fn block_read(&self, _size: usize) {}
+
+    /// Called when an actor writes a block of the given size to the blockstore.
+    fn block_written(&self, _size: usize) {}
+
+    /// Called when an actor is instantiated (along with the shared modules it imports), with how
+    /// long it took, including compiling its code if it wasn't cached.
+    fn actor_instantiated(&self, _duration: Duration) {}
+
+    /// Called when an actor's compiled code is looked up in the engine's module cache, with
+    /// whether it was found.
+    fn module_cache_lookup(&self, _hit: bool) {}
+}
+
+/// [`Metrics`] ignoring every metric, used when no other metrics are registered.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
+
+#[cfg(feature = "prometheus")]
+pub use self::prometheus_metrics::PrometheusMetrics;
+
+#[cfg(feature = "prometheus")]
+mod prometheus_metrics {
+    use std::time::Duration;
+
+    use fvm_shared::error::ExitCode;
+    use prometheus::{
+        exponential_buckets, Histogram, HistogramOpts, IntCounter, IntCounterVec, Opts, Registry,
+    };
+
+    use super::Metrics;
+    use crate::executor::ApplyKind;
+
+    /// [`Metrics`] exported as Prometheus counters and histograms, all prefixed with `fvm_`.
+    pub struct PrometheusMetrics {
+        messages: IntCounterVec,
+        message_gas: Histogram,
+        syscalls: IntCounterVec,
+        block_reads: IntCounter,
+        block_read_bytes: IntCounter,
+        block_writes: IntCounter,
+        block_written_bytes: IntCounter,
+        instantiation_seconds: Histogram,
+        module_cache_lookups: IntCounterVec,
+    }
+
+    impl PrometheusMetrics {
+        /// Creates the metrics, registering them with the given registry.
+        pub fn new(registry: &Registry) -> prometheus::Result<Self> {
+            let metrics = PrometheusMetrics {
+                messages: IntCounterVec::new(
+                    Opts::new("fvm_messages_applied_total", "Messages applied"),
+                    &["kind", "exit_code"],
+                )?,
+                message_gas: Histogram::with_opts(
+                    HistogramOpts::new("fvm_message_gas_used", "Gas used by applied messages")
+                        .buckets(exponential_buckets(100_000.0, 4.0, 10)?),
+                )?,
+                syscalls: IntCounterVec::new(
+                    Opts::new("fvm_syscalls_total", "Syscalls made by applied messages"),
+                    &["syscall"],
+                )?,
+                block_reads: IntCounter::new("fvm_block_reads_total", "Blocks read by actors")?,
+                block_read_bytes: IntCounter::new(
+                    "fvm_block_read_bytes_total",
+                    "Bytes of blocks read by actors",
+                )?,
+                block_writes: IntCounter::new(
+                    "fvm_block_writes_total",
+                    "Blocks written by actors",
+                )?,
+                block_written_bytes: IntCounter::new(
+                    "fvm_block_written_bytes_total",
+                    "Bytes of blocks written by actors",
+                )?,
+                instantiation_seconds: Histogram::with_opts(
+                    HistogramOpts::new(
+                        "fvm_actor_instantiation_seconds",
+                        "Time taken to instantiate actors",
+                    )
+                    .buckets(exponential_buckets(0.000_01, 4.0, 10)?),
+                )?,
+                module_cache_lookups: IntCounterVec::new(
+                    Opts::new(
+                        "fvm_module_cache_lookups_total",
+                        "Lookups of compiled actor code in the module cache",
+                    ),
+                    &["result"],
+                )?,
+            };
+            registry.register(Box::new(metrics.messages.clone()))?;
+            registry.register(Box::new(metrics.message_gas.clone()))?;
+            registry.register(Box::new(metrics.syscalls.clone()))?;
+            registry.register(Box::new(metrics.block_reads.clone()))?;
+            registry.register(Box::new(metrics.block_read_bytes.clone()))?;
+            registry.register(Box::new(metrics.block_writes.clone()))?;
+            registry.register(Box::new(metrics.block_written_bytes.clone()))?;
+            registry.register(Box::new(metrics.instantiation_seconds.clone()))?;
+            registry.register(Box::new(metrics.module_cache_lookups.clone()))?;
+            Ok(metrics)
+        }
+    }
+
+    impl Metrics for PrometheusMetrics {
+        fn message_applied(&self, kind: ApplyKind, exit_code: ExitCode, gas_used: u64) {
+            let kind = match kind {
+                ApplyKind::Explicit => "explicit",
+                ApplyKind::Implicit => "implicit",
+            };
+            self.messages
+                .with_label_values(&[kind, &exit_code.value().to_string()])
+                .inc();
+            self.message_gas.observe(gas_used as f64);
+        }
+
+        fn syscalls_called(&self, syscall: &str, count: u64) {
+            self.syscalls.with_label_values(&[syscall]).inc_by(count);
+        }
+
+        fn block_read(&self, size: usize) {
+            self.block_reads.inc();
+            self.block_read_bytes.inc_by(size as u64);
+        }
+
+        fn block_written(&self, size: usize) {
+            self.block_writes.inc();
+            self.block_written_bytes.inc_by(size as u64);
+        }
+
+        fn actor_instantiated(&self, duration: Duration) {
+            self.instantiation_seconds.observe(duration.as_secs_f64());
+        }
+
+        fn module_cache_lookup(&self, hit: bool) {
+            self.module_cache_lookups
+                .with_label_values(&[if hit { "hit" } else { "miss" }])
+                .inc();
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use std::time::Duration;
+
+        use fvm_shared::error::ExitCode;
+        use prometheus::Registry;
+
+        use super::PrometheusMetrics;
+        use crate::executor::ApplyKind;
+        use crate::machine::Metrics;
+
+        #[test]
+        fn exports_metrics() {
+            let registry = Registry::new();
+            let metrics = PrometheusMetrics::new(&registry).unwrap();
+            metrics.message_applied(ApplyKind::Explicit, ExitCode::OK, 1000);
+            metrics.message_applied(ApplyKind::Explicit, ExitCode::OK, 2000);
+            metrics.block_written(5);
+            metrics.block_written(7);
+            metrics.actor_instantiated(Duration::from_millis(1));
+            metrics.module_cache_lookup(false);
+
+            let families = registry.gather();
+            let value = |name: &str| {
+                let family = families
+                    .iter()
+                    .find(|f| f.get_name() == name)
+                    .unwrap_or_else(|| panic!("metric {name} not exported"));
+                let metric = &family.get_metric()[0];
+                if metric.has_histogram() {
+                    metric.get_histogram().get_sample_count() as f64
+                } else {
+                    metric.get_counter().get_value()
+                }
+            };
+            assert_eq!(value("fvm_messages_applied_total"), 2.0);
+            assert_eq!(value("fvm_message_gas_used"), 2.0);
+            assert_eq!(value("fvm_block_writes_total"), 2.0);
+            assert_eq!(value("fvm_block_written_bytes_total"), 12.0);
+            assert_eq!(value("fvm_actor_instantiation_seconds"), 1.0);
+            assert_eq!(value("fvm_module_cache_lookups_total"), 1.0);
+
+            // Metrics can only be registered once per registry.
+            assert!(PrometheusMetrics::new(&registry).is_err());
+        }
+    }
+}
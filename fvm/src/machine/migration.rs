@@ -0,0 +1,480 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! A framework for network upgrade state migrations: migrations are registered per actor code,
+//! and run over every actor in the state tree on a pool of worker threads.
+//!
+//! To run a [`StateMigration`] as an [upgrade](super::upgrade::Upgrade) migration, call
+//! [`StateMigration::run`] from the upgrade's [`Migration`](super::upgrade::Migration) hook,
+//! with a (thread-safe) handle to the node's blockstore.
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context as _};
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::ActorID;
+
+use crate::kernel::Context as _;
+use crate::state_tree::{ActorState, StateTree};
+
+/// Migrates the state of actors with a given code.
+pub trait ActorMigration<BS>: Send + Sync {
+    /// Migrates an actor's state (head), writing the new state to `store`. Results are
+    /// [cached](MigrationCache) by the actor's code and head, so the output must only depend on
+    /// those (and on the contents of the store).
+    fn migrate_state(
+        &self,
+        store: &BS,
+        input: ActorMigrationInput,
+    ) -> anyhow::Result<ActorMigrationOutput>;
+}
+
+/// The actor being migrated by an [`ActorMigration`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActorMigrationInput {
+    pub id: ActorID,
+    pub head: Cid,
+    /// The epoch the migration is run at.
+    pub epoch: ChainEpoch,
+}
+
+/// The result of an [`ActorMigration`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActorMigrationOutput {
+    pub new_code: Cid,
+    pub new_head: Cid,
+}
+
+/// The results of actor migrations, by old (code, head). Actors sharing the same code and head
+/// (e.g., empty accounts) are only migrated once, and a cache can be shared between migration
+/// runs.
+#[derive(Debug, Default)]
+pub struct MigrationCache {
+    entries: Mutex<HashMap<(Cid, Cid), ActorMigrationOutput>>,
+}
+
+impl MigrationCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the result of migrating an actor with the given code and head, if cached.
+    pub fn get(&self, code: &Cid, head: &Cid) -> Option<ActorMigrationOutput> {
+        self.entries.lock().unwrap().get(&(*code, *head)).copied()
+    }
+
+    /// Caches the result of migrating an actor with the given code and head.
+    pub fn insert(&self, code: Cid, head: Cid, output: ActorMigrationOutput) {
+        self.entries.lock().unwrap().insert((code, head), output);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Progress of a running [`StateMigration`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MigrationProgress {
+    /// The number of actors migrated (or skipped) so far.
+    pub done: usize,
+    /// The number of actors in the state tree.
+    pub total: usize,
+    /// The time since the migration started.
+    pub elapsed: Duration,
+}
+
+enum Migrator<BS> {
+    /// Changes the actor's code, keeping its state.
+    CodeChange(Cid),
+    Actor(Arc<dyn ActorMigration<BS>>),
+}
+
+/// Migrates a state tree by running the migration registered for each actor's code, in parallel.
+/// Actors with codes without a registered migration (e.g., user-deployed actors) are left as-is.
+pub struct StateMigration<BS> {
+    migrations: HashMap<Cid, Migrator<BS>>,
+    cache: Arc<MigrationCache>,
+    workers: usize,
+    progress: Option<(Duration, Box<dyn Fn(MigrationProgress) + Send + Sync>)>,
+}
+
+impl<BS> Default for StateMigration<BS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<BS> StateMigration<BS> {
+    /// Creates a migration with no actor migrations, running on one worker per CPU.
+    pub fn new() -> Self {
+        Self {
+            migrations: HashMap::new(),
+            cache: Default::default(),
+            workers: num_cpus::get(),
+            progress: None,
+        }
+    }
+
+    /// Migrates actors with code `old_code` with the given migration.
+    pub fn add_migration(
+        &mut self,
+        old_code: Cid,
+        migration: Arc<dyn ActorMigration<BS>>,
+    ) -> &mut Self {
+        self.migrations.insert(old_code, Migrator::Actor(migration));
+        self
+    }
+
+    /// Changes the code of actors with code `old_code` to `new_code`, keeping their state.
+    pub fn add_code_change(&mut self, old_code: Cid, new_code: Cid) -> &mut Self {
+        self.migrations
+            .insert(old_code, Migrator::CodeChange(new_code));
+        self
+    }
+
+    /// Sets the number of worker threads actors are migrated on.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Uses (and fills) the given cache of migrated actor heads.
+    pub fn with_cache(mut self, cache: Arc<MigrationCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Calls `report` with the migration's progress at most once per `interval`, and once the
+    /// migration is done.
+    pub fn with_progress(
+        mut self,
+        interval: Duration,
+        report: impl Fn(MigrationProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some((interval, Box::new(report)));
+        self
+    }
+
+    /// Returns the cache of migrated actor heads.
+    pub fn cache(&self) -> &Arc<MigrationCache> {
+        &self.cache
+    }
+}
+
+impl<BS> StateMigration<BS>
+where
+    BS: Blockstore + Sync,
+{
+    /// Migrates the state tree under `root`, returning the new state root.
+    pub fn run(&self, store: &BS, root: &Cid, epoch: ChainEpoch) -> anyhow::Result<Cid> {
+        let (mut tree, actors) = load_actors(store, root)?;
+        self.migrate_actors(store, actors, epoch, |id, actor| tree.set_actor(id, actor))?;
+        let new_root = tree
+            .flush()
+            .context("failed to flush migrated state tree")?;
+        Ok(new_root)
+    }
+
+    /// Runs the actor migrations over the state tree under `root` ahead of the upgrade, filling
+    /// the [cache](StateMigration::cache) without writing a new state tree (new actor states are
+    /// still written to the store).
+    ///
+    /// A node can pre-migrate a recent state root a few epochs before an upgrade (e.g., in the
+    /// background, see [`UpgradeSchedule::upcoming`](super::upgrade::UpgradeSchedule::upcoming)),
+    /// then [run](StateMigration::run) the migration at the upgrade epoch with the same cache:
+    /// only actors whose state changed since the pre-migration are migrated again. Pre-migrations
+    /// can be repeated, each one refreshing the cache with the actors changed since the last.
+    ///
+    /// Actor migrations are passed the pre-migration epoch, so their output must not depend on
+    /// it.
+    pub fn premigrate(&self, store: &BS, root: &Cid, epoch: ChainEpoch) -> anyhow::Result<()> {
+        let (_, actors) = load_actors(store, root)?;
+        self.migrate_actors(store, actors, epoch, |_, _| {})
+    }
+
+    /// Migrates the actors on the worker threads, passing the migrated actors to `apply` (on the
+    /// calling thread).
+    fn migrate_actors(
+        &self,
+        store: &BS,
+        actors: Vec<(ActorID, ActorState)>,
+        epoch: ChainEpoch,
+        mut apply: impl FnMut(ActorID, ActorState),
+    ) -> anyhow::Result<()> {
+        let start = Instant::now();
+        let total = actors.len();
+
+        // Workers pull actors from the queue, and send the results back to this thread.
+        let queue = Mutex::new(actors.into_iter());
+        let failed = AtomicBool::new(false);
+        thread::scope(|s| -> anyhow::Result<()> {
+            let (tx, rx) = mpsc::channel();
+            for _ in 0..self.workers {
+                let tx = tx.clone();
+                let (queue, failed) = (&queue, &failed);
+                s.spawn(move || {
+                    while !failed.load(Ordering::Relaxed) {
+                        let Some((id, actor)) = queue.lock().unwrap().next() else {
+                            break;
+                        };
+                        let result = self.migrate_actor(store, id, &actor, epoch);
+                        if tx.send((id, result)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+
+            let mut done = 0;
+            let mut last_report = Instant::now();
+            for (id, result) in rx {
+                let result = result.with_context(|| format!("failed to migrate actor {id}"));
+                match result {
+                    Ok(Some(actor)) => apply(id, actor),
+                    Ok(None) => {}
+                    Err(e) => {
+                        failed.store(true, Ordering::Relaxed);
+                        return Err(e);
+                    }
+                }
+                done += 1;
+                if let Some((interval, report)) = &self.progress {
+                    if last_report.elapsed() >= *interval {
+                        last_report = Instant::now();
+                        report(MigrationProgress {
+                            done,
+                            total,
+                            elapsed: start.elapsed(),
+                        });
+                    }
+                }
+            }
+            Ok(())
+        })?;
+
+        if let Some((_, report)) = &self.progress {
+            report(MigrationProgress {
+                done: total,
+                total,
+                elapsed: start.elapsed(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Migrates a single actor, returning its new state if it changed.
+    fn migrate_actor(
+        &self,
+        store: &BS,
+        id: ActorID,
+        actor: &ActorState,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<Option<ActorState>> {
+        let Some(migrator) = self.migrations.get(&actor.code) else {
+            return Ok(None);
+        };
+        let output = match self.cache.get(&actor.code, &actor.state) {
+            Some(output) => output,
+            None => {
+                let output = match migrator {
+                    Migrator::CodeChange(new_code) => ActorMigrationOutput {
+                        new_code: *new_code,
+                        new_head: actor.state,
+                    },
+                    Migrator::Actor(migration) => migration.migrate_state(
+                        store,
+                        ActorMigrationInput {
+                            id,
+                            head: actor.state,
+                            epoch,
+                        },
+                    )?,
+                };
+                self.cache.insert(actor.code, actor.state, output);
+                output
+            }
+        };
+        Ok(Some(ActorState {
+            code: output.new_code,
+            state: output.new_head,
+            ..actor.clone()
+        }))
+    }
+}
+
+/// Loads the state tree under `root`, and the actors in it.
+fn load_actors<BS: Blockstore>(
+    store: &BS,
+    root: &Cid,
+) -> anyhow::Result<(StateTree<&BS>, Vec<(ActorID, ActorState)>)> {
+    let tree = StateTree::new_from_root(store, root)
+        .with_context(|| format!("failed to load state tree {root}"))?;
+    let mut actors = Vec::new();
+    tree.for_each(|addr, actor| {
+        let id = addr
+            .id()
+            .map_err(|_| anyhow!("state tree contains non-ID address {addr}"))?;
+        actors.push((id, actor.clone()));
+        Ok(())
+    })
+    .context("failed to walk state tree")?;
+    Ok((tree, actors))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use cid::Cid;
+    use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+    use fvm_ipld_encoding::CborStore;
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::state::StateTreeVersion;
+    use multihash::Code;
+
+    use super::{ActorMigration, ActorMigrationInput, ActorMigrationOutput, StateMigration};
+    use crate::machine::Manifest;
+    use crate::state_tree::{ActorState, StateTree};
+
+    /// A thread-safe memory blockstore.
+    #[derive(Default)]
+    struct SyncStore(Mutex<MemoryBlockstore>);
+
+    impl Blockstore for SyncStore {
+        fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+            self.0.lock().unwrap().get(k)
+        }
+
+        fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+            self.0.lock().unwrap().put_keyed(k, block)
+        }
+    }
+
+    /// Counts the calls to a migration.
+    struct Counting<M>(M, Arc<AtomicUsize>);
+
+    impl<M: ActorMigration<SyncStore>> ActorMigration<SyncStore> for Counting<M> {
+        fn migrate_state(
+            &self,
+            store: &SyncStore,
+            input: ActorMigrationInput,
+        ) -> anyhow::Result<ActorMigrationOutput> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.migrate_state(store, input)
+        }
+    }
+
+    /// Migrates a `u64` state by doubling it.
+    struct Double(Cid);
+
+    impl ActorMigration<SyncStore> for Double {
+        fn migrate_state(
+            &self,
+            store: &SyncStore,
+            input: ActorMigrationInput,
+        ) -> anyhow::Result<ActorMigrationOutput> {
+            let value: u64 = store.get_cbor(&input.head)?.unwrap();
+            Ok(ActorMigrationOutput {
+                new_code: self.0,
+                new_head: store.put_cbor(&(value * 2), Code::Blake2b256)?,
+            })
+        }
+    }
+
+    #[test]
+    fn migrate_state_tree() {
+        let codes: Vec<Cid> = Manifest::DUMMY_CODES.iter().map(|(_, c)| *c).collect();
+        let (old, new, other) = (codes[0], codes[1], codes[2]);
+
+        let store = SyncStore::default();
+        let mut tree = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        for id in 100..200 {
+            // Half the actors share the same state.
+            let head = store.put_cbor(&(id % 2 * id), Code::Blake2b256).unwrap();
+            let code = if id < 190 { old } else { other };
+            let actor = ActorState::new(code, head, TokenAmount::from_atto(id), 0, None);
+            tree.set_actor(id, actor);
+        }
+        let root = tree.flush().unwrap();
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let mut migration = StateMigration::new()
+            .with_workers(4)
+            .with_progress(Duration::ZERO, {
+                let reports = reports.clone();
+                move |p| reports.lock().unwrap().push(p)
+            });
+        migration.add_migration(old, Arc::new(Double(new)));
+        let new_root = migration.run(&store, &root, 10).unwrap();
+
+        let tree = StateTree::new_from_root(&store, &new_root).unwrap();
+        for id in 100..200 {
+            let actor = tree.get_actor(id).unwrap().unwrap();
+            let value: u64 = store.get_cbor(&actor.state).unwrap().unwrap();
+            assert_eq!(actor.balance, TokenAmount::from_atto(id));
+            if id < 190 {
+                assert_eq!(actor.code, new);
+                assert_eq!(value, id % 2 * id * 2);
+            } else {
+                assert_eq!(actor.code, other);
+                assert_eq!(value, id % 2 * id);
+            }
+        }
+        // All even actors share the same (zero) state, so they're migrated once.
+        assert_eq!(migration.cache().len(), 46);
+        assert_eq!(reports.lock().unwrap().last().unwrap().done, 100);
+
+        // Migrations are deterministic.
+        let mut serial = StateMigration::new().with_workers(1);
+        serial.add_migration(old, Arc::new(Double(new)));
+        assert_eq!(serial.run(&store, &root, 10).unwrap(), new_root);
+    }
+
+    #[test]
+    fn premigration() {
+        let codes: Vec<Cid> = Manifest::DUMMY_CODES.iter().map(|(_, c)| *c).collect();
+        let (old, new) = (codes[0], codes[1]);
+
+        let store = SyncStore::default();
+        let mut tree = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        for id in 100..110 {
+            let head = store.put_cbor(&id, Code::Blake2b256).unwrap();
+            tree.set_actor(id, ActorState::new(old, head, Default::default(), 0, None));
+        }
+        let pre_root = tree.flush().unwrap();
+
+        // One actor changes between the pre-migration and the upgrade.
+        let head = store.put_cbor(&1000u64, Code::Blake2b256).unwrap();
+        tree.set_actor(100, ActorState::new(old, head, Default::default(), 0, None));
+        let root = tree.flush().unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut migration = StateMigration::new();
+        migration.add_migration(old, Arc::new(Counting(Double(new), calls.clone())));
+
+        migration.premigrate(&store, &pre_root, 5).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 10);
+        assert_eq!(migration.cache().len(), 10);
+
+        let new_root = migration.run(&store, &root, 10).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 11);
+
+        // The result matches a migration without a pre-migration.
+        let mut fresh = StateMigration::new();
+        fresh.add_migration(old, Arc::new(Double(new)));
+        assert_eq!(fresh.run(&store, &root, 10).unwrap(), new_root);
+    }
+}
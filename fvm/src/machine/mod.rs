@@ -1,33 +1,67 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::time::Duration;
+
+use anyhow::{anyhow, Context as _};
 use cid::Cid;
 use derive_more::{Deref, DerefMut};
 use fvm_ipld_blockstore::Blockstore;
-use fvm_shared::clock::ChainEpoch;
+use fvm_ipld_encoding::tuple::*;
+use fvm_ipld_encoding::{CBOR, DAG_CBOR, IPLD_RAW};
+use fvm_shared::address::Address;
+use fvm_shared::clock::{ChainEpoch, EPOCH_DURATION_SECONDS};
 use fvm_shared::econ::TokenAmount;
+use fvm_shared::event::Flags;
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::ActorID;
 use num_traits::Zero;
 
+use crate::blockstore::WritePolicy;
+use crate::eam_actor::EAM_ACTOR_ID;
 use crate::externs::Externs;
-use crate::gas::{price_list_by_network_version, PriceList};
-use crate::kernel::Result;
+use crate::gas::{price_list_by_network_version, PriceList, PriceSchedule};
+use crate::ipld;
+use crate::kernel::{Result, SupportedHashes};
 use crate::state_tree::StateTree;
+use crate::syscalls::SYSCALL_MODULES;
 
+mod actor_names;
+mod address_manager;
 mod default;
 
+pub use actor_names::ActorNameResolver;
+pub use address_manager::{AddressManager, EthAddressManager};
 pub use default::DefaultMachine;
 use fvm_shared::chainid::ChainID;
 
+mod event_sink;
 pub mod limiter;
 mod manifest;
+mod metric_sink;
+mod metrics;
+mod signature_verifier;
+mod thread_pool;
 
+pub use event_sink::{EventContext, EventSink};
 pub use manifest::Manifest;
+pub use metric_sink::MetricSink;
+#[cfg(feature = "prometheus")]
+pub use metrics::PrometheusMetrics;
+pub use metrics::{Metrics, NoopMetrics};
+pub use signature_verifier::{EthSecp256k1Verifier, SignatureVerifier};
+pub(crate) use thread_pool::shared_thread_pool;
 
 use self::limiter::MemoryLimiter;
 
 mod boxed;
 
+pub mod bundle;
+pub mod car;
+pub mod migration;
+pub mod upgrade;
+
+use upgrade::UpgradeSchedule;
+
 pub const REWARD_ACTOR_ID: ActorID = 2;
 
 /// Distinguished Account actor that is the destination of all burnt funds.
@@ -73,14 +107,345 @@ pub trait Machine: 'static {
         self.state_tree_mut().flush()
     }
 
+    /// Keeps the given block (and the blocks it links to) across the next [`Machine::flush`],
+    /// even if it isn't reachable from the state root (e.g., actor code installed at runtime).
+    ///
+    /// Machines that write blocks straight to their blockstore needn't do anything.
+    fn retain_block(&mut self, _k: Cid) {}
+
     /// Consumes the machine and returns the owned blockstore.
     fn into_store(self) -> Self::Blockstore;
 
+    /// Flushes the state-tree and writes every block reachable from the new state root to `writer`
+    /// as a CARv1 stream rooted at the state root. Returns the state root.
+    ///
+    /// Use [`car::import_car`] to load the exported state into a blockstore.
+    fn export_state_car<W>(&mut self, writer: W) -> anyhow::Result<Cid>
+    where
+        W: std::io::Write + Send + Unpin,
+        Self: Sized,
+    {
+        let root = self.flush()?;
+        car::export_car(self.blockstore(), &root, writer)?;
+        Ok(root)
+    }
+
     /// Returns a generated ID of a machine
     fn machine_id(&self) -> &str;
 
+    /// Returns a fingerprint of the consensus-relevant configuration of this machine: the FVM
+    /// version, the [`NetworkConfig`] limits, the price list, and the builtin actor code CIDs.
+    ///
+    /// Nodes applying the same tipset with different fingerprints may compute different state
+    /// roots. Comparing fingerprints helps detect misconfigured nodes before they split.
+    fn fingerprint(&self) -> [u8; 32] {
+        fingerprint(&self.context().network, self.builtin_actors())
+    }
+
     /// Creates a new limiter to track the resources of a message execution.
     fn new_limiter(&self) -> Self::Limiter;
+
+    /// Returns the thread pool dedicated to verifying proofs, shared by all messages executed by
+    /// this machine, if any. Otherwise, proofs are verified on the global rayon pool.
+    fn proof_pool(&self) -> Option<&rayon::ThreadPool> {
+        None
+    }
+
+    /// Returns the sink events emitted by actors should be pushed to, if any.
+    fn event_sink(&self) -> Option<&dyn EventSink> {
+        None
+    }
+
+    /// Returns the sink metrics reported by actors should be pushed to, if any.
+    fn metric_sink(&self) -> Option<&dyn MetricSink> {
+        None
+    }
+
+    /// Returns the receiver of the metrics collected by the FVM. By default, metrics are ignored.
+    fn metrics(&self) -> &dyn Metrics {
+        &NoopMetrics
+    }
+
+    /// Returns the verifier of signatures by f4 addresses in the given namespace, if any.
+    fn signature_verifier(&self, _namespace: ActorID) -> Option<&dyn SignatureVerifier> {
+        None
+    }
+
+    /// Returns the manager of the given f4 address namespace, if any. By default, only the
+    /// Ethereum address manager's namespace is managed.
+    fn address_manager(&self, namespace: ActorID) -> Option<&dyn AddressManager> {
+        (namespace == EAM_ACTOR_ID).then_some(&EthAddressManager as &dyn AddressManager)
+    }
+
+    /// Predicts the f4 address the manager of the given namespace assigns to the actor it creates
+    /// from `seed`. Returns `None` if the namespace has no manager, or if the derived subaddress is
+    /// too long.
+    fn predict_delegated_address(&self, namespace: ActorID, seed: &[u8]) -> Option<Address> {
+        let subaddress = self.address_manager(namespace)?.derive_subaddress(seed);
+        Address::new_delegated(namespace, &subaddress).ok()
+    }
+
+    /// Returns the resolver used to name the actors in call backtraces, if any.
+    fn actor_name_resolver(&self) -> Option<&dyn ActorNameResolver> {
+        None
+    }
+}
+
+/// The consensus-relevant parts of a network config (and the builtin actors), as included in the
+/// [machine fingerprint](Machine::fingerprint). These are hashed in their CBOR encoding, which
+/// (unlike their `Debug` output) is canonical and won't change between releases.
+#[derive(Serialize_tuple)]
+struct FingerprintedConfig<'a> {
+    network_version: NetworkVersion,
+    chain_id: u64,
+    max_call_depth: u32,
+    max_wasm_stack: u32,
+    max_inst_memory_bytes: u64,
+    max_memory_bytes: u64,
+    max_block_size: usize,
+    max_block_links: usize,
+    codecs: &'a CodecRegistry,
+    inline_cid_limits: &'a InlineCidLimits,
+    event_limits: &'a EventLimits,
+    system_events: bool,
+    builtin_actors_override: Option<Cid>,
+    builtin_actors: Vec<Cid>,
+    actor_debugging: bool,
+    price_list: &'a PriceList,
+    price_schedule: Option<&'a PriceSchedule>,
+    actor_redirect: &'a [(Cid, Cid)],
+    shared_modules: &'a [(String, Cid)],
+    lazy_syscall_linking: bool,
+    actor_factories: bool,
+    epoch_duration_seconds: u64,
+    blocks_per_epoch: u64,
+}
+
+/// Computes the [machine fingerprint](Machine::fingerprint) for the given network config and builtin
+/// actors.
+pub fn fingerprint(config: &NetworkConfig, builtin_actors: &Manifest) -> [u8; 32] {
+    let NetworkConfig {
+        network_version,
+        chain_id,
+        max_call_depth,
+        max_wasm_stack,
+        max_inst_memory_bytes,
+        max_memory_bytes,
+        max_block_size,
+        max_block_links,
+        codecs,
+        inline_cid_limits,
+        event_limits,
+        system_events,
+        builtin_actors_override,
+        actor_debugging,
+        price_list,
+        price_schedule,
+        actor_redirect,
+        shared_modules,
+        execution_timeout: _,
+        lazy_syscall_linking,
+        actor_factories,
+        epoch_duration_seconds,
+        blocks_per_epoch,
+    } = config;
+
+    let fingerprinted = FingerprintedConfig {
+        network_version: *network_version,
+        chain_id: u64::from(*chain_id),
+        max_call_depth: *max_call_depth,
+        max_wasm_stack: *max_wasm_stack,
+        max_inst_memory_bytes: *max_inst_memory_bytes,
+        max_memory_bytes: *max_memory_bytes,
+        max_block_size: *max_block_size,
+        max_block_links: *max_block_links,
+        codecs,
+        inline_cid_limits,
+        event_limits,
+        system_events: *system_events,
+        builtin_actors_override: *builtin_actors_override,
+        // Builtin actor types are numbered sequentially from 1, in manifest order.
+        builtin_actors: (1..)
+            .map_while(|id| builtin_actors.code_by_id(id).copied())
+            .collect(),
+        actor_debugging: *actor_debugging,
+        price_list,
+        price_schedule: *price_schedule,
+        actor_redirect,
+        shared_modules,
+        lazy_syscall_linking: *lazy_syscall_linking,
+        actor_factories: *actor_factories,
+        epoch_duration_seconds: *epoch_duration_seconds,
+        blocks_per_epoch: *blocks_per_epoch,
+    };
+
+    let mut state = blake2b_simd::Params::new().hash_length(32).to_state();
+    state.update(concat!("fvm-", env!("CARGO_PKG_VERSION"), ";").as_bytes());
+    state.update(
+        &fvm_ipld_encoding::to_vec(&fingerprinted).expect("network configs are serializable"),
+    );
+    state
+        .finalize()
+        .as_bytes()
+        .try_into()
+        .expect("hash length is 32 bytes")
+}
+
+/// The IPLD codecs and multihashes actors may use to create and link blocks.
+///
+/// Only codecs the FVM can scan for links (DAG-CBOR, CBOR, and raw) may be registered, as it must
+/// track the blocks each block links to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize_tuple)]
+pub struct CodecRegistry {
+    /// The codecs blocks may use.
+    codecs: Vec<u64>,
+    /// The multihashes blocks may be linked with, as (multihash code, digest length) pairs.
+    multihashes: Vec<(u64, u32)>,
+}
+
+impl Default for CodecRegistry {
+    /// CBOR, DAG-CBOR, and raw blocks, linked with 32-byte blake2b-256 hashes.
+    fn default() -> Self {
+        CodecRegistry {
+            codecs: vec![CBOR, DAG_CBOR, IPLD_RAW],
+            multihashes: vec![(SupportedHashes::Blake2b256.into(), 32)],
+        }
+    }
+}
+
+impl CodecRegistry {
+    /// Returns the codecs and multihashes allowed in the given network version. CBOR blocks are
+    /// only allowed from network version 18 (FEVM) on.
+    pub fn for_network_version(network_version: NetworkVersion) -> Self {
+        let mut registry = Self::default();
+        if network_version < NetworkVersion::V18 {
+            registry.codecs.retain(|&c| c != CBOR);
+        }
+        registry
+    }
+
+    /// Allows blocks to use the given codec. Fails if the FVM can't scan blocks with this codec for
+    /// links.
+    pub fn allow_codec(&mut self, codec: u64) -> anyhow::Result<&mut Self> {
+        if !ipld::SCANNABLE_CODECS.contains(&codec) {
+            return Err(anyhow!(
+                "codec {codec:#x} isn't supported: blocks using it can't be scanned for links"
+            ));
+        }
+        if !self.codecs.contains(&codec) {
+            self.codecs.push(codec);
+        }
+        Ok(self)
+    }
+
+    /// Allows blocks to be linked with the given multihash code and digest length.
+    pub fn allow_multihash(&mut self, code: u64, len: u32) -> &mut Self {
+        if !self.allows_multihash(code, len) {
+            self.multihashes.push((code, len));
+        }
+        self
+    }
+
+    /// Allows every codec and multihash the other registry allows, in addition to those this
+    /// registry already allows.
+    pub fn merge(&mut self, other: &CodecRegistry) -> &mut Self {
+        for &codec in &other.codecs {
+            if !self.codecs.contains(&codec) {
+                self.codecs.push(codec);
+            }
+        }
+        for &(code, len) in &other.multihashes {
+            self.allow_multihash(code, len);
+        }
+        self
+    }
+
+    /// Returns true if blocks may use the given codec.
+    pub fn allows_codec(&self, codec: u64) -> bool {
+        self.codecs.contains(&codec)
+    }
+
+    /// Returns true if blocks may be linked with the given multihash code and digest length.
+    pub fn allows_multihash(&self, code: u64, len: u32) -> bool {
+        self.multihashes.contains(&(code, len))
+    }
+}
+
+/// Limits on identity-hashed ("inline") CIDs, whose payload is embedded in the CID itself. These
+/// bloat the blocks linking to them and bypass the usual storage gas, so they're kept small.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize_tuple)]
+pub struct InlineCidLimits {
+    /// The maximum payload (digest) size, in bytes.
+    pub max_payload: usize,
+    /// The codecs inline CIDs may use.
+    pub codecs: Vec<u64>,
+    /// Whether actors may create inline CIDs with `block_link` (and open them with `block_open`,
+    /// without going through the blockstore).
+    pub linkable: bool,
+}
+
+impl Default for InlineCidLimits {
+    fn default() -> Self {
+        InlineCidLimits {
+            max_payload: 64,
+            codecs: vec![CBOR, DAG_CBOR, IPLD_RAW],
+            linkable: false,
+        }
+    }
+}
+
+impl InlineCidLimits {
+    /// Returns true if a block with the given codec and size may be inlined into a CID.
+    pub fn allows(&self, codec: u64, size: usize) -> bool {
+        size <= self.max_payload && self.codecs.contains(&codec)
+    }
+}
+
+/// The shape actor events must conform to. Events violating these limits are rejected when
+/// emitted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize_tuple)]
+pub struct EventLimits {
+    /// The maximum number of entries per event.
+    pub max_entries: usize,
+    /// The maximum number of indexed entries (entries with an indexed key or value) per event.
+    pub max_indexed_entries: usize,
+    /// The maximum length of an entry's key, in bytes.
+    pub max_key_len: usize,
+    /// The maximum length of all of an event's values combined, in bytes.
+    pub max_total_values_len: usize,
+    /// The maximum length of the keys and values of all events emitted by a message (excluding
+    /// those discarded due to aborts), in bytes.
+    pub max_message_payload: usize,
+    /// The codecs entry values may use.
+    pub codecs: Vec<u64>,
+    /// The flags entries may set.
+    pub flags: Flags,
+}
+
+impl Default for EventLimits {
+    fn default() -> Self {
+        EventLimits {
+            max_entries: 255,
+            max_indexed_entries: 255,
+            max_key_len: 31,
+            max_total_values_len: 8 << 10,
+            max_message_payload: 1 << 20,
+            codecs: vec![IPLD_RAW],
+            flags: Flags::FLAG_INDEXED_ALL,
+        }
+    }
+}
+
+impl EventLimits {
+    /// Returns the event limits in effect in the given network version. The per-message payload
+    /// is only capped from network version 22 on.
+    pub fn for_network_version(network_version: NetworkVersion) -> Self {
+        let mut limits = Self::default();
+        if network_version < NetworkVersion::V22 {
+            limits.max_message_payload = usize::MAX;
+        }
+        limits
+    }
 }
 
 /// Network-level settings. Except when testing locally, changing any of these likely requires a
@@ -121,6 +486,32 @@ pub struct NetworkConfig {
     /// DEFAULT: 1MiB
     pub max_block_size: usize,
 
+    /// The maximum number of links (to non-inline blocks) a block created in the FVM may contain.
+    ///
+    /// DEFAULT: 64Ki (more than a block of the default maximum size can hold)
+    pub max_block_links: usize,
+
+    /// The IPLD codecs and multihashes actors may use.
+    ///
+    /// DEFAULT: The codecs and multihashes allowed in the current network version.
+    pub codecs: CodecRegistry,
+
+    /// Limits on identity-hashed ("inline") CIDs linked from blocks.
+    ///
+    /// DEFAULT: 64 byte payloads, using any codec allowed for blocks, not linkable by actors.
+    pub inline_cid_limits: InlineCidLimits,
+
+    /// Limits on the events actors may emit.
+    ///
+    /// DEFAULT: Up to 255 entries with 31 byte keys and 8KiB of values, encoded as raw bytes.
+    pub event_limits: EventLimits,
+
+    /// Emit actor lifecycle events (see [`SystemEvent`](crate::call_manager::SystemEvent)) from
+    /// the system actor. This is a consensus-critical option (affects the events root).
+    ///
+    /// DEFAULT: `false`
+    pub system_events: bool,
+
     /// An override for builtin-actors. If specified, this should be the CID of a builtin-actors
     /// "manifest".
     ///
@@ -137,8 +528,54 @@ pub struct NetworkConfig {
     /// DEFAULT: The price-list for the current network version.
     pub price_list: &'static PriceList,
 
+    /// Price lists to use instead of the built-in ones, by network version (including when the
+    /// network version changes on upgrade). See [`NetworkConfig::price_schedule`].
+    ///
+    /// DEFAULT: None
+    pub price_schedule: Option<&'static PriceSchedule>,
+
     /// Actor redirects for debug execution
     pub actor_redirect: Vec<(Cid, Cid)>,
+
+    /// Shared library modules actors may import, as (import module name, code CID) pairs. See
+    /// [`NetworkConfig::add_shared_module`].
+    ///
+    /// DEFAULT: None
+    pub shared_modules: Vec<(String, Cid)>,
+
+    /// The maximum wall-clock time a message may spend executing actor code. Messages exceeding
+    /// it fail with a fatal error (not an actor error), so this is a safety net against mispriced
+    /// code (e.g., a hot loop that's too cheap), not a replacement for gas.
+    ///
+    /// DEFAULT: None
+    pub execution_timeout: Option<Duration>,
+
+    /// Let actors import syscalls this FVM doesn't provide (e.g., syscalls added in later
+    /// versions), instead of failing to load them. Calling such a syscall fails with
+    /// [`Forbidden`](fvm_shared::error::ErrorNumber::Forbidden), so actors can check for it (or
+    /// for the [capabilities](fvm_shared::sys::Capabilities) providing it) and degrade gracefully.
+    ///
+    /// DEFAULT: `false`
+    pub lazy_syscall_linking: bool,
+
+    /// Let actors create an actor and invoke its constructor in one step with the
+    /// `actor::create_and_invoke` syscall. Unlike `actor::create_actor`, any actor (e.g., a
+    /// user-deployed factory) may call it, not just the init actor. As only the init actor
+    /// allocates actor IDs, the FVM assigns IDs to actors created by other factories.
+    ///
+    /// DEFAULT: `false` (the syscall isn't linked)
+    pub actor_factories: bool,
+
+    /// The duration of an epoch, in seconds, for actors converting between epochs and real time
+    /// (e.g., payment channel deadlines).
+    ///
+    /// DEFAULT: 30 seconds
+    pub epoch_duration_seconds: u64,
+
+    /// The expected number of blocks per epoch.
+    ///
+    /// DEFAULT: 5
+    pub blocks_per_epoch: u64,
 }
 
 impl NetworkConfig {
@@ -154,8 +591,20 @@ impl NetworkConfig {
             actor_debugging: false,
             builtin_actors_override: None,
             price_list: price_list_by_network_version(network_version),
+            price_schedule: None,
             actor_redirect: vec![],
+            shared_modules: vec![],
             max_block_size: 1 << 20,
+            max_block_links: 1 << 16,
+            codecs: CodecRegistry::for_network_version(network_version),
+            inline_cid_limits: InlineCidLimits::default(),
+            event_limits: EventLimits::for_network_version(network_version),
+            system_events: false,
+            execution_timeout: None,
+            lazy_syscall_linking: false,
+            actor_factories: false,
+            epoch_duration_seconds: EPOCH_DURATION_SECONDS as u64,
+            blocks_per_epoch: 5,
         }
     }
 
@@ -166,6 +615,65 @@ impl NetworkConfig {
         self
     }
 
+    /// Enable actor lifecycle events. This is a consensus-critical option (affects the events
+    /// root) so it should only be enabled as a network-wide parameter.
+    pub fn enable_system_events(&mut self) -> &mut Self {
+        self.system_events = true;
+        self
+    }
+
+    /// Let actors import syscalls this FVM doesn't provide. This is a consensus-critical option
+    /// (affects which actors can be loaded). See [`NetworkConfig::lazy_syscall_linking`].
+    pub fn enable_lazy_syscall_linking(&mut self) -> &mut Self {
+        self.lazy_syscall_linking = true;
+        self
+    }
+
+    /// Let actors create actors with `actor::create_and_invoke`. This is a consensus-critical
+    /// option (affects which actors can be loaded, and who may create actors). See
+    /// [`NetworkConfig::actor_factories`].
+    pub fn enable_actor_factories(&mut self) -> &mut Self {
+        self.actor_factories = true;
+        self
+    }
+
+    /// Bound the wall-clock time a message may spend executing actor code. See
+    /// [`NetworkConfig::execution_timeout`].
+    pub fn execution_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.execution_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the epoch duration (in seconds) and expected blocks per epoch, for networks with
+    /// non-default block times. This is a consensus-critical option (actors may observe it).
+    pub fn epoch_timing(
+        &mut self,
+        epoch_duration_seconds: u64,
+        blocks_per_epoch: u64,
+    ) -> &mut Self {
+        self.epoch_duration_seconds = epoch_duration_seconds;
+        self.blocks_per_epoch = blocks_per_epoch;
+        self
+    }
+
+    /// Use the price lists from the given schedule (e.g., loaded with
+    /// [`PriceSchedule::from_cbor`]) instead of the built-in ones, for the network versions it
+    /// covers. This is a consensus-critical option, so the schedule should be a network-wide
+    /// parameter (or a fixture, for testing).
+    pub fn price_schedule(&mut self, schedule: &'static PriceSchedule) -> &mut Self {
+        self.price_schedule = Some(schedule);
+        self.price_list = self.price_list_for(self.network_version);
+        self
+    }
+
+    /// Returns the price list for the given network version, from the
+    /// [price schedule](NetworkConfig::price_schedule) if it has one.
+    pub fn price_list_for(&self, network_version: NetworkVersion) -> &'static PriceList {
+        self.price_schedule
+            .and_then(|schedule| schedule.get(network_version))
+            .unwrap_or_else(|| price_list_by_network_version(network_version))
+    }
+
     /// Override actors with the specific manifest. This is primarily useful for testing, or
     /// networks prior to NV16 (where the actor's "manifest" isn't specified on-chain).
     pub fn override_actors(&mut self, manifest: Cid) -> &mut Self {
@@ -173,12 +681,53 @@ impl NetworkConfig {
         self
     }
 
+    /// Set the maximum call depth. [`NetworkConfig::max_call_depth`].
+    pub fn max_call_depth(&mut self, depth: u32) -> &mut Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    /// Allow actors to use an additional IPLD codec. See [`NetworkConfig::codecs`] and
+    /// [`CodecRegistry::allow_codec`].
+    pub fn allow_codec(&mut self, codec: u64) -> anyhow::Result<&mut Self> {
+        self.codecs.allow_codec(codec)?;
+        Ok(self)
+    }
+
     /// Set actor redirects for debug execution
     pub fn redirect_actors(&mut self, actor_redirect: Vec<(Cid, Cid)>) -> &mut Self {
         self.actor_redirect = actor_redirect;
         self
     }
 
+    /// Register a shared library module (Wasm code stored under `code` in the state blockstore) that
+    /// actors may import functions from under the module name `name`, instead of each bundling
+    /// their own copy of common code (e.g., CBOR or bigint routines).
+    ///
+    /// Each invocation of an actor importing a shared module gets its own, private, instance of
+    /// it, so no state is shared between actors. Shared modules are instrumented like actor code
+    /// and charge gas from the same gas counter, and their memory counts towards the same limits.
+    /// A shared module may only import functions from shared modules registered before it: it
+    /// may not import actor code, nor syscalls (which operate on the memory of the actor's
+    /// instance). Actors importing a shared module that breaks this rule fail with a fatal error.
+    /// At most [`MAX_SHARED_MODULES`](crate::engine::MAX_SHARED_MODULES) shared modules may be
+    /// registered. Fails if the name is one of the syscall namespaces (e.g., `vm`), as the module
+    /// would shadow the syscalls.
+    pub fn add_shared_module(
+        &mut self,
+        name: impl Into<String>,
+        code: Cid,
+    ) -> anyhow::Result<&mut Self> {
+        let name = name.into();
+        if SYSCALL_MODULES.contains(&name.as_str()) {
+            return Err(anyhow!(
+                "shared module name {name} is reserved for syscalls"
+            ));
+        }
+        self.shared_modules.push((name, code));
+        Ok(self)
+    }
+
     /// Create a ['MachineContext'] for a given epoch, timestamp, and initial state.
     pub fn for_epoch(
         &self,
@@ -194,6 +743,13 @@ impl NetworkConfig {
             initial_state_root: initial_state,
             circ_supply: fvm_shared::TOTAL_FILECOIN.clone(),
             tracing: false,
+            index_events: false,
+            syscall_census: false,
+            write_policy: WritePolicy::default(),
+            flush_workers: 1,
+            proof_threads: 0,
+            proof_parallelism_per_message: 0,
+            upgrade_schedule: UpgradeSchedule::default(),
         }
     }
 
@@ -240,6 +796,53 @@ pub struct MachineContext {
     /// Whether or not to produce execution traces in the returned result.
     /// Not consensus-critical, but has a performance impact.
     pub tracing: bool,
+
+    /// Whether or not to retain the events emitted by each message so they can be retrieved,
+    /// grouped by message, once the tipset has been applied. Not consensus-critical.
+    pub index_events: bool,
+
+    /// Whether or not to count the syscalls made by each message, by syscall, and return the
+    /// counts in [`ApplyRet::syscall_counts`](crate::executor::ApplyRet::syscall_counts). Not
+    /// consensus-critical; intended for devnets, to inform gas schedule re-pricing.
+    pub syscall_census: bool,
+
+    /// When blocks written during execution reach the underlying blockstore. Not
+    /// consensus-critical, but [`WritePolicy::SizeCapped`] can be used to bound memory usage
+    /// (e.g., during large migrations).
+    ///
+    /// Default: [`WritePolicy::WriteBack`].
+    pub write_policy: WritePolicy,
+
+    /// The number of threads used to walk the new state when flushing it to the blockstore. Not
+    /// consensus-critical: the flushed state is the same regardless.
+    ///
+    /// Default: 1 (no extra threads).
+    pub flush_workers: usize,
+
+    /// The number of threads dedicated to verifying seal and PoSt proofs, shared by all messages
+    /// executed by the machine. Not consensus-critical.
+    ///
+    /// Default: 0 (use the global rayon pool).
+    pub proof_threads: usize,
+
+    /// The maximum number of proofs a single batch verification may verify concurrently, so that
+    /// one message can't monopolize the proof threads. Not consensus-critical.
+    ///
+    /// Default: 0 (no limit).
+    pub proof_parallelism_per_message: usize,
+
+    /// The network upgrades to apply. When the machine is constructed, the network version (and
+    /// the derived price list and codecs) and builtin actors are switched to those of the last
+    /// upgrade activated at or before [`MachineContext::epoch`], and the state is migrated if an
+    /// upgrade activates at exactly this epoch. The resulting context is available through
+    /// [`Machine::context`], and should be used to configure the engine. Codecs allowed through
+    /// [`MachineContext::codecs`] remain allowed after an upgrade.
+    ///
+    /// Upgrade migrations only run when a machine is constructed for the upgrade epoch, so
+    /// embedders must construct one even if the upgrade epoch is a null round.
+    ///
+    /// Default: no upgrades.
+    pub upgrade_schedule: UpgradeSchedule,
 }
 
 impl MachineContext {
@@ -260,4 +863,169 @@ impl MachineContext {
         self.tracing = true;
         self
     }
+
+    /// Enable event indexing. [`MachineContext::index_events`].
+    pub fn enable_event_index(&mut self) -> &mut Self {
+        self.index_events = true;
+        self
+    }
+
+    /// Enable the syscall census. [`MachineContext::syscall_census`].
+    pub fn enable_syscall_census(&mut self) -> &mut Self {
+        self.syscall_census = true;
+        self
+    }
+
+    /// Set [`MachineContext::write_policy`].
+    pub fn set_write_policy(&mut self, policy: WritePolicy) -> &mut Self {
+        self.write_policy = policy;
+        self
+    }
+
+    /// Set [`MachineContext::proof_threads`] and
+    /// [`MachineContext::proof_parallelism_per_message`].
+    pub fn set_proof_threads(&mut self, threads: usize, per_message: usize) -> &mut Self {
+        self.proof_threads = threads;
+        self.proof_parallelism_per_message = per_message;
+        self
+    }
+
+    /// Set [`MachineContext::upgrade_schedule`].
+    pub fn set_upgrade_schedule(&mut self, schedule: UpgradeSchedule) -> &mut Self {
+        self.upgrade_schedule = schedule;
+        self
+    }
+
+    /// Returns the network version in effect at [`MachineContext::epoch`], according to the
+    /// [upgrade schedule](MachineContext::upgrade_schedule).
+    pub fn effective_network_version(&self) -> NetworkVersion {
+        self.upgrade_schedule
+            .active_at(self.epoch)
+            .map_or(self.network_version, |u| u.network_version)
+    }
+
+    /// Applies the [upgrade schedule](MachineContext::upgrade_schedule) at the current epoch,
+    /// migrating the state in `store` if an upgrade activates at this epoch.
+    pub(crate) fn apply_upgrades(&mut self, store: &dyn Blockstore) -> anyhow::Result<()> {
+        let Some(upgrade) = self.upgrade_schedule.active_at(self.epoch).cloned() else {
+            return Ok(());
+        };
+        let nv = upgrade.network_version;
+        if nv != self.network_version {
+            // Switch to the new version's payload cap, unless the embedder customized it.
+            let limits = &mut self.network.event_limits;
+            let old_limits = EventLimits::for_network_version(self.network_version);
+            if limits.max_message_payload == old_limits.max_message_payload {
+                limits.max_message_payload =
+                    EventLimits::for_network_version(nv).max_message_payload;
+            }
+            self.network_version = nv;
+            self.price_list = self.network.price_list_for(nv);
+            // Keep the embedder's customizations: network upgrades only ever allow new codecs.
+            self.codecs.merge(&CodecRegistry::for_network_version(nv));
+        }
+        if let Some(actors) = upgrade.builtin_actors {
+            self.builtin_actors_override = Some(actors);
+        }
+        if let (true, Some(migration)) = (upgrade.epoch == self.epoch, &upgrade.migration) {
+            self.initial_state_root = migration
+                .migrate(store, self.initial_state_root, self.epoch)
+                .with_context(|| format!("failed to migrate state to network version {nv}"))?;
+        }
+        Ok(())
+    }
+
+    /// Set [`MachineContext::flush_workers`].
+    pub fn set_flush_workers(&mut self, workers: usize) -> &mut Self {
+        self.flush_workers = workers;
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use cid::Cid;
+    use fvm_ipld_encoding::{CBOR, DAG_CBOR, IPLD_RAW};
+    use fvm_shared::version::NetworkVersion;
+
+    use super::{fingerprint, CodecRegistry, EventLimits, Manifest, NetworkConfig};
+    use crate::kernel::SupportedHashes;
+
+    #[test]
+    fn codecs_by_network_version() {
+        let old = CodecRegistry::for_network_version(NetworkVersion::V17);
+        assert!(old.allows_codec(DAG_CBOR) && old.allows_codec(IPLD_RAW));
+        assert!(!old.allows_codec(CBOR));
+
+        let current = CodecRegistry::for_network_version(NetworkVersion::V21);
+        assert!(current.allows_codec(CBOR));
+        assert!(current.allows_multihash(SupportedHashes::Blake2b256.into(), 32));
+        assert!(!current.allows_multihash(SupportedHashes::Blake2b256.into(), 20));
+    }
+
+    #[test]
+    fn event_limits_by_network_version() {
+        let current = EventLimits::for_network_version(NetworkVersion::V21);
+        assert_eq!(current.max_message_payload, usize::MAX);
+
+        let next = EventLimits::for_network_version(NetworkVersion::V22);
+        assert_eq!(next, EventLimits::default());
+        assert_eq!(next.max_message_payload, 1 << 20);
+    }
+
+    #[test]
+    fn fingerprint_covers_config() {
+        let manifest = Manifest::dummy();
+        let base = NetworkConfig::new(NetworkVersion::V21);
+        let expected = fingerprint(&base, &manifest);
+        assert_eq!(fingerprint(&base.clone(), &manifest), expected);
+
+        // Timeouts are local policy, and don't affect the results of executions that complete.
+        let mut config = base.clone();
+        config.execution_timeout(Duration::from_secs(1));
+        assert_eq!(fingerprint(&config, &manifest), expected);
+
+        let mut config = base.clone();
+        config.override_actors(Cid::default());
+        assert_ne!(fingerprint(&config, &manifest), expected);
+
+        let mut config = base.clone();
+        config.event_limits.max_key_len += 1;
+        assert_ne!(fingerprint(&config, &manifest), expected);
+
+        let mut config = base.clone();
+        config.inline_cid_limits.linkable = true;
+        assert_ne!(fingerprint(&config, &manifest), expected);
+
+        let mut config = base.clone();
+        config.epoch_timing(4, 1);
+        assert_ne!(fingerprint(&config, &manifest), expected);
+
+        let mut config = base;
+        config.codecs = CodecRegistry::for_network_version(NetworkVersion::V17);
+        assert_ne!(fingerprint(&config, &manifest), expected);
+    }
+
+    #[test]
+    fn allow_codec() {
+        let mut registry = CodecRegistry::for_network_version(NetworkVersion::V17);
+        registry.allow_codec(CBOR).unwrap();
+        assert!(registry.allows_codec(CBOR));
+
+        // We can't scan DAG-JSON blocks for links, so we can't allow them.
+        registry.allow_codec(0x0129).unwrap_err();
+        assert!(!registry.allows_codec(0x0129));
+    }
+
+    #[test]
+    fn merge() {
+        let mut registry = CodecRegistry::for_network_version(NetworkVersion::V17);
+        registry.allow_multihash(SupportedHashes::Sha2_256.into(), 32);
+        registry.merge(&CodecRegistry::for_network_version(NetworkVersion::V21));
+        assert!(registry.allows_codec(CBOR));
+        assert!(registry.allows_multihash(SupportedHashes::Sha2_256.into(), 32));
+        assert!(registry.allows_multihash(SupportedHashes::Blake2b256.into(), 32));
+    }
 }
@@ -0,0 +1,97 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use fvm_shared::crypto::signature::ops::recover_secp_public_key;
+use fvm_shared::crypto::signature::{SignatureType, SECP_SIG_LEN};
+use multihash::MultihashDigest;
+
+use crate::kernel::SupportedHashes;
+
+/// Verifies signatures by delegated (f4) addresses of a given namespace, for the `verify_signature`
+/// syscall. Register it with
+/// [`DefaultMachine::with_signature_verifier`](super::DefaultMachine::with_signature_verifier).
+///
+/// Signature verification is consensus-critical: every node on a network must register the same
+/// verifiers, for the same namespaces.
+pub trait SignatureVerifier: Send + Sync + 'static {
+    /// Returns true if `signature` is a valid signature of type `sig_type` over `plaintext`, by the
+    /// signer with the given f4 subaddress. Malformed signatures are simply invalid.
+    fn verify(
+        &self,
+        sig_type: SignatureType,
+        signature: &[u8],
+        subaddress: &[u8],
+        plaintext: &[u8],
+    ) -> bool;
+}
+
+/// Verifies Ethereum-style signatures: secp256k1 signatures (`r || s || v`, with a recovery ID `v`
+/// of 0 or 1) over the keccak256 digest of the plaintext (e.g., an RLP-encoded transaction), by
+/// the signer whose Ethereum address is the subaddress. This is meant to be registered for the
+/// Ethereum address manager's namespace.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct EthSecp256k1Verifier;
+
+impl SignatureVerifier for EthSecp256k1Verifier {
+    fn verify(
+        &self,
+        sig_type: SignatureType,
+        signature: &[u8],
+        subaddress: &[u8],
+        plaintext: &[u8],
+    ) -> bool {
+        if sig_type != SignatureType::Secp256k1 {
+            return false;
+        }
+        let Ok(signature): Result<&[u8; SECP_SIG_LEN], _> = signature.try_into() else {
+            return false;
+        };
+        let hash: [u8; 32] = SupportedHashes::Keccak256
+            .digest(plaintext)
+            .digest()
+            .try_into()
+            .expect("keccak256 digests are 32 bytes");
+        let Ok(pubkey) = recover_secp_public_key(&hash, signature) else {
+            return false;
+        };
+        // The Ethereum address is the last 20 bytes of the keccak256 digest of the uncompressed
+        // public key, without its 0x04 prefix.
+        let key_hash = SupportedHashes::Keccak256.digest(&pubkey.serialize()[1..]);
+        key_hash.digest()[12..] == *subaddress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_shared::crypto::signature::{SignatureType, SECP_SIG_LEN};
+
+    use super::{EthSecp256k1Verifier, SignatureVerifier};
+
+    #[test]
+    fn eth_verifier_rejects_malformed_signatures() {
+        let verifier = EthSecp256k1Verifier;
+        let subaddress = [1u8; 20];
+        // Wrong signature type.
+        assert!(!verifier.verify(
+            SignatureType::BLS,
+            &[0; SECP_SIG_LEN],
+            &subaddress,
+            b"plaintext"
+        ));
+        // Wrong length.
+        assert!(!verifier.verify(
+            SignatureType::Secp256k1,
+            &[0; SECP_SIG_LEN - 1],
+            &subaddress,
+            b"plaintext"
+        ));
+        // Invalid recovery ID.
+        let mut signature = [1u8; SECP_SIG_LEN];
+        signature[SECP_SIG_LEN - 1] = 4;
+        assert!(!verifier.verify(
+            SignatureType::Secp256k1,
+            &signature,
+            &subaddress,
+            b"plaintext"
+        ));
+    }
+}
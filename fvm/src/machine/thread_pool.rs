@@ -0,0 +1,59 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context as _;
+use lazy_static::lazy_static;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+lazy_static! {
+    static ref POOLS: Mutex<HashMap<(&'static str, usize), Arc<ThreadPool>>> = Default::default();
+}
+
+/// Returns the process-wide thread pool with the given name and number of threads, creating it on
+/// first use. Machines are created per tipset (or more often), so they share their pools instead
+/// of each spawning (and tearing down) their own threads.
+pub(crate) fn shared_thread_pool(
+    name: &'static str,
+    threads: usize,
+) -> anyhow::Result<Arc<ThreadPool>> {
+    let mut pools = POOLS.lock().expect("thread pool registry poisoned");
+    if let Some(pool) = pools.get(&(name, threads)) {
+        return Ok(pool.clone());
+    }
+    let pool = Arc::new(
+        ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(move |i| format!("{name}-{i}"))
+            .build()
+            .with_context(|| format!("failed to create the {name} thread pool"))?,
+    );
+    pools.insert((name, threads), pool.clone());
+    Ok(pool)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::shared_thread_pool;
+
+    #[test]
+    fn pools_are_shared() {
+        let a = shared_thread_pool("fvm-test", 2).unwrap();
+        let b = shared_thread_pool("fvm-test", 2).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(a.current_num_threads(), 2);
+
+        // Pools with a different name or size are distinct.
+        let c = shared_thread_pool("fvm-test", 3).unwrap();
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(c.current_num_threads(), 3);
+        let d = shared_thread_pool("fvm-test-other", 2).unwrap();
+        assert!(!Arc::ptr_eq(&a, &d));
+
+        let name = a.install(|| std::thread::current().name().map(String::from));
+        assert!(name.unwrap().starts_with("fvm-test-"));
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Network upgrades: switching the network version (and builtin actors) at scheduled epochs, and
+//! migrating the state when an upgrade activates.
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::version::NetworkVersion;
+
+/// Migrates the state when a network upgrade activates.
+pub trait Migration: Send + Sync {
+    /// Migrates the state tree under `state_root` (in `store`) at the upgrade epoch, returning
+    /// the new state root. New blocks must be written to `store`.
+    fn migrate(
+        &self,
+        store: &dyn Blockstore,
+        state_root: Cid,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<Cid>;
+}
+
+impl<F> Migration for F
+where
+    F: Fn(&dyn Blockstore, Cid, ChainEpoch) -> anyhow::Result<Cid> + Send + Sync,
+{
+    fn migrate(
+        &self,
+        store: &dyn Blockstore,
+        state_root: Cid,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<Cid> {
+        self(store, state_root, epoch)
+    }
+}
+
+/// A network upgrade, activating at an epoch.
+#[derive(Clone)]
+pub struct Upgrade {
+    /// The first epoch executed with the new network version.
+    pub epoch: ChainEpoch,
+    /// The new network version.
+    pub network_version: NetworkVersion,
+    /// The builtin-actors bundle (root CID) to use from the upgrade on, if it changes. See
+    /// [`NetworkConfig::override_actors`](super::NetworkConfig::override_actors).
+    pub builtin_actors: Option<Cid>,
+    /// The state migration to run at the upgrade epoch, if any.
+    pub migration: Option<Arc<dyn Migration>>,
+}
+
+impl fmt::Debug for Upgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upgrade")
+            .field("epoch", &self.epoch)
+            .field("network_version", &self.network_version)
+            .field("builtin_actors", &self.builtin_actors)
+            .field("migration", &self.migration.is_some())
+            .finish()
+    }
+}
+
+/// The network upgrades to apply, by epoch. See
+/// [`MachineContext::upgrade_schedule`](super::MachineContext::upgrade_schedule).
+#[derive(Clone, Debug, Default)]
+pub struct UpgradeSchedule {
+    /// Upgrades, in increasing epoch (and network version) order.
+    upgrades: Vec<Upgrade>,
+}
+
+impl UpgradeSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedules an upgrade. Upgrades must be scheduled in order, with increasing epochs and
+    /// network versions.
+    pub fn add(&mut self, upgrade: Upgrade) -> anyhow::Result<&mut Self> {
+        if let Some(last) = self.upgrades.last() {
+            if upgrade.epoch <= last.epoch || upgrade.network_version <= last.network_version {
+                return Err(anyhow!(
+                    "upgrade to {} at epoch {} must come after the upgrade to {} at epoch {}",
+                    upgrade.network_version,
+                    upgrade.epoch,
+                    last.network_version,
+                    last.epoch
+                ));
+            }
+        }
+        self.upgrades.push(upgrade);
+        Ok(self)
+    }
+
+    /// Returns the last upgrade activated at or before the epoch, if any.
+    pub fn active_at(&self, epoch: ChainEpoch) -> Option<&Upgrade> {
+        self.upgrades.iter().rev().find(|u| u.epoch <= epoch)
+    }
+
+    /// Returns the upgrade activating at exactly the epoch, if any.
+    pub fn activating_at(&self, epoch: ChainEpoch) -> Option<&Upgrade> {
+        self.upgrades.iter().find(|u| u.epoch == epoch)
+    }
+
+    /// Returns the next upgrade activating after the epoch, if it activates within `lookahead`
+    /// epochs. Nodes can use this to start
+    /// [pre-migrating](super::migration::StateMigration::premigrate) the state ahead of the
+    /// upgrade.
+    pub fn upcoming(&self, epoch: ChainEpoch, lookahead: ChainEpoch) -> Option<&Upgrade> {
+        self.upgrades
+            .iter()
+            .find(|u| u.epoch > epoch)
+            .filter(|u| u.epoch - epoch <= lookahead)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.upgrades.is_empty()
+    }
+
+    /// Returns the scheduled upgrades, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &Upgrade> {
+        self.upgrades.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use cid::Cid;
+    use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+    use fvm_ipld_encoding::{CborStore, CBOR, DAG_CBOR};
+    use fvm_shared::clock::ChainEpoch;
+    use fvm_shared::version::NetworkVersion;
+    use multihash::Code;
+
+    use super::{Upgrade, UpgradeSchedule};
+    use crate::machine::{CodecRegistry, EventLimits, NetworkConfig};
+
+    fn upgrade(epoch: i64, nv: NetworkVersion) -> Upgrade {
+        Upgrade {
+            epoch,
+            network_version: nv,
+            builtin_actors: None,
+            migration: None,
+        }
+    }
+
+    #[test]
+    fn schedule() {
+        let mut schedule = UpgradeSchedule::new();
+        schedule
+            .add(upgrade(10, NetworkVersion::V21))
+            .unwrap()
+            .add(upgrade(20, NetworkVersion::V22))
+            .unwrap();
+        // Out of order.
+        schedule.add(upgrade(15, NetworkVersion::V20)).unwrap_err();
+        schedule.add(upgrade(30, NetworkVersion::V22)).unwrap_err();
+
+        assert!(schedule.active_at(9).is_none());
+        assert_eq!(
+            schedule.active_at(19).unwrap().network_version,
+            NetworkVersion::V21
+        );
+        assert_eq!(
+            schedule.active_at(100).unwrap().network_version,
+            NetworkVersion::V22
+        );
+        assert_eq!(schedule.activating_at(20).unwrap().epoch, 20);
+        assert!(schedule.activating_at(21).is_none());
+        assert_eq!(schedule.upcoming(5, 5).unwrap().epoch, 10);
+        assert!(schedule.upcoming(5, 4).is_none());
+        assert_eq!(schedule.upcoming(10, 10).unwrap().epoch, 20);
+    }
+
+    #[test]
+    fn apply_upgrades() {
+        let store = MemoryBlockstore::default();
+        let root = store.put_cbor(&"old", Code::Blake2b256).unwrap();
+        let migrated = store.put_cbor(&"new", Code::Blake2b256).unwrap();
+
+        let mut schedule = UpgradeSchedule::new();
+        schedule
+            .add(Upgrade {
+                migration: Some(Arc::new(
+                    move |_: &dyn Blockstore, _: Cid, _: ChainEpoch| -> anyhow::Result<Cid> {
+                        Ok(migrated)
+                    },
+                )),
+                ..upgrade(10, NetworkVersion::V21)
+            })
+            .unwrap();
+        let nc = NetworkConfig::new(NetworkVersion::V21);
+
+        // Migrations only run at the upgrade epoch.
+        for (epoch, expected) in [(9, root), (10, migrated), (11, root)] {
+            let mut mc = nc.for_epoch(epoch, 0, root);
+            mc.set_upgrade_schedule(schedule.clone());
+            mc.apply_upgrades(&store).unwrap();
+            assert_eq!(mc.initial_state_root, expected, "epoch {epoch}");
+        }
+    }
+
+    #[test]
+    fn upgrades_keep_codec_customizations() {
+        let store = MemoryBlockstore::default();
+        let root = store.put_cbor(&"old", Code::Blake2b256).unwrap();
+
+        let mut schedule = UpgradeSchedule::new();
+        schedule.add(upgrade(10, NetworkVersion::V21)).unwrap();
+
+        let mut mc = NetworkConfig::new(NetworkVersion::V21).for_epoch(10, 0, root);
+        mc.network_version = NetworkVersion::V17;
+        mc.codecs = CodecRegistry::for_network_version(NetworkVersion::V17);
+        mc.codecs.allow_multihash(Code::Sha2_256.into(), 32);
+        assert!(!mc.codecs.allows_codec(CBOR));
+        mc.set_upgrade_schedule(schedule);
+        mc.apply_upgrades(&store).unwrap();
+
+        assert_eq!(mc.network_version, NetworkVersion::V21);
+        assert!(mc.codecs.allows_multihash(Code::Sha2_256.into(), 32));
+        assert!(mc.codecs.allows_codec(DAG_CBOR));
+        assert!(mc.codecs.allows_codec(CBOR));
+    }
+
+    #[test]
+    fn upgrades_update_event_limits() {
+        let store = MemoryBlockstore::default();
+        let root = store.put_cbor(&"old", Code::Blake2b256).unwrap();
+
+        let mut schedule = UpgradeSchedule::new();
+        schedule.add(upgrade(10, NetworkVersion::V22)).unwrap();
+
+        // The per-message payload cap takes effect at the upgrade, as if the machine had been
+        // created at the new network version.
+        let nc = NetworkConfig::new(NetworkVersion::V21);
+        assert_eq!(nc.event_limits.max_message_payload, usize::MAX);
+        let mut mc = nc.for_epoch(10, 0, root);
+        mc.set_upgrade_schedule(schedule.clone());
+        mc.apply_upgrades(&store).unwrap();
+        assert_eq!(mc.network_version, NetworkVersion::V22);
+        assert_eq!(
+            mc.network.event_limits,
+            EventLimits::for_network_version(NetworkVersion::V22)
+        );
+
+        // Custom caps are kept.
+        let mut nc = NetworkConfig::new(NetworkVersion::V21);
+        nc.event_limits.max_message_payload = 100;
+        let mut mc = nc.for_epoch(10, 0, root);
+        mc.set_upgrade_schedule(schedule);
+        mc.apply_upgrades(&store).unwrap();
+        assert_eq!(mc.network.event_limits.max_message_payload, 100);
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! This module contains the types and functions to read the storage power actor's state. It does
+//! not contain the logic of the power actor: that lives on-chain as a WASM actor.
+//!
+//! It's intended for embedders that need to query network power (e.g., the total
+//! quality-adjusted power) without duplicating the state schema.
+//!
+//! ## Version compatibility
+//!
+//! This module only handles the state layout used by builtin-actors v9 onwards.
+
+use anyhow::Context;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::tuple::*;
+use fvm_ipld_encoding::CborStore;
+use fvm_shared::bigint::bigint_ser;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::sector::StoragePower;
+use fvm_shared::smooth::FilterEstimate;
+use fvm_shared::ActorID;
+
+use crate::kernel::{ClassifyResult, Result};
+use crate::state_tree::{ActorState, StateTree};
+
+pub const POWER_ACTOR_ID: ActorID = 4;
+
+#[derive(Serialize_tuple, Deserialize_tuple, Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    #[serde(with = "bigint_ser")]
+    pub total_raw_byte_power: StoragePower,
+    #[serde(with = "bigint_ser")]
+    pub total_bytes_committed: StoragePower,
+    #[serde(with = "bigint_ser")]
+    pub total_quality_adj_power: StoragePower,
+    #[serde(with = "bigint_ser")]
+    pub total_qa_bytes_committed: StoragePower,
+    pub total_pledge_collateral: TokenAmount,
+
+    #[serde(with = "bigint_ser")]
+    pub this_epoch_raw_byte_power: StoragePower,
+    #[serde(with = "bigint_ser")]
+    pub this_epoch_quality_adj_power: StoragePower,
+    pub this_epoch_pledge_collateral: TokenAmount,
+    pub this_epoch_qa_power_smoothed: FilterEstimate,
+
+    pub miner_count: i64,
+    /// Number of miners having proven the minimum consensus power.
+    pub miner_above_min_power_count: i64,
+
+    /// A queue of events to be triggered by cron, indexed by epoch.
+    pub cron_event_queue: Cid, // Multimap, (HAMT[ChainEpoch]AMT[CronEvent])
+
+    /// First epoch in which a cron task may be stored. Cron will iterate every epoch between this
+    /// and the current epoch inclusively to find tasks to execute.
+    pub first_cron_epoch: ChainEpoch,
+
+    /// Claimed power for each miner.
+    pub claims: Cid, // Map, HAMT[address]Claim
+
+    pub proof_validation_batch: Option<Cid>,
+}
+
+impl State {
+    /// Loads the power actor state from the supplied state tree.
+    pub fn load<B>(state_tree: &StateTree<B>) -> Result<(Self, ActorState)>
+    where
+        B: Blockstore,
+    {
+        let power_act = state_tree
+            .get_actor(POWER_ACTOR_ID)?
+            .context("power actor address could not be resolved")
+            .or_fatal()?;
+
+        let state = state_tree
+            .store()
+            .get_cbor(&power_act.state)
+            .or_fatal()?
+            .context("power actor state not found")
+            .or_fatal()?;
+
+        Ok((state, power_act))
+    }
+
+    /// Loads the power actor state from the state tree rooted at `root`.
+    pub fn load_from_root<B>(store: B, root: &Cid) -> Result<(Self, ActorState)>
+    where
+        B: Blockstore,
+    {
+        Self::load(&StateTree::new_from_root(store, root)?)
+    }
+}
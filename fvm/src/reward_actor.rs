@@ -0,0 +1,90 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! This module contains the types and functions to read the reward actor's state. It does not
+//! contain the logic of the reward actor: that lives on-chain as a WASM actor.
+//!
+//! It's intended for embedders that need to query reward-related values (e.g., the current
+//! baseline power or the reward for this epoch) without duplicating the state schema.
+//!
+//! ## Version compatibility
+//!
+//! This module only handles the state layout used by builtin-actors v9 onwards.
+
+use anyhow::Context;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::tuple::*;
+use fvm_ipld_encoding::CborStore;
+use fvm_shared::bigint::{bigint_ser, BigInt};
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::sector::StoragePower;
+use fvm_shared::smooth::FilterEstimate;
+
+use crate::kernel::{ClassifyResult, Result};
+pub use crate::machine::REWARD_ACTOR_ID;
+use crate::state_tree::{ActorState, StateTree};
+
+/// Number of token units in an abstract "spacetime" unit (byte-epochs).
+pub type Spacetime = BigInt;
+
+#[derive(Serialize_tuple, Deserialize_tuple, Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    /// Target CumsumRealized needs to reach for EffectiveNetworkTime to increase.
+    #[serde(with = "bigint_ser")]
+    pub cumsum_baseline: Spacetime,
+    /// CumsumRealized is cumulative sum of network power capped by BaselinePower(epoch).
+    #[serde(with = "bigint_ser")]
+    pub cumsum_realized: Spacetime,
+    /// The ceiling of real effective network time `theta` based on CumsumBaselinePower(theta) and
+    /// CumsumRealizedPower.
+    pub effective_network_time: ChainEpoch,
+    /// The baseline power the network has reached at EffectiveNetworkTime.
+    #[serde(with = "bigint_ser")]
+    pub effective_baseline_power: StoragePower,
+    /// The reward to be paid in per WinCount to block producers.
+    pub this_epoch_reward: TokenAmount,
+    /// Smoothed `this_epoch_reward`.
+    pub this_epoch_reward_smoothed: FilterEstimate,
+    /// The baseline power the network is targeting at the current epoch.
+    #[serde(with = "bigint_ser")]
+    pub this_epoch_baseline_power: StoragePower,
+    /// The epoch at which these values were computed.
+    pub epoch: ChainEpoch,
+    /// The total FIL awarded to block miners (simple + baseline).
+    pub total_storage_power_reward: TokenAmount,
+    /// Simple minting total.
+    pub simple_total: TokenAmount,
+    /// Baseline minting total.
+    pub baseline_total: TokenAmount,
+}
+
+impl State {
+    /// Loads the reward actor state from the supplied state tree.
+    pub fn load<B>(state_tree: &StateTree<B>) -> Result<(Self, ActorState)>
+    where
+        B: Blockstore,
+    {
+        let reward_act = state_tree
+            .get_actor(REWARD_ACTOR_ID)?
+            .context("reward actor address could not be resolved")
+            .or_fatal()?;
+
+        let state = state_tree
+            .store()
+            .get_cbor(&reward_act.state)
+            .or_fatal()?
+            .context("reward actor state not found")
+            .or_fatal()?;
+
+        Ok((state, reward_act))
+    }
+
+    /// Loads the reward actor state from the state tree rooted at `root`.
+    pub fn load_from_root<B>(store: B, root: &Cid) -> Result<(Self, ActorState)>
+    where
+        B: Blockstore,
+    {
+        Self::load(&StateTree::new_from_root(store, root)?)
+    }
+}
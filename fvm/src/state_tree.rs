@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 
 use anyhow::{anyhow, Context as _};
 use cid::{multihash, Cid};
@@ -45,6 +46,18 @@ struct ActorCacheEntry {
     actor: Option<ActorState>,
 }
 
+/// An actor changed within a state-tree transaction (see [`StateTree::transaction_changes`]) or
+/// between two state roots (see [`diff`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActorChange {
+    /// The changed actor.
+    pub id: ActorID,
+    /// The actor's prior state, or `None` if it didn't exist.
+    pub before: Option<ActorState>,
+    /// The actor's new state, or `None` if it has been deleted.
+    pub after: Option<ActorState>,
+}
+
 /// State snap shot layer.
 struct StateSnapLayer {
     /// The actor-cache height at which this snapshot was taken.
@@ -304,6 +317,39 @@ where
         Ok(())
     }
 
+    /// Returns the actors changed since the innermost transaction began, ordered by actor ID.
+    /// Actors that were only read, or that were modified and then restored, are omitted.
+    pub fn transaction_changes(&self) -> Result<Vec<ActorChange>> {
+        let layer = self
+            .layers
+            .last()
+            .context("not in a transaction")
+            .or_fatal()?;
+        let cache = self.actor_cache.borrow();
+        let mut changes = Vec::new();
+        for (&id, prev) in cache.changed_since(layer.actor_cache_height) {
+            let current = cache.get(&id);
+            let before = match prev {
+                Some(entry) => entry.actor.clone(),
+                // The actor wasn't cached when the transaction began, so it hadn't been modified
+                // since the last flush. Unless it's now dirty, it has only been read.
+                None if current.map_or(false, |e| e.dirty) => self
+                    .hamt
+                    .get(&Address::new_id(id).to_bytes())
+                    .with_context(|| format!("failed to lookup actor {}", id))
+                    .or_fatal()?
+                    .cloned(),
+                None => continue,
+            };
+            let after = current.and_then(|e| e.actor.clone());
+            if before != after {
+                changes.push(ActorChange { id, before, after });
+            }
+        }
+        changes.sort_by_key(|c| c.id);
+        Ok(changes)
+    }
+
     /// Returns true if we're inside of a transaction.
     pub fn in_transaction(&self) -> bool {
         !self.layers.is_empty()
@@ -372,3 +418,184 @@ where
         Ok(())
     }
 }
+
+/// Computes the actors created, modified, or deleted between two state roots, ordered by actor ID.
+/// Created actors have no `before` state and deleted actors have no `after` state.
+pub fn diff<S: Blockstore>(store: &S, pre_root: &Cid, post_root: &Cid) -> Result<Vec<ActorChange>> {
+    if pre_root == post_root {
+        return Ok(Vec::new());
+    }
+
+    fn collect<S: Blockstore>(store: &S, root: &Cid) -> Result<BTreeMap<ActorID, ActorState>> {
+        let mut actors = BTreeMap::new();
+        StateTree::new_from_root(store, root)?
+            .for_each(|addr, state| {
+                actors.insert(addr.id()?, state.clone());
+                Ok(())
+            })
+            .with_context(|| format!("failed to walk state tree {}", root))
+            .or_fatal()?;
+        Ok(actors)
+    }
+
+    let mut pre = collect(store, pre_root)?;
+    let post = collect(store, post_root)?;
+
+    let mut changes = Vec::new();
+    for (id, after) in post {
+        match pre.remove(&id) {
+            Some(before) if before == after => {}
+            before => changes.push(ActorChange {
+                id,
+                before,
+                after: Some(after),
+            }),
+        }
+    }
+    changes.extend(pre.into_iter().map(|(id, before)| ActorChange {
+        id,
+        before: Some(before),
+        after: None,
+    }));
+    changes.sort_by_key(|c| c.id);
+    Ok(changes)
+}
+
+/// A problem found by [`verify_state_root`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateIssue {
+    /// The actors HAMT is malformed or contains an undecodable entry. Actors after the bad entry
+    /// weren't checked.
+    Hamt(String),
+    /// An actors HAMT key isn't an ID address.
+    InvalidKey(Address),
+    /// An actor's state root is missing from the blockstore.
+    MissingState { id: ActorID, state: Cid },
+    /// The init actor is missing, or its state can't be loaded.
+    InitActor(String),
+    /// The init actor's address map is malformed or contains an undecodable entry.
+    AddressMap(String),
+    /// An address in the init actor's address map resolves to an actor that doesn't exist.
+    DanglingAddress { address: Address, id: ActorID },
+    /// An actor's delegated address doesn't resolve to that actor in the init actor's address map.
+    UnmappedDelegatedAddress { id: ActorID, address: Address },
+    /// An actor has an ID the init actor hasn't allocated yet.
+    UnallocatedId { id: ActorID, next_id: ActorID },
+}
+
+/// The result of [`verify_state_root`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateVerificationReport {
+    /// The number of actors checked.
+    pub actors: u64,
+    /// The problems found, if any.
+    pub issues: Vec<StateIssue>,
+}
+
+impl StateVerificationReport {
+    /// Returns true if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Walks the state tree under `root`, checking that:
+///
+/// 1. The actors HAMT can be traversed and every actor decodes.
+/// 2. Every actor is keyed by an ID address and its state root is present in the blockstore.
+/// 3. Every address in the init actor's address map resolves to an existing actor, every actor's
+///    delegated address resolves to that actor, and no actor has an unallocated ID.
+///
+/// This is useful after importing a snapshot, and when fuzzing. Problems with the state are
+/// reported in the returned report; an error is only returned if the state root itself can't be
+/// loaded or the blockstore fails.
+pub fn verify_state_root<S: Blockstore>(store: &S, root: &Cid) -> Result<StateVerificationReport> {
+    let tree = StateTree::new_from_root(store, root)?;
+    let mut report = StateVerificationReport::default();
+
+    // Delegated addresses, by actor.
+    let mut actors = BTreeMap::new();
+    let mut store_err = None;
+    if let Err(e) = tree.hamt.for_each(|k, actor: &ActorState| {
+        let addr = Address::from_bytes(&k.0)?;
+        report.actors += 1;
+        let id = match addr.id() {
+            Ok(id) => id,
+            Err(_) => {
+                report.issues.push(StateIssue::InvalidKey(addr));
+                return Ok(());
+            }
+        };
+        match store.has(&actor.state) {
+            Ok(true) => {}
+            Ok(false) => report.issues.push(StateIssue::MissingState {
+                id,
+                state: actor.state,
+            }),
+            Err(e) => {
+                store_err = Some(e);
+                return Err(anyhow!("blockstore failure"));
+            }
+        }
+        actors.insert(id, actor.delegated_address);
+        Ok(())
+    }) {
+        if let Some(e) = store_err {
+            return Err(e).context("failed to read actor state").or_fatal();
+        }
+        report.issues.push(StateIssue::Hamt(format!("{e:#}")));
+    }
+
+    let init = match crate::init_actor::State::load(&tree) {
+        Ok((init, _)) => init,
+        Err(e) => {
+            report.issues.push(StateIssue::InitActor(e.to_string()));
+            return Ok(report);
+        }
+    };
+
+    report
+        .issues
+        .extend(actors.keys().filter(|&&id| id >= init.next_id).map(|&id| {
+            StateIssue::UnallocatedId {
+                id,
+                next_id: init.next_id,
+            }
+        }));
+
+    let address_map =
+        match Hamt::<_, ActorID>::load_with_bit_width(&init.address_map, store, HAMT_BIT_WIDTH) {
+            Ok(map) => map,
+            Err(e) => {
+                report.issues.push(StateIssue::AddressMap(e.to_string()));
+                return Ok(report);
+            }
+        };
+    if let Err(e) = address_map.for_each(|k, &id| {
+        let address = Address::from_bytes(&k.0)?;
+        if !actors.contains_key(&id) {
+            report
+                .issues
+                .push(StateIssue::DanglingAddress { address, id });
+        }
+        Ok(())
+    }) {
+        report.issues.push(StateIssue::AddressMap(format!("{e:#}")));
+    }
+
+    for (&id, delegated) in &actors {
+        let Some(address) = delegated else { continue };
+        let resolved = address_map
+            .get(&address.to_bytes())
+            .context("failed to read init actor address map")
+            .or_fatal()?;
+        if resolved != Some(&id) {
+            report.issues.push(StateIssue::UnmappedDelegatedAddress {
+                id,
+                address: *address,
+            });
+        }
+    }
+
+    Ok(report)
+}
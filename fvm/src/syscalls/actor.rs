@@ -1,12 +1,15 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use anyhow::{anyhow, Context as _};
-use fvm_shared::{sys, ActorID};
+use cid::Cid;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::{sys, ActorID, IPLD_RAW};
+use multihash::MultihashDigest;
 
 use super::bind::ControlFlow;
 use super::error::Abort;
 use super::Context;
-use crate::kernel::{CallResult, ClassifyResult, Result};
+use crate::kernel::{CallResult, ClassifyResult, Result, SupportedHashes};
 use crate::{syscall_error, Kernel};
 
 pub fn resolve_address(
@@ -25,17 +28,11 @@ pub fn lookup_delegated_address(
     obuf_off: u32,
     obuf_len: u32,
 ) -> Result<u32> {
-    let obuf = context.memory.try_slice_mut(obuf_off, obuf_len)?;
+    context.memory.check_bounds(obuf_off, obuf_len)?;
     match context.kernel.lookup_delegated_address(actor_id)? {
-        Some(address) => {
-            let address = address.to_bytes();
-            obuf.get_mut(..address.len())
-                .ok_or_else(
-                    || syscall_error!(BufferTooSmall; "address output buffer is too small"),
-                )?
-                .copy_from_slice(&address);
-            Ok(address.len() as u32)
-        }
+        Some(address) => context
+            .memory
+            .write_output(&address.to_bytes(), obuf_off, obuf_len),
         None => Ok(0),
     }
 }
@@ -111,6 +108,46 @@ pub fn create_actor(
     context.kernel.create_actor(typ, actor_id, addr)
 }
 
+/// Creates an actor and invokes its constructor as a single atomic operation. The constructor's
+/// exit code and return value are reported in the returned receipt.
+#[allow(clippy::too_many_arguments)]
+pub fn create_and_invoke<K: Kernel>(
+    context: Context<'_, K>,
+    actor_id: u64, // ID
+    typ_off: u32,  // Cid
+    delegated_addr_off: u32,
+    delegated_addr_len: u32,
+    params_id: u32,
+    value_hi: u64,
+    value_lo: u64,
+) -> Result<sys::out::send::Send> {
+    let typ = context.memory.read_cid(typ_off)?;
+    let addr = (delegated_addr_len > 0)
+        .then(|| {
+            context
+                .memory
+                .read_address(delegated_addr_off, delegated_addr_len)
+        })
+        .transpose()?;
+    let value = TokenAmount::from_atto((value_hi as u128) << 64 | value_lo as u128);
+
+    let CallResult {
+        block_id,
+        block_stat,
+        exit_code,
+        ..
+    } = context
+        .kernel
+        .create_and_invoke::<K>(typ, actor_id, addr, params_id, &value)?;
+
+    Ok(sys::out::send::Send {
+        exit_code: exit_code.value(),
+        return_id: block_id,
+        return_codec: block_stat.codec,
+        return_size: block_stat.size,
+    })
+}
+
 pub fn upgrade_actor<K: Kernel>(
     context: Context<'_, K>,
     new_code_cid_off: u32,
@@ -126,6 +163,7 @@ pub fn upgrade_actor<K: Kernel>(
             block_id,
             block_stat,
             exit_code,
+            ..
         }) => {
             if exit_code.is_success() {
                 ControlFlow::Abort(Abort::Exit(exit_code, String::new(), block_id))
@@ -170,6 +208,23 @@ pub fn install_actor(
     context.kernel.install_actor(typ)
 }
 
+pub fn install_actor_code(
+    context: Context<'_, impl Kernel>,
+    code_id: u32,
+    cid_off: u32,
+    cid_len: u32,
+) -> Result<u32> {
+    // Check the output buffer before installing anything. Installed code is always keyed by a
+    // Blake2b256 IPLD_RAW CID, so we know exactly how much space we need.
+    context.memory.check_bounds(cid_off, cid_len)?;
+    let expected = Cid::new_v1(IPLD_RAW, SupportedHashes::Blake2b256.digest(&[])).encoded_len();
+    if (cid_len as usize) < expected {
+        return Err(syscall_error!(BufferTooSmall; "cid output buffer is too small").into());
+    }
+    let k = context.kernel.install_actor_code(code_id)?;
+    context.memory.write_cid(&k, cid_off, cid_len)
+}
+
 pub fn balance_of(context: Context<'_, impl Kernel>, actor_id: u64) -> Result<sys::TokenAmount> {
     let balance = context.kernel.balance_of(actor_id)?;
     balance
@@ -4,7 +4,7 @@ use std::mem;
 
 use fvm_shared::error::ErrorNumber;
 use fvm_shared::sys::SyscallSafe;
-use wasmtime::{Caller, Linker, WasmTy};
+use wasmtime::{Caller, FuncType, Linker, Val, WasmTy};
 
 use super::context::Memory;
 use super::error::Abort;
@@ -110,11 +110,7 @@ where
     fn into_control_flow(self) -> ControlFlow<Self::Value> {
         match self {
             Ok(value) => ControlFlow::Return(value),
-            Err(e) => match e {
-                ExecutionError::Syscall(err) => ControlFlow::Error(err),
-                ExecutionError::OutOfGas => ControlFlow::Abort(Abort::OutOfGas),
-                ExecutionError::Fatal(err) => ControlFlow::Abort(Abort::Fatal(err)),
-            },
+            Err(e) => e.into(),
         }
     }
 }
@@ -158,8 +154,12 @@ macro_rules! impl_bind_syscalls {
                     self.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>> $(, $t: $t)*| {
                         charge_for_exec(&mut caller)?;
 
+                        #[cfg(feature = "tracing")]
+                        let _span = tracing::trace_span!("syscall", module, name).entered();
+
                         let (mut memory, data) = memory_and_data(&mut caller);
                         charge_syscall_gas!(data.kernel);
+                        data.kernel.record_syscall(module, name);
 
                         let ctx = Context{kernel: &mut data.kernel, memory: &mut memory};
                         let out = syscall(ctx $(, $t)*).into_control_flow();
@@ -188,8 +188,12 @@ macro_rules! impl_bind_syscalls {
                     self.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>>, ret: u32 $(, $t: $t)*| {
                         charge_for_exec(&mut caller)?;
 
+                        #[cfg(feature = "tracing")]
+                        let _span = tracing::trace_span!("syscall", module, name).entered();
+
                         let (mut memory, data) = memory_and_data(&mut caller);
                         charge_syscall_gas!(data.kernel);
+                        data.kernel.record_syscall(module, name);
 
                         // We need to check to make sure we can store the return value _before_ we do anything.
                         if (ret as u64) > (memory.len() as u64)
@@ -239,3 +243,45 @@ impl_bind_syscalls!(A B C D E);
 impl_bind_syscalls!(A B C D E F);
 impl_bind_syscalls!(A B C D E F G);
 impl_bind_syscalls!(A B C D E F G H);
+
+/// The name unsupported syscalls are recorded under by [`bind_unsupported`].
+pub const UNSUPPORTED_SYSCALL: &str = "<unsupported>";
+
+/// Binds a stub for a syscall this FVM doesn't provide, failing with `Forbidden` when called (like
+/// any syscall, after charging for it). See [`NetworkConfig::lazy_syscall_linking`].
+///
+/// Stubs are recorded (in syscall counts and backtraces) under the name [`UNSUPPORTED_SYSCALL`],
+/// as the names of unsupported syscalls are chosen by actors.
+///
+/// [`NetworkConfig::lazy_syscall_linking`]: crate::machine::NetworkConfig::lazy_syscall_linking
+pub fn bind_unsupported<K: Kernel>(
+    linker: &mut Linker<InvocationData<K>>,
+    module: &'static str,
+    name: &str,
+    ty: FuncType,
+) -> anyhow::Result<()> {
+    let syscall = format!("{module}::{name}");
+    linker.func_new(module, name, ty, move |mut caller, _params, results| {
+        charge_for_exec(&mut caller)?;
+
+        let data = caller.data_mut();
+        charge_syscall_gas!(data.kernel);
+        data.kernel.record_syscall(module, UNSUPPORTED_SYSCALL);
+
+        log::trace!("syscall {}: unsupported", syscall);
+        let err = SyscallError::new(
+            ErrorNumber::Forbidden,
+            format_args!("syscall {syscall} not supported by this FVM"),
+        );
+        data.last_error = Some(backtrace::Cause::from_syscall(
+            module,
+            UNSUPPORTED_SYSCALL,
+            err,
+        ));
+        results[0] = Val::I32(ErrorNumber::Forbidden as i32);
+
+        update_gas_available(&mut caller)?;
+        Ok(())
+    })?;
+    Ok(())
+}
@@ -87,19 +87,31 @@ impl Memory {
         .context("failed to parse cid")
     }
 
-    pub fn write_cid(&mut self, k: &Cid, offset: u32, len: u32) -> Result<u32> {
+    /// Writes a variable-length syscall output into the output buffer at `offset`, returning the
+    /// output's length. This implements the convention for variable-length outputs: if the output
+    /// doesn't fit in the buffer, nothing is written and the syscall fails with `BufferTooSmall`
+    /// (and the actor may retry with a larger buffer, see `fvm_sdk::probe_and_fill`).
+    pub fn write_output(&mut self, data: &[u8], offset: u32, len: u32) -> Result<u32> {
         let out = self.try_slice_mut(offset, len)?;
+        out.get_mut(..data.len())
+            .ok_or_else(|| {
+                let needed = data.len();
+                syscall_error!(BufferTooSmall; "output buffer is too small ({len} < {needed} bytes)")
+            })?
+            .copy_from_slice(data);
+        Ok(data.len() as u32)
+    }
+
+    pub fn write_cid(&mut self, k: &Cid, offset: u32, len: u32) -> Result<u32> {
+        // Check the bounds before formatting the CID.
+        self.check_bounds(offset, len)?;
 
         let mut buf = Cursor::new([0u8; MAX_CID_LEN]);
         // At the moment, all CIDs are gauranteed to fit in 100 bytes (statically) because the max
         // digest size is 64, the max varint size is 9, and there are 4 varints plus the digest.
         k.write_bytes(&mut buf).expect("failed to format a cid");
-        let len = buf.position() as usize;
-        if len > out.len() {
-            return Err(syscall_error!(BufferTooSmall; "cid output buffer is too small").into());
-        }
-        out[..len].copy_from_slice(&buf.get_ref()[..len]);
-        Ok(len as u32)
+        let cid_len = buf.position() as usize;
+        self.write_output(&buf.get_ref()[..cid_len], offset, len)
     }
 
     pub fn read_address(&self, offset: u32, len: u32) -> Result<Address> {
@@ -188,9 +200,223 @@ mod test {
         expect_syscall_err!(IllegalArgument, mem.try_slice(u32::MAX, 0));
     }
 
+    #[test]
+    fn test_write_output_boundaries() {
+        let data = [1u8, 2, 3, 4];
+        let mut buf = [0u8; 8];
+        let mem = Memory::new(&mut buf);
+
+        // One byte too small: nothing is written.
+        expect_syscall_err!(BufferTooSmall, mem.write_output(&data, 0, 3));
+        expect_syscall_err!(BufferTooSmall, mem.write_output(&data, 4, 0));
+        assert_eq!(mem.try_slice(0, 8).unwrap(), &[0; 8]);
+
+        // Exact fit, and larger buffers.
+        assert_eq!(mem.write_output(&data, 4, 4).unwrap(), 4);
+        assert_eq!(mem.write_output(&data, 0, 5).unwrap(), 4);
+        assert_eq!(mem.try_slice(0, 8).unwrap(), &[1, 2, 3, 4, 1, 2, 3, 4]);
+
+        // Empty outputs fit anywhere in bounds.
+        assert_eq!(mem.write_output(&[], 8, 0).unwrap(), 0);
+
+        // Out of bounds buffers are rejected even if the output would fit.
+        expect_syscall_err!(IllegalArgument, mem.write_output(&data, 6, 4));
+    }
+
     #[test]
     fn test_read_slice_empty() {
         let mem = Memory::new(&mut []);
         mem.try_slice(0, 0).expect("slice was in bounds");
     }
+
+    /// Property tests for the bounds checking of every memory helper used by syscalls.
+    mod props {
+        use std::fmt::Debug;
+
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        use super::*;
+        use crate::kernel::{ExecutionError, SyscallError};
+
+        /// A memory along with an (offset, length) pair to access it with.
+        #[derive(Clone, Debug)]
+        struct Case {
+            mem: Vec<u8>,
+            offset: u32,
+            len: u32,
+        }
+
+        impl Case {
+            fn in_bounds(&self) -> bool {
+                (self.offset as u64) + (self.len as u64) <= self.mem.len() as u64
+            }
+        }
+
+        /// Mostly picks values around the end of memory, but sometimes picks values that overflow
+        /// when added.
+        fn position(size: u32) -> impl Strategy<Value = u32> {
+            prop_oneof![
+                1 => any::<u32>(),
+                1 => any::<u8>().prop_map(|d| u32::MAX - d as u32),
+                2 => 0..size + 2,
+            ]
+        }
+
+        fn case() -> impl Strategy<Value = Case> {
+            vec(any::<u8>(), 0..256)
+                .prop_flat_map(|mem| {
+                    let size = mem.len() as u32;
+                    (Just(mem), position(size), position(size))
+                })
+                .prop_map(|(mem, offset, len)| Case { mem, offset, len })
+        }
+
+        /// Returns the syscall error's message and number, panicking on any other kind of error.
+        fn into_syscall_error(err: ExecutionError) -> (String, ErrorNumber) {
+            match err {
+                ExecutionError::Syscall(SyscallError(msg, code)) => (msg, code),
+                ExecutionError::Fatal(err) => panic!("got unexpected fatal error: {}", err),
+                ExecutionError::OutOfGas => panic!("got unexpected out of gas"),
+            }
+        }
+
+        /// The error for a buffer that's out of bounds.
+        fn out_of_bounds(offset: u32, len: u32) -> (String, ErrorNumber) {
+            (
+                format!("buffer {} (length {}) out of bounds", offset, len),
+                ErrorNumber::IllegalArgument,
+            )
+        }
+
+        /// Checks that reading a value from the case's buffer fails with a bounds error if it's
+        /// out of bounds, and otherwise decodes the buffer exactly like `decode`. Decode errors
+        /// must be illegal arguments, and never look like bounds errors.
+        fn check_read<T: PartialEq + Debug, E>(
+            case: &Case,
+            res: Result<T>,
+            decode: impl FnOnce(&[u8]) -> std::result::Result<T, E>,
+        ) -> std::result::Result<(), TestCaseError> {
+            let expected = if case.in_bounds() {
+                let start = case.offset as usize;
+                decode(&case.mem[start..start + case.len as usize]).map_err(|_| None)
+            } else {
+                Err(Some(out_of_bounds(case.offset, case.len)))
+            };
+            match (res.map_err(into_syscall_error), expected) {
+                (Ok(v), Ok(expected)) => prop_assert_eq!(v, expected),
+                (Err(err), Err(Some(expected))) => prop_assert_eq!(err, expected),
+                (Err((msg, code)), Err(None)) => {
+                    prop_assert_eq!(code, ErrorNumber::IllegalArgument);
+                    prop_assert!(!msg.contains("out of bounds"), "{}", msg);
+                }
+                (res, expected) => prop_assert!(false, "got {:?}, expected {:?}", res, expected),
+            }
+            Ok(())
+        }
+
+        fn test_cid(digest: &[u8]) -> Cid {
+            let hash = cid::multihash::Multihash::wrap(SHA2_256, digest).unwrap();
+            Cid::new_v1(RAW, hash)
+        }
+
+        proptest! {
+            #[test]
+            fn check_bounds(mut case in case()) {
+                let (offset, len, in_bounds) = (case.offset, case.len, case.in_bounds());
+                let mem = Memory::new(&mut case.mem);
+                match mem.check_bounds(offset, len) {
+                    Ok(()) => prop_assert!(in_bounds),
+                    Err(err) => {
+                        prop_assert_eq!(into_syscall_error(err), out_of_bounds(offset, len))
+                    }
+                }
+            }
+
+            #[test]
+            fn try_slice(mut case in case()) {
+                let (offset, len, in_bounds) = (case.offset, case.len, case.in_bounds());
+                let mem = Memory::new(&mut case.mem);
+                match mem.try_slice(offset, len) {
+                    Ok(slice) => prop_assert!(in_bounds && slice.len() == len as usize),
+                    Err(err) => {
+                        prop_assert_eq!(into_syscall_error(err), out_of_bounds(offset, len))
+                    }
+                }
+            }
+
+            #[test]
+            fn try_slice_mut(mut case in case()) {
+                let (offset, len, in_bounds) = (case.offset, case.len, case.in_bounds());
+                let mem = Memory::new(&mut case.mem);
+                match mem.try_slice_mut(offset, len) {
+                    Ok(slice) => prop_assert!(in_bounds && slice.len() == len as usize),
+                    Err(err) => {
+                        prop_assert_eq!(into_syscall_error(err), out_of_bounds(offset, len))
+                    }
+                }
+            }
+
+            #[test]
+            fn read_address(case in case()) {
+                let mut mem = case.mem.clone();
+                let res = Memory::new(&mut mem).read_address(case.offset, case.len);
+                check_read(&case, res, Address::from_bytes)?;
+            }
+
+            #[test]
+            fn read_cbor(case in case()) {
+                let mut mem = case.mem.clone();
+                let res = Memory::new(&mut mem).read_cbor::<Vec<u64>>(case.offset, case.len);
+                check_read(&case, res, from_slice::<Vec<u64>>)?;
+            }
+
+            #[test]
+            fn read_cid(mut case in case()) {
+                // CIDs are read up to the end of memory, so only the offset matters.
+                let offset = case.offset;
+                let expected = match case.mem.get(offset as usize..) {
+                    Some(bytes) => Cid::read_bytes(bytes).map_err(|_| None),
+                    None => Err(Some((
+                        format!("cid at offset {} is out of bounds", offset),
+                        ErrorNumber::IllegalArgument,
+                    ))),
+                };
+                let res = Memory::new(&mut case.mem).read_cid(offset).map_err(into_syscall_error);
+                match (res, expected) {
+                    (Ok(k), Ok(expected)) => prop_assert_eq!(k, expected),
+                    (Err(err), Err(Some(expected))) => prop_assert_eq!(err, expected),
+                    (Err((msg, code)), Err(None)) => {
+                        prop_assert_eq!(code, ErrorNumber::IllegalArgument);
+                        prop_assert!(!msg.contains("out of bounds"), "{}", msg);
+                    }
+                    (res, expected) => {
+                        prop_assert!(false, "got {:?}, expected {:?}", res, expected)
+                    }
+                }
+            }
+
+            #[test]
+            fn write_cid(mut case in case(), digest in vec(any::<u8>(), 0..=64)) {
+                let k = test_cid(&digest);
+                let k_bytes = k.to_bytes();
+                let (offset, len, in_bounds) = (case.offset, case.len, case.in_bounds());
+                let mem = Memory::new(&mut case.mem);
+                match mem.write_cid(&k, offset, len) {
+                    Ok(written) => {
+                        prop_assert!(in_bounds);
+                        prop_assert_eq!(written as usize, k_bytes.len());
+                        let out = mem.try_slice(offset, written).unwrap();
+                        prop_assert_eq!(out, k_bytes.as_slice());
+                    }
+                    Err(err) => match into_syscall_error(err) {
+                        (_, ErrorNumber::BufferTooSmall) => {
+                            prop_assert!(in_bounds && (len as usize) < k_bytes.len())
+                        }
+                        err => prop_assert_eq!(err, out_of_bounds(offset, len)),
+                    },
+                }
+            }
+        }
+    }
 }
@@ -1,6 +1,10 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use fvm_shared::error::ErrorNumber;
+use fvm_shared::sys::MetricKind;
+
 use crate::kernel::{ClassifyResult, Result};
+use crate::syscall_error;
 use crate::syscalls::context::Context;
 use crate::Kernel;
 
@@ -45,3 +49,23 @@ pub fn store_artifact(
 
     Ok(())
 }
+
+pub fn metric(
+    context: Context<'_, impl Kernel>,
+    name_off: u32,
+    name_len: u32,
+    kind: u32,
+    value: i64,
+) -> Result<()> {
+    // No-op if disabled.
+    if !context.kernel.debug_enabled() {
+        return Ok(());
+    }
+
+    let kind = MetricKind::try_from(kind)
+        .map_err(|kind| syscall_error!(IllegalArgument; "unknown metric kind {}", kind))?;
+    let name = context.memory.try_slice(name_off, name_len)?;
+    let name = std::str::from_utf8(name).or_error(ErrorNumber::IllegalArgument)?;
+
+    context.kernel.record_metric(name, kind, value)
+}
@@ -3,7 +3,7 @@
 //! This module contains code used to convert errors to and from wasmtime traps.
 use anyhow::anyhow;
 use fvm_shared::error::ExitCode;
-use wasmtime::Trap;
+use wasmtime::{Trap, WasmBacktrace};
 
 use crate::call_manager::NO_DATA_BLOCK_ID;
 use crate::kernel::{BlockId, ExecutionError};
@@ -72,9 +72,17 @@ impl From<anyhow::Error> for Abort {
                 // I think this is fatal? But I'm not sure.
                 | Trap::StackOverflow => Abort::Exit(
                     ExitCode::SYS_ILLEGAL_INSTRUCTION,
-                    trap.to_string(),
+                    // Include the Wasm stack trace, if captured (when debugging actors).
+                    match e.downcast_ref::<WasmBacktrace>() {
+                        Some(bt) => format!("{trap}\n{bt}"),
+                        None => trap.to_string(),
+                    },
                     NO_DATA_BLOCK_ID,
                 ),
+                // Raised when the execution deadline passes (see
+                // `NetworkConfig::execution_timeout`). This isn't the actor's fault (as far as
+                // consensus is concerned), so it must not be turned into an exit code.
+                Trap::Interrupt => Abort::Fatal(anyhow!("actor execution timed out")),
                 _ => Abort::Fatal(anyhow!("unexpected wasmtime trap: {}", trap)),
             };
         };
@@ -85,3 +93,90 @@ impl From<anyhow::Error> for Abort {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+    use fvm_shared::error::ExitCode;
+    use wasmtime::Trap;
+
+    use super::Abort;
+    use crate::kernel::{self, ExecutionError};
+    use crate::syscall_error;
+    use crate::syscalls::bind::{ControlFlow, IntoControlFlow};
+
+    #[test]
+    fn execution_errors_to_aborts() {
+        let syscall = || ExecutionError::from(syscall_error!(NotFound; "missing"));
+
+        assert!(matches!(
+            Abort::from_error(ExitCode::USR_ILLEGAL_ARGUMENT, syscall()),
+            Abort::Exit(ExitCode::USR_ILLEGAL_ARGUMENT, _, 0)
+        ));
+        assert!(matches!(
+            Abort::from_error(ExitCode::USR_ILLEGAL_ARGUMENT, ExecutionError::OutOfGas),
+            Abort::OutOfGas
+        ));
+        assert!(matches!(
+            Abort::from_error(
+                ExitCode::USR_ILLEGAL_ARGUMENT,
+                ExecutionError::Fatal(anyhow!("broken"))
+            ),
+            Abort::Fatal(_)
+        ));
+
+        // Syscall errors are escalated when they're not expected.
+        assert!(matches!(
+            Abort::from_error_as_fatal(syscall()),
+            Abort::Fatal(_)
+        ));
+        assert!(matches!(
+            Abort::from_error_as_fatal(ExecutionError::OutOfGas),
+            Abort::OutOfGas
+        ));
+        assert!(matches!(
+            Abort::from_error_as_fatal(ExecutionError::Fatal(anyhow!("broken"))),
+            Abort::Fatal(_)
+        ));
+    }
+
+    #[test]
+    fn syscall_results_to_control_flow() {
+        let flow = |r: kernel::Result<u32>| r.into_control_flow();
+
+        assert!(matches!(flow(Ok(1)), ControlFlow::Return(1)));
+        assert!(matches!(
+            flow(Err(syscall_error!(NotFound; "missing").into())),
+            ControlFlow::Error(_)
+        ));
+        assert!(matches!(
+            flow(Err(ExecutionError::OutOfGas)),
+            ControlFlow::Abort(Abort::OutOfGas)
+        ));
+        assert!(matches!(
+            flow(Err(ExecutionError::Fatal(anyhow!("broken")))),
+            ControlFlow::Abort(Abort::Fatal(_))
+        ));
+    }
+
+    #[test]
+    fn traps_to_aborts() {
+        assert!(matches!(
+            Abort::from(anyhow::Error::from(Trap::UnreachableCodeReached)),
+            Abort::Exit(ExitCode::SYS_ILLEGAL_INSTRUCTION, _, _)
+        ));
+        assert!(matches!(
+            Abort::from(anyhow::Error::from(Trap::Interrupt)),
+            Abort::Fatal(_)
+        ));
+        // Aborts raised by syscalls round-trip through wasmtime's errors.
+        assert!(matches!(
+            Abort::from(anyhow::Error::from(Abort::OutOfGas)),
+            Abort::OutOfGas
+        ));
+        assert!(matches!(
+            Abort::from(anyhow!("something else")),
+            Abort::Fatal(_)
+        ));
+    }
+}
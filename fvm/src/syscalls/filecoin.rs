@@ -178,3 +178,41 @@ pub fn batch_verify_seals(
     }
     Ok(())
 }
+
+/// Verify a batch of window PoSts encoded as a CBOR array of `WindowPoStVerifyInfo`.
+///
+/// When successful, this method will write a single byte back into the array at `result_off` for
+/// each result: 0 for failed, 1 for success.
+pub fn batch_verify_posts(
+    context: Context<'_, impl FilecoinKernel>,
+    batch_off: u32,
+    batch_len: u32,
+    result_off: u32,
+) -> Result<()> {
+    // Check and decode params.
+    let batch = context
+        .memory
+        .read_cbor::<Vec<WindowPoStVerifyInfo>>(batch_off, batch_len)?;
+    let output = context
+        .memory
+        .try_slice_mut(result_off, batch.len() as u32)?;
+
+    // Execute.
+    let result = context.kernel.batch_verify_posts(&batch)?;
+
+    // Sanity check that we got the correct number of results.
+    if result.len() != batch.len() {
+        return Err(anyhow!(
+            "expected one result per input: {} != {}",
+            batch.len(),
+            result.len()
+        ))
+        .or_fatal();
+    }
+
+    // Return.
+    for (out, ok) in output.iter_mut().zip(result) {
+        *out = ok as u8;
+    }
+    Ok(())
+}
@@ -2,10 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::str;
 
+use fvm_shared::sys::PricedOperation;
+
 use super::Context;
 use crate::gas::Gas;
 use crate::kernel::{ClassifyResult, Result};
-use crate::Kernel;
+use crate::{syscall_error, Kernel};
 
 pub fn charge_gas(
     context: Context<'_, impl Kernel>,
@@ -25,3 +27,12 @@ pub fn charge_gas(
 pub fn available(context: Context<'_, impl Kernel>) -> Result<u64> {
     Ok(context.kernel.gas_available().round_down())
 }
+
+/// Returns the gas an operation of the given size would currently cost, rounded up to whole gas
+/// units. See [`PricedOperation`] for the supported operations.
+pub fn price(context: Context<'_, impl Kernel>, op: u32, size: u64, param: u64) -> Result<u64> {
+    let op = PricedOperation::try_from(op)
+        .map_err(|op| syscall_error!(IllegalArgument; "unknown priced operation {}", op))?;
+    let size = usize::try_from(size).or_illegal_argument()?;
+    Ok(context.kernel.price_of(op, size, param)?.round_up())
+}
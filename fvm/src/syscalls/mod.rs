@@ -1,6 +1,7 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use anyhow::{anyhow, Context as _};
+use fvm_shared::sys::Capabilities;
 use num_traits::Zero;
 use wasmtime::{AsContextMut, ExternType, Global, Linker, Memory, Module, Val};
 
@@ -32,6 +33,11 @@ mod vm;
 pub use context::Context;
 pub use error::Abort;
 
+/// The modules syscalls are imported from.
+pub const SYSCALL_MODULES: &[&str] = &[
+    "actor", "crypto", "debug", "event", "gas", "ipld", "network", "rand", "self", "send", "vm",
+];
+
 /// Invocation data attached to a wasm "store" and available to the syscall binding.
 pub struct InvocationData<K> {
     /// The kernel on which this actor is being executed.
@@ -236,6 +242,15 @@ fn min_table_elements(module: &Module) -> Option<u32> {
 
 use self::bind::BindSyscall;
 
+/// Returns the optional capabilities of this FVM build, reported to actors by the
+/// `vm::capabilities` syscall.
+pub fn capabilities() -> Capabilities {
+    let mut caps = Capabilities::empty();
+    caps.set(Capabilities::UPGRADE_ACTOR, cfg!(feature = "upgrade-actor"));
+    caps.set(Capabilities::INSTALL_ACTOR, cfg!(feature = "m2-native"));
+    caps
+}
+
 impl<K> SyscallHandler<K> for DefaultKernel<K::CallManager>
 where
     K: Kernel,
@@ -246,6 +261,11 @@ where
     ) -> anyhow::Result<()> {
         linker.bind("vm", "exit", vm::exit)?;
         linker.bind("vm", "message_context", vm::message_context)?;
+        linker.bind("vm", "call_depth", vm::call_depth)?;
+        linker.bind("vm", "call_stack", vm::call_stack)?;
+        linker.bind("vm", "set_reentrancy", vm::set_reentrancy)?;
+        linker.bind("vm", "abi_version", vm::abi_version)?;
+        linker.bind("vm", "capabilities", vm::capabilities)?;
 
         linker.bind(
             "network",
@@ -254,6 +274,8 @@ where
         )?;
         linker.bind("network", "context", network::context)?;
         linker.bind("network", "tipset_cid", network::tipset_cid)?;
+        linker.bind("network", "fingerprint", network::fingerprint)?;
+        linker.bind("network", "epoch_timing", network::epoch_timing)?;
 
         linker.bind("ipld", "block_open", ipld::block_open)?;
         linker.bind("ipld", "block_create", ipld::block_create)?;
@@ -275,6 +297,9 @@ where
         linker.bind("actor", "get_actor_code_cid", actor::get_actor_code_cid)?;
         linker.bind("actor", "next_actor_address", actor::next_actor_address)?;
         linker.bind("actor", "create_actor", actor::create_actor)?;
+        if self.call_manager.context().actor_factories {
+            linker.bind("actor", "create_and_invoke", actor::create_and_invoke)?;
+        }
         if cfg!(feature = "upgrade-actor") {
             // We disable/enable with the feature, but we always compile this code to ensure we don't
             // accidentally break it.
@@ -295,6 +320,7 @@ where
         // Only wire this syscall when M2 native is enabled.
         if cfg!(feature = "m2-native") {
             linker.bind("actor", "install_actor", actor::install_actor)?;
+            linker.bind("actor", "install_actor_code", actor::install_actor_code)?;
         }
 
         linker.bind("crypto", "verify_signature", crypto::verify_signature)?;
@@ -309,16 +335,24 @@ where
 
         linker.bind("rand", "get_chain_randomness", rand::get_chain_randomness)?;
         linker.bind("rand", "get_beacon_randomness", rand::get_beacon_randomness)?;
+        linker.bind(
+            "rand",
+            "get_beacon_randomness_bytes",
+            rand::get_beacon_randomness_bytes,
+        )?;
 
         linker.bind("gas", "charge", gas::charge_gas)?;
         linker.bind("gas", "available", gas::available)?;
+        linker.bind("gas", "price", gas::price)?;
 
         // Ok, this singled-out syscall should probably be in another category.
         linker.bind("send", "send", send::send)?;
+        linker.bind("send", "send_metered", send::send_metered)?;
 
         linker.bind("debug", "log", debug::log)?;
         linker.bind("debug", "enabled", debug::enabled)?;
         linker.bind("debug", "store_artifact", debug::store_artifact)?;
+        linker.bind("debug", "metric", debug::metric)?;
 
         Ok(())
     }
@@ -358,6 +392,7 @@ where
             filecoin::verify_replica_update,
         )?;
         linker.bind("crypto", "batch_verify_seals", filecoin::batch_verify_seals)?;
+        linker.bind("crypto", "batch_verify_posts", filecoin::batch_verify_posts)?;
 
         Ok(())
     }
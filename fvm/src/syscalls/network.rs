@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use anyhow::Context as _;
 use fvm_shared::sys;
-use fvm_shared::sys::out::network::NetworkContext;
+use fvm_shared::sys::out::network::{EpochTiming, NetworkContext};
 
 use super::Context;
 use crate::kernel::{ClassifyResult, Kernel, Result};
@@ -21,6 +21,14 @@ pub fn context(context: Context<'_, impl Kernel>) -> crate::kernel::Result<Netwo
     context.kernel.network_context()
 }
 
+pub fn fingerprint(context: Context<'_, impl Kernel>) -> Result<[u8; 32]> {
+    context.kernel.machine_fingerprint()
+}
+
+pub fn epoch_timing(context: Context<'_, impl Kernel>) -> Result<EpochTiming> {
+    context.kernel.epoch_timing()
+}
+
 pub fn tipset_cid(
     context: Context<'_, impl Kernel>,
     epoch: i64,
@@ -27,3 +27,22 @@ pub fn get_beacon_randomness(
 ) -> Result<[u8; RANDOMNESS_LENGTH]> {
     context.kernel.get_randomness_from_beacon(round)
 }
+
+/// Gets randomness from the beacon system, at the length used by the current network version
+/// (at most `MAX_RANDOMNESS_LENGTH` bytes), writing it into the supplied output buffer.
+/// Returns the number of bytes written, or fails with `BufferTooSmall` if the buffer can't hold
+/// the randomness.
+pub fn get_beacon_randomness_bytes(
+    context: Context<'_, impl Kernel>,
+    round: i64, // ChainEpoch
+    obuf_off: u32,
+    obuf_len: u32,
+) -> Result<u32> {
+    // Check the output bounds first so we don't do any work if they're incorrect.
+    context.memory.check_bounds(obuf_off, obuf_len)?;
+
+    let randomness = context.kernel.get_randomness_from_beacon_bytes(round)?;
+    context
+        .memory
+        .write_output(&randomness.0, obuf_off, obuf_len)
+}
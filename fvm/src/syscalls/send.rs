@@ -24,6 +24,33 @@ pub fn send<K: Kernel>(
     gas_limit: u64,
     flags: u64,
 ) -> Result<sys::out::send::Send> {
+    send_metered(
+        context,
+        recipient_off,
+        recipient_len,
+        method,
+        params_id,
+        value_hi,
+        value_lo,
+        gas_limit,
+        flags,
+    )
+    .map(|res| res.send)
+}
+
+/// Like [`send`], but also returns the gas consumed by the call.
+#[allow(clippy::too_many_arguments)]
+pub fn send_metered<K: Kernel>(
+    context: Context<'_, K>,
+    recipient_off: u32,
+    recipient_len: u32,
+    method: u64,
+    params_id: u32,
+    value_hi: u64,
+    value_lo: u64,
+    gas_limit: u64,
+    flags: u64,
+) -> Result<sys::out::send::SendResult> {
     let recipient: Address = context.memory.read_address(recipient_off, recipient_len)?;
     let value = TokenAmount::from_atto((value_hi as u128) << 64 | value_lo as u128);
 
@@ -41,14 +68,18 @@ pub fn send<K: Kernel>(
         block_id,
         block_stat,
         exit_code,
+        gas_used,
     } = context
         .kernel
         .send::<K>(&recipient, method, params_id, &value, gas_limit, flags)?;
 
-    Ok(sys::out::send::Send {
-        exit_code: exit_code.value(),
-        return_id: block_id,
-        return_codec: block_stat.codec,
-        return_size: block_stat.size,
+    Ok(sys::out::send::SendResult {
+        send: sys::out::send::Send {
+            exit_code: exit_code.value(),
+            return_id: block_id,
+            return_codec: block_stat.codec,
+            return_size: block_stat.size,
+        },
+        gas_used: gas_used.round_up(),
     })
 }
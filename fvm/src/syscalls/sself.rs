@@ -8,8 +8,8 @@ use crate::kernel::{ClassifyResult, Kernel, Result};
 
 /// Returns the root CID of the actor's state by writing it in the specified buffer.
 ///
-/// The returned u32 represents the _actual_ length of the CID. If the supplied
-/// buffer is smaller, no value will have been written. The caller must retry
+/// The returned u32 represents the length of the CID. If the supplied buffer is too small, no
+/// value will have been written and the syscall fails with `BufferTooSmall`. The caller must retry
 /// with a larger buffer.
 pub fn root(context: Context<'_, impl Kernel>, obuf_off: u32, obuf_len: u32) -> Result<u32> {
     context.memory.check_bounds(obuf_off, obuf_len)?;
@@ -2,10 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use fvm_shared::error::ExitCode;
 use fvm_shared::sys::out::vm::MessageContext;
+use fvm_shared::sys::{ReentrancyPolicy, SYSCALL_ABI_VERSION};
 
 use super::error::Abort;
 use super::Context;
 use crate::kernel::Kernel;
+use crate::syscall_error;
 
 /// The maximum message length included in the backtrace. Given 1024 levels, this gives us a total
 /// maximum of around 1MiB for debugging.
@@ -55,3 +57,42 @@ pub fn exit(
 pub fn message_context(context: Context<'_, impl Kernel>) -> crate::kernel::Result<MessageContext> {
     context.kernel.msg_context()
 }
+
+pub fn abi_version(_context: Context<'_, impl Kernel>) -> crate::kernel::Result<u32> {
+    Ok(SYSCALL_ABI_VERSION)
+}
+
+pub fn capabilities(_context: Context<'_, impl Kernel>) -> crate::kernel::Result<u64> {
+    Ok(super::capabilities().bits())
+}
+
+pub fn call_depth(context: Context<'_, impl Kernel>) -> crate::kernel::Result<u32> {
+    context.kernel.call_depth()
+}
+
+/// Writes the IDs of the actors on the call stack (as little-endian `u64`s) into the output buffer,
+/// starting with the receiver of the top-level message and ending with the calling actor. Returns
+/// the number of bytes written, or fails with `BufferTooSmall` if the buffer can't hold them.
+pub fn call_stack(
+    context: Context<'_, impl Kernel>,
+    obuf_off: u32,
+    obuf_len: u32,
+) -> crate::kernel::Result<u32> {
+    // Check the output bounds first so we don't do any work if they're incorrect.
+    context.memory.check_bounds(obuf_off, obuf_len)?;
+
+    let stack: Vec<u8> = context
+        .kernel
+        .call_stack()?
+        .into_iter()
+        .flat_map(u64::to_le_bytes)
+        .collect();
+    context.memory.write_output(&stack, obuf_off, obuf_len)
+}
+
+pub fn set_reentrancy(context: Context<'_, impl Kernel>, policy: u32) -> crate::kernel::Result<()> {
+    let policy = ReentrancyPolicy::try_from(policy).map_err(
+        |policy| syscall_error!(IllegalArgument; "unknown reentrancy policy {}", policy),
+    )?;
+    context.kernel.set_reentrancy(policy)
+}
@@ -6,10 +6,12 @@ mod ipld {
 
     use cid::Cid;
     use fvm::kernel::{IpldBlockOps, SupportedHashes};
-    use fvm::machine::Machine;
+    use fvm::machine::{CodecRegistry, Machine};
     use fvm_ipld_blockstore::Blockstore;
-    use fvm_ipld_encoding::{DAG_CBOR, IPLD_RAW};
-    use multihash::MultihashDigest;
+    use fvm_ipld_encoding::{CBOR, DAG_CBOR, IPLD_RAW};
+    use fvm_shared::version::NetworkVersion;
+    use fvm_shared::IDENTITY_HASH;
+    use multihash::{Multihash, MultihashDigest};
     use pretty_assertions::{assert_eq, assert_ne};
 
     use super::*;
@@ -125,7 +127,26 @@ mod ipld {
 
         expect_syscall_err!(Serialization, kern.block_create(DAG_CBOR, &[]));
 
-        // spec audit things arent (yet) tested
+        let too_large = vec![0u8; kern.machine().context().max_block_size + 1];
+        expect_syscall_err!(LimitExceeded, kern.block_create(IPLD_RAW, &too_large));
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_too_many_links() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+        kern.call_manager.machine.ctx.max_block_links = 2;
+
+        let id = kern.block_create(IPLD_RAW, b"foo")?;
+        let cid = kern.block_link(id, Code::Blake2b256.into(), 32)?;
+
+        kern.block_create(DAG_CBOR, &fvm_ipld_encoding::to_vec(&[cid; 2])?)?;
+        expect_syscall_err!(
+            LimitExceeded,
+            kern.block_create(DAG_CBOR, &fvm_ipld_encoding::to_vec(&[cid; 3])?)
+        );
+
         Ok(())
     }
 
@@ -236,6 +257,112 @@ mod ipld {
         Ok(())
     }
 
+    #[test]
+    fn link_registered_multihashes() -> anyhow::Result<()> {
+        let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+        call_manager
+            .machine
+            .ctx
+            .codecs
+            .allow_multihash(Code::Sha2_256.into(), 32);
+        let mut kern = TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            0,
+            0,
+            Zero::zero(),
+            false,
+        );
+
+        let block = "foo".as_bytes();
+        let id = kern.block_create(IPLD_RAW, block)?;
+
+        // Supported by the FVM, but not registered.
+        expect_syscall_err!(IllegalCid, kern.block_link(id, Code::Keccak256.into(), 32));
+        // Registered, but not with this length.
+        expect_syscall_err!(IllegalCid, kern.block_link(id, Code::Sha2_256.into(), 20));
+
+        let cid = kern.block_link(id, Code::Sha2_256.into(), 32)?;
+        assert_eq!(cid, Cid::new_v1(IPLD_RAW, Code::Sha2_256.digest(block)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_registered_codecs() -> anyhow::Result<()> {
+        let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+        call_manager.machine.ctx.codecs = CodecRegistry::for_network_version(NetworkVersion::V17);
+        let mut kern = TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            0,
+            0,
+            Zero::zero(),
+            false,
+        );
+
+        let block = fvm_ipld_encoding::to_vec(&"foo")?;
+        expect_syscall_err!(IllegalCodec, kern.block_create(CBOR, &block));
+        kern.block_create(DAG_CBOR, &block)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn link_inline() -> anyhow::Result<()> {
+        let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+        call_manager.machine.ctx.inline_cid_limits.linkable = true;
+        let mut kern = TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            0,
+            0,
+            Zero::zero(),
+            false,
+        );
+
+        let block = "foo".as_bytes();
+        let id = kern.block_create(IPLD_RAW, block)?;
+
+        // The hash length must match the block.
+        expect_syscall_err!(IllegalCid, kern.block_link(id, IDENTITY_HASH, 2));
+
+        let cid = kern.block_link(id, IDENTITY_HASH, block.len() as u32)?;
+        assert_eq!(
+            cid,
+            Cid::new_v1(IPLD_RAW, Multihash::wrap(IDENTITY_HASH, block)?)
+        );
+
+        // Inline blocks can be opened without being reachable or stored.
+        let other = Cid::new_v1(IPLD_RAW, Multihash::wrap(IDENTITY_HASH, b"bar")?);
+        let (other_id, stat) = kern.block_open(&other)?;
+        assert_eq!(stat.size, 3);
+        let mut buf = [0u8; 3];
+        assert_eq!(kern.block_read(other_id, 0, &mut buf)?, 0);
+        assert_eq!(&buf, b"bar");
+
+        let (call_manager, _) = kern.into_inner();
+        assert!(!call_manager.machine.blockstore().has(&cid)?);
+
+        // Large blocks can't be inlined.
+        let mut kern = TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            0,
+            0,
+            Zero::zero(),
+            false,
+        );
+        let id = kern.block_create(IPLD_RAW, &[0; 65])?;
+        expect_syscall_err!(LimitExceeded, kern.block_link(id, IDENTITY_HASH, 65));
+
+        Ok(())
+    }
+
     #[test]
     fn read() -> anyhow::Result<()> {
         let (mut kern, _) = build_inspecting_test()?;
@@ -433,6 +560,106 @@ mod ipld {
     }
 }
 
+mod actor {
+    use cid::Cid;
+    use fvm::gas::price_list_by_network_version;
+    use fvm::kernel::{ActorOps, GasOps};
+    use fvm::machine::Manifest;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn builtin_actor_types() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+
+        // Builtin actor types are numbered from 1, in manifest order.
+        for (i, (_, code)) in Manifest::DUMMY_CODES.iter().enumerate() {
+            let typ = i as u32 + 1;
+            assert_eq!(kern.get_builtin_actor_type(code)?, typ);
+            assert_eq!(kern.get_code_cid_for_type(typ)?, *code);
+        }
+
+        // Other code isn't a builtin actor, and other types have no code.
+        assert_eq!(kern.get_builtin_actor_type(&Cid::default())?, 0);
+        expect_syscall_err!(IllegalArgument, kern.get_code_cid_for_type(0));
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.get_code_cid_for_type(Manifest::DUMMY_CODES.len() as u32 + 1)
+        );
+
+        // Every lookup is charged, including failed ones.
+        let lookups = 2 * Manifest::DUMMY_CODES.len() as u64 + 3;
+        let price = price_list_by_network_version(STUB_NETWORK_VER).on_get_builtin_actor_type();
+        assert_eq!(kern.gas_used(), price.total() * lookups);
+
+        Ok(())
+    }
+}
+
+mod message {
+    use fvm::gas::price_list_by_network_version;
+    use fvm::kernel::{GasOps, MessageOps};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn call_stack() -> anyhow::Result<()> {
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.call_stack = vec![(1000, "invoke"), (1001, "invoke"), (1002, "upgrade")];
+        let kern = TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            1001,
+            1002,
+            0,
+            Zero::zero(),
+            false,
+        );
+
+        assert_eq!(kern.call_stack()?, vec![1000, 1001, 1002]);
+        let price = price_list_by_network_version(STUB_NETWORK_VER).on_call_stack(3);
+        assert_eq!(kern.gas_used(), price.total());
+
+        Ok(())
+    }
+}
+
+mod send {
+    use fvm::call_manager::NO_DATA_BLOCK_ID;
+    use fvm::gas::Gas;
+    use fvm::kernel::GasOps;
+    use fvm_shared::address::Address;
+    use fvm_shared::error::ExitCode;
+    use fvm_shared::sys::SendFlags;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn reports_gas_used() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+        // Gas charged before the send isn't attributed to it.
+        let _ = kern.charge_gas("before", Gas::new(123))?;
+
+        let res = kern.send::<TestingKernel>(
+            &Address::new_id(1001),
+            2,
+            NO_DATA_BLOCK_ID,
+            &Zero::zero(),
+            None,
+            SendFlags::empty(),
+        )?;
+        assert_eq!(res.exit_code, ExitCode::OK);
+        assert_eq!(res.block_id, NO_DATA_BLOCK_ID);
+        assert_eq!(res.gas_used, DUMMY_CALL_GAS);
+        assert_eq!(kern.gas_used(), Gas::new(123) + DUMMY_CALL_GAS);
+
+        Ok(())
+    }
+}
+
 mod gas {
     use fvm::gas::*;
     use fvm::kernel::GasOps;
@@ -526,4 +753,659 @@ mod gas {
 
         Ok(())
     }
+
+    #[test]
+    fn price_of() -> anyhow::Result<()> {
+        use fvm::kernel::SupportedHashes;
+        use fvm_shared::crypto::signature::SignatureType;
+        use fvm_shared::sys::PricedOperation;
+
+        let (kern, _) = build_inspecting_test()?;
+        let prices = price_list_by_network_version(STUB_NETWORK_VER);
+
+        assert_eq!(
+            kern.price_of(PricedOperation::BlockCreate, 100, 0)?,
+            prices.on_block_create(100, 0).total()
+        );
+        assert_eq!(
+            kern.price_of(PricedOperation::BlockRead, 100, 0)?,
+            prices.on_block_read(100).total()
+        );
+        assert_eq!(
+            kern.price_of(PricedOperation::Hash, 100, 0xb220)?,
+            prices.on_hashing(SupportedHashes::Blake2b256, 100).total()
+        );
+        assert_eq!(
+            kern.price_of(
+                PricedOperation::VerifySignature,
+                100,
+                SignatureType::BLS as u64
+            )?,
+            prices.on_verify_signature(SignatureType::BLS, 100).total()
+        );
+        assert_eq!(
+            kern.price_of(PricedOperation::ValueTransfer, 100, 0)?,
+            prices.on_value_transfer().total()
+        );
+
+        // Unknown hash functions and signature types are rejected.
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.price_of(PricedOperation::Hash, 100, 0xdead)
+        );
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.price_of(PricedOperation::VerifySignature, 100, 1 << 32)
+        );
+
+        // Querying prices is free.
+        assert_eq!(kern.gas_used(), Gas::new(0));
+
+        Ok(())
+    }
+}
+
+mod event {
+    use fvm::kernel::EventOps;
+    use fvm::machine::EventLimits;
+    use fvm_ipld_encoding::{DAG_CBOR, IPLD_RAW};
+    use fvm_shared::event::Flags;
+    use fvm_shared::sys::EventEntry;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// Emits an event with the given (flags, key, value) entries.
+    fn emit(kern: &mut TestingKernel, entries: &[(Flags, &str, &str)]) -> fvm::kernel::Result<()> {
+        let headers: Vec<_> = entries
+            .iter()
+            .map(|&(flags, key, value)| EventEntry {
+                flags,
+                codec: IPLD_RAW,
+                key_len: key.len() as u32,
+                val_len: value.len() as u32,
+            })
+            .collect();
+        let keys: Vec<u8> = entries.iter().flat_map(|e| e.1.bytes()).collect();
+        let values: Vec<u8> = entries.iter().flat_map(|e| e.2.bytes()).collect();
+        kern.emit_event(&headers, &keys, &values)
+    }
+
+    #[test]
+    fn max_message_payload() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+        kern.call_manager
+            .machine
+            .ctx
+            .network
+            .event_limits
+            .max_message_payload = 16;
+
+        let value = "1234567";
+        emit(&mut kern, &[(Flags::FLAG_INDEXED_ALL, "a", value)])?;
+        emit(&mut kern, &[(Flags::FLAG_INDEXED_ALL, "b", value)])?;
+        assert_eq!(kern.call_manager.events.len(), 2);
+
+        // Both events fill the payload cap, so any further payload exceeds it.
+        expect_syscall_err!(
+            LimitExceeded,
+            emit(&mut kern, &[(Flags::FLAG_INDEXED_ALL, "c", "")])
+        );
+        assert_eq!(kern.call_manager.events.len(), 2);
+
+        Ok(())
+    }
+
+    /// Builds a kernel with the given event limits.
+    fn with_limits(f: impl FnOnce(&mut EventLimits)) -> anyhow::Result<TestingKernel> {
+        let (mut kern, _) = build_inspecting_test()?;
+        f(&mut kern.call_manager.machine.ctx.network.event_limits);
+        Ok(kern)
+    }
+
+    #[test]
+    fn max_entries() -> anyhow::Result<()> {
+        let mut kern = with_limits(|l| l.max_entries = 2)?;
+        let entry = (Flags::empty(), "k", "v");
+        emit(&mut kern, &[entry, entry])?;
+        expect_syscall_err!(LimitExceeded, emit(&mut kern, &[entry, entry, entry]));
+        Ok(())
+    }
+
+    #[test]
+    fn max_indexed_entries() -> anyhow::Result<()> {
+        let mut kern = with_limits(|l| l.max_indexed_entries = 1)?;
+        let indexed = (Flags::FLAG_INDEXED_KEY, "k", "v");
+        let unindexed = (Flags::empty(), "k", "v");
+        emit(&mut kern, &[indexed, unindexed, unindexed])?;
+        expect_syscall_err!(LimitExceeded, emit(&mut kern, &[indexed, indexed]));
+        Ok(())
+    }
+
+    #[test]
+    fn max_key_len() -> anyhow::Result<()> {
+        let mut kern = with_limits(|l| l.max_key_len = 3)?;
+        emit(&mut kern, &[(Flags::empty(), "abc", "v")])?;
+        expect_syscall_err!(
+            LimitExceeded,
+            emit(&mut kern, &[(Flags::empty(), "abcd", "v")])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn max_total_values_len() -> anyhow::Result<()> {
+        let mut kern = with_limits(|l| l.max_total_values_len = 4)?;
+        emit(
+            &mut kern,
+            &[(Flags::empty(), "a", "12"), (Flags::empty(), "b", "34")],
+        )?;
+        expect_syscall_err!(
+            LimitExceeded,
+            emit(
+                &mut kern,
+                &[(Flags::empty(), "a", "12"), (Flags::empty(), "b", "345")]
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn codecs() -> anyhow::Result<()> {
+        let mut kern = with_limits(|l| l.codecs = vec![DAG_CBOR])?;
+        // `emit` uses IPLD_RAW, which is no longer allowed.
+        expect_syscall_err!(IllegalCodec, emit(&mut kern, &[(Flags::empty(), "k", "v")]));
+        assert!(kern.call_manager.events.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn flags() -> anyhow::Result<()> {
+        let mut kern = with_limits(|l| l.flags = Flags::FLAG_INDEXED_KEY)?;
+        emit(&mut kern, &[(Flags::FLAG_INDEXED_KEY, "k", "v")])?;
+        expect_syscall_err!(
+            IllegalArgument,
+            emit(&mut kern, &[(Flags::FLAG_INDEXED_VALUE, "k", "v")])
+        );
+        assert_eq!(kern.call_manager.events.len(), 1);
+        Ok(())
+    }
+}
+
+mod network {
+    use fvm::gas::price_list_by_network_version;
+    use fvm::kernel::{GasOps, NetworkOps};
+    use fvm_shared::sys::out::network::EpochTiming;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn epoch_timing() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+        assert_eq!(
+            kern.epoch_timing()?,
+            EpochTiming {
+                epoch_duration_seconds: 30,
+                blocks_per_epoch: 5,
+            }
+        );
+        let price = price_list_by_network_version(STUB_NETWORK_VER).on_network_context();
+        assert_eq!(kern.gas_used(), price.total());
+
+        // Networks with other block times report their own.
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.machine.ctx.network.epoch_timing(4, 1);
+        let kern = TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            0,
+            0,
+            Zero::zero(),
+            false,
+        );
+        assert_eq!(
+            kern.epoch_timing()?,
+            EpochTiming {
+                epoch_duration_seconds: 4,
+                blocks_per_epoch: 1,
+            }
+        );
+
+        Ok(())
+    }
+}
+
+mod rand {
+    use fvm::gas::price_list_by_network_version;
+    use fvm::kernel::{GasOps, RandomnessOps};
+    use fvm_shared::randomness::Randomness;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// Builds a kernel at epoch 10, whose beacon returns `randomness` for every round.
+    fn build_rand_kernel(randomness: Vec<u8>) -> TestingKernel {
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.machine.ctx.epoch = 10;
+        call_manager.machine.externs.beacon_randomness = Some(Randomness(randomness));
+        TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            0,
+            0,
+            Zero::zero(),
+            false,
+        )
+    }
+
+    #[test]
+    fn beacon_randomness_bytes() -> anyhow::Result<()> {
+        let kern = build_rand_kernel(vec![7; 32]);
+        assert_eq!(
+            kern.get_randomness_from_beacon_bytes(4)?,
+            Randomness(vec![7; 32])
+        );
+        let price = price_list_by_network_version(STUB_NETWORK_VER).on_get_randomness(6);
+        assert_eq!(kern.gas_used(), price.total());
+
+        // Randomness can't be drawn from the future.
+        expect_syscall_err!(IllegalArgument, kern.get_randomness_from_beacon_bytes(11));
+
+        Ok(())
+    }
+
+    #[test]
+    fn beacon_randomness_bytes_length_mismatch() -> anyhow::Result<()> {
+        // The length is fixed by the network version, so a node returning anything else is broken.
+        let kern = build_rand_kernel(vec![7; 48]);
+        let err = kern
+            .get_randomness_from_beacon_bytes(4)
+            .expect_err("expected a length mismatch to abort");
+        assert!(err.is_fatal(), "expected a fatal error, got {}", err);
+
+        Ok(())
+    }
+}
+
+mod debug {
+    use std::sync::{Arc, Mutex};
+
+    use fvm::kernel::DebugOps;
+    use fvm::machine::MetricSink;
+    use fvm_shared::sys::MetricKind;
+    use fvm_shared::ActorID;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    type Recorded = Arc<Mutex<Vec<(ActorID, String, MetricKind, i64)>>>;
+
+    /// A metric sink recording every metric it receives.
+    #[derive(Clone, Default)]
+    struct RecordingSink(Recorded);
+
+    impl MetricSink for RecordingSink {
+        fn record_metric(&self, actor: ActorID, name: &str, kind: MetricKind, value: i64) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((actor, name.to_owned(), kind, value));
+        }
+    }
+
+    #[test]
+    fn record_metric() -> anyhow::Result<()> {
+        let sink = RecordingSink::default();
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.machine.metric_sink = Some(Box::new(sink.clone()));
+        let kern = TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            1000,
+            0,
+            Zero::zero(),
+            false,
+        );
+
+        kern.record_metric("calls", MetricKind::Counter, 1)?;
+        kern.record_metric("queue", MetricKind::Gauge, -3)?;
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            vec![
+                (1000, "calls".to_owned(), MetricKind::Counter, 1),
+                (1000, "queue".to_owned(), MetricKind::Gauge, -3),
+            ]
+        );
+
+        // Malformed names are rejected, and never reach the sink.
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.record_metric("", MetricKind::Counter, 1)
+        );
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.record_metric(&"a".repeat(257), MetricKind::Counter, 1)
+        );
+        kern.record_metric(&"a".repeat(256), MetricKind::Counter, 1)?;
+        assert_eq!(sink.0.lock().unwrap().len(), 3);
+
+        // Without a sink, metrics are dropped.
+        let (kern, _) = build_inspecting_test()?;
+        kern.record_metric("calls", MetricKind::Counter, 1)?;
+
+        Ok(())
+    }
+}
+
+mod filecoin {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use cid::Cid;
+    use futures::future::BoxFuture;
+    use futures::FutureExt;
+    use fvm::externs::ProofsBackend;
+    use fvm::gas::price_list_by_network_version;
+    use fvm::kernel::filecoin::{DefaultFilecoinKernel, FilecoinKernel};
+    use fvm::kernel::{ExecutionError, GasOps, Result};
+    use fvm::syscall_error;
+    use fvm_shared::randomness::Randomness;
+    use fvm_shared::sector::{
+        AggregateSealVerifyProofAndInfos, RegisteredSealProof, RegisteredUpdateProof,
+        ReplicaUpdateInfo, SealVerifyInfo, SectorID, WindowPoStVerifyInfo,
+    };
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// A proofs backend deciding the outcome of each seal by its sector number.
+    struct FakeBackend;
+
+    const VALID: u64 = 0;
+    const INVALID: u64 = 1;
+    const MALFORMED: u64 = 2;
+    const UNREACHABLE: u64 = 3;
+    const MISBEHAVING: u64 = 4;
+
+    impl FakeBackend {
+        fn outcome(number: u64) -> Result<bool> {
+            match number {
+                VALID => Ok(true),
+                INVALID => Ok(false),
+                MALFORMED => Err(syscall_error!(IllegalArgument; "malformed proof").into()),
+                UNREACHABLE => Err(ExecutionError::Fatal(anyhow::anyhow!(
+                    "service unreachable"
+                ))),
+                MISBEHAVING => Err(syscall_error!(NotFound; "unexpected error").into()),
+                _ => panic!("proof verification panicked"),
+            }
+        }
+    }
+
+    impl ProofsBackend for FakeBackend {
+        fn verify_seal<'a>(&'a self, info: &'a SealVerifyInfo) -> BoxFuture<'a, Result<bool>> {
+            let res = Self::outcome(info.sector_id.number);
+            async move { res }.boxed()
+        }
+
+        fn verify_post<'a>(
+            &'a self,
+            info: &'a WindowPoStVerifyInfo,
+        ) -> BoxFuture<'a, Result<bool>> {
+            let res = Self::outcome(info.prover);
+            async move { res }.boxed()
+        }
+
+        fn verify_aggregate_seals<'a>(
+            &'a self,
+            _: &'a AggregateSealVerifyProofAndInfos,
+        ) -> BoxFuture<'a, Result<bool>> {
+            unimplemented!()
+        }
+
+        fn verify_replica_update<'a>(
+            &'a self,
+            info: &'a ReplicaUpdateInfo,
+        ) -> BoxFuture<'a, Result<bool>> {
+            let number = u64::from_le_bytes(info.proof.as_slice().try_into().unwrap());
+            let res = Self::outcome(number);
+            async move { res }.boxed()
+        }
+    }
+
+    /// A proofs backend accepting every seal, recording how many seals it verifies concurrently
+    /// and the threads it verifies them on.
+    #[derive(Clone, Default)]
+    struct RecordingBackend(Arc<Recorded>);
+
+    #[derive(Default)]
+    struct Recorded {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+        threads: Mutex<Vec<Option<String>>>,
+    }
+
+    impl ProofsBackend for RecordingBackend {
+        fn verify_seal<'a>(&'a self, _: &'a SealVerifyInfo) -> BoxFuture<'a, Result<bool>> {
+            let recorded = &self.0;
+            let in_flight = recorded.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            recorded
+                .max_in_flight
+                .fetch_max(in_flight, Ordering::SeqCst);
+            recorded
+                .threads
+                .lock()
+                .unwrap()
+                .push(std::thread::current().name().map(String::from));
+            std::thread::sleep(Duration::from_millis(20));
+            recorded.in_flight.fetch_sub(1, Ordering::SeqCst);
+            async { Ok(true) }.boxed()
+        }
+
+        fn verify_post<'a>(&'a self, _: &'a WindowPoStVerifyInfo) -> BoxFuture<'a, Result<bool>> {
+            unimplemented!()
+        }
+
+        fn verify_aggregate_seals<'a>(
+            &'a self,
+            _: &'a AggregateSealVerifyProofAndInfos,
+        ) -> BoxFuture<'a, Result<bool>> {
+            unimplemented!()
+        }
+
+        fn verify_replica_update<'a>(
+            &'a self,
+            _: &'a ReplicaUpdateInfo,
+        ) -> BoxFuture<'a, Result<bool>> {
+            unimplemented!()
+        }
+    }
+
+    fn build_filecoin_kernel() -> DefaultFilecoinKernel<TestingKernel> {
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.machine.externs.proofs_backend = Some(Box::new(FakeBackend));
+        DefaultFilecoinKernel(TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            0,
+            0,
+            Zero::zero(),
+            false,
+        ))
+    }
+
+    fn seal(number: u64) -> SealVerifyInfo {
+        SealVerifyInfo {
+            registered_proof: RegisteredSealProof::StackedDRG32GiBV1P1,
+            sector_id: SectorID {
+                miner: 1000,
+                number,
+            },
+            deal_ids: Vec::new(),
+            randomness: Randomness(vec![0; 32]),
+            interactive_randomness: Randomness(vec![0; 32]),
+            proof: Vec::new(),
+            sealed_cid: Cid::default(),
+            unsealed_cid: Cid::default(),
+        }
+    }
+
+    /// A window PoSt whose outcome is decided by its prover (see [`FakeBackend`]).
+    fn post(prover: u64) -> WindowPoStVerifyInfo {
+        WindowPoStVerifyInfo {
+            randomness: Randomness(vec![0; 32]),
+            proofs: Vec::new(),
+            challenged_sectors: Vec::new(),
+            prover,
+        }
+    }
+
+    /// A replica update whose outcome is decided by `number` (see [`FakeBackend`]).
+    fn replica_update(number: u64) -> ReplicaUpdateInfo {
+        ReplicaUpdateInfo {
+            update_proof_type: RegisteredUpdateProof::StackedDRG32GiBV1,
+            old_sealed_cid: Cid::default(),
+            new_sealed_cid: Cid::default(),
+            new_unsealed_cid: Cid::default(),
+            proof: number.to_le_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn verify_replica_update() -> anyhow::Result<()> {
+        let kern = build_filecoin_kernel();
+
+        assert!(kern.verify_replica_update(&replica_update(VALID))?);
+        assert!(!kern.verify_replica_update(&replica_update(INVALID))?);
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.verify_replica_update(&replica_update(MALFORMED))
+        );
+        let err = kern
+            .verify_replica_update(&replica_update(UNREACHABLE))
+            .expect_err("expected an unreachable backend to abort");
+        assert!(err.is_fatal(), "expected a fatal error, got {}", err);
+
+        // Every verification is charged, whatever its outcome.
+        let price = price_list_by_network_version(STUB_NETWORK_VER)
+            .on_verify_replica_update(&replica_update(VALID));
+        assert_eq!(kern.gas_used(), price.total() * 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn batch_verify_posts() -> anyhow::Result<()> {
+        let kern = build_filecoin_kernel();
+        let posts = [post(VALID), post(INVALID), post(MALFORMED), post(99)];
+
+        let res = kern.batch_verify_posts(&posts)?;
+        assert_eq!(res, vec![true, false, false, false]);
+
+        // Every proof is charged as if verified individually.
+        let price = price_list_by_network_version(STUB_NETWORK_VER).on_verify_post(&post(VALID));
+        assert_eq!(kern.gas_used(), price.total() * 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn batch_verify_posts_fatal() -> anyhow::Result<()> {
+        let kern = build_filecoin_kernel();
+        let posts = [post(VALID), post(UNREACHABLE), post(INVALID)];
+
+        let err = kern
+            .batch_verify_posts(&posts)
+            .expect_err("expected an unreachable backend to abort the batch");
+        assert!(err.is_fatal(), "expected a fatal error, got {}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn batch_verify_seals_invalid() -> anyhow::Result<()> {
+        let kern = build_filecoin_kernel();
+        let seals = [
+            seal(VALID),
+            seal(INVALID),
+            seal(VALID),
+            seal(MALFORMED),
+            seal(99),
+        ];
+
+        let res = kern.batch_verify_seals(&seals)?;
+        assert_eq!(res, vec![true, false, true, false, false]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn batch_verify_seals_fatal() -> anyhow::Result<()> {
+        let kern = build_filecoin_kernel();
+        let seals = [seal(VALID), seal(UNREACHABLE), seal(INVALID)];
+
+        let err = kern
+            .batch_verify_seals(&seals)
+            .expect_err("expected an unreachable backend to abort the batch");
+        assert!(err.is_fatal(), "expected a fatal error, got {}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn batch_verify_seals_unexpected_error() -> anyhow::Result<()> {
+        let kern = build_filecoin_kernel();
+        let seals = [seal(MISBEHAVING), seal(VALID)];
+
+        let err = kern
+            .batch_verify_seals(&seals)
+            .expect_err("expected an unexpected backend error to abort the batch");
+        assert!(err.is_fatal(), "expected a fatal error, got {}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn batch_verify_seals_parallelism_per_message() -> anyhow::Result<()> {
+        let backend = RecordingBackend::default();
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.machine.externs.proofs_backend = Some(Box::new(backend.clone()));
+        call_manager.machine.ctx.proof_parallelism_per_message = 2;
+        call_manager.machine.proof_pool = Some(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(8)
+                .thread_name(|i| format!("test-proofs-{i}"))
+                .build()?,
+        );
+        let kern = DefaultFilecoinKernel(TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            0,
+            0,
+            Zero::zero(),
+            false,
+        ));
+
+        let seals: Vec<_> = (0..8).map(|_| seal(VALID)).collect();
+        assert_eq!(kern.batch_verify_seals(&seals)?, vec![true; 8]);
+
+        // The proofs were verified on the machine's proof pool, but at most two at a time.
+        let recorded = &backend.0;
+        assert!(recorded.max_in_flight.load(Ordering::SeqCst) <= 2);
+        let threads = recorded.threads.lock().unwrap();
+        assert_eq!(threads.len(), 8);
+        assert!(threads.iter().all(|name| name
+            .as_deref()
+            .unwrap_or_default()
+            .starts_with("test-proofs-")));
+
+        Ok(())
+    }
 }
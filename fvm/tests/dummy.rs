@@ -6,12 +6,15 @@ use std::rc::Rc;
 
 use anyhow::Context;
 use cid::Cid;
-use fvm::call_manager::{Backtrace, CallManager, Entrypoint, FinishRet, InvocationResult};
+use fvm::call_manager::{
+    Backtrace, CallManager, DebugOutput, Entrypoint, FinishRet, InvocationResult, SystemEvent,
+};
 use fvm::engine::Engine;
-use fvm::externs::{Chain, Consensus, Externs, Rand};
+use fvm::externs::{Chain, Consensus, Externs, ProofsBackend, Rand};
 use fvm::gas::{Gas, GasCharge, GasTimer, GasTracker};
+use fvm::kernel::filecoin::SyncProofsBackend;
 use fvm::machine::limiter::MemoryLimiter;
-use fvm::machine::{Machine, MachineContext, Manifest, NetworkConfig};
+use fvm::machine::{Machine, MachineContext, Manifest, MetricSink, NetworkConfig};
 use fvm::state_tree::StateTree;
 use fvm::{kernel, Kernel};
 use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
@@ -19,8 +22,11 @@ use fvm_ipld_encoding::{CborStore, DAG_CBOR};
 use fvm_shared::address::Address;
 use fvm_shared::bigint::Zero;
 use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
 use fvm_shared::event::StampedEvent;
+use fvm_shared::randomness::Randomness;
 use fvm_shared::state::StateTreeVersion;
+use fvm_shared::sys::ReentrancyPolicy;
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::{ActorID, IDENTITY_HASH};
 use multihash::{Code, Multihash};
@@ -28,9 +34,22 @@ use multihash::{Code, Multihash};
 pub const STUB_NETWORK_VER: NetworkVersion = NetworkVersion::V21;
 
 /// Unimplemented and empty `Externs` impl
-pub struct DummyExterns;
+#[derive(Default)]
+pub struct DummyExterns {
+    /// Overrides the backend proofs are verified with.
+    pub proofs_backend: Option<Box<dyn ProofsBackend>>,
+    /// The beacon randomness returned (at its native length) for every round.
+    pub beacon_randomness: Option<Randomness>,
+}
 
-impl Externs for DummyExterns {}
+impl Externs for DummyExterns {
+    fn proofs_backend(&self) -> &dyn ProofsBackend {
+        match &self.proofs_backend {
+            Some(backend) => backend.as_ref(),
+            None => &SyncProofsBackend,
+        }
+    }
+}
 
 impl Rand for DummyExterns {
     fn get_chain_randomness(
@@ -46,6 +65,15 @@ impl Rand for DummyExterns {
     ) -> anyhow::Result<[u8; 32]> {
         todo!()
     }
+
+    fn get_beacon_randomness_bytes(
+        &self,
+        _round: fvm_shared::clock::ChainEpoch,
+    ) -> anyhow::Result<Randomness> {
+        self.beacon_randomness
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("no beacon randomness"))
+    }
 }
 
 impl Consensus for DummyExterns {
@@ -101,6 +129,9 @@ pub struct DummyMachine {
     pub state_tree: StateTree<MemoryBlockstore>,
     pub ctx: MachineContext,
     pub builtin_actors: Manifest,
+    pub externs: DummyExterns,
+    pub proof_pool: Option<rayon::ThreadPool>,
+    pub metric_sink: Option<Box<dyn MetricSink>>,
 }
 
 impl DummyMachine {
@@ -137,6 +168,9 @@ impl DummyMachine {
             ctx,
             state_tree,
             builtin_actors: manifest,
+            externs: DummyExterns::default(),
+            proof_pool: None,
+            metric_sink: None,
         })
     }
 }
@@ -155,7 +189,7 @@ impl Machine for DummyMachine {
     }
 
     fn externs(&self) -> &Self::Externs {
-        &DummyExterns
+        &self.externs
     }
 
     fn builtin_actors(&self) -> &Manifest {
@@ -181,6 +215,14 @@ impl Machine for DummyMachine {
     fn new_limiter(&self) -> Self::Limiter {
         DummyLimiter::default()
     }
+
+    fn proof_pool(&self) -> Option<&rayon::ThreadPool> {
+        self.proof_pool.as_ref()
+    }
+
+    fn metric_sink(&self) -> Option<&dyn MetricSink> {
+        self.metric_sink.as_deref()
+    }
 }
 
 /// Minimal *pseudo-functional* implementation CallManager
@@ -192,6 +234,8 @@ pub struct DummyCallManager {
     pub origin_address: Address,
     pub nonce: u64,
     pub test_data: Rc<RefCell<TestData>>,
+    pub events: Vec<StampedEvent>,
+    pub call_stack: Vec<(ActorID, &'static str)>,
     limits: DummyLimiter,
 }
 
@@ -202,6 +246,9 @@ pub struct TestData {
 
 const BLOCK_GAS_LIMIT: Gas = Gas::new(fvm_shared::BLOCK_GAS_LIMIT);
 
+/// The gas consumed by every actor call made through the [`DummyCallManager`].
+pub const DUMMY_CALL_GAS: Gas = Gas::new(1000);
+
 impl DummyCallManager {
     pub fn new_stub() -> (Self, Rc<RefCell<TestData>>) {
         let rc = Rc::new(RefCell::new(TestData {
@@ -215,6 +262,8 @@ impl DummyCallManager {
                 origin: 0,
                 nonce: 0,
                 test_data: rc,
+                events: Vec::new(),
+                call_stack: Vec::new(),
                 limits: DummyLimiter::default(),
                 origin_address: Address::new_id(0),
                 gas_premium: TokenAmount::zero(),
@@ -235,6 +284,8 @@ impl DummyCallManager {
                 origin: 0,
                 nonce: 0,
                 test_data: rc,
+                events: Vec::new(),
+                call_stack: Vec::new(),
                 limits: DummyLimiter::default(),
                 origin_address: Address::new_id(0),
                 gas_premium: TokenAmount::zero(),
@@ -270,6 +321,8 @@ impl CallManager for DummyCallManager {
             origin_address,
             nonce,
             test_data: rc,
+            events: Vec::new(),
+            call_stack: Vec::new(),
             limits,
         }
     }
@@ -284,16 +337,19 @@ impl CallManager for DummyCallManager {
         _gas_limit: Option<Gas>,
         _read_only: bool,
     ) -> kernel::Result<InvocationResult> {
-        // Ok(InvocationResult::Return(None))
-        todo!()
+        // Pretend the callee ran, consuming a fixed amount of gas, and returned nothing.
+        self.charge_gas(GasCharge::new("OnDummyCall", DUMMY_CALL_GAS, Gas::zero()))?;
+        Ok(InvocationResult {
+            exit_code: ExitCode::OK,
+            value: None,
+        })
     }
 
     fn with_transaction(
         &mut self,
-        _f: impl FnOnce(&mut Self) -> kernel::Result<InvocationResult>,
+        f: impl FnOnce(&mut Self) -> kernel::Result<InvocationResult>,
     ) -> kernel::Result<InvocationResult> {
-        // Ok(InvocationResult::Return(None))
-        todo!()
+        f(self)
     }
 
     fn finish(self) -> (kernel::Result<FinishRet>, Self::Machine) {
@@ -307,6 +363,8 @@ impl CallManager for DummyCallManager {
                 exec_trace: Vec::new(),
                 events: Vec::new(),
                 events_root: None,
+                syscall_counts: None,
+                debug_output: Vec::new(),
             }),
             self.machine,
         )
@@ -359,6 +417,10 @@ impl CallManager for DummyCallManager {
     }
 
     fn get_call_stack(&self) -> &[(ActorID, &'static str)] {
+        &self.call_stack
+    }
+
+    fn set_reentrancy_policy(&mut self, _policy: ReentrancyPolicy) -> kernel::Result<()> {
         todo!()
     }
 
@@ -366,14 +428,34 @@ impl CallManager for DummyCallManager {
         todo!()
     }
 
+    fn call_depth(&self) -> u32 {
+        todo!()
+    }
+
     fn limiter_mut(&mut self) -> &mut <Self::Machine as Machine>::Limiter {
         &mut self.limits
     }
 
-    fn append_event(&mut self, _evt: StampedEvent) {
+    fn append_event(&mut self, evt: StampedEvent) {
+        self.events.push(evt)
+    }
+
+    fn append_debug_output(&mut self, _output: DebugOutput) {
         todo!()
     }
 
+    fn emit_system_event(&mut self, _evt: SystemEvent) {
+        todo!()
+    }
+
+    fn events_payload(&self) -> usize {
+        self.events
+            .iter()
+            .flat_map(|evt| &evt.event.entries)
+            .map(|e| e.key.len() + e.value.len())
+            .sum()
+    }
+
     fn resolve_address(&self, address: &Address) -> fvm::kernel::Result<Option<ActorID>> {
         self.machine.state_tree().lookup_id(address)
     }
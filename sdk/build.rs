@@ -0,0 +1,11 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+fn main() {
+    // With the `testing` feature, syscalls made natively are handled by the mock runtime (see
+    // `fvm_sdk::testing`) instead of being imported from the FVM. Wasm builds always import them.
+    let testing = std::env::var_os("CARGO_FEATURE_TESTING").is_some();
+    let wasm = std::env::var("CARGO_CFG_TARGET_ARCH").map_or(false, |arch| arch == "wasm32");
+    if testing && !wasm {
+        println!("cargo:rustc-cfg=mock_syscalls");
+    }
+}
@@ -0,0 +1,468 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Machine-readable descriptions of an actor's interface (methods, parameter/return types, and
+//! events), for client code generation and for decoding messages in explorers.
+//!
+//! Actors declare their interface with the [`actor_abi!`][crate::actor_abi] macro, which generates
+//! an `abi()` function. With the `abi` feature enabled, the description can be rendered as JSON,
+//! usually from the actor's build script or a test:
+//!
+//! ```ignore
+//! std::fs::write("abi.json", my_actor::abi().to_json())?;
+//! ```
+//!
+//! The macro also generates a `methods` module with one [`ActorMethod`] marker type per method.
+//! These can be used with [`Call`] to build typed calls, either from another actor or from
+//! off-chain tooling:
+//!
+//! ```ignore
+//! let call = Call::<token::methods::Transfer>::new(token_addr, TransferParams { .. });
+//! // From an actor:
+//! let resp = call.send_raw(None, SendFlags::empty())?;
+//! let ret = Call::<token::methods::Transfer>::decode_return(&resp)?;
+//! // Off-chain:
+//! let msg = call.message(sender, nonce)?;
+//! ```
+use fvm_ipld_encoding::de::DeserializeOwned;
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::serde::Serialize;
+use fvm_ipld_encoding::RawBytes;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ErrorNumber;
+use fvm_shared::message::Message;
+use fvm_shared::sys::SendFlags;
+use fvm_shared::{MethodNum, Response};
+
+use crate::SyscallResult;
+
+/// The description of an actor's interface.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(crate = "fvm_ipld_encoding::serde")]
+pub struct ActorAbi {
+    /// The actor's name.
+    pub name: &'static str,
+    /// The methods exported by the actor, in declaration order.
+    pub methods: Vec<MethodAbi>,
+    /// The events the actor may emit.
+    pub events: Vec<EventAbi>,
+}
+
+/// The description of a single actor method.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(crate = "fvm_ipld_encoding::serde")]
+pub struct MethodAbi {
+    /// The method's name.
+    pub name: &'static str,
+    /// The method number callers must use to invoke this method.
+    pub number: MethodNum,
+    /// The (CBOR-encoded) parameter type, or `None` if the method takes no parameters.
+    pub params: Option<&'static str>,
+    /// The (CBOR-encoded) return type, or `None` if the method returns nothing.
+    pub returns: Option<&'static str>,
+}
+
+/// The description of an event.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(crate = "fvm_ipld_encoding::serde")]
+pub struct EventAbi {
+    /// The event's name.
+    pub name: &'static str,
+    /// The event's entries, in emission order.
+    pub fields: Vec<EventFieldAbi>,
+}
+
+/// The description of a single event entry.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(crate = "fvm_ipld_encoding::serde")]
+pub struct EventFieldAbi {
+    /// The entry's key.
+    pub key: &'static str,
+    /// The (CBOR-encoded) value type.
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+    /// Whether the entry is indexed by the client.
+    pub indexed: bool,
+}
+
+impl ActorAbi {
+    /// Looks up a method by number.
+    pub fn method(&self, number: MethodNum) -> Option<&MethodAbi> {
+        self.methods.iter().find(|m| m.number == number)
+    }
+
+    /// Renders the description as pretty-printed JSON.
+    #[cfg(feature = "abi")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("actor ABI is always serializable")
+    }
+}
+
+/// Returns the name of the type `T` for use in an ABI description, or `None` for the unit type.
+#[doc(hidden)]
+pub fn type_schema<T: ?Sized>() -> Option<&'static str> {
+    match std::any::type_name::<T>() {
+        "()" => None,
+        name => Some(name),
+    }
+}
+
+/// A method exported by an actor. Implementations are generated by
+/// [`actor_abi!`][crate::actor_abi] in the actor's `methods` module.
+pub trait ActorMethod {
+    /// The method number.
+    const NUMBER: MethodNum;
+    /// The parameter type, `()` if the method takes no parameters.
+    type Params: Serialize;
+    /// The return type, `()` if the method returns nothing.
+    type Return: DeserializeOwned;
+}
+
+/// A typed call to the method `M` of an actor.
+pub struct Call<M: ActorMethod> {
+    /// The actor to call.
+    pub to: Address,
+    /// The method parameters.
+    pub params: M::Params,
+    /// The value to transfer with the call.
+    pub value: TokenAmount,
+}
+
+impl<M: ActorMethod> Call<M> {
+    /// Creates a call to the actor at `to` that transfers no value.
+    pub fn new(to: Address, params: M::Params) -> Self {
+        Call {
+            to,
+            params,
+            value: TokenAmount::default(),
+        }
+    }
+
+    /// Sets the value to transfer with the call.
+    pub fn with_value(mut self, value: TokenAmount) -> Self {
+        self.value = value;
+        self
+    }
+
+    /// Encodes the parameters as a CBOR block, or `None` if the method takes no parameters.
+    pub fn params_block(&self) -> Result<Option<IpldBlock>, fvm_ipld_encoding::Error> {
+        if type_schema::<M::Params>().is_none() {
+            return Ok(None);
+        }
+        IpldBlock::serialize_cbor(&self.params)
+    }
+
+    /// Builds an unsigned message for this call. The gas fields are left at zero and should be
+    /// set (e.g., from an estimate) before signing.
+    pub fn message(
+        &self,
+        from: Address,
+        sequence: u64,
+    ) -> Result<Message, fvm_ipld_encoding::Error> {
+        let params = self
+            .params_block()?
+            .map(|b| RawBytes::new(b.data))
+            .unwrap_or_default();
+        Ok(Message {
+            version: 0,
+            from,
+            to: self.to,
+            sequence,
+            value: self.value.clone(),
+            method_num: M::NUMBER,
+            params,
+            gas_limit: 0,
+            gas_fee_cap: TokenAmount::default(),
+            gas_premium: TokenAmount::default(),
+        })
+    }
+
+    /// Sends the call from the current actor, returning the raw response.
+    pub fn send_raw(&self, gas_limit: Option<u64>, flags: SendFlags) -> SyscallResult<Response> {
+        let params = self
+            .params_block()
+            .map_err(|_| ErrorNumber::Serialization)?;
+        crate::send::send(
+            &self.to,
+            M::NUMBER,
+            params,
+            self.value.clone(),
+            gas_limit,
+            flags,
+        )
+    }
+
+    /// Decodes the return value of a successful call. Returns `None` if the callee returned no
+    /// data.
+    pub fn decode_return(
+        response: &Response,
+    ) -> Result<Option<M::Return>, fvm_ipld_encoding::Error> {
+        response
+            .return_data
+            .as_ref()
+            .map(|b| b.deserialize())
+            .transpose()
+    }
+}
+
+/// Declares an actor's ABI, generating a `pub fn abi() -> ActorAbi` describing it and a
+/// `pub mod methods` containing an [`ActorMethod`] marker type per method.
+///
+/// Methods that take no parameters or return nothing use `()`. Event entries marked `#[indexed]`
+/// are indexed by the client.
+///
+/// ```ignore
+/// fvm_sdk::actor_abi! {
+///     name = "token";
+///     methods {
+///         Constructor = 1 (ConstructorParams) -> ();
+///         Transfer = 2 (TransferParams) -> TransferReturn;
+///         TotalSupply = 3 () -> TokenAmount;
+///     }
+///     events {
+///         transfer { #[indexed] from: ActorID, #[indexed] to: ActorID, amount: TokenAmount }
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! actor_abi {
+    (
+        name = $name:literal;
+        methods {
+            $( $method:ident = $number:literal ( $($params:ty)? ) -> $ret:ty; )*
+        }
+        $(
+            events {
+                $( $event:ident { $( $(#[$indexed:ident])? $key:ident : $fty:ty ),* $(,)? } )*
+            }
+        )?
+    ) => {
+        /// Typed markers for this actor's methods, for use with [`Call`]($crate::abi::Call).
+        #[allow(dead_code)]
+        pub mod methods {
+            #[allow(unused_imports)]
+            use super::*;
+
+            $(
+                #[doc = concat!("The `", stringify!($method), "` method.")]
+                #[derive(Debug, Clone, Copy)]
+                pub struct $method;
+
+                impl $crate::abi::ActorMethod for $method {
+                    const NUMBER: u64 = $number;
+                    type Params = ($($params)?);
+                    type Return = $ret;
+                }
+            )*
+        }
+
+        /// Returns the description of this actor's interface.
+        pub fn abi() -> $crate::abi::ActorAbi {
+            $crate::abi::ActorAbi {
+                name: $name,
+                methods: vec![
+                    $(
+                        $crate::abi::MethodAbi {
+                            name: stringify!($method),
+                            number: $number,
+                            params: $crate::abi::type_schema::<($($params)?)>(),
+                            returns: $crate::abi::type_schema::<$ret>(),
+                        },
+                    )*
+                ],
+                events: vec![
+                    $($(
+                        $crate::abi::EventAbi {
+                            name: stringify!($event),
+                            fields: vec![
+                                $(
+                                    $crate::abi::EventFieldAbi {
+                                        key: stringify!($key),
+                                        ty: std::any::type_name::<$fty>(),
+                                        indexed: stringify!($($indexed)?) == "indexed",
+                                    },
+                                )*
+                            ],
+                        },
+                    )*)?
+                ],
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use fvm_ipld_encoding::ipld_block::IpldBlock;
+    use fvm_ipld_encoding::RawBytes;
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::error::ExitCode;
+    use fvm_shared::Response;
+
+    use super::{ActorAbi, Call, EventAbi, EventFieldAbi, MethodAbi};
+
+    crate::actor_abi! {
+        name = "counter";
+        methods {
+            Constructor = 1 () -> ();
+            Increment = 2 (u64) -> u64;
+            Balance = 3 () -> TokenAmount;
+        }
+        events {
+            incremented { #[indexed] by: u64, total: u64 }
+        }
+    }
+
+    #[test]
+    fn describes_actor() {
+        let method = |name, number, params, returns| MethodAbi {
+            name,
+            number,
+            params,
+            returns,
+        };
+        let field = |key, ty, indexed| EventFieldAbi { key, ty, indexed };
+        assert_eq!(
+            abi(),
+            ActorAbi {
+                name: "counter",
+                methods: vec![
+                    method("Constructor", 1, None, None),
+                    method("Increment", 2, Some("u64"), Some("u64")),
+                    method("Balance", 3, None, Some("fvm_shared::econ::TokenAmount")),
+                ],
+                events: vec![EventAbi {
+                    name: "incremented",
+                    fields: vec![field("by", "u64", true), field("total", "u64", false)],
+                }],
+            }
+        );
+        assert_eq!(abi().method(2).unwrap().name, "Increment");
+        assert!(abi().method(4).is_none());
+    }
+
+    #[cfg(feature = "abi")]
+    #[test]
+    fn json_golden() {
+        const GOLDEN: &str = r#"{
+  "name": "counter",
+  "methods": [
+    {
+      "name": "Constructor",
+      "number": 1,
+      "params": null,
+      "returns": null
+    },
+    {
+      "name": "Increment",
+      "number": 2,
+      "params": "u64",
+      "returns": "u64"
+    },
+    {
+      "name": "Balance",
+      "number": 3,
+      "params": null,
+      "returns": "fvm_shared::econ::TokenAmount"
+    }
+  ],
+  "events": [
+    {
+      "name": "incremented",
+      "fields": [
+        {
+          "key": "by",
+          "type": "u64",
+          "indexed": true
+        },
+        {
+          "key": "total",
+          "type": "u64",
+          "indexed": false
+        }
+      ]
+    }
+  ]
+}"#;
+        assert_eq!(abi().to_json(), GOLDEN);
+    }
+
+    #[test]
+    fn builds_messages() {
+        let to = Address::new_id(1000);
+        let from = Address::new_id(100);
+
+        let msg = Call::<methods::Increment>::new(to, 5)
+            .with_value(TokenAmount::from_atto(7))
+            .message(from, 3)
+            .unwrap();
+        assert_eq!((msg.from, msg.to, msg.sequence), (from, to, 3));
+        assert_eq!(msg.method_num, 2);
+        assert_eq!(msg.value, TokenAmount::from_atto(7));
+        assert_eq!(msg.params, RawBytes::serialize(5u64).unwrap());
+
+        // Methods without parameters send none at all.
+        let call = Call::<methods::Balance>::new(to, ());
+        assert_eq!(call.params_block().unwrap(), None);
+        let msg = call.message(from, 0).unwrap();
+        assert_eq!(msg.method_num, 3);
+        assert!(msg.params.is_empty());
+    }
+
+    #[test]
+    fn decodes_returns() {
+        let response = |return_data| Response {
+            exit_code: ExitCode::OK,
+            return_data,
+        };
+        let ret = IpldBlock::serialize_cbor(&TokenAmount::from_atto(42)).unwrap();
+        assert_eq!(
+            Call::<methods::Balance>::decode_return(&response(ret)).unwrap(),
+            Some(TokenAmount::from_atto(42))
+        );
+        assert_eq!(
+            Call::<methods::Constructor>::decode_return(&response(None)).unwrap(),
+            None
+        );
+
+        // A return value of the wrong type is a decode error.
+        let ret = IpldBlock::serialize_cbor(&"not a number").unwrap();
+        assert!(Call::<methods::Increment>::decode_return(&response(ret)).is_err());
+    }
+
+    #[cfg(mock_syscalls)]
+    #[test]
+    fn round_trips_through_send() {
+        use fvm_shared::sys::SendFlags;
+
+        use crate::testing::{ExpectedSend, MockRuntime};
+
+        let counter = Address::new_id(1001);
+        let mut rt = MockRuntime::new(1000);
+        rt.balance = TokenAmount::from_atto(10);
+        rt.expect_send(ExpectedSend {
+            to: counter,
+            method: 2,
+            params: IpldBlock::serialize_cbor(&5u64).unwrap(),
+            value: TokenAmount::from_atto(3),
+            response: Ok(Response {
+                exit_code: ExitCode::OK,
+                return_data: IpldBlock::serialize_cbor(&6u64).unwrap(),
+            }),
+        });
+
+        let total = rt
+            .call(|| {
+                let call = Call::<methods::Increment>::new(counter, 5)
+                    .with_value(TokenAmount::from_atto(3));
+                let response = call.send_raw(None, SendFlags::empty()).unwrap();
+                assert_eq!(response.exit_code, ExitCode::OK);
+                Call::<methods::Increment>::decode_return(&response).unwrap()
+            })
+            .unwrap();
+        assert_eq!(total, Some(6));
+        assert_eq!(rt.balance, TokenAmount::from_atto(7));
+        rt.verify();
+    }
+}
@@ -1,5 +1,6 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::convert::TryInto;
 use std::ptr; // no_std
 
 use cid::Cid;
@@ -7,10 +8,10 @@ use fvm_ipld_encoding::ipld_block::IpldBlock;
 use fvm_shared::address::{Address, Payload, MAX_ADDRESS_LEN};
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ErrorNumber;
-use fvm_shared::{ActorID, Response, MAX_CID_LEN};
+use fvm_shared::{ActorID, Response, IPLD_RAW, MAX_CID_LEN};
 use log::error;
 
-use crate::{build_response, sys, SyscallResult, NO_DATA_BLOCK_ID};
+use crate::{build_response, probe_and_fill, sys, SyscallResult, NO_DATA_BLOCK_ID};
 
 /// Resolves the ID address of an actor. Returns `None` if the address cannot be resolved.
 /// Successfully resolving an address doesn't necessarily mean the actor exists (e.g., if the
@@ -33,34 +34,40 @@ pub fn resolve_address(addr: &Address) -> Option<ActorID> {
 /// Looks up the delegated (f4) address of the specified actor. Returns `None` if the actor doesn't
 /// exist or it doesn't have f4 address.
 pub fn lookup_delegated_address(addr: ActorID) -> Option<Address> {
-    let mut out_buffer = [0u8; MAX_ADDRESS_LEN];
-    unsafe {
-        match sys::actor::lookup_delegated_address(
-            addr,
-            out_buffer.as_mut_ptr(),
-            out_buffer.len() as u32,
-        ) {
-            Ok(0) => None,
-            Ok(length) => match Address::from_bytes(&out_buffer[..length as usize]) {
-                Ok(addr) => Some(addr),
-                // Ok, so, we _log_ this error (if debugging is enabled) but otherwise move on.
-                // Why? Because the system may add _new_ address classes. In that case, the "least
-                // bad" thing to do here is to claim that the target actor doesn't have an f1/f3/f4
-                // address, which is likely correct.
-                //
-                // https://github.com/filecoin-project/builtin-actors/issues/738
-                Err(e) => {
-                    error!(
-                        "unexpected address from 'lookup_delegated_address' with protocol {}: {}",
-                        out_buffer[0], e
-                    );
-                    None
-                }
-            },
-            // We're flattening the "not found" error here, but that's probably reasonable for most users.
-            Err(ErrorNumber::NotFound) => None,
-            Err(other) => panic!("unexpected address resolution failure: {}", other),
-        }
+    let res = probe_and_fill(MAX_ADDRESS_LEN, MAX_ADDRESS_LEN, |buf| unsafe {
+        sys::actor::lookup_delegated_address(addr, buf.as_mut_ptr(), buf.len() as u32)
+    });
+    match res {
+        Ok(bytes) if bytes.is_empty() => None,
+        Ok(bytes) => match Address::from_bytes(&bytes) {
+            Ok(addr) => Some(addr),
+            // Ok, so, we _log_ this error (if debugging is enabled) but otherwise move on.
+            // Why? Because the system may add _new_ address classes. In that case, the "least
+            // bad" thing to do here is to claim that the target actor doesn't have an f1/f3/f4
+            // address, which is likely correct.
+            //
+            // https://github.com/filecoin-project/builtin-actors/issues/738
+            Err(e) => {
+                error!(
+                    "unexpected address from 'lookup_delegated_address' with protocol {}: {}",
+                    bytes[0], e
+                );
+                None
+            }
+        },
+        // We're flattening the "not found" error here, but that's probably reasonable for most users.
+        Err(ErrorNumber::NotFound) => None,
+        Err(other) => panic!("unexpected address resolution failure: {}", other),
+    }
+}
+
+/// Looks up the namespace (the ID of the address manager, e.g., the EAM for Ethereum addresses) of
+/// the specified actor's delegated (f4) address. Returns `None` if the actor doesn't exist or
+/// doesn't have an f4 address.
+pub fn lookup_delegated_namespace(addr: ActorID) -> Option<ActorID> {
+    match lookup_delegated_address(addr)?.payload() {
+        Payload::Delegated(delegated) => Some(delegated.namespace()),
+        _ => None,
     }
 }
 
@@ -70,13 +77,13 @@ pub fn get_actor_code_cid(addr: &Address) -> Option<Cid> {
     // this call should be a no-op. But it's more convenient for users to take addresses.
     let id = resolve_address(addr)?;
 
-    let mut buf = [0u8; MAX_CID_LEN];
-    unsafe {
-        match sys::actor::get_actor_code_cid(id, buf.as_mut_ptr(), MAX_CID_LEN as u32) {
-            Ok(len) => Some(Cid::read_bytes(&buf[..len as usize]).expect("invalid cid returned")),
-            Err(ErrorNumber::NotFound) => None,
-            Err(other) => panic!("unexpected code cid resolution failure: {}", other),
-        }
+    let res = probe_and_fill(MAX_CID_LEN, MAX_CID_LEN, |buf| unsafe {
+        sys::actor::get_actor_code_cid(id, buf.as_mut_ptr(), buf.len() as u32)
+    });
+    match res {
+        Ok(bytes) => Some(Cid::read_bytes(bytes.as_slice()).expect("invalid cid returned")),
+        Err(ErrorNumber::NotFound) => None,
+        Err(other) => panic!("unexpected code cid resolution failure: {}", other),
     }
 }
 
@@ -107,6 +114,47 @@ pub fn create_actor(
     }
 }
 
+/// Creates a new actor of the specified type under the provided address and invokes its
+/// constructor, rolling back the actor's creation if the constructor aborts.
+///
+/// Actors other than the init actor must pass an `actor_id` of 0: the new actor is then registered
+/// under the address [`next_actor_address`] returns before the call.
+pub fn create_and_invoke(
+    actor_id: ActorID,
+    code_cid: &Cid,
+    delegated_address: Option<Address>,
+    params: Option<IpldBlock>,
+    value: TokenAmount,
+) -> SyscallResult<Response> {
+    let value: sys::TokenAmount = value
+        .try_into()
+        .map_err(|_| ErrorNumber::InsufficientFunds)?;
+    unsafe {
+        let cid = code_cid.to_bytes();
+        let addr_bytes = delegated_address.map(|addr| addr.to_bytes());
+        let (addr_off, addr_len) = addr_bytes
+            .as_deref()
+            .map(|v| (v.as_ptr(), v.len()))
+            .unwrap_or((ptr::null(), 0));
+
+        let params_id = match params {
+            Some(p) => sys::ipld::block_create(p.codec, p.data.as_ptr(), p.data.len() as u32)?,
+            None => NO_DATA_BLOCK_ID,
+        };
+
+        let send = sys::actor::create_and_invoke(
+            actor_id,
+            cid.as_ptr(),
+            addr_off,
+            addr_len as u32,
+            params_id,
+            value.hi,
+            value.lo,
+        )?;
+        build_response(send)
+    }
+}
+
 /// Upgrades an actor using the given block which includes the old code cid and the upgrade params
 pub fn upgrade_actor(new_code_cid: &Cid, params: Option<IpldBlock>) -> SyscallResult<Response> {
     unsafe {
@@ -129,6 +177,17 @@ pub fn install_actor(code_cid: &Cid) -> SyscallResult<()> {
     unsafe { sys::actor::install_actor(cid.as_ptr()) }
 }
 
+/// Validates and installs the given actor Wasm code, returning its code CID.
+/// Note: this is a privileged syscall, restricted to the init actor.
+pub fn install_actor_code(code: &[u8]) -> SyscallResult<Cid> {
+    let mut buf = [0u8; MAX_CID_LEN];
+    unsafe {
+        let code_id = sys::ipld::block_create(IPLD_RAW, code.as_ptr(), code.len() as u32)?;
+        let len = sys::actor::install_actor_code(code_id, buf.as_mut_ptr(), MAX_CID_LEN as u32)?;
+        Ok(Cid::read_bytes(&buf[..len as usize]).expect("invalid cid returned"))
+    }
+}
+
 /// Determines whether the supplied CodeCID belongs to a built-in actor type,
 /// and to which.
 pub fn get_builtin_actor_type(code_cid: &Cid) -> Option<i32> {
@@ -149,12 +208,11 @@ pub fn get_builtin_actor_type(code_cid: &Cid) -> Option<i32> {
 /// Returns the CodeCID for a built-in actor type. Aborts with IllegalArgument
 /// if the supplied type is invalid.
 pub fn get_code_cid_for_type(typ: i32) -> Cid {
-    let mut buf = [0u8; MAX_CID_LEN];
-    unsafe {
-        let len = sys::actor::get_code_cid_for_type(typ, buf.as_mut_ptr(), MAX_CID_LEN as u32)
-            .expect("failed to get CodeCID for type");
-        Cid::read_bytes(&buf[..len as usize]).expect("invalid cid returned")
-    }
+    let bytes = probe_and_fill(MAX_CID_LEN, MAX_CID_LEN, |buf| unsafe {
+        sys::actor::get_code_cid_for_type(typ, buf.as_mut_ptr(), buf.len() as u32)
+    })
+    .expect("failed to get CodeCID for type");
+    Cid::read_bytes(bytes.as_slice()).expect("invalid cid returned")
 }
 
 /// Retrieves the balance of the specified actor, or None if the actor doesn't exist.
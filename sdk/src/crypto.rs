@@ -209,3 +209,18 @@ pub fn batch_verify_seals(batch: &[SealVerifyInfo]) -> SyscallResult<Vec<bool>>
         result
     })
 }
+
+pub fn batch_verify_posts(batch: &[WindowPoStVerifyInfo]) -> SyscallResult<Vec<bool>> {
+    let encoded = to_vec(batch).expect("failed to marshal batch post verification input");
+
+    Ok(unsafe {
+        let mut result: Vec<bool> = Vec::with_capacity(batch.len());
+        sys::crypto::batch_verify_posts(
+            encoded.as_ptr(),
+            encoded.len() as u32,
+            result.as_mut_ptr() as *mut u8,
+        )?;
+        result.set_len(batch.len());
+        result
+    })
+}
@@ -1,14 +1,13 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-use lazy_static::lazy_static;
+use fvm_shared::sys::MetricKind;
 use log::LevelFilter;
 
-use crate::sys;
+use crate::{sys, Memoized};
 
-lazy_static! {
-    /// Lazily memoizes if debug mode is enabled.
-    static ref DEBUG_ENABLED: bool = unsafe { sys::debug::enabled().unwrap() >= 0 };
-}
+/// Lazily memoizes if debug mode is enabled.
+static DEBUG_ENABLED: Memoized<bool> =
+    Memoized::new(|| unsafe { sys::debug::enabled().unwrap() >= 0 });
 
 /// Logs a message on the node.
 #[inline]
@@ -41,10 +40,19 @@ pub fn store_artifact(name: impl AsRef<str>, data: impl AsRef<[u8]>) {
     }
 }
 
+/// Reports a metric to the host's metrics sink. Counters are incremented by `value`, while gauges
+/// are set to `value`. This is a no-op unless debug mode is enabled (e.g., on devnets).
+pub fn metric(name: impl AsRef<str>, kind: MetricKind, value: i64) {
+    let name = name.as_ref();
+    unsafe {
+        sys::debug::metric(name.as_ptr(), name.len() as u32, kind as u32, value).unwrap();
+    }
+}
+
 /// Returns whether debug mode is enabled.
 #[inline(always)]
 pub fn enabled() -> bool {
-    *DEBUG_ENABLED
+    DEBUG_ENABLED.get()
 }
 
 /// Logger is a debug-only logger that uses the FVM syscalls.
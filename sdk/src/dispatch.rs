@@ -0,0 +1,171 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Support for the [`dispatch!`](crate::dispatch!) macro, which generates an actor's `invoke`
+//! entrypoint from its typed methods.
+use fvm_ipld_encoding::de::DeserializeOwned;
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::ser::Serialize;
+use fvm_ipld_encoding::CBOR;
+use fvm_shared::error::ExitCode;
+use fvm_shared::sys::BlockId;
+use fvm_shared::MethodNum;
+
+use crate::error::ActorError;
+use crate::{ipld, message, vm, NO_DATA_BLOCK_ID};
+
+/// CBOR `null`, the encoding of missing parameters and return values (e.g., `()` and `None`).
+const CBOR_NULL: &[u8] = &[0xf6];
+
+/// Runs the actor's entrypoint: initializes the SDK and calls `dispatch` with the method number
+/// and parameters, returning its result or aborting with its error.
+#[doc(hidden)]
+pub fn invoke(
+    params: BlockId,
+    dispatch: impl FnOnce(MethodNum, BlockId) -> Result<BlockId, ActorError>,
+) -> u32 {
+    crate::initialize();
+    match dispatch(message::method_number(), params) {
+        Ok(ret) => ret,
+        Err(e) => vm::exit(e.exit_code.value(), None, Some(&e.message)),
+    }
+}
+
+/// Calls a typed method: decodes its parameters, and encodes its return value.
+#[doc(hidden)]
+pub fn call_method<P, R, E>(
+    params: BlockId,
+    method: impl FnOnce(P) -> Result<R, E>,
+) -> Result<BlockId, ActorError>
+where
+    P: DeserializeOwned,
+    R: Serialize,
+    E: Into<ActorError>,
+{
+    let serialization =
+        |e: fvm_ipld_encoding::Error| ActorError::new(ExitCode::USR_SERIALIZATION, e.to_string());
+    let params = match message::params_raw(params).map_err(|e| {
+        ActorError::new(
+            ExitCode::USR_SERIALIZATION,
+            format!("failed to read parameters: {e}"),
+        )
+    })? {
+        Some(block) => block.deserialize(),
+        None => fvm_ipld_encoding::from_slice(CBOR_NULL),
+    }
+    .map_err(serialization)?;
+
+    let ret = method(params).map_err(Into::into)?;
+
+    let ret = IpldBlock::serialize(CBOR, &ret).map_err(serialization)?;
+    if ret.data == CBOR_NULL {
+        return Ok(NO_DATA_BLOCK_ID);
+    }
+    ipld::put_block(ret.codec, &ret.data).map_err(|e| {
+        ActorError::new(
+            ExitCode::USR_SERIALIZATION,
+            format!("failed to store return value: {e}"),
+        )
+    })
+}
+
+/// The error returned when invoking a method number the actor doesn't handle.
+#[doc(hidden)]
+pub fn unhandled_method(method: MethodNum) -> ActorError {
+    ActorError::new(
+        ExitCode::USR_UNHANDLED_MESSAGE,
+        format!("unhandled method {method}"),
+    )
+}
+
+/// Generates the actor's `invoke` entrypoint, dispatching each method number to a typed method.
+///
+/// Methods take their (CBOR-decoded) parameters, and return a `Result` whose value is
+/// CBOR-encoded as the return value, and whose error converts into an [`ActorError`] to abort
+/// with. Methods without parameters take `()`, and methods returning `()` or `None` return no
+/// data. Invoking any other method number aborts with `USR_UNHANDLED_MESSAGE`.
+///
+/// ```ignore
+/// fn constructor(params: ConstructorParams) -> Result<(), ActorError> { ... }
+/// fn balance(_: ()) -> Result<TokenAmount, ActorError> { ... }
+///
+/// fvm_sdk::dispatch! {
+///     1 => constructor,
+///     2 => balance,
+/// }
+/// ```
+#[macro_export]
+macro_rules! dispatch {
+    ($($number:literal => $method:path),* $(,)?) => {
+        #[no_mangle]
+        pub fn invoke(params: u32) -> u32 {
+            $crate::dispatch::invoke(params, |method, params| match method {
+                $($number => $crate::dispatch::call_method(params, $method),)*
+                _ => Err($crate::dispatch::unhandled_method(method)),
+            })
+        }
+    };
+}
+
+#[cfg(all(test, mock_syscalls))]
+mod test {
+    use fvm_ipld_encoding::ipld_block::IpldBlock;
+    use fvm_shared::error::ExitCode;
+
+    use crate::error::ActorError;
+    use crate::testing::{Exit, MockRuntime};
+    use crate::NO_DATA_BLOCK_ID;
+
+    fn double(x: u64) -> Result<u64, ActorError> {
+        Ok(x * 2)
+    }
+
+    fn nothing(_: ()) -> Result<(), ActorError> {
+        Ok(())
+    }
+
+    fn fail(_: ()) -> Result<(), ActorError> {
+        Err(ActorError::new(ExitCode::USR_FORBIDDEN, "nope"))
+    }
+
+    crate::dispatch! {
+        2 => double,
+        3 => nothing,
+        4 => fail,
+    }
+
+    #[test]
+    fn dispatches_methods() {
+        let mut rt = MockRuntime::new(1000);
+
+        rt.method_number = 2;
+        let params = rt.put_block(IpldBlock::serialize_cbor(&21u64).unwrap().unwrap());
+        let ret = rt.call(|| invoke(params)).unwrap();
+        assert_eq!(rt.block(ret).unwrap().deserialize::<u64>().unwrap(), 42);
+
+        // Parameters that don't decode abort with USR_SERIALIZATION.
+        let params = rt.put_block(IpldBlock::serialize_cbor("x").unwrap().unwrap());
+        let exit = rt.call(|| invoke(params)).unwrap_err();
+        assert_eq!(exit.code, ExitCode::USR_SERIALIZATION);
+
+        // Methods returning `()` take no parameters, and return no data.
+        rt.method_number = 3;
+        assert_eq!(
+            rt.call(|| invoke(NO_DATA_BLOCK_ID)).unwrap(),
+            NO_DATA_BLOCK_ID
+        );
+
+        rt.method_number = 4;
+        assert_eq!(
+            rt.call(|| invoke(NO_DATA_BLOCK_ID)).unwrap_err(),
+            Exit {
+                code: ExitCode::USR_FORBIDDEN,
+                data: None,
+                message: Some("nope".into()),
+            }
+        );
+
+        rt.method_number = 5;
+        let exit = rt.call(|| invoke(NO_DATA_BLOCK_ID)).unwrap_err();
+        assert_eq!(exit.code, ExitCode::USR_UNHANDLED_MESSAGE);
+    }
+}
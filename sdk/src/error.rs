@@ -29,3 +29,107 @@ pub enum EpochBoundsError {
     #[error("the requested epoch exceeds the maximum lookback")]
     ExceedsLookback,
 }
+
+/// Returned when drawing randomness for a [`crate::rand::RandomnessRequest`] fails.
+#[derive(Copy, Clone, Debug, Error, Eq, PartialEq)]
+pub enum RandomnessError {
+    #[error("randomness isn't available before epoch {ready_at}")]
+    TooEarly {
+        ready_at: fvm_shared::clock::ChainEpoch,
+    },
+    #[error("randomness expired after epoch {expired_at}")]
+    Expired {
+        expired_at: fvm_shared::clock::ChainEpoch,
+    },
+    #[error("randomness unavailable: {0}")]
+    Unavailable(fvm_shared::error::ErrorNumber),
+}
+
+/// Returned by [`crate::send::Response::require_ok`] when the receiving actor exited with a
+/// non-zero exit code.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+#[error("send failed with exit code {exit_code}")]
+pub struct SendFailure {
+    /// The exit code returned by the receiving actor.
+    pub exit_code: fvm_shared::error::ExitCode,
+    /// The return value (usually an error payload) returned by the receiving actor.
+    pub return_data: Option<fvm_ipld_encoding::ipld_block::IpldBlock>,
+}
+
+#[derive(Debug, Error)]
+pub enum ReturnError {
+    #[error(transparent)]
+    Failed(#[from] SendFailure),
+    #[error("failed to decode return value: {0}")]
+    Decode(#[from] fvm_ipld_encoding::Error),
+}
+
+/// Returned when loading or saving an actor's typed state with [`crate::state`] fails.
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error(transparent)]
+    Read(#[from] StateReadError),
+    #[error(transparent)]
+    Update(#[from] StateUpdateError),
+    #[error("failed to access the state block: {0}")]
+    Block(fvm_shared::error::ErrorNumber),
+    #[error("failed to encode or decode the state: {0}")]
+    Codec(#[from] fvm_ipld_encoding::Error),
+}
+
+/// Returned by [`crate::send::call`] when sending a message or decoding its result fails.
+#[derive(Debug, Error)]
+pub enum CallError {
+    #[error("failed to encode parameters: {0}")]
+    Params(fvm_ipld_encoding::Error),
+    #[error("send failed: {0}")]
+    Send(fvm_shared::error::ErrorNumber),
+    #[error(transparent)]
+    Return(#[from] ReturnError),
+}
+
+/// An error returned by an actor method dispatched with [`dispatch!`](crate::dispatch!), aborting
+/// the invocation with the given exit code and message.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+#[error("{message} (exit code {exit_code})")]
+pub struct ActorError {
+    pub exit_code: fvm_shared::error::ExitCode,
+    pub message: String,
+}
+
+impl ActorError {
+    pub fn new(exit_code: fvm_shared::error::ExitCode, message: impl Into<String>) -> Self {
+        ActorError {
+            exit_code,
+            message: message.into(),
+        }
+    }
+
+    pub fn illegal_argument(message: impl Into<String>) -> Self {
+        Self::new(fvm_shared::error::ExitCode::USR_ILLEGAL_ARGUMENT, message)
+    }
+
+    pub fn illegal_state(message: impl Into<String>) -> Self {
+        Self::new(fvm_shared::error::ExitCode::USR_ILLEGAL_STATE, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(fvm_shared::error::ExitCode::USR_FORBIDDEN, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(fvm_shared::error::ExitCode::USR_NOT_FOUND, message)
+    }
+}
+
+impl From<StateError> for ActorError {
+    fn from(e: StateError) -> Self {
+        Self::illegal_state(e.to_string())
+    }
+}
+
+impl From<CallError> for ActorError {
+    fn from(e: CallError) -> Self {
+        Self::new(fvm_shared::error::ExitCode::USR_UNSPECIFIED, e.to_string())
+    }
+}
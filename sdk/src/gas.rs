@@ -1,6 +1,8 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-use crate::sys;
+use fvm_shared::sys::PricedOperation;
+
+use crate::{sys, SyscallResult};
 
 /// Charge gas for the operation identified by name.
 pub fn charge(name: &str, compute: u64) {
@@ -12,3 +14,10 @@ pub fn charge(name: &str, compute: u64) {
 pub fn available() -> u64 {
     unsafe { sys::gas::available() }.expect("failed to check available gas")
 }
+
+/// Returns the gas an operation of `size` bytes would currently cost, so actors can adapt to the
+/// network's price list instead of hard-coding it. See [`PricedOperation`] for the meaning of
+/// `param`; it's ignored by operations that don't take one.
+pub fn price(op: PricedOperation, size: u64, param: u64) -> SyscallResult<u64> {
+    unsafe { sys::gas::price(op as u32, size, param) }
+}
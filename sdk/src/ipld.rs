@@ -2,7 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use cid::multihash::Multihash;
 use cid::Cid;
+use fvm_ipld_encoding::de::DeserializeOwned;
+use fvm_ipld_encoding::ser::Serialize;
+use fvm_ipld_encoding::DAG_CBOR;
+use fvm_shared::crypto::hash::SupportedHashes;
 use fvm_shared::error::ErrorNumber;
+use fvm_shared::sys::out::ipld::IpldStat;
 use fvm_shared::MAX_CID_LEN;
 
 use crate::{sys, SyscallResult};
@@ -40,6 +45,19 @@ pub fn put(mh_code: u64, mh_size: u32, codec: u64, data: &[u8]) -> SyscallResult
     }
 }
 
+/// Store `value` as a DAG-CBOR block, linked with a blake2b-256 CID (the usual format of actor
+/// state). Fails with [`ErrorNumber::Serialization`] if `value` can't be encoded.
+pub fn put_cbor<T: Serialize + ?Sized>(value: &T) -> SyscallResult<Cid> {
+    let data = fvm_ipld_encoding::to_vec(value).map_err(|_| ErrorNumber::Serialization)?;
+    put(SupportedHashes::Blake2b256 as u64, 32, DAG_CBOR, &data)
+}
+
+/// Get a block (see [`get`]) and decode it as CBOR. Fails with [`ErrorNumber::Serialization`] if
+/// the block can't be decoded as `T`.
+pub fn get_cbor<T: DeserializeOwned>(cid: &Cid) -> SyscallResult<T> {
+    fvm_ipld_encoding::from_slice(&get(cid)?).map_err(|_| ErrorNumber::Serialization)
+}
+
 /// Get a block. It's valid to call this on:
 ///
 /// 1. All CIDs returned by prior calls to `get_root`...
@@ -101,6 +119,11 @@ pub fn get_block(id: fvm_shared::sys::BlockId, size_hint: Option<u32>) -> Syscal
     Ok(buf)
 }
 
+/// Returns the codec and size of the block referenced by BlockId.
+pub fn stat(id: fvm_shared::sys::BlockId) -> SyscallResult<IpldStat> {
+    unsafe { sys::ipld::block_stat(id) }
+}
+
 /// Writes the supplied block and returns the BlockId.
 pub fn put_block(
     codec: fvm_shared::sys::Codec,
@@ -108,3 +131,33 @@ pub fn put_block(
 ) -> SyscallResult<fvm_shared::sys::BlockId> {
     unsafe { sys::ipld::block_create(codec, data.as_ptr(), data.len() as u32) }
 }
+
+#[cfg(all(test, mock_syscalls))]
+mod test {
+    use fvm_ipld_encoding::DAG_CBOR;
+    use fvm_shared::error::ErrorNumber;
+
+    use crate::testing::MockRuntime;
+
+    #[test]
+    fn cbor_blocks() {
+        let mut rt = MockRuntime::new(1000);
+        let cid = rt
+            .call(|| {
+                let cid = super::put_cbor(&(1u64, "two")).unwrap();
+                assert_eq!(super::get_cbor::<(u64, String)>(&cid).unwrap().1, "two");
+                assert_eq!(
+                    super::get_cbor::<u64>(&cid),
+                    Err(ErrorNumber::Serialization)
+                );
+
+                let id = super::put_block(DAG_CBOR, &[0xf6]).unwrap();
+                let stat = super::stat(id).unwrap();
+                assert_eq!((stat.codec, stat.size), (DAG_CBOR, 1));
+                cid
+            })
+            .unwrap();
+        assert_eq!(cid.codec(), DAG_CBOR);
+        assert!(rt.store.contains_key(&cid));
+    }
+}
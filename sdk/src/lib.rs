@@ -1,18 +1,24 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+pub mod abi;
 pub mod actor;
 pub mod crypto;
 pub mod debug;
+pub mod dispatch;
 pub mod error;
 pub mod event;
 pub mod gas;
 pub mod ipld;
 pub mod message;
+pub mod mmr;
 pub mod network;
 pub mod rand;
 pub mod send;
 pub mod sself;
+pub mod state;
 pub mod sys;
+#[cfg(mock_syscalls)]
+pub mod testing;
 pub mod vm;
 
 /// BlockID representing nil parameters or return data.
@@ -20,6 +26,35 @@ pub const NO_DATA_BLOCK_ID: u32 = 0;
 
 // TODO: provide a custom panic handler?
 
+/// A syscall result that doesn't change during an invocation (e.g., the message context), looked
+/// up on first use and memoized.
+///
+/// Under the [mock runtime](crate::testing), whose context changes between calls, it's looked up
+/// every time instead.
+pub(crate) struct Memoized<T> {
+    lookup: fn() -> T,
+    #[cfg(not(mock_syscalls))]
+    value: std::sync::OnceLock<T>,
+}
+
+impl<T: Copy> Memoized<T> {
+    pub(crate) const fn new(lookup: fn() -> T) -> Self {
+        Memoized {
+            lookup,
+            #[cfg(not(mock_syscalls))]
+            value: std::sync::OnceLock::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn get(&self) -> T {
+        #[cfg(not(mock_syscalls))]
+        return *self.value.get_or_init(self.lookup);
+        #[cfg(mock_syscalls)]
+        return (self.lookup)();
+    }
+}
+
 #[inline]
 pub(crate) fn status_code_to_bool(code: i32) -> bool {
     code == 0
@@ -47,6 +82,33 @@ pub fn initialize() {
     vm::set_panic_handler();
 }
 
+/// Reads a variable-length syscall output, following the FVM's output buffer convention: the
+/// syscall either writes its entire output and returns its length, or fails with
+/// [`BufferTooSmall`](fvm_shared::error::ErrorNumber::BufferTooSmall) without writing anything.
+///
+/// `fill` is called with a buffer of `initial_len` bytes, doubled (up to `max_len` bytes) each
+/// time the output doesn't fit, and must pass the buffer to the syscall. Returns the output.
+pub fn probe_and_fill(
+    initial_len: usize,
+    max_len: usize,
+    mut fill: impl FnMut(&mut [u8]) -> SyscallResult<u32>,
+) -> SyscallResult<Vec<u8>> {
+    let mut buf = vec![0u8; initial_len.min(max_len)];
+    loop {
+        match fill(&mut buf) {
+            Ok(len) => {
+                buf.truncate(len as usize);
+                return Ok(buf);
+            }
+            Err(fvm_shared::error::ErrorNumber::BufferTooSmall) if buf.len() < max_len => {
+                let len = (buf.len() * 2).clamp(1, max_len);
+                buf.resize(len, 0);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 fn build_response(send: fvm_shared::sys::out::send::Send) -> SyscallResult<fvm_shared::Response> {
     let exit_code = fvm_shared::error::ExitCode::new(send.exit_code);
     let return_data = if send.return_id == NO_DATA_BLOCK_ID {
@@ -73,3 +135,14 @@ fn build_response(send: fvm_shared::sys::out::send::Send) -> SyscallResult<fvm_s
         return_data,
     })
 }
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn mock_syscalls_cfg() {
+        // Set by the build script: syscalls are mocked natively with the `testing` feature, and
+        // always imported from the FVM in Wasm.
+        let expected = cfg!(all(feature = "testing", not(target_arch = "wasm32")));
+        assert_eq!(cfg!(mock_syscalls), expected);
+    }
+}
@@ -2,56 +2,98 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::convert::TryInto;
 
+use fvm_ipld_encoding::de::DeserializeOwned;
 use fvm_ipld_encoding::ipld_block::IpldBlock;
 use fvm_shared::econ::TokenAmount;
-use fvm_shared::sys::out::vm::MessageContext;
+use fvm_shared::error::ErrorNumber;
 use fvm_shared::sys::BlockId;
 use fvm_shared::{ActorID, MethodNum};
 
-use crate::{sys, SyscallResult, NO_DATA_BLOCK_ID};
+use crate::{sys, Memoized, SyscallResult, NO_DATA_BLOCK_ID};
 
-lazy_static::lazy_static! {
-    pub(crate) static ref MESSAGE_CONTEXT: MessageContext = {
-        unsafe {
-            sys::vm::message_context().expect("failed to lookup message context")
-        }
-    };
+pub(crate) static MESSAGE_CONTEXT: Memoized<fvm_shared::sys::out::vm::MessageContext> =
+    Memoized::new(|| unsafe {
+        sys::vm::message_context().expect("failed to lookup message context")
+    });
+
+/// The context of the message being executed. See [`context`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageContext {
+    /// The origin of the (explicit) message.
+    pub origin: ActorID,
+    /// The nonce of the (explicit) message.
+    pub nonce: u64,
+    /// The caller of this actor.
+    pub caller: ActorID,
+    /// This actor.
+    pub receiver: ActorID,
+    /// The method number invoked on this actor.
+    pub method_number: MethodNum,
+    /// The value received from the caller.
+    pub value_received: TokenAmount,
+    /// The execution gas premium.
+    pub gas_premium: TokenAmount,
+    /// Whether the invocation is read-only (see [`vm::read_only`](crate::vm::read_only)).
+    pub read_only: bool,
+}
+
+/// Returns the context of the message being executed.
+pub fn context() -> MessageContext {
+    let ctx = MESSAGE_CONTEXT.get();
+    MessageContext {
+        origin: ctx.origin,
+        nonce: ctx.nonce,
+        caller: ctx.caller,
+        receiver: ctx.receiver,
+        method_number: ctx.method_number,
+        value_received: ctx.value_received.into(),
+        gas_premium: ctx.gas_premium.into(),
+        read_only: ctx.flags.read_only(),
+    }
 }
 
 /// Returns the nonce from the (explicit) message.
 #[inline(always)]
 pub fn nonce() -> u64 {
-    MESSAGE_CONTEXT.nonce
+    MESSAGE_CONTEXT.get().nonce
 }
 
 /// Returns the ID address of the caller.
 #[inline(always)]
 pub fn caller() -> ActorID {
-    MESSAGE_CONTEXT.caller
+    MESSAGE_CONTEXT.get().caller
+}
+
+/// Returns true if the caller has a delegated (f4) address under one of the given namespaces (e.g.,
+/// the EAM's ID, to accept calls from any EVM contract or Ethereum account).
+pub fn caller_in_namespace(allowed_namespaces: &[ActorID]) -> bool {
+    crate::actor::lookup_delegated_namespace(caller())
+        .map_or(false, |ns| allowed_namespaces.contains(&ns))
 }
 
 /// Returns the ID address of the origin
 #[inline(always)]
 pub fn origin() -> ActorID {
-    MESSAGE_CONTEXT.origin
+    MESSAGE_CONTEXT.get().origin
 }
 
 /// Returns the ID address of the actor.
 #[inline(always)]
 pub fn receiver() -> ActorID {
-    MESSAGE_CONTEXT.receiver
+    MESSAGE_CONTEXT.get().receiver
 }
 
 /// Returns the message's method number.
 #[inline(always)]
 pub fn method_number() -> MethodNum {
-    MESSAGE_CONTEXT.method_number
+    MESSAGE_CONTEXT.get().method_number
 }
 
 /// Returns the value received from the caller in AttoFIL.
 #[inline(always)]
 pub fn value_received() -> TokenAmount {
     MESSAGE_CONTEXT
+        .get()
         .value_received
         .try_into()
         .expect("invalid bigint")
@@ -60,6 +102,7 @@ pub fn value_received() -> TokenAmount {
 /// Returns the execution gas premium
 pub fn gas_premium() -> TokenAmount {
     MESSAGE_CONTEXT
+        .get()
         .gas_premium
         .try_into()
         .expect("invalid bigint")
@@ -70,11 +113,93 @@ pub fn params_raw(id: BlockId) -> SyscallResult<Option<IpldBlock>> {
     if id == NO_DATA_BLOCK_ID {
         return Ok(None);
     }
-    unsafe {
-        let fvm_shared::sys::out::ipld::IpldStat { codec, size } = sys::ipld::block_stat(id)?;
-        Ok(Some(IpldBlock {
-            codec,
-            data: crate::ipld::get_block(id, Some(size))?,
-        }))
+    let fvm_shared::sys::out::ipld::IpldStat { codec, size } = crate::ipld::stat(id)?;
+    Ok(Some(IpldBlock {
+        codec,
+        data: crate::ipld::get_block(id, Some(size))?,
+    }))
+}
+
+/// Returns the message parameters decoded as `T`, or `None` if there are no parameters. Fails with
+/// [`ErrorNumber::Serialization`] if they can't be decoded.
+pub fn params<T: DeserializeOwned>(id: BlockId) -> SyscallResult<Option<T>> {
+    params_raw(id)?
+        .map(|block| block.deserialize())
+        .transpose()
+        .map_err(|_| ErrorNumber::Serialization)
+}
+
+#[cfg(all(test, mock_syscalls))]
+mod test {
+    use fvm_ipld_encoding::ipld_block::IpldBlock;
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::error::ErrorNumber;
+
+    use super::{caller_in_namespace, context, params, MessageContext};
+    use crate::testing::MockRuntime;
+    use crate::NO_DATA_BLOCK_ID;
+
+    #[test]
+    fn message_context() {
+        let mut rt = MockRuntime::new(1000);
+        rt.caller = 100;
+        rt.origin = 101;
+        rt.nonce = 3;
+        rt.method_number = 2;
+        rt.value_received = TokenAmount::from_atto(10);
+        rt.gas_premium = TokenAmount::from_atto(1);
+        rt.read_only = true;
+        assert_eq!(
+            rt.call(context).unwrap(),
+            MessageContext {
+                origin: 101,
+                nonce: 3,
+                caller: 100,
+                receiver: 1000,
+                method_number: 2,
+                value_received: TokenAmount::from_atto(10),
+                gas_premium: TokenAmount::from_atto(1),
+                read_only: true,
+            }
+        );
+    }
+
+    #[test]
+    fn decode_params() {
+        let mut rt = MockRuntime::new(1000);
+        let id = rt.put_block(IpldBlock::serialize_cbor(&7u64).unwrap().unwrap());
+        rt.call(|| {
+            assert_eq!(params::<u64>(id), Ok(Some(7)));
+            assert_eq!(params::<String>(id), Err(ErrorNumber::Serialization));
+            assert_eq!(params::<u64>(NO_DATA_BLOCK_ID), Ok(None));
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn caller_namespace() {
+        const EAM: u64 = 10;
+        let mut rt = MockRuntime::new(1000);
+        rt.delegated_addresses
+            .insert(100, Address::new_delegated(EAM, &[1; 20]).unwrap());
+        rt.delegated_addresses
+            .insert(101, Address::new_delegated(32, &[1; 20]).unwrap());
+
+        rt.caller = 100;
+        rt.call(|| {
+            assert!(caller_in_namespace(&[EAM]));
+            assert!(caller_in_namespace(&[32, EAM]));
+            assert!(!caller_in_namespace(&[32]));
+            assert!(!caller_in_namespace(&[]));
+        })
+        .unwrap();
+
+        rt.caller = 101;
+        rt.call(|| assert!(!caller_in_namespace(&[EAM]))).unwrap();
+
+        // Callers without an f4 address aren't in any namespace.
+        rt.caller = 102;
+        rt.call(|| assert!(!caller_in_namespace(&[EAM]))).unwrap();
     }
 }
@@ -0,0 +1,324 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! A rolling commitment over append-only data (a Merkle mountain range).
+//!
+//! Appending to a [`MerkleMountainRange`] writes the new entry and at most `log2(n)` small nodes,
+//! instead of rewriting a path through a large AMT. The structure itself (the entry count and the
+//! peaks) is small enough to live directly in the actor's state, and is the commitment: proofs
+//! that an entry was appended are generated off-chain from the actor's state and checked against
+//! it with [`MerkleMountainRange::verify`].
+//!
+//! Entries are stored as raw blocks and inner nodes as DAG-CBOR `[left, right]` pairs, all hashed
+//! with blake2b-256.
+use cid::Cid;
+use fvm_ipld_encoding::de::{Deserialize, Deserializer};
+use fvm_ipld_encoding::ser::{Serialize, Serializer};
+use fvm_ipld_encoding::{from_slice, to_vec, DAG_CBOR, IPLD_RAW};
+use fvm_shared::crypto::hash::SupportedHashes;
+use fvm_shared::error::ErrorNumber;
+
+use crate::{ipld, SyscallResult};
+
+const BLAKE2B_256: u64 = SupportedHashes::Blake2b256 as u64;
+
+/// An append-only Merkle mountain range. Encoded as a `[size, peaks]` tuple.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MerkleMountainRange {
+    /// The number of entries appended so far.
+    pub size: u64,
+    /// The roots of the perfect binary trees making up the range, from the tallest (oldest
+    /// entries) to the shortest. There's one tree per bit set in `size`.
+    pub peaks: Vec<Cid>,
+}
+
+/// A proof that an entry was appended to a [`MerkleMountainRange`]. Encoded as an
+/// `[index, siblings]` tuple.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MmrProof {
+    /// The index of the entry.
+    pub index: u64,
+    /// The siblings of the nodes on the path from the entry to its peak, bottom-up.
+    pub siblings: Vec<Cid>,
+}
+
+impl Serialize for MerkleMountainRange {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        (&self.size, &self.peaks).serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for MerkleMountainRange {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (size, peaks) = Deserialize::deserialize(deserializer)?;
+        Ok(Self { size, peaks })
+    }
+}
+
+impl Serialize for MmrProof {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        (&self.index, &self.siblings).serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for MmrProof {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (index, siblings) = Deserialize::deserialize(deserializer)?;
+        Ok(Self { index, siblings })
+    }
+}
+
+impl MerkleMountainRange {
+    /// Creates an empty range.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an entry, returning its index.
+    pub fn append(&mut self, entry: &[u8]) -> SyscallResult<u64> {
+        let index = self.size;
+        let mut node = ipld::put(BLAKE2B_256, 32, IPLD_RAW, entry)?;
+        // Merge the new entry with every peak of the same height (one per trailing set bit).
+        let mut carry = self.size;
+        while carry & 1 == 1 {
+            let left = self.peaks.pop().expect("peaks match the size");
+            let pair = to_vec(&(left, node)).map_err(|_| ErrorNumber::Serialization)?;
+            node = ipld::put(BLAKE2B_256, 32, DAG_CBOR, &pair)?;
+            carry >>= 1;
+        }
+        self.peaks.push(node);
+        self.size += 1;
+        Ok(index)
+    }
+
+    /// Returns the peak containing the entry at `index`, its height, and the position of the entry
+    /// within it, or `None` if there's no such entry.
+    fn locate(&self, index: u64) -> Option<(usize, u32, u64)> {
+        let mut start = 0;
+        let mut peak = 0;
+        for height in (0..u64::BITS).rev() {
+            if self.size & (1 << height) == 0 {
+                continue;
+            }
+            let count = 1 << height;
+            if index < start + count {
+                return Some((peak, height, index - start));
+            }
+            start += count;
+            peak += 1;
+        }
+        None
+    }
+
+    /// Generates a proof for the entry at `index`, loading inner nodes with `get` (e.g.,
+    /// [`ipld::get`] from within an actor, or a blockstore off-chain). Returns `Ok(None)` if
+    /// there's no such entry.
+    pub fn prove<E, F>(&self, index: u64, mut get: F) -> Result<Option<MmrProof>, E>
+    where
+        F: FnMut(&Cid) -> Result<Vec<u8>, E>,
+        E: From<fvm_ipld_encoding::Error>,
+    {
+        let Some((peak, height, position)) = self.locate(index) else {
+            return Ok(None);
+        };
+        let mut node = self.peaks[peak];
+        let mut siblings = Vec::with_capacity(height as usize);
+        // Walk down from the peak, following the bits of the position from the most significant.
+        for level in (0..height).rev() {
+            let (left, right): (Cid, Cid) = from_slice(&get(&node)?)?;
+            if position & (1 << level) == 0 {
+                siblings.push(right);
+                node = left;
+            } else {
+                siblings.push(left);
+                node = right;
+            }
+        }
+        siblings.reverse();
+        Ok(Some(MmrProof { index, siblings }))
+    }
+
+    /// Checks that `entry` was appended at `proof.index`. `cid_of` must compute the blake2b-256 CID
+    /// of a block with the given codec and data.
+    pub fn verify<F>(&self, entry: &[u8], proof: &MmrProof, mut cid_of: F) -> bool
+    where
+        F: FnMut(u64, &[u8]) -> Cid,
+    {
+        let Some((peak, height, position)) = self.locate(proof.index) else {
+            return false;
+        };
+        if proof.siblings.len() != height as usize {
+            return false;
+        }
+        let mut node = cid_of(IPLD_RAW, entry);
+        for (level, sibling) in proof.siblings.iter().enumerate() {
+            let pair = if position & (1 << level) == 0 {
+                to_vec(&(node, sibling))
+            } else {
+                to_vec(&(sibling, node))
+            };
+            let Ok(pair) = pair else { return false };
+            node = cid_of(DAG_CBOR, &pair);
+        }
+        node == self.peaks[peak]
+    }
+}
+
+#[cfg(all(test, mock_syscalls))]
+mod test {
+    use cid::multihash::{Code, MultihashDigest};
+    use cid::Cid;
+
+    use super::{MerkleMountainRange, MmrProof};
+    use crate::testing::MockRuntime;
+
+    fn cid_of(codec: u64, data: &[u8]) -> Cid {
+        Cid::new_v1(codec, Code::Blake2b256.digest(data))
+    }
+
+    fn entry(i: u64) -> Vec<u8> {
+        format!("entry {i}").into_bytes()
+    }
+
+    /// Appends `count` entries to a new range, returning the runtime holding its nodes.
+    fn build(count: u64) -> (MockRuntime, MerkleMountainRange) {
+        let mut rt = MockRuntime::new(1000);
+        let mmr = rt
+            .call(|| {
+                let mut mmr = MerkleMountainRange::new();
+                for i in 0..count {
+                    assert_eq!(mmr.append(&entry(i)).unwrap(), i);
+                }
+                mmr
+            })
+            .unwrap();
+        (rt, mmr)
+    }
+
+    fn prove(rt: &MockRuntime, mmr: &MerkleMountainRange, index: u64) -> Option<MmrProof> {
+        mmr.prove(index, |k| {
+            rt.store
+                .get(k)
+                .cloned()
+                .ok_or_else(|| fvm_ipld_encoding::Error {
+                    description: format!("missing node {k}"),
+                    protocol: fvm_ipld_encoding::CodecProtocol::Cbor,
+                })
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn append() {
+        let (_, mmr) = build(0);
+        assert_eq!(mmr, MerkleMountainRange::default());
+
+        // One peak per bit set in the size.
+        for count in [1, 2, 3, 7, 8, 13] {
+            let (_, mmr) = build(count);
+            assert_eq!(mmr.size, count);
+            assert_eq!(mmr.peaks.len(), count.count_ones() as usize);
+        }
+
+        // A single entry is its own peak.
+        let (_, mmr) = build(1);
+        assert_eq!(
+            mmr.peaks,
+            vec![cid_of(fvm_ipld_encoding::IPLD_RAW, &entry(0))]
+        );
+    }
+
+    #[test]
+    fn root() {
+        // The range commits to the entries and their order.
+        let (_, a) = build(5);
+        let (_, b) = build(5);
+        assert_eq!(a, b);
+
+        let mut rt = MockRuntime::new(1000);
+        let reordered = rt
+            .call(|| {
+                let mut mmr = MerkleMountainRange::new();
+                for i in [1, 0, 2, 3, 4] {
+                    mmr.append(&entry(i)).unwrap();
+                }
+                mmr
+            })
+            .unwrap();
+        assert_eq!(reordered.size, a.size);
+        assert_ne!(reordered.peaks, a.peaks);
+
+        // Encodes as a [size, peaks] tuple.
+        let encoded = fvm_ipld_encoding::to_vec(&a).unwrap();
+        assert_eq!(
+            encoded,
+            fvm_ipld_encoding::to_vec(&(5u64, &a.peaks)).unwrap()
+        );
+        assert_eq!(
+            fvm_ipld_encoding::from_slice::<MerkleMountainRange>(&encoded).unwrap(),
+            a
+        );
+    }
+
+    #[test]
+    fn proofs() {
+        let (rt, mmr) = build(13);
+        for i in 0..mmr.size {
+            let proof = prove(&rt, &mmr, i).unwrap();
+            assert_eq!(proof.index, i);
+            assert!(mmr.verify(&entry(i), &proof, cid_of), "entry {i}");
+        }
+        assert_eq!(prove(&rt, &mmr, 13), None);
+    }
+
+    #[test]
+    fn failing_proofs() {
+        let (rt, mmr) = build(13);
+        let proof = prove(&rt, &mmr, 5).unwrap();
+
+        // Wrong entry.
+        assert!(!mmr.verify(&entry(6), &proof, cid_of));
+
+        // Wrong index.
+        let moved = MmrProof {
+            index: 4,
+            ..proof.clone()
+        };
+        assert!(!mmr.verify(&entry(5), &moved, cid_of));
+
+        // Out of range.
+        let out_of_range = MmrProof {
+            index: 13,
+            ..proof.clone()
+        };
+        assert!(!mmr.verify(&entry(5), &out_of_range, cid_of));
+
+        // Tampered sibling.
+        let mut tampered = proof.clone();
+        tampered.siblings[1] = cid_of(fvm_ipld_encoding::IPLD_RAW, b"forged");
+        assert!(!mmr.verify(&entry(5), &tampered, cid_of));
+
+        // Missing sibling.
+        let mut truncated = proof.clone();
+        truncated.siblings.pop();
+        assert!(!mmr.verify(&entry(5), &truncated, cid_of));
+
+        // The last entry is its own peak in a range of 13 entries, but not in a range of 14.
+        let last = prove(&rt, &mmr, 12).unwrap();
+        assert!(last.siblings.is_empty());
+        assert!(mmr.verify(&entry(12), &last, cid_of));
+        let (_, longer) = build(14);
+        assert!(!longer.verify(&entry(12), &last, cid_of));
+    }
+}
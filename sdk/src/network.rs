@@ -5,35 +5,33 @@ use fvm_shared::chainid::ChainID;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ErrorNumber;
-use fvm_shared::sys::out::network::NetworkContext;
+use fvm_shared::sys::out::network::{EpochTiming, NetworkContext};
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::MAX_CID_LEN;
 
 use crate::error::EpochBoundsError;
-use crate::sys;
+use crate::{probe_and_fill, sys, Memoized};
 
-lazy_static::lazy_static! {
-    pub(crate) static ref NETWORK_CONTEXT: NetworkContext = {
-        unsafe {
-            sys::network::context().expect("failed to lookup network context")
-        }
-    };
-}
+pub(crate) static NETWORK_CONTEXT: Memoized<NetworkContext> =
+    Memoized::new(|| unsafe { sys::network::context().expect("failed to lookup network context") });
+static EPOCH_TIMING: Memoized<EpochTiming> = Memoized::new(|| unsafe {
+    sys::network::epoch_timing().expect("failed to lookup epoch timing")
+});
 
 pub fn chain_id() -> ChainID {
-    NETWORK_CONTEXT.chain_id.into()
+    NETWORK_CONTEXT.get().chain_id.into()
 }
 
 pub fn curr_epoch() -> ChainEpoch {
-    NETWORK_CONTEXT.epoch
+    NETWORK_CONTEXT.get().epoch
 }
 
 pub fn version() -> NetworkVersion {
-    NETWORK_CONTEXT.network_version
+    NETWORK_CONTEXT.get().network_version
 }
 
 pub fn base_fee() -> TokenAmount {
-    NETWORK_CONTEXT.base_fee.into()
+    NETWORK_CONTEXT.get().base_fee.into()
 }
 
 pub fn total_fil_circ_supply() -> TokenAmount {
@@ -46,20 +44,35 @@ pub fn total_fil_circ_supply() -> TokenAmount {
 
 /// Returns the current block time in seconds since the EPOCH.
 pub fn tipset_timestamp() -> u64 {
-    NETWORK_CONTEXT.timestamp
+    NETWORK_CONTEXT.get().timestamp
+}
+
+/// Returns the duration of an epoch, in seconds.
+pub fn epoch_duration_seconds() -> u64 {
+    EPOCH_TIMING.get().epoch_duration_seconds
+}
+
+/// Returns the expected number of blocks per epoch.
+pub fn blocks_per_epoch() -> u64 {
+    EPOCH_TIMING.get().blocks_per_epoch
 }
 
 /// Returns the tipset CID of the specified epoch, if available. Allows querying from now up to
 /// finality (900 epochs).
 pub fn tipset_cid(epoch: ChainEpoch) -> Result<Cid, EpochBoundsError> {
-    let mut buf = [0u8; MAX_CID_LEN];
-
-    unsafe {
-        match sys::network::tipset_cid(epoch, buf.as_mut_ptr(), MAX_CID_LEN as u32) {
-            Ok(len) => Ok(Cid::read_bytes(&buf[..len as usize]).expect("invalid cid")),
-            Err(ErrorNumber::IllegalArgument) => Err(EpochBoundsError::Invalid),
-            Err(ErrorNumber::LimitExceeded) => Err(EpochBoundsError::ExceedsLookback),
-            Err(other) => panic!("unexpected cid resolution failure: {}", other),
-        }
+    let res = probe_and_fill(MAX_CID_LEN, MAX_CID_LEN, |buf| unsafe {
+        sys::network::tipset_cid(epoch, buf.as_mut_ptr(), buf.len() as u32)
+    });
+    match res {
+        Ok(bytes) => Ok(Cid::read_bytes(bytes.as_slice()).expect("invalid cid")),
+        Err(ErrorNumber::IllegalArgument) => Err(EpochBoundsError::Invalid),
+        Err(ErrorNumber::LimitExceeded) => Err(EpochBoundsError::ExceedsLookback),
+        Err(other) => panic!("unexpected cid resolution failure: {}", other),
     }
 }
+
+/// Returns the fingerprint of the executing machine's consensus-relevant configuration (FVM
+/// version, network limits, price list, and builtin actor code CIDs).
+pub fn machine_fingerprint() -> [u8; 32] {
+    unsafe { sys::network::fingerprint().expect("failed to get machine fingerprint") }
+}
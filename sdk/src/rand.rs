@@ -1,9 +1,10 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use fvm_shared::clock::ChainEpoch;
-use fvm_shared::randomness::RANDOMNESS_LENGTH;
+use fvm_shared::randomness::{Randomness, MAX_RANDOMNESS_LENGTH, RANDOMNESS_LENGTH};
 
-use crate::{sys, SyscallResult};
+use crate::error::RandomnessError;
+use crate::{probe_and_fill, sys, SyscallResult};
 
 /// Gets 32 bytes of randomness from the ticket chain.
 /// The supplied output buffer must have at least 32 bytes of capacity.
@@ -20,3 +21,84 @@ pub fn get_chain_randomness(round: ChainEpoch) -> SyscallResult<[u8; RANDOMNESS_
 pub fn get_beacon_randomness(round: ChainEpoch) -> SyscallResult<[u8; RANDOMNESS_LENGTH]> {
     unsafe { sys::rand::get_beacon_randomness(round) }
 }
+
+/// Gets randomness from the beacon system, at the length used by the current network version.
+/// Unlike [`get_beacon_randomness`], this keeps working if a future beacon returns randomness of
+/// a length other than 32 bytes.
+pub fn get_beacon_randomness_bytes(round: ChainEpoch) -> SyscallResult<Randomness> {
+    let bytes = probe_and_fill(RANDOMNESS_LENGTH, MAX_RANDOMNESS_LENGTH, |buf| unsafe {
+        sys::rand::get_beacon_randomness_bytes(round, buf.as_mut_ptr(), buf.len() as u32)
+    })?;
+    Ok(Randomness(bytes))
+}
+
+/// Bounds on the age of the randomness an actor may draw, relative to the current epoch.
+///
+/// Actors that consume randomness (e.g., lotteries or oracles) should commit to a _future_ round
+/// when a request is made, and only draw randomness for that round once it's in the window:
+///
+/// - `min_lookback` epochs after the round, so the randomness can't change under a reorg shorter
+///   than that.
+/// - Until `max_lookback` epochs after the round, so requests expire (and the cost of looking
+///   the randomness up stays bounded).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RandomnessWindow {
+    pub min_lookback: ChainEpoch,
+    pub max_lookback: ChainEpoch,
+}
+
+impl RandomnessWindow {
+    /// Checks that randomness for `round` may be drawn at epoch `current`.
+    pub fn check(&self, current: ChainEpoch, round: ChainEpoch) -> Result<(), RandomnessError> {
+        let ready_at = round.saturating_add(self.min_lookback);
+        let expired_at = round.saturating_add(self.max_lookback);
+        if current < ready_at {
+            Err(RandomnessError::TooEarly { ready_at })
+        } else if current > expired_at {
+            Err(RandomnessError::Expired { expired_at })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A request for beacon randomness from a future round, fulfilled once the round is within the
+/// request's [`RandomnessWindow`]. Requests are plain data, meant to be stored in actor state
+/// between the request and its fulfillment.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RandomnessRequest {
+    /// The beacon round the randomness will be drawn from.
+    pub round: ChainEpoch,
+    pub window: RandomnessWindow,
+}
+
+impl RandomnessRequest {
+    /// Creates a request for randomness from `delay` epochs after the current epoch. The delay
+    /// should be positive, so the randomness isn't known when the request is made.
+    pub fn new(delay: ChainEpoch, window: RandomnessWindow) -> Self {
+        Self {
+            round: crate::network::curr_epoch().saturating_add(delay),
+            window,
+        }
+    }
+
+    /// Checks that the request may be fulfilled at the current epoch.
+    pub fn check(&self) -> Result<(), RandomnessError> {
+        self.window.check(crate::network::curr_epoch(), self.round)
+    }
+
+    /// Fulfills the request, returning the beacon randomness of the requested round mixed with
+    /// `entropy` (e.g., a request ID, so concurrent requests for the same round get distinct
+    /// randomness). Fails if the request may not be fulfilled at the current epoch.
+    ///
+    /// The result is always 32 bytes, whatever the length of the beacon's randomness.
+    pub fn fulfill(&self, entropy: &[u8]) -> Result<[u8; RANDOMNESS_LENGTH], RandomnessError> {
+        self.check()?;
+        let beacon =
+            get_beacon_randomness_bytes(self.round).map_err(RandomnessError::Unavailable)?;
+        let mut data = Vec::with_capacity(beacon.0.len() + entropy.len());
+        data.extend_from_slice(&beacon.0);
+        data.extend_from_slice(entropy);
+        Ok(crate::crypto::hash_blake2b(&data))
+    }
+}
@@ -2,15 +2,105 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::convert::TryInto;
 
+use fvm_ipld_encoding::de::DeserializeOwned;
 use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::ser::Serialize;
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
-use fvm_shared::error::ErrorNumber;
+use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::sys::SendFlags;
-use fvm_shared::{MethodNum, Response};
+use fvm_shared::MethodNum;
 
+use crate::error::{CallError, ReturnError, SendFailure};
 use crate::{build_response, sys, SyscallResult, NO_DATA_BLOCK_ID};
 
+/// The result of a send performed with [`invoke`].
+///
+/// Unlike [`fvm_shared::Response`], this also records the gas consumed by the call and offers
+/// combinators for handling non-zero exit codes explicitly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    /// The exit code returned by the receiving actor.
+    pub exit_code: ExitCode,
+    /// The value returned by the receiving actor, if any.
+    pub return_data: Option<IpldBlock>,
+    /// The gas consumed by the call, as reported by the FVM: the callee's execution, and the cost
+    /// of the send itself.
+    pub gas_used: u64,
+}
+
+impl Response {
+    /// Returns true if the receiving actor exited successfully.
+    pub fn is_success(&self) -> bool {
+        self.exit_code.is_success()
+    }
+
+    /// Returns the return value if the receiving actor exited successfully, or a [`SendFailure`]
+    /// carrying the exit code and return value otherwise.
+    pub fn require_ok(self) -> Result<Option<IpldBlock>, SendFailure> {
+        if self.is_success() {
+            Ok(self.return_data)
+        } else {
+            Err(SendFailure {
+                exit_code: self.exit_code,
+                return_data: self.return_data,
+            })
+        }
+    }
+
+    /// Requires a successful exit code and decodes the return value as `T`.
+    pub fn map_return<T: DeserializeOwned>(self) -> Result<Option<T>, ReturnError> {
+        match self.require_ok()? {
+            Some(block) => Ok(Some(block.deserialize()?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl From<Response> for fvm_shared::Response {
+    fn from(res: Response) -> Self {
+        fvm_shared::Response {
+            exit_code: res.exit_code,
+            return_data: res.return_data,
+        }
+    }
+}
+
+/// Sends a message to another actor, like [`send`], returning a typed [`Response`] that also
+/// records the gas used by the call.
+pub fn invoke(
+    to: &Address,
+    method: MethodNum,
+    params: Option<IpldBlock>,
+    value: TokenAmount,
+    gas_limit: Option<u64>,
+    flags: SendFlags,
+) -> SyscallResult<Response> {
+    let recipient = to.to_bytes();
+    let value: sys::TokenAmount = value
+        .try_into()
+        .map_err(|_| ErrorNumber::InsufficientFunds)?;
+    unsafe {
+        let params_id = create_params_block(params)?;
+        let res = sys::send::send_metered(
+            recipient.as_ptr(),
+            recipient.len() as u32,
+            method,
+            params_id,
+            value.hi,
+            value.lo,
+            gas_limit.unwrap_or(u64::MAX),
+            flags,
+        )?;
+        let response = build_response(res.send)?;
+        Ok(Response {
+            exit_code: response.exit_code,
+            return_data: response.return_data,
+            gas_used: res.gas_used,
+        })
+    }
+}
+
 /// Sends a message to another actor.
 pub fn send(
     to: &Address,
@@ -19,18 +109,13 @@ pub fn send(
     value: TokenAmount,
     gas_limit: Option<u64>,
     flags: SendFlags,
-) -> SyscallResult<Response> {
+) -> SyscallResult<fvm_shared::Response> {
     let recipient = to.to_bytes();
     let value: sys::TokenAmount = value
         .try_into()
         .map_err(|_| ErrorNumber::InsufficientFunds)?;
     unsafe {
-        // Insert parameters as a block. Nil parameters is represented as the
-        // NO_DATA_BLOCK_ID block ID in the FFI interface.
-        let params_id = match params {
-            Some(p) => sys::ipld::block_create(p.codec, p.data.as_ptr(), p.data.len() as u32)?,
-            None => NO_DATA_BLOCK_ID,
-        };
+        let params_id = create_params_block(params)?;
 
         // Perform the syscall to send the message.
         let send = sys::send::send(
@@ -47,3 +132,214 @@ pub fn send(
         build_response(send)
     }
 }
+
+/// Calls a method on another actor with CBOR-encoded `params`, returning its decoded return value.
+/// Fails if the send fails, or the receiving actor exits with a non-zero exit code (see
+/// [`SendFailure`]).
+///
+/// A missing return value is decoded like CBOR `null` (e.g., as `()` or `None`).
+pub fn call<T, R>(
+    to: &Address,
+    method: MethodNum,
+    params: &T,
+    value: TokenAmount,
+) -> Result<R, CallError>
+where
+    T: Serialize + ?Sized,
+    R: DeserializeOwned,
+{
+    call_with_flags(to, method, params, value, SendFlags::empty())
+}
+
+/// Like [`call`], with the given send flags (e.g., [`SendFlags::READ_ONLY`]).
+pub fn call_with_flags<T, R>(
+    to: &Address,
+    method: MethodNum,
+    params: &T,
+    value: TokenAmount,
+    flags: SendFlags,
+) -> Result<R, CallError>
+where
+    T: Serialize + ?Sized,
+    R: DeserializeOwned,
+{
+    let params = IpldBlock::serialize_cbor(params).map_err(CallError::Params)?;
+    let res = send(to, method, params, value, None, flags).map_err(CallError::Send)?;
+    let ret = match res.return_data {
+        Some(block) if res.exit_code.is_success() => block.deserialize(),
+        // CBOR null.
+        None if res.exit_code.is_success() => fvm_ipld_encoding::from_slice(&[0xf6]),
+        return_data => {
+            return Err(ReturnError::from(SendFailure {
+                exit_code: res.exit_code,
+                return_data,
+            })
+            .into())
+        }
+    };
+    ret.map_err(|e| ReturnError::from(e).into())
+}
+
+/// Inserts send parameters as a block. Nil parameters are represented as the NO_DATA_BLOCK_ID
+/// block ID in the FFI interface.
+fn create_params_block(params: Option<IpldBlock>) -> SyscallResult<u32> {
+    match params {
+        Some(p) => unsafe {
+            sys::ipld::block_create(p.codec, p.data.as_ptr(), p.data.len() as u32)
+        },
+        None => Ok(NO_DATA_BLOCK_ID),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use fvm_ipld_encoding::ipld_block::IpldBlock;
+    use fvm_shared::error::ExitCode;
+
+    use super::Response;
+    use crate::error::{ReturnError, SendFailure};
+
+    fn response(exit_code: ExitCode, ret: u64) -> Response {
+        Response {
+            exit_code,
+            return_data: IpldBlock::serialize_cbor(&ret).unwrap(),
+            gas_used: 10,
+        }
+    }
+
+    #[test]
+    fn combinators() {
+        let ok = response(ExitCode::OK, 5);
+        assert!(ok.is_success());
+        assert_eq!(
+            ok.clone().require_ok().unwrap(),
+            IpldBlock::serialize_cbor(&5u64).unwrap()
+        );
+        assert_eq!(ok.clone().map_return::<u64>().unwrap(), Some(5));
+        assert!(matches!(
+            ok.map_return::<String>(),
+            Err(ReturnError::Decode(_))
+        ));
+
+        // Non-zero exit codes keep the return value (e.g., an error payload).
+        let failed = response(ExitCode::USR_FORBIDDEN, 7);
+        assert!(!failed.is_success());
+        let failure = SendFailure {
+            exit_code: ExitCode::USR_FORBIDDEN,
+            return_data: IpldBlock::serialize_cbor(&7u64).unwrap(),
+        };
+        assert_eq!(failed.clone().require_ok().unwrap_err(), failure);
+        assert!(matches!(
+            failed.map_return::<u64>(),
+            Err(ReturnError::Failed(f)) if f == failure
+        ));
+
+        // No return value decodes to nothing.
+        let empty = Response {
+            exit_code: ExitCode::OK,
+            return_data: None,
+            gas_used: 0,
+        };
+        assert_eq!(empty.map_return::<u64>().unwrap(), None);
+    }
+
+    #[cfg(mock_syscalls)]
+    #[test]
+    fn invoke_reports_exit_code() {
+        use fvm_shared::address::Address;
+        use fvm_shared::econ::TokenAmount;
+        use fvm_shared::sys::SendFlags;
+
+        use crate::testing::{ExpectedSend, MockRuntime};
+
+        let to = Address::new_id(1001);
+        let mut rt = MockRuntime::new(1000);
+        rt.expect_send(ExpectedSend {
+            to,
+            method: 2,
+            params: None,
+            value: TokenAmount::default(),
+            response: Ok(fvm_shared::Response {
+                exit_code: ExitCode::USR_FORBIDDEN,
+                return_data: IpldBlock::serialize_cbor(&7u64).unwrap(),
+            }),
+        });
+
+        let res = rt
+            .call(|| {
+                super::invoke(
+                    &to,
+                    2,
+                    None,
+                    TokenAmount::default(),
+                    None,
+                    SendFlags::empty(),
+                )
+            })
+            .unwrap()
+            .unwrap();
+        // The mock runtime doesn't meter gas.
+        let expected = Response {
+            gas_used: 0,
+            ..response(ExitCode::USR_FORBIDDEN, 7)
+        };
+        assert_eq!(res, expected);
+        rt.verify();
+    }
+
+    #[cfg(mock_syscalls)]
+    #[test]
+    fn call_decodes_return() {
+        use fvm_shared::address::Address;
+        use fvm_shared::econ::TokenAmount;
+        use fvm_shared::error::ErrorNumber;
+
+        use crate::error::CallError;
+        use crate::testing::{ExpectedSend, MockRuntime};
+
+        let to = Address::new_id(1001);
+        let mut rt = MockRuntime::new(1000);
+        let mut expect = |response| {
+            rt.expect_send(ExpectedSend {
+                to,
+                method: 2,
+                params: IpldBlock::serialize_cbor("hi").unwrap(),
+                value: TokenAmount::default(),
+                response,
+            })
+        };
+        expect(Ok(fvm_shared::Response {
+            exit_code: ExitCode::OK,
+            return_data: IpldBlock::serialize_cbor(&7u64).unwrap(),
+        }));
+        expect(Ok(fvm_shared::Response {
+            exit_code: ExitCode::OK,
+            return_data: None,
+        }));
+        expect(Ok(fvm_shared::Response {
+            exit_code: ExitCode::USR_FORBIDDEN,
+            return_data: None,
+        }));
+        expect(Err(ErrorNumber::NotFound));
+
+        rt.call(|| {
+            let call = || super::call::<_, Option<u64>>(&to, 2, "hi", TokenAmount::default());
+            assert_eq!(call().unwrap(), Some(7));
+            // No return value decodes like CBOR null.
+            assert_eq!(call().unwrap(), None);
+            assert!(matches!(
+                call(),
+                Err(CallError::Return(ReturnError::Failed(SendFailure {
+                    exit_code: ExitCode::USR_FORBIDDEN,
+                    return_data: None,
+                })))
+            ));
+            assert!(matches!(
+                call(),
+                Err(CallError::Send(ErrorNumber::NotFound))
+            ));
+        })
+        .unwrap();
+        rt.verify();
+    }
+}
@@ -6,20 +6,20 @@ use fvm_shared::error::ErrorNumber;
 use fvm_shared::MAX_CID_LEN;
 
 use crate::error::{ActorDeleteError, StateReadError, StateUpdateError};
-use crate::sys;
+use crate::{probe_and_fill, sys};
 
 /// Get the IPLD root CID. Fails if the actor doesn't have state (before the first call to
 /// `set_root` and after actor deletion).
 pub fn root() -> Result<Cid, StateReadError> {
-    let mut buf = [0u8; MAX_CID_LEN];
-    unsafe {
-        let len = sys::sself::root(buf.as_mut_ptr(), buf.len() as u32).map_err(|e| match e {
-            ErrorNumber::IllegalOperation => StateReadError,
-            e => panic!("unexpected error from `self::root` syscall: {}", e),
-        })? as usize;
+    let buf = probe_and_fill(MAX_CID_LEN, MAX_CID_LEN, |buf| unsafe {
+        sys::sself::root(buf.as_mut_ptr(), buf.len() as u32)
+    })
+    .map_err(|e| match e {
+        ErrorNumber::IllegalOperation => StateReadError,
+        e => panic!("unexpected error from `self::root` syscall: {}", e),
+    })?;
 
-        Ok(Cid::read_bytes(&buf[..len]).expect("runtime returned an invalid CID"))
-    }
+    Ok(Cid::read_bytes(buf.as_slice()).expect("runtime returned an invalid CID"))
 }
 
 /// Set the actor's state-tree root.
@@ -0,0 +1,127 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Typed actor state, stored as a single DAG-CBOR block at the actor's state root.
+//!
+//! The state root block is cached when loaded and saved, so loading the state again (e.g., in
+//! [`mutate`]) doesn't read the block again, and saving unchanged state is a no-op.
+use std::cell::RefCell;
+
+use cid::Cid;
+use fvm_ipld_encoding::de::DeserializeOwned;
+use fvm_ipld_encoding::ser::Serialize;
+use fvm_ipld_encoding::DAG_CBOR;
+use fvm_shared::crypto::hash::SupportedHashes;
+
+use crate::error::StateError;
+use crate::{ipld, sself};
+
+thread_local! {
+    /// The last loaded or saved state root, and its block.
+    static ROOT_CACHE: RefCell<Option<(Cid, Vec<u8>)>> = RefCell::new(None);
+}
+
+/// Loads the actor's state. Fails if the actor has no state (see [`sself::root`]).
+pub fn load<T: DeserializeOwned>() -> Result<T, StateError> {
+    let root = sself::root()?;
+    let cached = ROOT_CACHE.with(|cache| match &*cache.borrow() {
+        Some((cid, block)) if *cid == root => {
+            Some(fvm_ipld_encoding::from_slice(block).map_err(StateError::from))
+        }
+        _ => None,
+    });
+    if let Some(state) = cached {
+        return state;
+    }
+
+    let block = ipld::get(&root).map_err(StateError::Block)?;
+    let state = fvm_ipld_encoding::from_slice(&block)?;
+    ROOT_CACHE.with(|cache| *cache.borrow_mut() = Some((root, block)));
+    Ok(state)
+}
+
+/// Saves `state` as the actor's state, returning the new state root. Does nothing if the state is
+/// unchanged since it was last loaded or saved.
+pub fn save<T: Serialize>(state: &T) -> Result<Cid, StateError> {
+    let block = fvm_ipld_encoding::to_vec(state)?;
+    let unchanged = ROOT_CACHE.with(|cache| match &*cache.borrow() {
+        Some((cid, cached)) if *cached == block => Some(*cid),
+        _ => None,
+    });
+    if let Some(root) = unchanged {
+        if sself::root().ok() == Some(root) {
+            return Ok(root);
+        }
+    }
+
+    let root = ipld::put(SupportedHashes::Blake2b256 as u64, 32, DAG_CBOR, &block)
+        .map_err(StateError::Block)?;
+    sself::set_root(&root)?;
+    ROOT_CACHE.with(|cache| *cache.borrow_mut() = Some((root, block)));
+    Ok(root)
+}
+
+/// Loads the actor's state, calls `f` to modify it, and saves it if `f` succeeds. The state is
+/// left untouched if `f` fails.
+///
+/// ```ignore
+/// let count = state::mutate(|st: &mut State| {
+///     st.count += 1;
+///     Ok::<_, StateError>(st.count)
+/// })?;
+/// ```
+pub fn mutate<T, R, E>(f: impl FnOnce(&mut T) -> Result<R, E>) -> Result<R, E>
+where
+    T: Serialize + DeserializeOwned,
+    E: From<StateError>,
+{
+    let mut state = load()?;
+    let ret = f(&mut state)?;
+    save(&state)?;
+    Ok(ret)
+}
+
+#[cfg(all(test, mock_syscalls))]
+mod test {
+    use fvm_shared::error::ErrorNumber;
+
+    use crate::error::StateError;
+    use crate::testing::MockRuntime;
+
+    #[test]
+    fn load_mutate_save() {
+        let mut rt = MockRuntime::new(1000);
+
+        // The actor has no state yet.
+        rt.call(|| assert!(matches!(super::load::<u64>(), Err(StateError::Read(_)))))
+            .unwrap();
+
+        rt.set_state(&1u64);
+        let count = rt
+            .call(|| {
+                super::mutate(|st: &mut u64| {
+                    *st += 1;
+                    Ok::<_, StateError>(*st)
+                })
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(rt.get_state::<u64>(), 2);
+
+        // Failures leave the state untouched.
+        let root = rt.root;
+        rt.call(|| {
+            super::mutate(|st: &mut u64| {
+                *st += 1;
+                Err::<(), _>(StateError::Block(ErrorNumber::Forbidden))
+            })
+        })
+        .unwrap()
+        .unwrap_err();
+        assert_eq!(rt.root, root);
+
+        // Saving unchanged state keeps the root.
+        let saved = rt.call(|| super::save(&2u64)).unwrap().unwrap();
+        assert_eq!(Some(saved), root);
+    }
+}
@@ -131,6 +131,42 @@ super::fvm_syscalls! {
         delegated_addr_len: u32,
     ) -> Result<()>;
 
+    /// Creates a new actor in the state-tree (see [`create_actor`]) and invokes its constructor
+    /// with the specified parameters and value as a single atomic operation. If the constructor
+    /// aborts, the actor's creation is rolled back.
+    ///
+    /// # Parameters
+    ///
+    /// - `actor_id`, `typ_off`, `delegated_addr_off`, and `delegated_addr_len` are as in
+    ///   [`create_actor`]. Only the init actor may choose the new actor's ID: other actors must
+    ///   pass 0, and the new actor is registered under the address returned by
+    ///   [`next_actor_address`] (call it first to learn the address).
+    /// - `params` is the IPLD block handle passed to the constructor.
+    /// - `value_hi` and `value_lo` are the high and low 64 bits of the value transferred to the
+    ///   new actor.
+    ///
+    /// # Returns
+    ///
+    /// The constructor's exit code and return value, as with `send`.
+    ///
+    /// # Errors
+    ///
+    /// | Error         | Reason                                                          |
+    /// |---------------|-----------------------------------------------------------------|
+    /// | [`Forbidden`] | a caller other than the init actor passed a non-zero `actor_id` |
+    /// | [`ReadOnly`]  | the calling actor is read-only                                  |
+    ///
+    /// Only linked if the network enables actor factories.
+    pub fn create_and_invoke(
+        actor_id: u64,
+        typ_off: *const u8,
+        delegated_addr_off: *const u8,
+        delegated_addr_len: u32,
+        params: u32,
+        value_hi: u64,
+        value_lo: u64,
+    ) -> Result<Send>;
+
 
     /// Atomically transition to the new actor code. On success, this syscall does not return to the
     /// current actor. Instead, the target actor "replaces" the invocation.
@@ -170,6 +206,30 @@ super::fvm_syscalls! {
     /// **Privileged:** May only be called by the init actor.
     pub fn install_actor(cid_off: *const u8) -> Result<()>;
 
+    /// Validates and installs the actor code in the specified IPLD_RAW block, writing its CID
+    /// into the output buffer.
+    ///
+    /// # Parameters
+    ///
+    /// - `code` is the block handle of the actor's Wasm code.
+    /// - `cid_off` and `cid_len` specify the location and length of the output buffer.
+    ///
+    /// # Returns
+    ///
+    /// The length of the CID written to the output buffer.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                       |
+    /// |---------------------|--------------------------------------------------------------|
+    /// | [`InvalidHandle`]   | the code block handle doesn't exist.                         |
+    /// | [`IllegalCodec`]    | the code block isn't IPLD_RAW.                               |
+    /// | [`IllegalArgument`] | the code failed validation, or the output buffer is invalid. |
+    /// | [`BufferTooSmall`]  | the output buffer is too small.                              |
+    ///
+    /// **Privileged:** May only be called by the init actor.
+    pub fn install_actor_code(code: u32, cid_off: *mut u8, cid_len: u32) -> Result<u32>;
+
     /// Gets the balance of the specified actor.
     ///
     /// # Arguments
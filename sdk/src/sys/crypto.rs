@@ -13,7 +13,9 @@ use crate::sys::ErrorNumber::*;
 super::fvm_syscalls! {
     module = "crypto";
 
-    /// Verifies that a signature is valid for an f1 or f3 address and plaintext.
+    /// Verifies that a signature is valid for an f1 or f3 address and plaintext. Signatures by f4
+    /// addresses are supported in namespaces for which the node has a signature verifier (e.g.,
+    /// Ethereum-style signatures for the Ethereum address manager's namespace).
     ///
     /// Returns 0 on success, or -1 if the signature fails to validate.
     ///
@@ -28,6 +30,7 @@ super::fvm_syscalls! {
     /// | Error               | Reason                                               |
     /// |---------------------|------------------------------------------------------|
     /// | [`IllegalArgument`] | signature, address, or plaintext buffers are invalid |
+    /// | [`IllegalArgument`] | the address isn't supported                          |
     pub fn verify_signature(
         sig_type: u32,
         sig_off: *const u8,
@@ -207,4 +210,26 @@ super::fvm_syscalls! {
     /// |---------------------|--------------------------|
     /// | [`IllegalArgument`] | an argument is malformed |
     pub fn batch_verify_seals(batch_off: *const u8, batch_len: u32, result_off: *const u8) -> Result<()>;
+
+    /// Verifies a batch of window proofs of spacetime.
+    ///
+    /// Gas is charged for each proof as if it were verified individually, but the proofs are
+    /// verified concurrently.
+    ///
+    /// # Arguments
+    ///
+    /// - `batch_off` and `batch_len` specify the location and length of a cbor-encoded list of
+    ///   [`WindowPoStVerifyInfo`][fvm_shared::sector::WindowPoStVerifyInfo] in tuple
+    ///   representation.
+    /// - `results_off` specifies the location of a length `L` byte buffer where the results of the
+    ///   verification will be written, where `L` is the number of proofs in the batch. For each
+    ///   proof in the input list (in input order), a 1 or 0 byte will be written on success or
+    ///   failure, respectively. Malformed proofs are reported as failures.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                   |
+    /// |---------------------|--------------------------|
+    /// | [`IllegalArgument`] | an argument is malformed |
+    pub fn batch_verify_posts(batch_off: *const u8, batch_len: u32, result_off: *const u8) -> Result<()>;
 }
@@ -14,4 +14,14 @@ super::fvm_syscalls! {
 
     /// Save data as a debug artifact on the node.
     pub fn store_artifact(name_off: *const u8, name_len: u32, data_off: *const u8, data_len: u32) -> Result<()>;
+
+    /// Reports a metric (a [`MetricKind`](fvm_shared::sys::MetricKind)) to the node. This is a
+    /// no-op unless debug mode is enabled.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                     |
+    /// |---------------------|--------------------------------------------|
+    /// | [`IllegalArgument`] | the name or kind is invalid                |
+    pub fn metric(name_off: *const u8, name_len: u32, kind: u32, value: i64) -> Result<()>;
 }
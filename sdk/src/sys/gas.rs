@@ -26,4 +26,19 @@ super::fvm_syscalls! {
 
     /// Returns the amount of gas remaining.
     pub fn available() -> Result<u64>;
+
+    /// Returns the gas an operation would currently cost, rounded up to whole gas units.
+    ///
+    /// # Arguments
+    ///
+    /// - `op` is the [`PricedOperation`][fvm_shared::sys::PricedOperation] to price.
+    /// - `size` is the size of the operation, in bytes.
+    /// - `param` is the operation's parameter, if any (e.g., the multihash code when hashing).
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                       |
+    /// |---------------------|----------------------------------------------|
+    /// | [`IllegalArgument`] | unknown operation, or an invalid parameter   |
+    pub fn price(op: u32, size: u64, param: u64) -> Result<u64>;
 }
@@ -105,13 +105,7 @@ macro_rules! fvm_syscalls {
         #[allow(clippy::missing_safety_doc)]
         #[allow(clippy::too_many_arguments)]
         $v unsafe fn $name($($args:$args_ty),*) -> Result<(), $crate::sys::ErrorNumber> {
-            #[link(wasm_import_module = $module)]
-            extern "C" {
-                #[link_name = stringify!($name)]
-                fn syscall($($args:$args_ty),*) -> u32;
-            }
-
-            let code = syscall($($args),*);
+            let code = $crate::sys::__fvm_syscall!($module, $name, ($($args: $args_ty),*));
 
             if code == 0 {
                 Ok(())
@@ -130,14 +124,9 @@ macro_rules! fvm_syscalls {
         #[allow(clippy::missing_safety_doc)]
         #[allow(clippy::too_many_arguments)]
         $v unsafe fn $name($($args:$args_ty),*) -> Result<$ret, $crate::sys::ErrorNumber> {
-            #[link(wasm_import_module = $module)]
-            extern "C" {
-                #[link_name = stringify!($name)]
-                fn syscall(ret: *mut $ret $(, $args : $args_ty)*) -> u32;
-            }
-
             let mut ret = std::mem::MaybeUninit::<$ret>::uninit();
-            let code = syscall(ret.as_mut_ptr(), $($args),*);
+            let ret_ptr = ret.as_mut_ptr();
+            let code = $crate::sys::__fvm_syscall!($module, $name, ret_ptr: *mut $ret, ($($args: $args_ty),*));
 
             if code == 0 {
                 Ok(ret.assume_init())
@@ -157,13 +146,7 @@ macro_rules! fvm_syscalls {
         #[allow(clippy::missing_safety_doc)]
         #[allow(clippy::too_many_arguments)]
         $v unsafe fn $name($($args:$args_ty),*) -> ! {
-            #[link(wasm_import_module = $module)]
-            extern "C" {
-                #[link_name = stringify!($name)]
-                fn syscall($($args : $args_ty),*) -> u32;
-            }
-
-            syscall($($args),*);
+            $crate::sys::__fvm_syscall!($module, $name, ($($args: $args_ty),*));
 
             // This should be unreachable unless the syscall has a bug. We abort instead of panicing
             // to help the compiler optimize. It has no way of _proving_ that the syscall doesn't
@@ -180,4 +163,51 @@ macro_rules! fvm_syscalls {
     (module = $module:literal;) => {};
 }
 
-pub use fvm_syscalls;
+/// Makes a single syscall, returning its status code. Imports the syscall from the FVM.
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(mock_syscalls))]
+macro_rules! __fvm_syscall {
+    ($module:literal, $name:ident, $ret:ident: $ret_ty:ty, ($($args:ident : $args_ty:ty),*)) => {{
+        #[link(wasm_import_module = $module)]
+        extern "C" {
+            #[link_name = stringify!($name)]
+            fn syscall(ret: $ret_ty $(, $args : $args_ty)*) -> u32;
+        }
+        syscall($ret, $($args),*)
+    }};
+    ($module:literal, $name:ident, ($($args:ident : $args_ty:ty),*)) => {{
+        #[link(wasm_import_module = $module)]
+        extern "C" {
+            #[link_name = stringify!($name)]
+            fn syscall($($args : $args_ty),*) -> u32;
+        }
+        syscall($($args),*)
+    }};
+}
+
+/// Makes a single syscall, returning its status code. Handles the syscall with the
+/// [mock runtime](crate::testing).
+#[doc(hidden)]
+#[macro_export]
+#[cfg(mock_syscalls)]
+macro_rules! __fvm_syscall {
+    ($module:literal, $name:ident, $ret:ident: $ret_ty:ty, ($($args:ident : $args_ty:ty),*)) => {
+        $crate::testing::dispatch(
+            $module,
+            stringify!($name),
+            $ret as *mut u8,
+            &[$($crate::testing::SyscallArg::to_arg($args)),*],
+        )
+    };
+    ($module:literal, $name:ident, ($($args:ident : $args_ty:ty),*)) => {
+        $crate::testing::dispatch(
+            $module,
+            stringify!($name),
+            std::ptr::null_mut(),
+            &[$($crate::testing::SyscallArg::to_arg($args)),*],
+        )
+    };
+}
+
+pub use {__fvm_syscall, fvm_syscalls};
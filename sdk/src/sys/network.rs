@@ -4,7 +4,7 @@
 
 // for documentation links
 #[doc(inline)]
-pub use fvm_shared::sys::out::network::NetworkContext;
+pub use fvm_shared::sys::out::network::{EpochTiming, NetworkContext};
 
 #[cfg(doc)]
 use crate::sys::ErrorNumber::*;
@@ -49,4 +49,20 @@ super::fvm_syscalls! {
     ///
     /// None
     pub fn context() -> Result<NetworkContext>;
+
+    /// Returns the fingerprint of the executing machine's consensus-relevant configuration (FVM
+    /// version, network limits, price list, and builtin actor code CIDs).
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn fingerprint() -> Result<[u8; 32]>;
+
+    /// Returns the network's epoch duration (in seconds) and expected number of blocks per
+    /// epoch.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn epoch_timing() -> Result<EpochTiming>;
 }
@@ -42,4 +42,28 @@ super::fvm_syscalls! {
     pub fn get_beacon_randomness(
         epoch: i64,
     ) -> Result<[u8; RANDOMNESS_LENGTH]>;
+
+    /// Gets randomness from the beacon system (currently Drand), at the length used by the
+    /// current network version (at most `MAX_RANDOMNESS_LENGTH` bytes), writing it into the
+    /// output buffer.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Arguments
+    ///
+    /// - `epoch` is the epoch to pull the randomness from.
+    /// - `obuf_off` and `obuf_len` specify the location and length of the output buffer.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                      |
+    /// |---------------------|---------------------------------------------|
+    /// | [`LimitExceeded`]   | lookback exceeds limit.                     |
+    /// | [`IllegalArgument`] | invalid buffer, etc.                        |
+    /// | [`BufferTooSmall`]  | the output buffer can't fit the randomness. |
+    pub fn get_beacon_randomness_bytes(
+        epoch: i64,
+        obuf_off: *mut u8,
+        obuf_len: u32,
+    ) -> Result<u32>;
 }
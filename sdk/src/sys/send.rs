@@ -55,4 +55,19 @@ super::fvm_syscalls! {
         gas_limit: u64,
         flags: SendFlags,
     ) -> Result<Send>;
+
+    /// Sends a message to another actor like [`send`], and also returns the gas consumed by the
+    /// call (the callee's execution, and the cost of the send itself).
+    ///
+    /// Takes the same arguments, and fails with the same errors, as [`send`].
+    pub fn send_metered(
+        recipient_off: *const u8,
+        recipient_len: u32,
+        method: u64,
+        params: u32,
+        value_hi: u64,
+        value_lo: u64,
+        gas_limit: u64,
+        flags: SendFlags,
+    ) -> Result<SendResult>;
 }
@@ -5,6 +5,10 @@
 #[doc(inline)]
 pub use fvm_shared::sys::out::vm::MessageContext;
 
+// for documentation links
+#[cfg(doc)]
+use crate::sys::ErrorNumber::*;
+
 super::fvm_syscalls! {
     module = "vm";
 
@@ -36,4 +40,60 @@ super::fvm_syscalls! {
     ///
     /// None
     pub fn message_context() -> Result<MessageContext>;
+
+    /// Returns the depth of the current invocation on the call stack. The top-level message
+    /// executes at depth 1. Sends made at the network's maximum call depth fail with
+    /// [`LimitExceeded`][fvm_shared::error::ErrorNumber::LimitExceeded].
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn call_depth() -> Result<u32>;
+
+    /// Writes the IDs of the actors on the call stack into the output buffer, as little-endian
+    /// `u64`s. The first is the receiver of the top-level message and the last is the calling
+    /// actor, so the stack holds [`call_depth`] entries. The message's origin isn't included.
+    ///
+    /// # Arguments
+    ///
+    /// - `obuf_off` and `obuf_len` specify the location and length of the output buffer.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                            |
+    /// |---------------------|---------------------------------------------------|
+    /// | [`BufferTooSmall`]  | if the output buffer can't hold the call stack    |
+    /// | [`IllegalArgument`] | if the output buffer isn't valid, in memory, etc. |
+    pub fn call_stack(obuf_off: *mut u8, obuf_len: u32) -> Result<u32>;
+
+    /// Sets whether the calling actor may be re-entered (directly or transitively) before the
+    /// current invocation returns. See
+    /// [`ReentrancyPolicy`][fvm_shared::sys::ReentrancyPolicy] for the accepted values.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                    |
+    /// |---------------------|---------------------------|
+    /// | [`IllegalArgument`] | unknown reentrancy policy |
+    pub fn set_reentrancy(policy: u32) -> Result<()>;
+
+    /// Returns the version of the syscall ABI implemented by the FVM
+    /// ([`SYSCALL_ABI_VERSION`][fvm_shared::sys::SYSCALL_ABI_VERSION] as of the FVM's release).
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn abi_version() -> Result<u32>;
+
+    /// Returns the optional capabilities supported by the FVM, as a
+    /// [`Capabilities`][fvm_shared::sys::Capabilities] bitset.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn capabilities() -> Result<u64>;
 }
@@ -0,0 +1,824 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! A mock runtime for unit testing actors natively (outside of Wasm and the FVM).
+//!
+//! With the `testing` feature enabled, syscalls made on non-Wasm targets are handled by a
+//! [`MockRuntime`] instead of being imported from the FVM. Configure the runtime (the message and
+//! network context, the actor's state, other actors, randomness) and the expected sends and
+//! signature checks, then run the actor's code with [`MockRuntime::call`]:
+//!
+//! ```ignore
+//! let mut rt = MockRuntime::new(1000);
+//! rt.caller = 100;
+//! rt.set_state(&State::default());
+//! rt.expect_send(ExpectedSend {
+//!     to: Address::new_id(100),
+//!     method: 2,
+//!     params: None,
+//!     value: TokenAmount::zero(),
+//!     response: Ok(Response { exit_code: ExitCode::OK, return_data: None }),
+//! });
+//!
+//! let params = rt.put_block(IpldBlock::serialize_cbor(&params).unwrap().unwrap());
+//! let ret = rt.call(|| invoke(params)).unwrap();
+//! rt.verify();
+//! assert_eq!(rt.get_state::<State>().count, 1);
+//! ```
+//!
+//! Syscalls the mock doesn't support (e.g., actor creation and proof verification) panic.
+//!
+//! The SDK's panic handler ([`crate::initialize`]) is not installed on non-Wasm targets in this
+//! mode, so that panics in the actor fail the test as usual.
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+
+use cid::multihash::{Code, Multihash, MultihashDigest};
+use cid::Cid;
+use fvm_ipld_encoding::de::DeserializeOwned;
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::ser::Serialize;
+use fvm_ipld_encoding::DAG_CBOR;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::crypto::hash::SupportedHashes;
+use fvm_shared::crypto::signature::Signature;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::event::{ActorEvent, Entry};
+use fvm_shared::sys::out::ipld::{IpldOpen, IpldStat};
+use fvm_shared::sys::out::network::{EpochTiming, NetworkContext};
+use fvm_shared::sys::out::send::{Send, SendResult};
+use fvm_shared::sys::out::vm::{ContextFlags, MessageContext};
+use fvm_shared::sys::{BlockId, Capabilities, EventEntry, SendFlags, SYSCALL_ABI_VERSION};
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::{ActorID, MethodNum, Response, IDENTITY_HASH};
+use num_traits::FromPrimitive;
+
+thread_local! {
+    static RUNTIME: RefCell<Option<MockRuntime>> = RefCell::new(None);
+}
+
+/// A send the actor is expected to make, and the response to return to it.
+#[derive(Clone, Debug)]
+pub struct ExpectedSend {
+    pub to: Address,
+    pub method: MethodNum,
+    pub params: Option<IpldBlock>,
+    pub value: TokenAmount,
+    /// The response to the send, or the syscall error to fail it with.
+    pub response: Result<Response, ErrorNumber>,
+}
+
+/// A signature check the actor is expected to make, and its result.
+#[derive(Clone, Debug)]
+pub struct ExpectedSignature {
+    pub signature: Signature,
+    pub signer: Address,
+    pub plaintext: Vec<u8>,
+    pub valid: bool,
+}
+
+/// How the actor exited, if it exited explicitly (e.g., by aborting).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Exit {
+    pub code: ExitCode,
+    pub data: Option<IpldBlock>,
+    pub message: Option<String>,
+}
+
+/// A mock of the FVM, handling the syscalls made by an actor. See the [module](self)
+/// documentation.
+#[derive(Clone, Debug)]
+pub struct MockRuntime {
+    // Message context.
+    pub receiver: ActorID,
+    pub caller: ActorID,
+    pub origin: ActorID,
+    pub nonce: u64,
+    pub method_number: MethodNum,
+    pub value_received: TokenAmount,
+    pub gas_premium: TokenAmount,
+    pub read_only: bool,
+
+    // Network context.
+    pub epoch: ChainEpoch,
+    pub timestamp: u64,
+    pub base_fee: TokenAmount,
+    pub chain_id: u64,
+    pub network_version: NetworkVersion,
+    pub circ_supply: TokenAmount,
+    pub fingerprint: [u8; 32],
+    pub tipset_cids: HashMap<ChainEpoch, Cid>,
+    pub chain_randomness: HashMap<ChainEpoch, [u8; 32]>,
+    pub beacon_randomness: HashMap<ChainEpoch, [u8; 32]>,
+
+    // The actor.
+    pub balance: TokenAmount,
+    /// The actor's state root, if it has state.
+    pub root: Option<Cid>,
+    /// Whether the actor deleted itself.
+    pub deleted: bool,
+    /// The blocks available to the actor, by CID.
+    pub store: HashMap<Cid, Vec<u8>>,
+    pub gas_available: u64,
+    pub debug: bool,
+    /// The capabilities reported to the actor.
+    pub capabilities: Capabilities,
+
+    // Other actors.
+    pub actor_ids: HashMap<Address, ActorID>,
+    pub delegated_addresses: HashMap<ActorID, Address>,
+    pub actor_codes: HashMap<ActorID, Cid>,
+    pub balances: HashMap<ActorID, TokenAmount>,
+    /// The builtin actor types of code CIDs.
+    pub builtin_types: HashMap<Cid, i32>,
+
+    // Outputs.
+    /// The events emitted by the actor.
+    pub events: Vec<ActorEvent>,
+    /// The debug messages logged by the actor.
+    pub logs: Vec<String>,
+    /// The gas charged explicitly by the actor, by name.
+    pub gas_charges: Vec<(String, u64)>,
+
+    blocks: Vec<IpldBlock>,
+    expected_sends: VecDeque<ExpectedSend>,
+    expected_signatures: VecDeque<ExpectedSignature>,
+}
+
+impl MockRuntime {
+    /// Creates a runtime for the actor with the given ID, with no state and an empty context.
+    pub fn new(receiver: ActorID) -> Self {
+        MockRuntime {
+            receiver,
+            caller: 0,
+            origin: 0,
+            nonce: 0,
+            method_number: 0,
+            value_received: TokenAmount::default(),
+            gas_premium: TokenAmount::default(),
+            read_only: false,
+            epoch: 0,
+            timestamp: 0,
+            base_fee: TokenAmount::default(),
+            chain_id: 0,
+            network_version: NetworkVersion::V21,
+            circ_supply: TokenAmount::default(),
+            fingerprint: [0; 32],
+            tipset_cids: HashMap::new(),
+            chain_randomness: HashMap::new(),
+            beacon_randomness: HashMap::new(),
+            balance: TokenAmount::default(),
+            root: None,
+            deleted: false,
+            store: HashMap::new(),
+            gas_available: u64::MAX,
+            debug: false,
+            capabilities: Capabilities::all(),
+            actor_ids: HashMap::new(),
+            delegated_addresses: HashMap::new(),
+            actor_codes: HashMap::new(),
+            balances: HashMap::new(),
+            builtin_types: HashMap::new(),
+            events: Vec::new(),
+            logs: Vec::new(),
+            gas_charges: Vec::new(),
+            blocks: Vec::new(),
+            expected_sends: VecDeque::new(),
+            expected_signatures: VecDeque::new(),
+        }
+    }
+
+    /// Runs `f` (typically the actor's `invoke` entrypoint) against this runtime. Returns its
+    /// result, or how the actor exited if it exited explicitly.
+    ///
+    /// Blocks are never freed, so block IDs (e.g., of the parameters and return value) remain
+    /// valid across calls.
+    pub fn call<R>(&mut self, f: impl FnOnce() -> R) -> Result<R, Exit> {
+        let rt = std::mem::replace(self, MockRuntime::new(self.receiver));
+        RUNTIME.with(|cell| *cell.borrow_mut() = Some(rt));
+        let res = panic::catch_unwind(AssertUnwindSafe(f));
+        *self = RUNTIME
+            .with(|cell| cell.borrow_mut().take())
+            .expect("mock runtime removed during the call");
+        match res {
+            Ok(ret) => Ok(ret),
+            Err(payload) => match payload.downcast::<Exit>() {
+                Ok(exit) => Err(*exit),
+                Err(payload) => panic::resume_unwind(payload),
+            },
+        }
+    }
+
+    /// Expects the actor to make the given send, after all previously expected sends.
+    pub fn expect_send(&mut self, send: ExpectedSend) {
+        self.expected_sends.push_back(send);
+    }
+
+    /// Expects the actor to check the given signature, after all previously expected checks.
+    pub fn expect_verify_signature(&mut self, check: ExpectedSignature) {
+        self.expected_signatures.push_back(check);
+    }
+
+    /// Panics if the actor didn't make all expected sends and signature checks.
+    #[track_caller]
+    pub fn verify(&self) {
+        assert!(
+            self.expected_sends.is_empty(),
+            "expected sends weren't made: {:?}",
+            self.expected_sends
+        );
+        assert!(
+            self.expected_signatures.is_empty(),
+            "expected signature checks weren't made: {:?}",
+            self.expected_signatures
+        );
+    }
+
+    /// Registers a block with the runtime (e.g., parameters to pass to the actor), returning its
+    /// ID.
+    pub fn put_block(&mut self, block: IpldBlock) -> BlockId {
+        self.blocks.push(block);
+        self.blocks.len() as BlockId
+    }
+
+    /// Returns the block with the given ID (e.g., returned by the actor), if any.
+    pub fn block(&self, id: BlockId) -> Option<&IpldBlock> {
+        match id {
+            0 => None,
+            id => self.blocks.get(id as usize - 1),
+        }
+    }
+
+    /// Stores a block, returning its (blake2b-256) CID.
+    pub fn put(&mut self, codec: u64, data: Vec<u8>) -> Cid {
+        let cid = Cid::new_v1(codec, Code::Blake2b256.digest(&data));
+        self.store.insert(cid, data);
+        cid
+    }
+
+    /// Stores `state` and sets it as the actor's state root.
+    pub fn set_state<S: Serialize>(&mut self, state: &S) -> Cid {
+        let data = fvm_ipld_encoding::to_vec(state).expect("failed to encode state");
+        let root = self.put(DAG_CBOR, data);
+        self.root = Some(root);
+        root
+    }
+
+    /// Decodes the actor's state. Panics if the actor has no state.
+    #[track_caller]
+    pub fn get_state<S: DeserializeOwned>(&self) -> S {
+        let root = self.root.expect("actor has no state");
+        let data = self.store.get(&root).expect("state root not in store");
+        fvm_ipld_encoding::from_slice(data).expect("failed to decode state")
+    }
+
+    fn open(&mut self, codec: u64, data: Vec<u8>) -> BlockId {
+        self.put_block(IpldBlock { codec, data })
+    }
+
+    fn get_block(&self, id: BlockId) -> Result<&IpldBlock, ErrorNumber> {
+        self.block(id).ok_or(ErrorNumber::InvalidHandle)
+    }
+
+    fn resolve(&self, addr: &Address) -> Option<ActorID> {
+        addr.id().ok().or_else(|| self.actor_ids.get(addr).copied())
+    }
+
+    fn check_writable(&self) -> Result<(), ErrorNumber> {
+        if self.read_only {
+            Err(ErrorNumber::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+
+    unsafe fn handle(
+        &mut self,
+        module: &str,
+        name: &str,
+        ret: *mut u8,
+        a: &[u64],
+    ) -> Result<(), ErrorNumber> {
+        match (module, name) {
+            ("ipld", "block_open") => {
+                let cid = read_cid(a[0]);
+                let data = self.store.get(&cid).ok_or(ErrorNumber::NotFound)?.clone();
+                let size = data.len() as u32;
+                let id = self.open(cid.codec(), data);
+                write_ret(
+                    ret,
+                    IpldOpen {
+                        codec: cid.codec(),
+                        id,
+                        size,
+                    },
+                );
+            }
+            ("ipld", "block_create") => {
+                let data = slice(a[1], a[2]).to_vec();
+                let id = self.open(a[0], data);
+                write_ret(ret, id);
+            }
+            ("ipld", "block_read") => {
+                let block = self.get_block(a[0] as BlockId)?;
+                let (offset, max_len) = (a[1] as usize, a[3] as usize);
+                let data = block.data.get(offset..).unwrap_or_default();
+                let len = data.len().min(max_len);
+                slice_mut(a[2], len as u64).copy_from_slice(&data[..len]);
+                let remaining = block.data.len() as i64 - (offset + max_len) as i64;
+                write_ret(ret, remaining as i32);
+            }
+            ("ipld", "block_stat") => {
+                let block = self.get_block(a[0] as BlockId)?;
+                write_ret(
+                    ret,
+                    IpldStat {
+                        codec: block.codec,
+                        size: block.data.len() as u32,
+                    },
+                );
+            }
+            ("ipld", "block_link") => {
+                let block = self.get_block(a[0] as BlockId)?.clone();
+                let cid = match (a[1], a[2]) {
+                    (code, 32) if code == SupportedHashes::Blake2b256 as u64 => {
+                        self.put(block.codec, block.data)
+                    }
+                    (IDENTITY_HASH, len) if len as usize == block.data.len() => Cid::new_v1(
+                        block.codec,
+                        Multihash::wrap(IDENTITY_HASH, &block.data)
+                            .map_err(|_| ErrorNumber::IllegalCid)?,
+                    ),
+                    _ => return Err(ErrorNumber::IllegalCid),
+                };
+                let len = write_out(slice_mut(a[3], a[4]), &cid.to_bytes())?;
+                write_ret(ret, len);
+            }
+
+            ("sself", "root") => {
+                let root = self.root.ok_or(ErrorNumber::IllegalOperation)?;
+                let len = write_out(slice_mut(a[0], a[1]), &root.to_bytes())?;
+                write_ret(ret, len);
+            }
+            ("sself", "set_root") => {
+                if self.deleted {
+                    return Err(ErrorNumber::IllegalOperation);
+                }
+                self.check_writable()?;
+                let cid = read_cid(a[0]);
+                if !self.store.contains_key(&cid) {
+                    return Err(ErrorNumber::NotFound);
+                }
+                self.root = Some(cid);
+            }
+            ("sself", "current_balance") => write_ret(ret, sys_amount(&self.balance)),
+            ("sself", "self_destruct") => {
+                self.check_writable()?;
+                if a[0] == 0 && !self.balance.is_zero() {
+                    return Err(ErrorNumber::IllegalOperation);
+                }
+                self.balance = TokenAmount::default();
+                self.root = None;
+                self.deleted = true;
+            }
+
+            ("vm", "exit") => {
+                let data = match a[1] as BlockId {
+                    0 => None,
+                    id => Some(self.get_block(id).expect("invalid exit block").clone()),
+                };
+                let message =
+                    (a[3] > 0).then(|| String::from_utf8_lossy(slice(a[2], a[3])).into_owned());
+                panic::panic_any(Exit {
+                    code: ExitCode::new(a[0] as u32),
+                    data,
+                    message,
+                });
+            }
+            ("vm", "message_context") => write_ret(
+                ret,
+                MessageContext {
+                    origin: self.origin,
+                    nonce: self.nonce,
+                    caller: self.caller,
+                    receiver: self.receiver,
+                    method_number: self.method_number,
+                    value_received: sys_amount(&self.value_received),
+                    gas_premium: sys_amount(&self.gas_premium),
+                    flags: if self.read_only {
+                        ContextFlags::READ_ONLY
+                    } else {
+                        ContextFlags::empty()
+                    },
+                },
+            ),
+            ("vm", "call_depth") => write_ret(ret, 1u32),
+            ("vm", "set_reentrancy") => {}
+            ("vm", "abi_version") => write_ret(ret, SYSCALL_ABI_VERSION),
+            ("vm", "capabilities") => write_ret(ret, self.capabilities.bits()),
+
+            ("network", "context") => write_ret(
+                ret,
+                NetworkContext {
+                    epoch: self.epoch,
+                    timestamp: self.timestamp,
+                    base_fee: sys_amount(&self.base_fee),
+                    chain_id: self.chain_id,
+                    network_version: self.network_version,
+                },
+            ),
+            ("network", "total_fil_circ_supply") => write_ret(ret, sys_amount(&self.circ_supply)),
+            ("network", "tipset_cid") => {
+                let epoch = a[0] as ChainEpoch;
+                let cid = self
+                    .tipset_cids
+                    .get(&epoch)
+                    .unwrap_or_else(|| panic!("no tipset CID set for epoch {epoch}"));
+                let len = write_out(slice_mut(a[1], a[2]), &cid.to_bytes())?;
+                write_ret(ret, len);
+            }
+            ("network", "fingerprint") => write_ret(ret, self.fingerprint),
+            ("network", "epoch_timing") => write_ret(
+                ret,
+                EpochTiming {
+                    epoch_duration_seconds: 30,
+                    blocks_per_epoch: 5,
+                },
+            ),
+
+            ("rand", "get_chain_randomness") => {
+                let epoch = a[0] as ChainEpoch;
+                let rand = self
+                    .chain_randomness
+                    .get(&epoch)
+                    .unwrap_or_else(|| panic!("no chain randomness set for epoch {epoch}"));
+                write_ret(ret, *rand);
+            }
+            ("rand", "get_beacon_randomness") => {
+                let epoch = a[0] as ChainEpoch;
+                let rand = self
+                    .beacon_randomness
+                    .get(&epoch)
+                    .unwrap_or_else(|| panic!("no beacon randomness set for epoch {epoch}"));
+                write_ret(ret, *rand);
+            }
+            ("rand", "get_beacon_randomness_bytes") => {
+                let epoch = a[0] as ChainEpoch;
+                let rand = self
+                    .beacon_randomness
+                    .get(&epoch)
+                    .unwrap_or_else(|| panic!("no beacon randomness set for epoch {epoch}"));
+                let len = write_out(slice_mut(a[1], a[2]), rand)?;
+                write_ret(ret, len);
+            }
+
+            ("actor", "resolve_address") => {
+                let addr = read_address(a[0], a[1])?;
+                write_ret(ret, self.resolve(&addr).ok_or(ErrorNumber::NotFound)?);
+            }
+            ("actor", "lookup_delegated_address") => {
+                let len = match self.delegated_addresses.get(&a[0]) {
+                    Some(addr) => write_out(slice_mut(a[1], a[2]), &addr.to_bytes())?,
+                    None => 0,
+                };
+                write_ret(ret, len);
+            }
+            ("actor", "get_actor_code_cid") => {
+                let code = self.actor_codes.get(&a[0]).ok_or(ErrorNumber::NotFound)?;
+                let len = write_out(slice_mut(a[1], a[2]), &code.to_bytes())?;
+                write_ret(ret, len);
+            }
+            ("actor", "get_builtin_actor_type") => {
+                let code = read_cid(a[0]);
+                write_ret(ret, self.builtin_types.get(&code).copied().unwrap_or(0));
+            }
+            ("actor", "get_code_cid_for_type") => {
+                let typ = a[0] as i32;
+                let code = self
+                    .builtin_types
+                    .iter()
+                    .find_map(|(code, t)| (*t == typ).then_some(*code))
+                    .ok_or(ErrorNumber::IllegalArgument)?;
+                let len = write_out(slice_mut(a[1], a[2]), &code.to_bytes())?;
+                write_ret(ret, len);
+            }
+            ("actor", "balance_of") => {
+                let balance = if a[0] == self.receiver {
+                    &self.balance
+                } else {
+                    self.balances.get(&a[0]).ok_or(ErrorNumber::NotFound)?
+                };
+                write_ret(ret, sys_amount(balance));
+            }
+
+            ("send", "send") => {
+                let send = self.send(a)?;
+                write_ret(ret, send);
+            }
+            ("send", "send_metered") => {
+                let send = self.send(a)?;
+                write_ret(ret, SendResult { send, gas_used: 0 });
+            }
+
+            ("crypto", "verify_signature") => {
+                let expected = self
+                    .expected_signatures
+                    .pop_front()
+                    .expect("unexpected signature check");
+                let signature = Signature {
+                    sig_type: FromPrimitive::from_u64(a[0]).ok_or(ErrorNumber::IllegalArgument)?,
+                    bytes: slice(a[1], a[2]).to_vec(),
+                };
+                assert_eq!(signature, expected.signature, "unexpected signature");
+                assert_eq!(
+                    read_address(a[3], a[4])?,
+                    expected.signer,
+                    "unexpected signer"
+                );
+                assert_eq!(
+                    slice(a[5], a[6]),
+                    expected.plaintext.as_slice(),
+                    "unexpected plaintext"
+                );
+                write_ret(ret, if expected.valid { 0i32 } else { -1i32 });
+            }
+            ("crypto", "hash") => {
+                let data = slice(a[1], a[2]);
+                let digest = match a[0] {
+                    code if code == SupportedHashes::Blake2b256 as u64 => {
+                        Code::Blake2b256.digest(data)
+                    }
+                    code if code == SupportedHashes::Blake2b512 as u64 => {
+                        Code::Blake2b512.digest(data)
+                    }
+                    code => panic!("hash function {code:#x} not supported by the mock runtime"),
+                };
+                let out = slice_mut(a[3], a[4]);
+                let len = out.len().min(digest.digest().len());
+                out[..len].copy_from_slice(&digest.digest()[..len]);
+                write_ret(ret, len as u32);
+            }
+
+            ("event", "emit_event") => {
+                let headers =
+                    std::slice::from_raw_parts(a[0] as usize as *const EventEntry, a[1] as usize);
+                let (mut keys, mut values) = (slice(a[2], a[3]), slice(a[4], a[5]));
+                let mut entries = Vec::with_capacity(headers.len());
+                for header in headers {
+                    let (key, rest) = keys.split_at(header.key_len as usize);
+                    keys = rest;
+                    let (value, rest) = values.split_at(header.val_len as usize);
+                    values = rest;
+                    entries.push(Entry {
+                        flags: header.flags,
+                        key: String::from_utf8(key.to_vec())
+                            .map_err(|_| ErrorNumber::IllegalArgument)?,
+                        codec: header.codec,
+                        value: value.to_vec(),
+                    });
+                }
+                self.events.push(ActorEvent { entries });
+            }
+
+            ("gas", "charge") => {
+                let name = String::from_utf8_lossy(slice(a[0], a[1])).into_owned();
+                self.gas_available = self.gas_available.saturating_sub(a[2]);
+                self.gas_charges.push((name, a[2]));
+            }
+            ("gas", "available") => write_ret(ret, self.gas_available),
+
+            ("debug", "enabled") => write_ret(ret, if self.debug { 0i32 } else { -1i32 }),
+            ("debug", "log") => {
+                let msg = String::from_utf8_lossy(slice(a[0], a[1])).into_owned();
+                self.logs.push(msg);
+            }
+            ("debug", "store_artifact") | ("debug", "metric") => {}
+
+            (module, name) => panic!("syscall {module}::{name} not supported by the mock runtime"),
+        }
+        Ok(())
+    }
+
+    unsafe fn send(&mut self, a: &[u64]) -> Result<Send, ErrorNumber> {
+        let to = read_address(a[0], a[1])?;
+        let params = match a[3] as BlockId {
+            0 => None,
+            id => Some(self.get_block(id)?.clone()),
+        };
+        let value = TokenAmount::from_atto((a[4] as u128) << 64 | a[5] as u128);
+        let expected = self
+            .expected_sends
+            .pop_front()
+            .unwrap_or_else(|| panic!("unexpected send to {to}, method {}", a[2]));
+        assert_eq!(to, expected.to, "unexpected send recipient");
+        assert_eq!(a[2], expected.method, "unexpected send method");
+        assert_eq!(params, expected.params, "unexpected send parameters");
+        assert_eq!(value, expected.value, "unexpected send value");
+
+        if !value.is_zero() {
+            self.check_writable()?;
+            if value > self.balance {
+                return Err(ErrorNumber::InsufficientFunds);
+            }
+        }
+        let response = expected.response?;
+        self.balance -= &value;
+
+        let (return_id, return_codec, return_size) = match response.return_data {
+            Some(block) => {
+                let (codec, size) = (block.codec, block.data.len() as u32);
+                (self.put_block(block), codec, size)
+            }
+            None => (0, 0, 0),
+        };
+        Ok(Send {
+            exit_code: response.exit_code.value(),
+            return_id,
+            return_codec,
+            return_size,
+        })
+    }
+}
+
+/// Handles a syscall made with the `testing` feature enabled, on a non-Wasm target. Called by the
+/// syscall shims: `ret` points to where the syscall's return value (if any) is written, and `args`
+/// are the syscall's arguments.
+///
+/// # Safety
+///
+/// The arguments must be valid for the syscall, as they would be in Wasm.
+#[doc(hidden)]
+pub unsafe fn dispatch(module: &str, name: &str, ret: *mut u8, args: &[u64]) -> u32 {
+    RUNTIME.with(|cell| {
+        let mut rt = cell.borrow_mut();
+        let rt = rt
+            .as_mut()
+            .unwrap_or_else(|| panic!("syscall {module}::{name} made outside of a mock runtime"));
+        match rt.handle(module, name, ret, args) {
+            Ok(()) => 0,
+            Err(e) => e as u32,
+        }
+    })
+}
+
+/// A syscall argument, passed to [`dispatch`] as a `u64`.
+#[doc(hidden)]
+pub trait SyscallArg {
+    fn to_arg(self) -> u64;
+}
+
+macro_rules! impl_syscall_arg {
+    ($($t:ty)*) => {
+        $(impl SyscallArg for $t {
+            fn to_arg(self) -> u64 {
+                self as u64
+            }
+        })*
+    };
+}
+
+impl_syscall_arg!(u32 u64 i32 i64 bool);
+
+impl<T> SyscallArg for *const T {
+    fn to_arg(self) -> u64 {
+        self as usize as u64
+    }
+}
+
+impl<T> SyscallArg for *mut T {
+    fn to_arg(self) -> u64 {
+        self as usize as u64
+    }
+}
+
+impl SyscallArg for SendFlags {
+    fn to_arg(self) -> u64 {
+        self.bits()
+    }
+}
+
+unsafe fn slice<'a>(off: u64, len: u64) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(off as usize as *const u8, len as usize)
+    }
+}
+
+unsafe fn slice_mut<'a>(off: u64, len: u64) -> &'a mut [u8] {
+    if len == 0 {
+        &mut []
+    } else {
+        std::slice::from_raw_parts_mut(off as usize as *mut u8, len as usize)
+    }
+}
+
+unsafe fn write_ret<T>(ret: *mut u8, value: T) {
+    (ret as *mut T).write_unaligned(value)
+}
+
+/// Copies `data` to `buf`, following the FVM's output buffer convention.
+fn write_out(buf: &mut [u8], data: &[u8]) -> Result<u32, ErrorNumber> {
+    let out = buf
+        .get_mut(..data.len())
+        .ok_or(ErrorNumber::BufferTooSmall)?;
+    out.copy_from_slice(data);
+    Ok(data.len() as u32)
+}
+
+unsafe fn read_address(off: u64, len: u64) -> Result<Address, ErrorNumber> {
+    Address::from_bytes(slice(off, len)).map_err(|_| ErrorNumber::IllegalArgument)
+}
+
+/// Reads a CID from memory, without reading past its end.
+unsafe fn read_cid(off: u64) -> Cid {
+    struct Reader(*const u8);
+
+    impl std::io::Read for Reader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            unsafe {
+                std::ptr::copy_nonoverlapping(self.0, buf.as_mut_ptr(), buf.len());
+                self.0 = self.0.add(buf.len());
+            }
+            Ok(buf.len())
+        }
+    }
+
+    Cid::read_bytes(Reader(off as usize as *const u8)).expect("actor passed an invalid CID")
+}
+
+fn sys_amount(amount: &TokenAmount) -> fvm_shared::sys::TokenAmount {
+    amount
+        .clone()
+        .try_into()
+        .expect("token amounts in the mock runtime must fit in 128 bits")
+}
+
+#[cfg(test)]
+mod test {
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::error::ExitCode;
+
+    use super::{Exit, ExpectedSend, MockRuntime};
+
+    #[test]
+    fn state_and_exit() {
+        let mut rt = MockRuntime::new(1000);
+        rt.set_state(&5u64);
+        rt.call(|| {
+            let root = crate::sself::root().unwrap();
+            let count: u64 = crate::ipld::get_cbor(&root).unwrap();
+            let root = crate::ipld::put_cbor(&(count + 1)).unwrap();
+            crate::sself::set_root(&root).unwrap();
+        })
+        .unwrap();
+        assert_eq!(rt.get_state::<u64>(), 6);
+
+        let exit = rt
+            .call(|| crate::vm::abort(ExitCode::USR_FORBIDDEN.value(), Some("nope")))
+            .unwrap_err();
+        assert_eq!(
+            exit,
+            Exit {
+                code: ExitCode::USR_FORBIDDEN,
+                data: None,
+                message: Some("nope".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn context_changes_between_calls() {
+        let mut rt = MockRuntime::new(1000);
+        rt.epoch = 10;
+        assert_eq!(rt.call(crate::network::curr_epoch).unwrap(), 10);
+        rt.epoch = 11;
+        assert_eq!(rt.call(crate::network::curr_epoch).unwrap(), 11);
+
+        rt.debug = true;
+        assert!(rt.call(crate::debug::enabled).unwrap());
+        rt.debug = false;
+        assert!(!rt.call(crate::debug::enabled).unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected sends weren't made")]
+    fn verify_missing_send() {
+        let mut rt = MockRuntime::new(1000);
+        rt.expect_send(ExpectedSend {
+            to: Address::new_id(1001),
+            method: 2,
+            params: None,
+            value: TokenAmount::default(),
+            response: Ok(fvm_shared::Response {
+                exit_code: ExitCode::OK,
+                return_data: None,
+            }),
+        });
+        rt.call(|| ()).unwrap();
+        rt.verify();
+    }
+}
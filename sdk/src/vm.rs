@@ -3,7 +3,10 @@
 use std::ptr;
 
 use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::ser::Serialize;
 use fvm_shared::error::ExitCode;
+use fvm_shared::sys::{Capabilities, ReentrancyPolicy};
+use fvm_shared::ActorID;
 
 use crate::sys;
 
@@ -17,7 +20,51 @@ pub const NO_DATA_BLOCK_ID: u32 = 0;
 /// - Value transfers are forbidden.
 /// - Events are discarded.
 pub fn read_only() -> bool {
-    super::message::MESSAGE_CONTEXT.flags.read_only()
+    super::message::MESSAGE_CONTEXT.get().flags.read_only()
+}
+
+/// Returns the depth of the current invocation on the call stack, starting at 1 for the top-level
+/// message.
+pub fn call_depth() -> u32 {
+    unsafe { sys::vm::call_depth().expect("failed to lookup call depth") }
+}
+
+/// Returns the IDs of the actors on the call stack, starting with the receiver of the top-level
+/// message and ending with this actor. The origin of the message isn't included (see
+/// [`message::origin`](crate::message::origin)), and the immediate caller is the second-to-last
+/// entry, if any. Use [`actor::get_actor_code_cid`](crate::actor::get_actor_code_cid) to check the
+/// types of the actors in the chain.
+pub fn call_stack() -> Vec<ActorID> {
+    let len = call_depth() as usize * std::mem::size_of::<ActorID>();
+    let bytes = crate::probe_and_fill(len, len, |buf| unsafe {
+        sys::vm::call_stack(buf.as_mut_ptr(), buf.len() as u32)
+    })
+    .expect("failed to lookup call stack");
+    bytes
+        .chunks_exact(std::mem::size_of::<ActorID>())
+        .map(|id| ActorID::from_le_bytes(id.try_into().unwrap()))
+        .collect()
+}
+
+/// Sets whether this actor may be re-entered (directly or transitively) until the current
+/// invocation returns. With [`ReentrancyPolicy::Forbid`], any call that would re-enter this actor
+/// fails with [`Forbidden`][fvm_shared::error::ErrorNumber::Forbidden].
+pub fn set_reentrancy(policy: ReentrancyPolicy) {
+    unsafe {
+        sys::vm::set_reentrancy(policy as u32).expect("failed to set the reentrancy policy");
+    }
+}
+
+/// Returns the version of the syscall ABI implemented by the FVM executing this actor.
+pub fn abi_version() -> u32 {
+    unsafe { sys::vm::abi_version().expect("failed to lookup the syscall ABI version") }
+}
+
+/// Returns the optional capabilities supported by the FVM executing this actor. Check for a
+/// capability before making the syscalls it provides.
+pub fn capabilities() -> Capabilities {
+    let caps = unsafe { sys::vm::capabilities().expect("failed to lookup capabilities") };
+    Capabilities::from_bits_truncate(caps)
 }
 
 /// Abort execution; exit code must be non zero.
@@ -48,11 +95,32 @@ pub fn exit(code: u32, data: Option<IpldBlock>, message: Option<&str>) -> ! {
     }
 }
 
+/// Abort execution with the given exit code, attaching the CBOR-encoded `reason` as the return
+/// data so that the caller can decode why the call failed (see
+/// [`Response::deserialize_return`][fvm_shared::Response::deserialize_return]).
+///
+/// Actor-defined failure codes should be at or above
+/// [`ExitCode::FIRST_ACTOR_SPECIFIC_EXIT_CODE`].
+pub fn revert<T: Serialize + ?Sized>(code: ExitCode, reason: &T, message: Option<&str>) -> ! {
+    if code.is_success() {
+        abort(ExitCode::USR_ASSERTION_FAILED.value(), message)
+    }
+    match IpldBlock::serialize_cbor(reason) {
+        Ok(data) => exit(code.value(), data, message),
+        Err(e) => abort(
+            ExitCode::USR_SERIALIZATION.value(),
+            Some(&format!("failed to serialize revert reason: {e}")),
+        ),
+    }
+}
+
 /// Sets a panic handler to turn all panics into aborts with `USR_ASSERTION_FAILED`. This should be
 /// called early in the actor to improve debuggability.
 ///
 /// NOTE: This will incure a small cost on failure (to format an error message).
 pub fn set_panic_handler() {
+    // Natively, under the mock runtime, panics must unwind to fail the test.
+    #[cfg(not(mock_syscalls))]
     std::panic::set_hook(Box::new(|info| {
         abort(
             ExitCode::USR_ASSERTION_FAILED.value(),
@@ -60,3 +128,22 @@ pub fn set_panic_handler() {
         )
     }));
 }
+
+#[cfg(all(test, mock_syscalls))]
+mod test {
+    use fvm_shared::sys::{Capabilities, SYSCALL_ABI_VERSION};
+
+    use crate::testing::MockRuntime;
+
+    #[test]
+    fn abi_version_and_capabilities() {
+        let mut rt = MockRuntime::new(1000);
+        assert_eq!(rt.call(super::abi_version).unwrap(), SYSCALL_ABI_VERSION);
+        assert_eq!(rt.call(super::capabilities).unwrap(), Capabilities::all());
+
+        rt.capabilities = Capabilities::INSTALL_ACTOR;
+        let caps = rt.call(super::capabilities).unwrap();
+        assert!(caps.contains(Capabilities::INSTALL_ACTOR));
+        assert!(!caps.contains(Capabilities::UPGRADE_ACTOR));
+    }
+}
@@ -37,6 +37,15 @@ pub enum Error {
     NonDelegatedAddress,
 }
 
+/// A detailed error returned by [`parse_lossless`](super::parse_lossless).
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum ParseError {
+    #[error("invalid address {input:?}: {error}")]
+    Invalid { input: String, error: Error },
+    #[error("address {input:?} is not canonical, expected {canonical:?}")]
+    NonCanonical { input: String, canonical: String },
+}
+
 impl From<num::ParseIntError> for Error {
     fn from(_: num::ParseIntError) -> Error {
         Error::InvalidPayload
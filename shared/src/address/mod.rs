@@ -17,7 +17,7 @@ use data_encoding_macro::new_encoding;
 use fvm_ipld_encoding::strict_bytes;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-pub use self::errors::Error;
+pub use self::errors::{Error, ParseError};
 pub use self::network::{current_network, set_current_network, Network};
 pub use self::payload::{DelegatedAddress, Payload};
 pub use self::protocol::Protocol;
@@ -180,10 +180,20 @@ impl Address {
 
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let protocol = self.protocol();
+        fmt::Display::fmt(&NetworkAddress(current_network(), self), f)
+    }
+}
+
+/// Formats an address for a specific network (see [`Network::format_address`]).
+struct NetworkAddress<'a>(Network, &'a Address);
+
+impl fmt::Display for NetworkAddress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let NetworkAddress(network, addr) = self;
+        let protocol = addr.protocol();
 
         // write `fP` where P is the protocol number.
-        write!(f, "{}{}", current_network().to_prefix(), protocol)?;
+        write!(f, "{}{}", network.to_prefix(), protocol)?;
 
         fn write_payload(
             f: &mut fmt::Formatter<'_>,
@@ -207,7 +217,7 @@ impl fmt::Display for Address {
             f.write_str(&ADDRESS_ENCODER.encode(&buf))
         }
 
-        match self.payload() {
+        match addr.payload() {
             Payload::ID(id) => write!(f, "{}", id),
             Payload::Secp256k1(data) | Payload::Actor(data) => {
                 write_payload(f, protocol, None, data)
@@ -288,26 +298,30 @@ pub(self) fn parse_address(addr: &str) -> Result<(Address, Network), Error> {
         Ok(payload)
     }
 
+    // Parses a decimal actor ID. Unlike `u64::from_str`, this rejects signs.
+    fn parse_id(raw: &str) -> Result<u64, Error> {
+        if raw.len() > 20 {
+            // 20 is max u64 as string
+            return Err(Error::InvalidLength);
+        }
+        if !raw.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::InvalidPayload);
+        }
+        Ok(raw.parse::<u64>()?)
+    }
+
     // bytes after the protocol character is the data payload of the address
     let raw = addr.get(2..).ok_or(Error::InvalidPayload)?;
     let addr = match protocol {
         Protocol::ID => {
-            if raw.len() > 20 {
-                // 20 is max u64 as string
-                return Err(Error::InvalidLength);
-            }
-            let id = raw.parse::<u64>()?;
+            let id = parse_id(raw)?;
             Address {
                 payload: Payload::ID(id),
             }
         }
         Protocol::Delegated => {
             let (id, subaddr) = raw.split_once('f').ok_or(Error::InvalidPayload)?;
-            if id.len() > 20 {
-                // 20 is max u64 as string
-                return Err(Error::InvalidLength);
-            }
-            let id = id.parse::<u64>()?;
+            let id = parse_id(id)?;
             // decode subaddr
             let subaddr_csum = ADDRESS_ENCODER.decode(subaddr.as_bytes())?;
             // validate and split subaddr.
@@ -337,7 +351,7 @@ pub(self) fn parse_address(addr: &str) -> Result<(Address, Network), Error> {
                 _ => unreachable!(),
             } != payload.len()
             {
-                return Err(Error::InvalidPayload);
+                return Err(Error::InvalidPayloadLength(payload.len()));
             }
 
             Address::new(protocol, payload)?
@@ -346,6 +360,27 @@ pub(self) fn parse_address(addr: &str) -> Result<(Address, Network), Error> {
     Ok((addr, network))
 }
 
+/// Parses an address, accepting only its canonical textual form (e.g., without leading zeros in
+/// IDs), and returns the network it belongs to. Unlike [`Address::from_str`], this accepts
+/// addresses for any network, and reports errors in detail.
+///
+/// Parsing an address with this function and formatting it with [`Network::format_address`]
+/// always yields the original text.
+pub fn parse_lossless(addr: &str) -> Result<(Address, Network), ParseError> {
+    let (parsed, network) = parse_address(addr).map_err(|error| ParseError::Invalid {
+        input: addr.into(),
+        error,
+    })?;
+    let canonical = network.format_address(&parsed);
+    if canonical != addr {
+        return Err(ParseError::NonCanonical {
+            input: addr.into(),
+            canonical,
+        });
+    }
+    Ok((parsed, network))
+}
+
 impl FromStr for Address {
     type Err = Error;
     fn from_str(addr: &str) -> Result<Self, Error> {
@@ -50,6 +50,11 @@ impl Network {
         }
         Ok(addr)
     }
+
+    /// Formats an address for this network, regardless of the current network.
+    pub fn format_address(self, addr: &Address) -> String {
+        super::NetworkAddress(self, addr).to_string()
+    }
 }
 
 /// Gets the current network.
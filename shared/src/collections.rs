@@ -0,0 +1,174 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Collections with a deterministic iteration order, for use in consensus-critical code.
+//!
+//! `HashMap` and `HashSet` iterate in an order that depends on a per-process random seed, so any
+//! output derived from iterating over them (serialized state, events, gas charges, errors, etc.)
+//! may differ between nodes. These collections iterate in key order instead.
+
+use std::collections::{btree_map, btree_set, BTreeMap, BTreeSet};
+use std::ops::{Deref, DerefMut};
+
+use serde::{Deserialize, Serialize};
+
+/// A map iterating in key order. Derefs to a [`BTreeMap`].
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DeterministicMap<K: Ord, V>(BTreeMap<K, V>);
+
+/// A set iterating in key order. Derefs to a [`BTreeSet`].
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DeterministicSet<T: Ord>(BTreeSet<T>);
+
+impl<K: Ord, V> DeterministicMap<K, V> {
+    pub fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    pub fn into_inner(self) -> BTreeMap<K, V> {
+        self.0
+    }
+}
+
+impl<T: Ord> DeterministicSet<T> {
+    pub fn new() -> Self {
+        Self(BTreeSet::new())
+    }
+
+    pub fn into_inner(self) -> BTreeSet<T> {
+        self.0
+    }
+}
+
+impl<K: Ord, V> Default for DeterministicMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> Default for DeterministicSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> Deref for DeterministicMap<K, V> {
+    type Target = BTreeMap<K, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<K: Ord, V> DerefMut for DeterministicMap<K, V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: Ord> Deref for DeterministicSet<T> {
+    type Target = BTreeSet<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Ord> DerefMut for DeterministicSet<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<K: Ord, V> From<BTreeMap<K, V>> for DeterministicMap<K, V> {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        Self(map)
+    }
+}
+
+impl<T: Ord> From<BTreeSet<T>> for DeterministicSet<T> {
+    fn from(set: BTreeSet<T>) -> Self {
+        Self(set)
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for DeterministicMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<T: Ord> FromIterator<T> for DeterministicSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for DeterministicMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl<T: Ord> Extend<T> for DeterministicSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl<K: Ord, V> IntoIterator for DeterministicMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = btree_map::IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a DeterministicMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = btree_map::Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<T: Ord> IntoIterator for DeterministicSet<T> {
+    type Item = T;
+    type IntoIter = btree_set::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T: Ord> IntoIterator for &'a DeterministicSet<T> {
+    type Item = &'a T;
+    type IntoIter = btree_set::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_encoding::{from_slice, to_vec};
+
+    use super::{DeterministicMap, DeterministicSet};
+
+    #[test]
+    fn ordered_iteration_and_serde() {
+        let map: DeterministicMap<u64, &str> = [(3, "c"), (1, "a"), (2, "b")].into_iter().collect();
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let set: DeterministicSet<u64> = [3, 1, 2].into_iter().collect();
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        let encoded = to_vec(&set).unwrap();
+        assert_eq!(encoded, to_vec(&[1u64, 2, 3]).unwrap());
+        assert_eq!(from_slice::<DeterministicSet<u64>>(&encoded).unwrap(), set);
+    }
+}
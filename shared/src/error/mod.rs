@@ -33,6 +33,18 @@ impl ExitCode {
     pub fn is_system_error(self) -> bool {
         self.value < (Self::FIRST_USER_EXIT_CODE)
     }
+
+    /// Returns true if the exit code is one of the standard exit codes shared by all actors
+    /// (see the `USR_*` constants).
+    pub fn is_standard_user_error(self) -> bool {
+        (Self::FIRST_USER_EXIT_CODE..Self::FIRST_ACTOR_SPECIFIC_EXIT_CODE).contains(&self.value)
+    }
+
+    /// Returns true if the exit code is in the range reserved for actor-defined exit codes. The
+    /// meaning of these codes is specific to the aborting actor.
+    pub fn is_actor_specific(self) -> bool {
+        self.value >= Self::FIRST_ACTOR_SPECIFIC_EXIT_CODE
+    }
 }
 
 impl From<u32> for ExitCode {
@@ -114,6 +126,11 @@ impl ExitCode {
     // pub const RESERVED_29: ExitCode = ExitCode::new(29);
     // pub const RESERVED_30: ExitCode = ExitCode::new(30);
     // pub const RESERVED_31: ExitCode = ExitCode::new(31);
+
+    /// The lowest exit code that an actor may define for its own use. Callers should interpret
+    /// codes at or above this value (and any accompanying return data) according to the ABI of
+    /// the actor that aborted.
+    pub const FIRST_ACTOR_SPECIFIC_EXIT_CODE: u32 = 32;
 }
 
 /// When a syscall fails, it returns an `ErrorNumber` to indicate why. The syscalls themselves
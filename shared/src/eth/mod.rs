@@ -0,0 +1,488 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Import of signed Ethereum transactions (legacy and [EIP-1559]), so EVM-compatible embedders can
+//! execute them as FVM [`Message`]s sent from the signer's f4 (delegated) address.
+//!
+//! [EIP-1559]: https://eips.ethereum.org/EIPS/eip-1559
+
+pub mod rlp;
+
+use fvm_ipld_encoding::{BytesSer, RawBytes};
+use libsecp256k1::{recover, Message as SecpMessage, RecoveryId, Signature as SecpSignature};
+use multihash::{Hasher, Keccak256};
+use num_bigint::{BigInt, Sign};
+use thiserror::Error;
+
+use self::rlp::Item;
+use crate::address::Address;
+use crate::chainid::ChainID;
+use crate::econ::TokenAmount;
+use crate::message::Message;
+use crate::{ActorID, MethodNum};
+
+/// The ID of the Ethereum Address Manager actor, whose namespace f4 Ethereum addresses live in.
+pub const EAM_ACTOR_ID: ActorID = 10;
+
+/// The EAM method deploying a contract on behalf of an Ethereum account (`CreateExternal`).
+pub const EAM_CREATE_EXTERNAL_METHOD: MethodNum = 4;
+
+/// The EVM actor method invoking a contract (`InvokeContract`, FRC-0042).
+pub const EVM_INVOKE_CONTRACT_METHOD: MethodNum = 3844450837;
+
+/// The [EIP-2718](https://eips.ethereum.org/EIPS/eip-2718) type of EIP-1559 transactions.
+pub const EIP1559_TX_TYPE: u8 = 0x02;
+
+/// Half the order of the secp256k1 curve. Signatures with higher `s` values are malleable, and
+/// rejected per [EIP-2](https://eips.ethereum.org/EIPS/eip-2).
+const SECP256K1_HALF_N: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum Error {
+    #[error("invalid RLP: {0}")]
+    Rlp(#[from] rlp::Error),
+    #[error("unsupported transaction type {0:#x}")]
+    UnsupportedType(u8),
+    #[error("expected a list of {expected} fields")]
+    FieldCount { expected: usize },
+    #[error("invalid {field}: {reason}")]
+    InvalidField {
+        field: &'static str,
+        reason: &'static str,
+    },
+    #[error("access lists are not supported")]
+    AccessListUnsupported,
+    #[error("transaction chain ID {actual:?} doesn't match the expected chain ID {expected}")]
+    ChainIdMismatch { expected: u64, actual: Option<u64> },
+    #[error("invalid signature: {0}")]
+    InvalidSignature(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionType {
+    /// A legacy transaction, optionally replay-protected with a chain ID per
+    /// [EIP-155](https://eips.ethereum.org/EIPS/eip-155).
+    Legacy,
+    /// An EIP-1559 (type 2) transaction.
+    Eip1559,
+}
+
+/// A parsed, signed, Ethereum transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthTransaction {
+    pub tx_type: TransactionType,
+    /// The chain ID, or `None` for legacy transactions without replay protection.
+    pub chain_id: Option<u64>,
+    pub nonce: u64,
+    /// The gas premium. This is the gas price for legacy transactions.
+    pub max_priority_fee_per_gas: TokenAmount,
+    /// The gas fee cap. This is the gas price for legacy transactions.
+    pub max_fee_per_gas: TokenAmount,
+    pub gas_limit: u64,
+    /// The recipient, or `None` to deploy a contract.
+    pub to: Option<[u8; 20]>,
+    pub value: TokenAmount,
+    pub input: Vec<u8>,
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    pub recovery_id: u8,
+    signing_hash: [u8; 32],
+}
+
+impl EthTransaction {
+    /// Parses an RLP-encoded signed transaction, as submitted with `eth_sendRawTransaction`.
+    pub fn parse(raw: &[u8]) -> Result<Self, Error> {
+        match raw.first() {
+            Some(&EIP1559_TX_TYPE) => Self::parse_eip1559(&raw[1..]),
+            // Legacy transactions are bare RLP lists.
+            Some(&prefix) if prefix >= 0xc0 => Self::parse_legacy(raw),
+            Some(&prefix) => Err(Error::UnsupportedType(prefix)),
+            None => Err(rlp::Error::UnexpectedEnd.into()),
+        }
+    }
+
+    fn parse_eip1559(payload: &[u8]) -> Result<Self, Error> {
+        let fields = list(&rlp::decode(payload)?, 12)?;
+        match &fields[8] {
+            Item::List(access_list) if access_list.is_empty() => {}
+            Item::List(_) => return Err(Error::AccessListUnsupported),
+            Item::Bytes(_) => return Err(invalid("access list", "not a list")),
+        }
+        let recovery_id = match uint(&fields[9], "y parity")? {
+            [] => 0,
+            [1] => 1,
+            _ => return Err(invalid("y parity", "not 0 or 1")),
+        };
+
+        let mut signing_payload = vec![EIP1559_TX_TYPE];
+        rlp::encode(&Item::List(fields[..9].to_vec()), &mut signing_payload);
+
+        Ok(EthTransaction {
+            tx_type: TransactionType::Eip1559,
+            chain_id: Some(u64_field(&fields[0], "chain ID")?),
+            nonce: u64_field(&fields[1], "nonce")?,
+            max_priority_fee_per_gas: amount(&fields[2], "max priority fee per gas")?,
+            max_fee_per_gas: amount(&fields[3], "max fee per gas")?,
+            gas_limit: u64_field(&fields[4], "gas limit")?,
+            to: recipient(&fields[5])?,
+            value: amount(&fields[6], "value")?,
+            input: bytes(&fields[7], "input")?.to_vec(),
+            r: word(&fields[10], "r")?,
+            s: word(&fields[11], "s")?,
+            recovery_id,
+            signing_hash: keccak256(&signing_payload),
+        })
+    }
+
+    fn parse_legacy(raw: &[u8]) -> Result<Self, Error> {
+        let fields = list(&rlp::decode(raw)?, 9)?;
+        let (chain_id, recovery_id) = match u64_field(&fields[6], "v")? {
+            v @ (27 | 28) => (None, (v - 27) as u8),
+            v if v >= 35 => (Some((v - 35) / 2), ((v - 35) % 2) as u8),
+            _ => return Err(invalid("v", "not a valid recovery ID")),
+        };
+
+        // EIP-155 transactions commit to the chain ID in place of the signature.
+        let mut signing_fields = fields[..6].to_vec();
+        let chain_id_bytes = chain_id.map(u64::to_be_bytes);
+        if let Some(chain_id_bytes) = &chain_id_bytes {
+            let leading_zeros = chain_id_bytes.iter().take_while(|&&b| b == 0).count();
+            signing_fields.extend([
+                Item::Bytes(&chain_id_bytes[leading_zeros..]),
+                Item::Bytes(&[]),
+                Item::Bytes(&[]),
+            ]);
+        }
+        let mut signing_payload = Vec::new();
+        rlp::encode(&Item::List(signing_fields), &mut signing_payload);
+
+        let gas_price = amount(&fields[1], "gas price")?;
+        Ok(EthTransaction {
+            tx_type: TransactionType::Legacy,
+            chain_id,
+            nonce: u64_field(&fields[0], "nonce")?,
+            max_priority_fee_per_gas: gas_price.clone(),
+            max_fee_per_gas: gas_price,
+            gas_limit: u64_field(&fields[2], "gas limit")?,
+            to: recipient(&fields[3])?,
+            value: amount(&fields[4], "value")?,
+            input: bytes(&fields[5], "input")?.to_vec(),
+            r: word(&fields[7], "r")?,
+            s: word(&fields[8], "s")?,
+            recovery_id,
+            signing_hash: keccak256(&signing_payload),
+        })
+    }
+
+    /// The hash the sender signed.
+    pub fn signing_hash(&self) -> &[u8; 32] {
+        &self.signing_hash
+    }
+
+    /// Recovers the sender's f4 address from the signature.
+    pub fn sender(&self) -> Result<Address, Error> {
+        if self.s > SECP256K1_HALF_N {
+            return Err(Error::InvalidSignature("s value is too high".into()));
+        }
+        let mut sig = [0u8; 64];
+        sig[..32].copy_from_slice(&self.r);
+        sig[32..].copy_from_slice(&self.s);
+        let sig = SecpSignature::parse_standard(&sig)
+            .map_err(|e| Error::InvalidSignature(format!("{:?}", e)))?;
+        let recovery_id = RecoveryId::parse(self.recovery_id)
+            .map_err(|e| Error::InvalidSignature(format!("{:?}", e)))?;
+        let key = recover(&SecpMessage::parse(&self.signing_hash), &sig, &recovery_id)
+            .map_err(|e| Error::InvalidSignature(format!("{:?}", e)))?;
+
+        // The Ethereum address is the last 20 bytes of the hash of the uncompressed key (without
+        // its 0x04 tag).
+        let hash = keccak256(&key.serialize()[1..]);
+        Ok(Address::new_delegated(EAM_ACTOR_ID, &hash[12..]).expect("20 byte subaddress is valid"))
+    }
+
+    /// Converts the transaction into a message from the sender's f4 address:
+    ///
+    /// - Calls invoke the recipient's `InvokeContract` method with the input as CBOR bytes.
+    /// - Deployments invoke the EAM's `CreateExternal` method with the init code as CBOR bytes.
+    /// - The gas limit and nonce are used as is, and the fees are denominated in attoFIL.
+    pub fn to_message(&self) -> Result<Message, Error> {
+        let from = self.sender()?;
+        let (to, method_num) = match &self.to {
+            Some(to) => (eth_to_address(to), EVM_INVOKE_CONTRACT_METHOD),
+            None => (Address::new_id(EAM_ACTOR_ID), EAM_CREATE_EXTERNAL_METHOD),
+        };
+        let params = if self.input.is_empty() {
+            RawBytes::default()
+        } else {
+            RawBytes::serialize(BytesSer(&self.input)).expect("bytes can always be encoded")
+        };
+        Ok(Message {
+            version: 0,
+            from,
+            to,
+            sequence: self.nonce,
+            value: self.value.clone(),
+            method_num,
+            params,
+            gas_limit: self.gas_limit,
+            gas_fee_cap: self.max_fee_per_gas.clone(),
+            gas_premium: self.max_priority_fee_per_gas.clone(),
+        })
+    }
+}
+
+/// Parses and validates an RLP-encoded signed transaction for the given chain, converting it into a
+/// message (see [`EthTransaction::to_message`]). Transactions without replay protection (i.e.,
+/// without a chain ID) are rejected.
+pub fn parse_transaction(raw: &[u8], chain_id: ChainID) -> Result<Message, Error> {
+    let tx = EthTransaction::parse(raw)?;
+    let expected = u64::from(chain_id);
+    if tx.chain_id != Some(expected) {
+        return Err(Error::ChainIdMismatch {
+            expected,
+            actual: tx.chain_id,
+        });
+    }
+    tx.to_message()
+}
+
+/// Converts an Ethereum address into an f4 address, or an ID address if it's a "masked" ID address
+/// (`0xff`, followed by 11 zero bytes and a big-endian actor ID).
+fn eth_to_address(addr: &[u8; 20]) -> Address {
+    match addr.split_at(12) {
+        ([0xff, prefix @ ..], id) if prefix.iter().all(|&b| b == 0) => {
+            Address::new_id(u64::from_be_bytes(id.try_into().expect("8 byte actor ID")))
+        }
+        _ => Address::new_delegated(EAM_ACTOR_ID, addr).expect("20 byte subaddress is valid"),
+    }
+}
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::default();
+    hasher.update(data);
+    hasher
+        .finalize()
+        .try_into()
+        .expect("keccak256 digests are 32 bytes")
+}
+
+fn invalid(field: &'static str, reason: &'static str) -> Error {
+    Error::InvalidField { field, reason }
+}
+
+fn list<'a, 'b>(item: &'b Item<'a>, expected: usize) -> Result<&'b [Item<'a>], Error> {
+    match item {
+        Item::List(items) if items.len() == expected => Ok(items),
+        _ => Err(Error::FieldCount { expected }),
+    }
+}
+
+fn bytes<'a>(item: &Item<'a>, field: &'static str) -> Result<&'a [u8], Error> {
+    match item {
+        Item::Bytes(bytes) => Ok(bytes),
+        Item::List(_) => Err(invalid(field, "not a byte string")),
+    }
+}
+
+/// Returns the big-endian bytes of an unsigned integer field, which must be minimally encoded.
+fn uint<'a>(item: &Item<'a>, field: &'static str) -> Result<&'a [u8], Error> {
+    match bytes(item, field)? {
+        [0, ..] => Err(invalid(field, "leading zeros")),
+        bytes => Ok(bytes),
+    }
+}
+
+fn u64_field(item: &Item, field: &'static str) -> Result<u64, Error> {
+    let bytes = uint(item, field)?;
+    if bytes.len() > 8 {
+        return Err(invalid(field, "exceeds 64 bits"));
+    }
+    Ok(bytes.iter().fold(0, |n, &b| (n << 8) | b as u64))
+}
+
+fn amount(item: &Item, field: &'static str) -> Result<TokenAmount, Error> {
+    let bytes = uint(item, field)?;
+    if bytes.len() > 32 {
+        return Err(invalid(field, "exceeds 256 bits"));
+    }
+    Ok(TokenAmount::from_atto(BigInt::from_bytes_be(
+        Sign::Plus,
+        bytes,
+    )))
+}
+
+fn word(item: &Item, field: &'static str) -> Result<[u8; 32], Error> {
+    let bytes = uint(item, field)?;
+    if bytes.len() > 32 {
+        return Err(invalid(field, "exceeds 256 bits"));
+    }
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(bytes);
+    Ok(word)
+}
+
+fn recipient(item: &Item) -> Result<Option<[u8; 20]>, Error> {
+    match bytes(item, "recipient")? {
+        [] => Ok(None),
+        addr => addr
+            .try_into()
+            .map(Some)
+            .map_err(|_| invalid("recipient", "not 20 bytes")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_encoding::{BytesSer, RawBytes};
+    use libsecp256k1::{sign, Message as SecpMessage, SecretKey};
+
+    use super::rlp::{self, Item};
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        data_encoding::HEXLOWER.decode(s.as_bytes()).unwrap()
+    }
+
+    fn eth_address(s: &str) -> Address {
+        Address::new_delegated(EAM_ACTOR_ID, &unhex(s)).unwrap()
+    }
+
+    #[test]
+    fn eip155_example() {
+        // From https://eips.ethereum.org/EIPS/eip-155.
+        let raw = unhex(concat!(
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000",
+            "8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f",
+            "761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        ));
+        let tx = EthTransaction::parse(&raw).unwrap();
+        assert_eq!(tx.tx_type, TransactionType::Legacy);
+        assert_eq!(tx.chain_id, Some(1));
+        assert_eq!(
+            &tx.signing_hash()[..],
+            unhex("daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53")
+        );
+
+        let msg = parse_transaction(&raw, ChainID::from(1)).unwrap();
+        assert_eq!(
+            msg.from,
+            eth_address("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f")
+        );
+        assert_eq!(
+            msg.to,
+            eth_address("3535353535353535353535353535353535353535")
+        );
+        assert_eq!(msg.sequence, 9);
+        assert_eq!(msg.value, TokenAmount::from_whole(1));
+        assert_eq!(msg.method_num, EVM_INVOKE_CONTRACT_METHOD);
+        assert_eq!(msg.params, RawBytes::default());
+        assert_eq!(msg.gas_limit, 21000);
+        assert_eq!(msg.gas_fee_cap, TokenAmount::from_nano(20));
+        assert_eq!(msg.gas_premium, TokenAmount::from_nano(20));
+
+        assert_eq!(
+            parse_transaction(&raw, ChainID::from(314)),
+            Err(Error::ChainIdMismatch {
+                expected: 314,
+                actual: Some(1)
+            })
+        );
+    }
+
+    fn strip(bytes: &[u8]) -> &[u8] {
+        &bytes[bytes.iter().take_while(|&&b| b == 0).count()..]
+    }
+
+    /// Signs an EIP-1559 transaction with the given fields (minus the signature).
+    fn sign_eip1559(key: &SecretKey, fields: Vec<Item>) -> Vec<u8> {
+        let mut payload = vec![EIP1559_TX_TYPE];
+        rlp::encode(&Item::List(fields.clone()), &mut payload);
+        let (sig, recovery_id) = sign(&SecpMessage::parse(&keccak256(&payload)), key);
+        let sig = sig.serialize();
+        let y_parity = [recovery_id.serialize()];
+
+        let mut fields: Vec<Item> = fields;
+        fields.push(Item::Bytes(strip(&y_parity)));
+        fields.push(Item::Bytes(strip(&sig[..32])));
+        fields.push(Item::Bytes(strip(&sig[32..])));
+        let mut raw = vec![EIP1559_TX_TYPE];
+        rlp::encode(&Item::List(fields), &mut raw);
+        raw
+    }
+
+    #[test]
+    fn eip1559() {
+        let key = SecretKey::parse(&[0x46; 32]).unwrap();
+        let init_code = [0xfe; 64];
+        let fields = vec![
+            Item::Bytes(&[0x01, 0x3a]),       // chain ID 314
+            Item::Bytes(&[0x07]),             // nonce
+            Item::Bytes(&[0x01, 0x00]),       // max priority fee per gas
+            Item::Bytes(&[0x10, 0x00]),       // max fee per gas
+            Item::Bytes(&[0x0f, 0x42, 0x40]), // gas limit
+            Item::Bytes(&[]),                 // deployment
+            Item::Bytes(&[]),                 // value
+            Item::Bytes(&init_code),          // input
+            Item::List(vec![]),               // access list
+        ];
+        let raw = sign_eip1559(&key, fields.clone());
+
+        let msg = parse_transaction(&raw, ChainID::from(314)).unwrap();
+        assert_eq!(
+            msg.from,
+            eth_address("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f")
+        );
+        assert_eq!(msg.to, Address::new_id(EAM_ACTOR_ID));
+        assert_eq!(msg.method_num, EAM_CREATE_EXTERNAL_METHOD);
+        assert_eq!(
+            msg.params,
+            RawBytes::serialize(BytesSer(&init_code)).unwrap()
+        );
+        assert_eq!(msg.sequence, 7);
+        assert_eq!(msg.gas_limit, 1_000_000);
+        assert_eq!(msg.gas_premium, TokenAmount::from_atto(0x100));
+        assert_eq!(msg.gas_fee_cap, TokenAmount::from_atto(0x1000));
+        assert_eq!(msg.value, TokenAmount::default());
+
+        // Calls to masked ID addresses go to the ID address.
+        let mut masked = [0u8; 20];
+        masked[0] = 0xff;
+        masked[19] = 0x42;
+        let mut call = fields.clone();
+        call[5] = Item::Bytes(&masked);
+        let msg = parse_transaction(&sign_eip1559(&key, call), ChainID::from(314)).unwrap();
+        assert_eq!(msg.to, Address::new_id(0x42));
+        assert_eq!(msg.method_num, EVM_INVOKE_CONTRACT_METHOD);
+
+        // Access lists aren't supported.
+        let mut access_list = fields.clone();
+        access_list[8] = Item::List(vec![Item::List(vec![])]);
+        assert_eq!(
+            EthTransaction::parse(&sign_eip1559(&key, access_list)),
+            Err(Error::AccessListUnsupported)
+        );
+
+        // Integers must be minimally encoded.
+        let mut padded = fields;
+        padded[1] = Item::Bytes(&[0x00, 0x07]);
+        assert_eq!(
+            EthTransaction::parse(&sign_eip1559(&key, padded)),
+            Err(invalid("nonce", "leading zeros"))
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(
+            EthTransaction::parse(&[0x01, 0xc0]),
+            Err(Error::UnsupportedType(0x01))
+        );
+        assert_eq!(
+            EthTransaction::parse(&[0x02, 0xc0]),
+            Err(Error::FieldCount { expected: 12 })
+        );
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! A minimal, strict, [RLP](https://ethereum.org/en/developers/docs/data-structures-and-encoding/rlp/)
+//! codec. Only canonical encodings are accepted, so decoding and re-encoding an item always yields
+//! the original bytes.
+
+use thiserror::Error;
+
+/// The maximum nesting depth of lists. Decoding recurses into nested lists, so this bounds the
+/// stack used on untrusted input. Transactions need at most 4 levels (the transaction, its access
+/// list, an access list entry, and its storage keys).
+pub const MAX_DEPTH: usize = 4;
+
+/// A decoded RLP item, borrowing from the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item<'a> {
+    Bytes(&'a [u8]),
+    List(Vec<Item<'a>>),
+}
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum Error {
+    #[error("unexpected end of input")]
+    UnexpectedEnd,
+    #[error("{0} trailing bytes after item")]
+    TrailingBytes(usize),
+    #[error("non-canonical encoding")]
+    NonCanonical,
+    #[error("item length overflows")]
+    Overflow,
+    #[error("lists nested more than {} levels deep", MAX_DEPTH)]
+    TooDeep,
+}
+
+/// Decodes a single item, which must span the entire input. Lists may be nested up to
+/// [`MAX_DEPTH`] levels deep.
+pub fn decode(data: &[u8]) -> Result<Item, Error> {
+    let (item, rest) = decode_item(data, 0)?;
+    if !rest.is_empty() {
+        return Err(Error::TrailingBytes(rest.len()));
+    }
+    Ok(item)
+}
+
+/// Decodes the item at the start of the input (nested in `depth` lists), returning the remaining
+/// input.
+fn decode_item(data: &[u8], depth: usize) -> Result<(Item, &[u8]), Error> {
+    let (&prefix, rest) = data.split_first().ok_or(Error::UnexpectedEnd)?;
+    let (is_list, len, data) = match prefix {
+        // Single bytes below 0x80 are their own encoding.
+        0x00..=0x7f => return Ok((Item::Bytes(&data[..1]), rest)),
+        0x80..=0xb7 => (false, (prefix - 0x80) as usize, rest),
+        0xb8..=0xbf => {
+            let (len, data) = decode_length(rest, (prefix - 0xb7) as usize)?;
+            (false, len, data)
+        }
+        0xc0..=0xf7 => (true, (prefix - 0xc0) as usize, rest),
+        0xf8..=0xff => {
+            let (len, data) = decode_length(rest, (prefix - 0xf7) as usize)?;
+            (true, len, data)
+        }
+    };
+    if data.len() < len {
+        return Err(Error::UnexpectedEnd);
+    }
+    let (payload, rest) = data.split_at(len);
+    let item = if is_list {
+        if depth >= MAX_DEPTH {
+            return Err(Error::TooDeep);
+        }
+        let mut items = Vec::new();
+        let mut payload = payload;
+        while !payload.is_empty() {
+            let (item, rest) = decode_item(payload, depth + 1)?;
+            items.push(item);
+            payload = rest;
+        }
+        Item::List(items)
+    } else {
+        // Single bytes below 0x80 must be encoded as themselves.
+        if len == 1 && payload[0] < 0x80 {
+            return Err(Error::NonCanonical);
+        }
+        Item::Bytes(payload)
+    };
+    Ok((item, rest))
+}
+
+/// Decodes a big-endian "long form" length of `len_len` bytes.
+fn decode_length(data: &[u8], len_len: usize) -> Result<(usize, &[u8]), Error> {
+    if data.len() < len_len {
+        return Err(Error::UnexpectedEnd);
+    }
+    let (len_bytes, data) = data.split_at(len_len);
+    if len_bytes[0] == 0 {
+        return Err(Error::NonCanonical);
+    }
+    if len_len > std::mem::size_of::<usize>() {
+        return Err(Error::Overflow);
+    }
+    let len = len_bytes
+        .iter()
+        .fold(0usize, |len, &b| (len << 8) | b as usize);
+    // Short lengths must use the short form.
+    if len <= 55 {
+        return Err(Error::NonCanonical);
+    }
+    Ok((len, data))
+}
+
+/// Encodes an item, appending it to the output.
+pub fn encode(item: &Item, out: &mut Vec<u8>) {
+    match item {
+        Item::Bytes([b]) if *b < 0x80 => out.push(*b),
+        Item::Bytes(bytes) => {
+            encode_header(0x80, bytes.len(), out);
+            out.extend_from_slice(bytes);
+        }
+        Item::List(items) => {
+            let mut payload = Vec::new();
+            for item in items {
+                encode(item, &mut payload);
+            }
+            encode_header(0xc0, payload.len(), out);
+            out.extend_from_slice(&payload);
+        }
+    }
+}
+
+fn encode_header(offset: u8, len: usize, out: &mut Vec<u8>) {
+    if len <= 55 {
+        out.push(offset + len as u8);
+    } else {
+        let len_bytes = len.to_be_bytes();
+        let len_bytes = &len_bytes[len_bytes.iter().take_while(|&&b| b == 0).count()..];
+        out.push(offset + 55 + len_bytes.len() as u8);
+        out.extend_from_slice(len_bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, encode_header, Error, Item, MAX_DEPTH};
+
+    fn roundtrip(data: &[u8], expected: Item) {
+        let item = decode(data).unwrap();
+        assert_eq!(item, expected);
+        let mut out = Vec::new();
+        encode(&item, &mut out);
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn canonical() {
+        roundtrip(&[0x80], Item::Bytes(&[]));
+        roundtrip(&[0x0f], Item::Bytes(&[0x0f]));
+        roundtrip(&[0x81, 0x80], Item::Bytes(&[0x80]));
+        roundtrip(&[0x83, b'd', b'o', b'g'], Item::Bytes(b"dog"));
+        roundtrip(&[0xc0], Item::List(vec![]));
+        roundtrip(
+            &[0xc5, 0x80, 0xc0, 0x82, 0x04, 0x00],
+            Item::List(vec![
+                Item::Bytes(&[]),
+                Item::List(vec![]),
+                Item::Bytes(&[0x04, 0x00]),
+            ]),
+        );
+
+        let long = [0xaa; 56];
+        let mut data = vec![0xb8, 56];
+        data.extend_from_slice(&long);
+        roundtrip(&data, Item::Bytes(&long));
+    }
+
+    #[test]
+    fn non_canonical() {
+        assert_eq!(decode(&[0x81, 0x00]), Err(Error::NonCanonical));
+        assert_eq!(decode(&[0xb8, 0x01, 0xaa]), Err(Error::NonCanonical));
+        assert_eq!(decode(&[0xb9, 0x00, 0x38]), Err(Error::NonCanonical));
+        assert_eq!(decode(&[0x82, 0xaa]), Err(Error::UnexpectedEnd));
+        assert_eq!(decode(&[0x80, 0x80]), Err(Error::TrailingBytes(1)));
+        assert_eq!(decode(&[]), Err(Error::UnexpectedEnd));
+    }
+
+    #[test]
+    fn max_depth() {
+        // Wraps an empty list in `levels - 1` more lists. Built back to front, prepending a header
+        // for everything after it at each level.
+        let nested = |levels: usize| {
+            let mut data = vec![0xc0];
+            for _ in 1..levels {
+                let mut header = Vec::new();
+                encode_header(0xc0, data.len(), &mut header);
+                data.extend(header.iter().rev());
+            }
+            data.reverse();
+            data
+        };
+        assert!(decode(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(decode(&nested(MAX_DEPTH + 1)), Err(Error::TooDeep));
+        // Deep nesting is rejected without recursing all the way down.
+        assert_eq!(decode(&nested(100_000)), Err(Error::TooDeep));
+    }
+}
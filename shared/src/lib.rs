@@ -13,12 +13,15 @@ pub mod address;
 pub mod bigint;
 pub mod chainid;
 pub mod clock;
+pub mod collections;
 pub mod commcid;
 pub mod consensus;
 pub mod crypto;
 pub mod deal;
 pub mod econ;
 pub mod error;
+#[cfg(feature = "eth")]
+pub mod eth;
 pub mod event;
 pub mod math;
 pub mod message;
@@ -121,6 +124,20 @@ pub struct Response {
     pub return_data: Option<IpldBlock>,
 }
 
+impl Response {
+    /// Decodes the return data, if any. On failure, this is the data the callee attached to its
+    /// exit (e.g., a revert reason).
+    pub fn deserialize_return<'de, T>(&'de self) -> Result<Option<T>, fvm_ipld_encoding::Error>
+    where
+        T: serde::Deserialize<'de>,
+    {
+        self.return_data
+            .as_ref()
+            .map(|b| b.deserialize())
+            .transpose()
+    }
+}
+
 // This is a somewhat nasty hack that lets us unwrap in a const function.
 const fn const_unwrap<T: Copy, E>(r: Result<T, E>) -> T {
     let v = match r {
@@ -158,3 +175,24 @@ fn test_empty_arr_cid() {
     let expected = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&empty));
     assert_eq!(EMPTY_ARR_CID, expected);
 }
+
+#[test]
+fn test_response_deserialize_return() {
+    let resp = Response {
+        exit_code: ExitCode::new(ExitCode::FIRST_ACTOR_SPECIFIC_EXIT_CODE),
+        return_data: IpldBlock::serialize_cbor(&"insufficient collateral").unwrap(),
+    };
+    assert!(resp.exit_code.is_actor_specific());
+    assert!(!resp.exit_code.is_standard_user_error());
+    assert_eq!(
+        resp.deserialize_return::<String>().unwrap().as_deref(),
+        Some("insufficient collateral")
+    );
+
+    let empty = Response {
+        exit_code: ExitCode::USR_ILLEGAL_ARGUMENT,
+        return_data: None,
+    };
+    assert!(empty.exit_code.is_standard_user_error());
+    assert_eq!(empty.deserialize_return::<String>().unwrap(), None);
+}
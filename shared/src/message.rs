@@ -3,9 +3,11 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use anyhow::anyhow;
+use cid::Cid;
 use fvm_ipld_encoding::de::{Deserialize, Deserializer};
 use fvm_ipld_encoding::ser::{Serialize, Serializer};
 use fvm_ipld_encoding::RawBytes;
+use serde_tuple::*;
 
 use crate::address::Address;
 use crate::econ::TokenAmount;
@@ -111,3 +113,18 @@ impl quickcheck::Arbitrary for Message {
         }
     }
 }
+
+/// The method a gas payer actor must export to sponsor the gas of other actors' messages (the
+/// FRC-42 hash of `AuthorizeGasSponsorship`). It's invoked (read-only) with
+/// [`AuthorizeGasSponsorshipParams`] and must exit successfully to approve the sponsorship.
+pub const AUTHORIZE_GAS_SPONSORSHIP_METHOD: MethodNum = 1158864337;
+
+/// The parameters passed to a gas payer's [`AUTHORIZE_GAS_SPONSORSHIP_METHOD`].
+#[derive(Serialize_tuple, Deserialize_tuple, PartialEq, Eq, Clone, Debug)]
+pub struct AuthorizeGasSponsorshipParams {
+    /// The CID of the sponsored message.
+    pub message: Cid,
+    /// Opaque approval data supplied alongside the message (e.g., the payer's signature over the
+    /// message CID).
+    pub approval: RawBytes,
+}
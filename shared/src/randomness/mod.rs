@@ -5,14 +5,28 @@
 use fvm_ipld_encoding::{BytesDe, BytesSer};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::version::NetworkVersion;
+
 // TODO: turn this back into a 32byte array once we no longer need go compat. It's a vec so that the
 // errors match.
 /// String of random bytes usually generated from a randomness beacon or from tickets on chain.
 #[derive(PartialEq, Eq, Default, Clone, Debug)]
 pub struct Randomness(pub Vec<u8>);
 
+/// The length of chain (ticket) randomness, and of the fixed-length randomness syscalls.
 pub const RANDOMNESS_LENGTH: usize = 32;
 
+/// The maximum length of beacon randomness, on any network version. Buffers of this size can hold
+/// the randomness returned by the variable-length beacon randomness syscall (e.g., 48-byte BLS
+/// signatures from a future beacon).
+pub const MAX_RANDOMNESS_LENGTH: usize = 64;
+
+/// Returns the length of the beacon randomness returned by the variable-length beacon randomness
+/// syscall on the given network version.
+pub const fn beacon_randomness_length(_network_version: NetworkVersion) -> usize {
+    RANDOMNESS_LENGTH
+}
+
 impl Serialize for Randomness {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -49,6 +49,87 @@ impl<'a> TryFrom<&'a crate::econ::TokenAmount> for TokenAmount {
     }
 }
 
+/// The kinds of metrics actors may report to the host with the `debug::metric` syscall.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum MetricKind {
+    /// A counter, incremented by the reported value.
+    Counter = 0,
+    /// A gauge, set to the reported value.
+    Gauge = 1,
+}
+
+impl TryFrom<u32> for MetricKind {
+    type Error = u32;
+
+    fn try_from(kind: u32) -> Result<Self, Self::Error> {
+        match kind {
+            0 => Ok(MetricKind::Counter),
+            1 => Ok(MetricKind::Gauge),
+            other => Err(other),
+        }
+    }
+}
+
+/// The operations whose gas cost actors may query with the `gas::price` syscall. Each is priced for
+/// a `size` (in bytes), and some take an additional parameter.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum PricedOperation {
+    /// Creating a block of `size` bytes (not counting the cost of its links).
+    BlockCreate = 0,
+    /// Reading `size` bytes of a block.
+    BlockRead = 1,
+    /// Hashing `size` bytes with the hash function whose multihash code is the parameter.
+    Hash = 2,
+    /// Verifying a signature over `size` bytes of plaintext, with the signature type (see
+    /// [`SignatureType`](crate::crypto::signature::SignatureType)) given as the parameter.
+    VerifySignature = 3,
+    /// Transferring value to another actor (`size` is ignored).
+    ValueTransfer = 4,
+}
+
+impl TryFrom<u32> for PricedOperation {
+    type Error = u32;
+
+    fn try_from(op: u32) -> Result<Self, Self::Error> {
+        match op {
+            0 => Ok(PricedOperation::BlockCreate),
+            1 => Ok(PricedOperation::BlockRead),
+            2 => Ok(PricedOperation::Hash),
+            3 => Ok(PricedOperation::VerifySignature),
+            4 => Ok(PricedOperation::ValueTransfer),
+            other => Err(other),
+        }
+    }
+}
+
+/// Whether an actor may be re-entered while it's executing, as set with the `vm::set_reentrancy`
+/// syscall.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[repr(u32)]
+pub enum ReentrancyPolicy {
+    /// The actor may be re-entered (the default).
+    #[default]
+    Allow = 0,
+    /// Calls into the actor (directly or transitively) fail with `Forbidden` until the current
+    /// invocation returns. Plain value transfers (method 0) are still allowed as they don't run
+    /// the actor's code.
+    Forbid = 1,
+}
+
+impl TryFrom<u32> for ReentrancyPolicy {
+    type Error = u32;
+
+    fn try_from(policy: u32) -> Result<Self, Self::Error> {
+        match policy {
+            0 => Ok(ReentrancyPolicy::Allow),
+            1 => Ok(ReentrancyPolicy::Forbid),
+            other => Err(other),
+        }
+    }
+}
+
 bitflags! {
     /// Flags passed to the send syscall.
     #[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
@@ -67,6 +148,24 @@ impl SendFlags {
     }
 }
 
+/// The version of the syscall ABI implemented by the FVM, returned by the `vm::abi_version`
+/// syscall. Bumped whenever syscalls are added or their semantics change.
+pub const SYSCALL_ABI_VERSION: u32 = 1;
+
+bitflags! {
+    /// Optional capabilities of the FVM, returned by the `vm::capabilities` syscall. Actors should
+    /// check for a capability before making the syscalls it provides.
+    #[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
+    #[repr(transparent)]
+    pub struct Capabilities: u64 {
+        /// Actors may upgrade themselves (`actor::upgrade_actor`).
+        const UPGRADE_ACTOR = 1 << 0;
+        /// Actors may install new actor code (`actor::install_actor` and
+        /// `actor::install_actor_code`).
+        const INSTALL_ACTOR = 1 << 1;
+    }
+}
+
 /// A fixed sized struct for serializing an [event `Entry`](crate::event::Entry) separately from the
 /// key/value bytes.
 #[repr(C, packed)]
@@ -106,8 +205,10 @@ assert_syscall_safe! {
     out::ipld::IpldOpen,
     out::ipld::IpldStat,
     out::send::Send,
+    out::send::SendResult,
     out::crypto::VerifyConsensusFault,
     out::network::NetworkContext,
+    out::network::EpochTiming,
     out::vm::MessageContext,
 }
 
@@ -44,6 +44,16 @@ pub mod send {
         pub return_codec: u64,
         pub return_size: u32,
     }
+
+    /// The result of a send, along with the gas it consumed.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[repr(packed, C)]
+    pub struct SendResult {
+        pub send: Send,
+        /// The gas consumed by the call: the callee's execution, and the cost of the send itself
+        /// (e.g., the value transfer).
+        pub gas_used: u64,
+    }
 }
 
 pub mod crypto {
@@ -124,4 +134,13 @@ pub mod network {
         /// The network version.
         pub network_version: NetworkVersion,
     }
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[repr(packed, C)]
+    pub struct EpochTiming {
+        /// The (expected) duration of an epoch, in seconds.
+        pub epoch_duration_seconds: u64,
+        /// The expected number of blocks per epoch.
+        pub blocks_per_epoch: u64,
+    }
 }
@@ -8,7 +8,8 @@ use std::str::FromStr;
 use data_encoding::{DecodeError, DecodeKind};
 use fvm_ipld_encoding::{from_slice, to_vec};
 use fvm_shared::address::{
-    Address, Error, Protocol, BLS_PUB_LEN, MAX_SUBADDRESS_LEN, PAYLOAD_HASH_LEN, SECP_PUB_LEN,
+    parse_lossless, Address, Error, Network, ParseError, Protocol, BLS_PUB_LEN, MAX_SUBADDRESS_LEN,
+    PAYLOAD_HASH_LEN, SECP_PUB_LEN,
 };
 use quickcheck_macros::quickcheck;
 
@@ -620,3 +621,82 @@ fn prop_address_roundtrip(addr0: Address) -> Result<(), String> {
     }
     Ok(())
 }
+
+#[test]
+fn lossless_parsing() {
+    assert_eq!(
+        parse_lossless("t01234"),
+        Ok((Address::new_id(1234), Network::Testnet))
+    );
+
+    // Unlike `from_str`, only canonical addresses are accepted.
+    assert_eq!(Address::from_str("f001234"), Ok(Address::new_id(1234)));
+    assert_eq!(
+        parse_lossless("f001234"),
+        Err(ParseError::NonCanonical {
+            input: "f001234".into(),
+            canonical: "f01234".into(),
+        })
+    );
+    let delegated = Network::Mainnet
+        .format_address(&Address::new_delegated(10, &[0; PAYLOAD_HASH_LEN]).unwrap());
+    let padded = delegated.replacen("f410f", "f4010f", 1);
+    assert_eq!(
+        parse_lossless(&padded),
+        Err(ParseError::NonCanonical {
+            input: padded.clone(),
+            canonical: delegated,
+        })
+    );
+
+    // Signs are rejected outright.
+    for input in [
+        "f0+1234",
+        "f0-1234",
+        "f4+10f2gfvuyh7v2sx3patm5k23wdzmhyhtmqctasbr23y",
+    ] {
+        assert_eq!(
+            parse_lossless(input),
+            Err(ParseError::Invalid {
+                input: input.into(),
+                error: Error::InvalidPayload,
+            }),
+            "input {input}",
+        );
+    }
+
+    // Payloads with valid checksums must still have the right length for their protocol.
+    let payload = [0; PAYLOAD_HASH_LEN - 1];
+    let checksum = blake2b_simd::Params::new()
+        .hash_length(4)
+        .to_state()
+        .update(&[Protocol::Secp256k1 as u8])
+        .update(&payload)
+        .finalize();
+    let encoded = data_encoding::BASE32_NOPAD
+        .encode(&[&payload[..], checksum.as_bytes()].concat())
+        .to_lowercase();
+    let input = format!("f1{encoded}");
+    assert_eq!(
+        parse_lossless(&input),
+        Err(ParseError::Invalid {
+            input: input.clone(),
+            error: Error::InvalidPayloadLength(PAYLOAD_HASH_LEN - 1),
+        })
+    );
+}
+
+#[quickcheck]
+fn prop_address_text_roundtrip(addr0: Address, testnet: bool) -> Result<(), String> {
+    let network = if testnet {
+        Network::Testnet
+    } else {
+        Network::Mainnet
+    };
+    let text = network.format_address(&addr0);
+    let (addr1, network1) = parse_lossless(&text).map_err(|e| e.to_string())?;
+    if addr1 != addr0 || network1 != network {
+        return Err(format!("address {text} differs after roundtrip"));
+    }
+    Ok(())
+}
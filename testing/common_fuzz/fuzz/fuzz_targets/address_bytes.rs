@@ -0,0 +1,21 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+#![no_main]
+
+use fvm_shared::address::{parse_lossless, Address, Network};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let addr = match Address::from_bytes(data) {
+        Ok(addr) => addr,
+        Err(_) => return,
+    };
+    assert_eq!(addr.to_bytes(), data, "address bytes must roundtrip");
+
+    let text = Network::Testnet.format_address(&addr);
+    assert_eq!(
+        parse_lossless(&text),
+        Ok((addr, Network::Testnet)),
+        "formatted addresses must parse"
+    );
+});
@@ -0,0 +1,33 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+#![no_main]
+
+use std::str::FromStr;
+
+use fvm_shared::address::{parse_lossless, Address};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    let lenient = Address::from_str(data);
+    let (addr, network) = match parse_lossless(data) {
+        Ok(res) => res,
+        Err(_) => return,
+    };
+    if network == fvm_shared::address::current_network() {
+        assert_eq!(
+            lenient,
+            Ok(addr),
+            "lossless parsing must agree with from_str"
+        );
+    }
+    assert_eq!(
+        network.format_address(&addr),
+        data,
+        "lossless parsing must roundtrip"
+    );
+    assert_eq!(
+        Address::from_bytes(&addr.to_bytes()),
+        Ok(addr),
+        "parsed addresses must roundtrip through bytes"
+    );
+});
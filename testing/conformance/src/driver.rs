@@ -190,6 +190,17 @@ fn compare_state_roots(bs: &MemoryBlockstore, root: &Cid, vector: &MessageVector
     ))
 }
 
+/// Returns the length of a message as included on chain, given the length of the encoded
+/// unsigned message. Messages from secp256k1 accounts are included with their signature.
+pub fn raw_length(msg: &Message, encoded_len: usize) -> usize {
+    if msg.from.protocol() == Protocol::Secp256k1 {
+        // 65 bytes signature + 1 byte type + 3 bytes for field info.
+        encoded_len + SECP_SIG_LEN + 4
+    } else {
+        encoded_len
+    }
+}
+
 /// Represents the result from running a vector.
 pub enum VariantResult {
     /// The vector succeeded.
@@ -247,11 +258,7 @@ pub fn run_variant(
         let msg: Message = from_slice(&m.bytes)?;
 
         // Execute the message.
-        let mut raw_length = m.bytes.len();
-        if msg.from.protocol() == Protocol::Secp256k1 {
-            // 65 bytes signature + 1 byte type + 3 bytes for field info.
-            raw_length += SECP_SIG_LEN + 4;
-        }
+        let raw_length = raw_length(&msg, m.bytes.len());
 
         let start = Instant::now();
         let ret = match exec.execute_message(msg, ApplyKind::Explicit, raw_length) {
@@ -311,3 +318,36 @@ pub fn run_variant(
 
     Ok(VariantResult::Ok { id })
 }
+
+/// Runs every variant of a message vector, checking the results against the vector's
+/// postconditions. Returns one result per variant.
+///
+/// This loads the vector's state into a fresh blockstore and runs the variants one after the
+/// other, without collecting stats or traces. Use [`run_variant`] directly for more control.
+pub async fn run_vector(v: &MessageVector, engines: &MultiEngine) -> Result<Vec<VariantResult>> {
+    if !v.is_supported() {
+        return Ok(v
+            .preconditions
+            .variants
+            .iter()
+            .map(|variant| VariantResult::Skipped {
+                id: variant.id.clone(),
+                reason: "selector not supported".to_owned(),
+            })
+            .collect());
+    }
+
+    let (bs, imported_roots) = v.seed_blockstore().await?;
+    if !imported_roots.contains(&v.preconditions.state_tree.root_cid) {
+        return Err(anyhow!(
+            "imported roots do not contain precondition CID {}",
+            v.preconditions.state_tree.root_cid
+        ));
+    }
+
+    v.preconditions
+        .variants
+        .iter()
+        .map(|variant| run_variant(bs.clone(), v, variant, engines, true, None, None))
+        .collect()
+}
@@ -0,0 +1,156 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Generation of message test vectors from live executions, so that other implementations can be
+//! tested against the reference FVM's results.
+use anyhow::Context as _;
+use cid::Cid;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use fvm::executor::ApplyRet;
+use fvm::externs::{ExternLookup, ReplayLog};
+use fvm::machine::car::export_car_roots;
+use fvm::machine::MachineContext;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::to_vec;
+use fvm_shared::message::Message;
+use fvm_shared::receipt::Receipt;
+use num_traits::ToPrimitive;
+
+use crate::vector::{
+    ApplyMessage, GenerationData, MessageVector, MetaData, PostConditions, PreConditions,
+    RandomnessKind, RandomnessMatch, RandomnessRule, StateTreeVector, Variant,
+};
+
+/// Records messages applied to a machine, along with their receipts, and generates a
+/// [`MessageVector`] reproducing the execution.
+///
+/// Create the generator from the context of the machine _before_ applying any messages, then
+/// [record](Self::record) each message (with its result) as it's applied, and finally
+/// [finish](Self::finish) the vector with the flushed state root. The generated vector is run like
+/// any other vector (see [`crate::driver::run_variant`]).
+///
+/// Messages must be applied explicitly, with a raw length equal to the length of the encoded
+/// message (plus the length of the signature for secp256k1 senders, see
+/// [`crate::driver::raw_length`]), otherwise the gas used won't match when running the vector.
+pub struct VectorGenerator {
+    id: String,
+    description: String,
+    variant: Variant,
+    preconditions: PreConditions,
+    apply_messages: Vec<ApplyMessage>,
+    receipts: Vec<Receipt>,
+    randomness: Vec<RandomnessMatch>,
+}
+
+impl VectorGenerator {
+    /// Creates a generator for a vector with the given ID, starting from the machine's initial
+    /// state, at its epoch and network version.
+    pub fn new(id: impl Into<String>, context: &MachineContext) -> Self {
+        let id = id.into();
+        VectorGenerator {
+            variant: Variant {
+                id: id.clone(),
+                epoch: context.epoch,
+                nv: context.network.network_version.into(),
+            },
+            preconditions: PreConditions {
+                state_tree: StateTreeVector {
+                    root_cid: context.initial_state_root,
+                },
+                basefee: context.base_fee.atto().to_u128(),
+                circ_supply: context.circ_supply.atto().to_u128(),
+                variants: Vec::new(),
+            },
+            id,
+            description: String::new(),
+            apply_messages: Vec::new(),
+            receipts: Vec::new(),
+            randomness: Vec::new(),
+        }
+    }
+
+    /// Sets the vector's description.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Records the randomness looked up during the execution, so it's replayed when running the
+    /// vector. Record the machine's externs with [`fvm::externs::RecordingExterns`] to get the
+    /// log.
+    ///
+    /// Only chain and beacon randomness can be stored in vectors. Other lookups are ignored.
+    pub fn randomness(mut self, log: &ReplayLog) -> Self {
+        self.randomness
+            .extend(log.0.iter().filter_map(|lookup| match lookup {
+                ExternLookup::ChainRandomness { epoch, randomness } => Some(RandomnessMatch {
+                    on: RandomnessRule {
+                        kind: RandomnessKind::Chain,
+                        epoch: *epoch,
+                    },
+                    ret: randomness.to_vec(),
+                }),
+                ExternLookup::BeaconRandomness { epoch, randomness } => Some(RandomnessMatch {
+                    on: RandomnessRule {
+                        kind: RandomnessKind::Beacon,
+                        epoch: *epoch,
+                    },
+                    ret: randomness.to_vec(),
+                }),
+                _ => None,
+            }));
+        self
+    }
+
+    /// Records an applied message and its result.
+    pub fn record(&mut self, msg: &Message, ret: &ApplyRet) -> anyhow::Result<()> {
+        self.apply_messages.push(ApplyMessage {
+            bytes: to_vec(msg).context("failed to encode message")?,
+            epoch_offset: None,
+        });
+        self.receipts.push(ret.msg_receipt.clone());
+        Ok(())
+    }
+
+    /// Generates the vector, given the state root after applying (and flushing) all recorded
+    /// messages. The vector embeds all state reachable from the initial and final state roots,
+    /// which must be in `store`.
+    pub fn finish<BS: Blockstore>(
+        self,
+        store: &BS,
+        post_state_root: Cid,
+    ) -> anyhow::Result<MessageVector> {
+        let pre_state_root = self.preconditions.state_tree.root_cid;
+        let mut car = GzEncoder::new(Vec::new(), Compression::default());
+        export_car_roots(store, &[pre_state_root, post_state_root], &mut car)?;
+        let car = car.finish().context("failed to compress state")?;
+
+        Ok(MessageVector {
+            selector: None,
+            meta: Some(MetaData {
+                id: self.id,
+                version: String::new(),
+                description: self.description,
+                comment: String::new(),
+                gen: vec![GenerationData {
+                    source: env!("CARGO_PKG_NAME").into(),
+                    version: env!("CARGO_PKG_VERSION").into(),
+                }],
+            }),
+            car,
+            preconditions: PreConditions {
+                variants: vec![self.variant],
+                ..self.preconditions
+            },
+            apply_messages: self.apply_messages,
+            postconditions: PostConditions {
+                state_tree: StateTreeVector {
+                    root_cid: post_state_root,
+                },
+                receipts: self.receipts,
+                receipts_roots: Vec::new(),
+            },
+            randomness: self.randomness,
+        })
+    }
+}
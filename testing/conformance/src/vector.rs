@@ -4,7 +4,7 @@
 
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, BufWriter, Write};
 use std::path::Path;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -18,17 +18,17 @@ use fvm_ipld_car::load_car;
 use fvm_ipld_encoding::tuple::*;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::receipt::Receipt;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::actors::load_actors;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StateTreeVector {
     #[serde(with = "super::cidjson")]
     pub root_cid: Cid,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GenerationData {
     #[serde(default)]
     pub source: String,
@@ -36,7 +36,7 @@ pub struct GenerationData {
     pub version: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MetaData {
     pub id: String,
     #[serde(default)]
@@ -48,7 +48,7 @@ pub struct MetaData {
     pub gen: Vec<GenerationData>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PreConditions {
     pub state_tree: StateTreeVector,
     #[serde(default)]
@@ -59,7 +59,7 @@ pub struct PreConditions {
     pub variants: Vec<Variant>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PostConditions {
     pub state_tree: StateTreeVector,
     #[serde(with = "message_receipt_vec")]
@@ -68,7 +68,7 @@ pub struct PostConditions {
     pub receipts_roots: Vec<Cid>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Selector {
     #[serde(default)]
     pub chaos_actor: Option<String>,
@@ -88,7 +88,7 @@ impl Selector {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Variant {
     pub id: String,
     pub epoch: ChainEpoch,
@@ -99,14 +99,14 @@ pub struct Variant {
 pub type Randomness = Vec<RandomnessMatch>;
 
 /// One randomness entry.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RandomnessMatch {
     pub on: RandomnessRule,
     #[serde(with = "base64_bytes")]
     pub ret: Vec<u8>,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum RandomnessKind {
     Beacon,
@@ -114,14 +114,15 @@ pub enum RandomnessKind {
 }
 
 /// Rule for matching when randomness is returned.
-#[derive(Debug, Deserialize_tuple, PartialEq, Eq, Clone)]
+#[derive(Debug, Serialize_tuple, Deserialize_tuple, PartialEq, Eq, Clone)]
 pub struct RandomnessRule {
     pub kind: RandomnessKind,
     pub epoch: ChainEpoch,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MessageVector {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub selector: Option<Selector>,
     #[serde(rename = "_meta")]
     pub meta: Option<MetaData>,
@@ -175,6 +176,32 @@ impl MessageVector {
         Ok(serde_json::from_str(&vector_json)?)
     }
 
+    /// Writes the message vector to a file, as JSON.
+    pub fn to_file(&self, path: &Path) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.to_writer(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the message vector to `writer`, as JSON.
+    pub fn to_writer(&self, writer: impl Write) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Tagged<'a> {
+            class: &'static str,
+            #[serde(flatten)]
+            vector: &'a MessageVector,
+        }
+        serde_json::to_writer_pretty(
+            writer,
+            &Tagged {
+                class: "message",
+                vector: self,
+            },
+        )
+        .context("failed to write test vector")
+    }
+
     /// Returns true if the vector is supported.
     pub fn is_supported(&self) -> bool {
         self.selector.as_ref().map_or(true, Selector::supported)
@@ -207,7 +234,7 @@ impl<R: std::io::Read + Unpin + std::io::BufRead> AsyncRead for GzipDecoder<R> {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ApplyMessage {
     #[serde(with = "base64_bytes")]
     pub bytes: Vec<u8>,
@@ -223,6 +250,15 @@ mod base64_bytes {
 
     use super::*;
 
+    pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        base64::engine::general_purpose::STANDARD
+            .encode(bytes)
+            .serialize(serializer)
+    }
+
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
     where
         D: Deserializer<'de>,
@@ -240,7 +276,7 @@ mod message_receipt_vec {
 
     use super::*;
 
-    #[derive(Deserialize)]
+    #[derive(Serialize, Deserialize)]
     pub struct MessageReceiptVector {
         exit_code: ExitCode,
         #[serde(rename = "return", with = "base64_bytes")]
@@ -248,6 +284,21 @@ mod message_receipt_vec {
         gas_used: u64,
     }
 
+    pub fn serialize<S>(receipts: &[Receipt], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        receipts
+            .iter()
+            .map(|r| MessageReceiptVector {
+                exit_code: r.exit_code,
+                return_value: r.return_data.to_vec(),
+                gas_used: r.gas_used,
+            })
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Receipt>, D::Error>
     where
         D: Deserializer<'de>,
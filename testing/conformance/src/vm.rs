@@ -13,7 +13,10 @@ use fvm::call_manager::{CallManager, DefaultCallManager};
 use fvm::gas::{price_list_by_network_version, Gas, GasTimer, PriceList};
 use fvm::kernel::*;
 use fvm::machine::limiter::MemoryLimiter;
-use fvm::machine::{DefaultMachine, Machine, MachineContext, Manifest, NetworkConfig};
+use fvm::machine::{
+    ActorNameResolver, AddressManager, DefaultMachine, EventSink, Machine, MachineContext,
+    Manifest, MetricSink, Metrics, NetworkConfig, SignatureVerifier,
+};
 use fvm::state_tree::StateTree;
 use fvm::DefaultKernel;
 use fvm_ipld_blockstore::MemoryBlockstore;
@@ -25,11 +28,11 @@ use fvm_shared::crypto::signature::{
 };
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::piece::PieceInfo;
-use fvm_shared::randomness::RANDOMNESS_LENGTH;
+use fvm_shared::randomness::{Randomness, RANDOMNESS_LENGTH};
 use fvm_shared::sector::{
     AggregateSealVerifyProofAndInfos, RegisteredSealProof, ReplicaUpdateInfo, SealVerifyInfo,
 };
-use fvm_shared::sys::{EventEntry, SendFlags};
+use fvm_shared::sys::{EventEntry, MetricKind, PricedOperation, ReentrancyPolicy, SendFlags};
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::{ActorID, MethodNum, TOTAL_FILECOIN};
 use wasmtime::Linker;
@@ -161,6 +164,10 @@ where
         self.machine.state_tree_mut()
     }
 
+    fn retain_block(&mut self, k: Cid) {
+        self.machine.retain_block(k)
+    }
+
     fn into_store(self) -> Self::Blockstore {
         self.machine.into_store()
     }
@@ -173,6 +180,38 @@ where
         self.machine.machine_id()
     }
 
+    fn fingerprint(&self) -> [u8; 32] {
+        self.machine.fingerprint()
+    }
+
+    fn proof_pool(&self) -> Option<&rayon::ThreadPool> {
+        self.machine.proof_pool()
+    }
+
+    fn event_sink(&self) -> Option<&dyn EventSink> {
+        self.machine.event_sink()
+    }
+
+    fn metric_sink(&self) -> Option<&dyn MetricSink> {
+        self.machine.metric_sink()
+    }
+
+    fn metrics(&self) -> &dyn Metrics {
+        self.machine.metrics()
+    }
+
+    fn signature_verifier(&self, namespace: ActorID) -> Option<&dyn SignatureVerifier> {
+        self.machine.signature_verifier(namespace)
+    }
+
+    fn address_manager(&self, namespace: ActorID) -> Option<&dyn AddressManager> {
+        self.machine.address_manager(namespace)
+    }
+
+    fn actor_name_resolver(&self) -> Option<&dyn ActorNameResolver> {
+        self.machine.actor_name_resolver()
+    }
+
     fn new_limiter(&self) -> Self::Limiter {
         TestLimiter {
             inner: self.machine.new_limiter(),
@@ -258,6 +297,18 @@ where
     fn upgrade_actor<KK>(&mut self, new_code_cid: Cid, params_id: BlockId) -> Result<CallResult> {
         self.0.upgrade_actor::<Self>(new_code_cid, params_id)
     }
+
+    fn create_and_invoke<KK>(
+        &mut self,
+        code_cid: Cid,
+        actor_id: ActorID,
+        delegated_address: Option<Address>,
+        params_id: BlockId,
+        value: &TokenAmount,
+    ) -> Result<CallResult> {
+        self.0
+            .create_and_invoke::<Self>(code_cid, actor_id, delegated_address, params_id, value)
+    }
 }
 
 impl<M, C, K> SyscallHandler<TestKernel<K>> for TestKernel<K>
@@ -305,6 +356,10 @@ where
         Ok(())
     }
 
+    fn install_actor_code(&mut self, code_id: BlockId) -> Result<Cid> {
+        self.0.install_actor_code(code_id)
+    }
+
     fn get_builtin_actor_type(&self, code_cid: &Cid) -> Result<u32> {
         self.0.get_builtin_actor_type(code_cid)
     }
@@ -378,6 +433,13 @@ where
         self.0.verify_post(verify_info)
     }
 
+    fn batch_verify_posts(
+        &self,
+        infos: &[fvm_shared::sector::WindowPoStVerifyInfo],
+    ) -> Result<Vec<bool>> {
+        self.0.batch_verify_posts(infos)
+    }
+
     // NOT forwarded
     fn batch_verify_seals(&self, vis: &[SealVerifyInfo]) -> Result<Vec<bool>> {
         Ok(vec![true; vis.len()])
@@ -452,7 +514,7 @@ where
     C: CallManager<Machine = TestMachine<M>>,
     K: Kernel<CallManager = C>,
 {
-    fn log(&self, msg: String) {
+    fn log(&mut self, msg: String) {
         self.0.log(msg)
     }
 
@@ -460,9 +522,13 @@ where
         self.0.debug_enabled()
     }
 
-    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()> {
+    fn store_artifact(&mut self, name: &str, data: &[u8]) -> Result<()> {
         self.0.store_artifact(name, data)
     }
+
+    fn record_metric(&self, name: &str, kind: MetricKind, value: i64) -> Result<()> {
+        self.0.record_metric(name, kind, value)
+    }
 }
 
 impl<M, C, K> GasOps for TestKernel<K>
@@ -483,9 +549,17 @@ where
         self.0.price_list()
     }
 
+    fn price_of(&self, op: PricedOperation, size: usize, param: u64) -> Result<Gas> {
+        self.0.price_of(op, size, param)
+    }
+
     fn gas_available(&self) -> Gas {
         self.0.gas_available()
     }
+
+    fn record_syscall(&self, module: &'static str, name: &'static str) {
+        self.0.record_syscall(module, name)
+    }
 }
 
 impl<M, C, K> MessageOps for TestKernel<K>
@@ -497,6 +571,18 @@ where
     fn msg_context(&self) -> Result<fvm_shared::sys::out::vm::MessageContext> {
         self.0.msg_context()
     }
+
+    fn call_depth(&self) -> Result<u32> {
+        self.0.call_depth()
+    }
+
+    fn call_stack(&self) -> Result<Vec<ActorID>> {
+        self.0.call_stack()
+    }
+
+    fn set_reentrancy(&mut self, policy: ReentrancyPolicy) -> Result<()> {
+        self.0.set_reentrancy(policy)
+    }
 }
 
 impl<M, C, K> NetworkOps for TestKernel<K>
@@ -512,6 +598,14 @@ where
     fn tipset_cid(&self, epoch: ChainEpoch) -> Result<Cid> {
         self.0.tipset_cid(epoch)
     }
+
+    fn machine_fingerprint(&self) -> Result<[u8; 32]> {
+        self.0.machine_fingerprint()
+    }
+
+    fn epoch_timing(&self) -> Result<fvm_shared::sys::out::network::EpochTiming> {
+        self.0.epoch_timing()
+    }
 }
 
 impl<M, C, K> RandomnessOps for TestKernel<K>
@@ -533,6 +627,10 @@ where
     ) -> Result<[u8; RANDOMNESS_LENGTH]> {
         self.0.get_randomness_from_beacon(rand_epoch)
     }
+
+    fn get_randomness_from_beacon_bytes(&self, rand_epoch: ChainEpoch) -> Result<Randomness> {
+        self.0.get_randomness_from_beacon_bytes(rand_epoch)
+    }
 }
 
 impl<M, C, K> SelfOps for TestKernel<K>
@@ -1,10 +1,7 @@
 // Copyright 2021-2023 Protocol Labs
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
-use std::collections::HashMap;
 use std::env::var;
-use std::fs::File;
-use std::io::BufReader;
 use std::iter;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -17,7 +14,7 @@ use fvm::engine::MultiEngine;
 use fvm_conformance_tests::driver::*;
 use fvm_conformance_tests::report;
 use fvm_conformance_tests::tracing::{TestTraceExporter, TestTraceExporterRef};
-use fvm_conformance_tests::vector::{MessageVector, Selector};
+use fvm_conformance_tests::vector::MessageVector;
 use fvm_conformance_tests::vm::{TestStatsGlobal, TestStatsRef};
 use itertools::Itertools;
 use lazy_static::lazy_static;
@@ -191,111 +188,74 @@ async fn run_vector(
     stats: TestStatsRef,
     tracer: TestTraceExporterRef,
 ) -> anyhow::Result<impl Iterator<Item = impl Future<Output = anyhow::Result<VariantResult>>>> {
-    let file = File::open(&path)?;
-    let reader = BufReader::new(file);
-
-    // Test vectors have the form:
-    //
-    //     { "class": ..., rest... }
-    //
-    // Unfortunately:
-    // 1. That means we need to use serde's "flatten" and/or "tag" feature to decode them.
-    // 2. Serde's JSON library doesn't support arbitrary precision numbers when doing this.
-    // 3. The circulating supply doesn't fit in a u64, and f64 isn't precise enough.
-    //
-    // So we manually:
-    // 1. Decode into a map of `String` -> `raw data`.
-    // 2. Pull off the class.
-    // 3. Re-serialize.
-    // 4. Decode into the correct type.
-    //
-    // Upstream bug is https://github.com/serde-rs/serde/issues/1183 (or at least that looks like
-    // the most appropriate one out of all the related issues).
-    let mut vector: HashMap<String, Box<serde_json::value::RawValue>> =
-        serde_json::from_reader(reader).context("failed to parse vector")?;
-    let class_json = vector
-        .remove("class")
-        .context("expected test vector to have a class")?;
-
-    let class: &str =
-        serde_json::from_str(class_json.get()).context("failed to parse test vector class")?;
-    let vector_json = serde_json::to_string(&vector)?;
+    let v = MessageVector::from_file(&path).context("failed to parse message vector")?;
+    if !v.is_supported() {
+        Ok(either::Either::Left(
+            v.preconditions.variants.into_iter().map(|variant| {
+                futures::future::Either::Left(async move {
+                    Ok(VariantResult::Skipped {
+                        id: variant.id,
+                        reason: "selector not supported".to_owned(),
+                    })
+                })
+            }),
+        ))
+    } else {
+        // First import the blockstore and do some sanity checks.
+        let (bs, imported_root) = v.seed_blockstore().await?;
+        if !imported_root.contains(&v.preconditions.state_tree.root_cid) {
+            return Err(anyhow!(
+                "imported roots ({}) do not contain precondition CID {}",
+                imported_root.iter().join(", "),
+                v.preconditions.state_tree.root_cid
+            ));
+        }
+        if !imported_root.contains(&v.postconditions.state_tree.root_cid) {
+            let msg = format!(
+                "imported roots ({}) do not contain postcondition CID {}",
+                imported_root.iter().join(", "),
+                v.postconditions.state_tree.root_cid
+            );
 
-    match class {
-        "message" => {
-            let v: MessageVector =
-                serde_json::from_str(&vector_json).context("failed to parse message vector")?;
-            let skip = !v.selector.as_ref().map_or(true, Selector::supported);
-            if skip {
-                Ok(either::Either::Left(
-                    v.preconditions.variants.into_iter().map(|variant| {
-                        futures::future::Either::Left(async move {
-                            Ok(VariantResult::Skipped {
-                                id: variant.id,
-                                reason: "selector not supported".to_owned(),
-                            })
-                        })
-                    }),
-                ))
-            } else {
-                // First import the blockstore and do some sanity checks.
-                let (bs, imported_root) = v.seed_blockstore().await?;
-                if !imported_root.contains(&v.preconditions.state_tree.root_cid) {
-                    return Err(anyhow!(
-                        "imported roots ({}) do not contain precondition CID {}",
-                        imported_root.iter().join(", "),
-                        v.preconditions.state_tree.root_cid
-                    ));
+            match *TEST_VECTOR_POSTCONDITION_MISSING_ACTION {
+                ErrorAction::Error => {
+                    return Err(anyhow!(msg));
                 }
-                if !imported_root.contains(&v.postconditions.state_tree.root_cid) {
-                    let msg = format!(
-                        "imported roots ({}) do not contain postcondition CID {}",
-                        imported_root.iter().join(", "),
-                        v.postconditions.state_tree.root_cid
-                    );
-
-                    match *TEST_VECTOR_POSTCONDITION_MISSING_ACTION {
-                        ErrorAction::Error => {
-                            return Err(anyhow!(msg));
-                        }
-                        ErrorAction::Warn => {
-                            eprintln!("WARN: {msg} in {}", path.display())
-                        }
-                        ErrorAction::Ignore => (),
-                    }
+                ErrorAction::Warn => {
+                    eprintln!("WARN: {msg} in {}", path.display())
                 }
-
-                let v = sync::Arc::new(v);
-                Ok(either::Either::Right(
-                    (0..v.preconditions.variants.len()).map(move |i| {
-                        let v = v.clone();
-                        let bs = bs.clone();
-                        let path = path.clone();
-                        let variant_id = v.preconditions.variants[i].id.clone();
-                        let name = format!("{} | {}", path.display(), variant_id);
-                        let stats = stats.clone();
-                        let tracer = tracer.clone();
-                        futures::future::Either::Right(
-                            task::Builder::new()
-                                .name(name.clone())
-                                .spawn(async move {
-                                    run_variant(
-                                        bs,
-                                        &v,
-                                        &v.preconditions.variants[i],
-                                        &ENGINES,
-                                        true,
-                                        stats,
-                                        tracer.map(|t| t.export_fun(path, variant_id)),
-                                    )
-                                    .with_context(|| format!("failed to run {name}"))
-                                })
-                                .unwrap(),
-                        )
-                    }),
-                ))
+                ErrorAction::Ignore => (),
             }
         }
-        other => Err(anyhow!("unknown test vector class: {}", other)),
+
+        let v = sync::Arc::new(v);
+        Ok(either::Either::Right(
+            (0..v.preconditions.variants.len()).map(move |i| {
+                let v = v.clone();
+                let bs = bs.clone();
+                let path = path.clone();
+                let variant_id = v.preconditions.variants[i].id.clone();
+                let name = format!("{} | {}", path.display(), variant_id);
+                let stats = stats.clone();
+                let tracer = tracer.clone();
+                futures::future::Either::Right(
+                    task::Builder::new()
+                        .name(name.clone())
+                        .spawn(async move {
+                            run_variant(
+                                bs,
+                                &v,
+                                &v.preconditions.variants[i],
+                                &ENGINES,
+                                true,
+                                stats,
+                                tracer.map(|t| t.export_fun(path, variant_id)),
+                            )
+                            .with_context(|| format!("failed to run {name}"))
+                        })
+                        .unwrap(),
+                )
+            }),
+        ))
     }
 }
@@ -0,0 +1,39 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use fvm::executor::ApplyRet;
+use fvm_shared::event::StampedEvent;
+use fvm_shared::ActorID;
+
+/// Assertions on the result of a message, for concise actor tests.
+pub trait ApplyRetAssertions {
+    /// Returns the first event emitted by the message that matches `matcher`. Panics, listing the
+    /// emitted events, if none does.
+    fn expect_event(&self, matcher: impl Fn(&StampedEvent) -> bool) -> &StampedEvent;
+
+    /// Panics unless the message used between `lo` and `hi` gas (inclusive).
+    fn assert_gas_between(&self, lo: u64, hi: u64);
+}
+
+impl ApplyRetAssertions for ApplyRet {
+    #[track_caller]
+    fn expect_event(&self, matcher: impl Fn(&StampedEvent) -> bool) -> &StampedEvent {
+        match self.events.iter().find(|evt| matcher(evt)) {
+            Some(evt) => evt,
+            None => panic!("no matching event; emitted: {:#?}", self.events),
+        }
+    }
+
+    #[track_caller]
+    fn assert_gas_between(&self, lo: u64, hi: u64) {
+        let gas_used = self.msg_receipt.gas_used;
+        assert!(
+            (lo..=hi).contains(&gas_used),
+            "expected between {lo} and {hi} gas to be used, but {gas_used} was"
+        );
+    }
+}
+
+/// Matches events emitted by `emitter` with an entry with the given key.
+pub fn event_with_key(emitter: ActorID, key: &str) -> impl Fn(&StampedEvent) -> bool + '_ {
+    move |evt| evt.emitter == emitter && evt.event.entries.iter().any(|e| e.key == key)
+}
@@ -7,6 +7,7 @@ use fvm_shared::IDENTITY_HASH;
 use multihash::Multihash;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
+#[derive(Clone)]
 pub struct DummyExterns;
 
 impl Externs for DummyExterns {}
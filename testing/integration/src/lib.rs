@@ -1,5 +1,6 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+pub mod assertions;
 mod builtin;
 pub mod bundle;
 pub mod dummy;
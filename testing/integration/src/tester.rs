@@ -3,23 +3,30 @@
 use anyhow::{anyhow, Context, Result};
 use cid::Cid;
 use fvm::call_manager::DefaultCallManager;
+use fvm::cron_actor::{CRON_ACTOR_ID, EPOCH_TICK_METHOD};
 use fvm::engine::EnginePool;
-use fvm::executor::DefaultExecutor;
+use fvm::executor::{ApplyKind, ApplyRet, DefaultExecutor, Executor};
 use fvm::externs::Externs;
 use fvm::kernel::filecoin::DefaultFilecoinKernel;
 use fvm::machine::{DefaultMachine, Machine, MachineContext, NetworkConfig};
 use fvm::state_tree::{ActorState, StateTree};
+use fvm::system_actor::SYSTEM_ACTOR_ID;
 use fvm::{init_actor, system_actor, DefaultKernel};
 use fvm_ipld_blockstore::{Block, Blockstore, MemoryBlockstore};
-use fvm_ipld_encoding::{ser, CborStore};
+use fvm_ipld_encoding::{de, ser, CborStore};
 use fvm_shared::address::{Address, Protocol};
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::crypto::signature::SignatureType;
 use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
 use fvm_shared::state::StateTreeVersion;
 use fvm_shared::version::NetworkVersion;
-use fvm_shared::{ActorID, IPLD_RAW};
+use fvm_shared::{ActorID, BLOCK_GAS_LIMIT, IPLD_RAW};
 use lazy_static::lazy_static;
 use libsecp256k1::{PublicKey, SecretKey};
 use multihash::Code;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 
 use crate::builtin::{
     fetch_builtin_code_cid, set_burnt_funds_account, set_eam_actor, set_init_actor, set_sys_actor,
@@ -40,6 +47,19 @@ pub type IntegrationExecutor<B, E> =
 
 pub type Account = (ActorID, Address);
 
+/// An account created with [`Tester::create_account`], along with its private key.
+pub struct KeyedAccount {
+    pub id: ActorID,
+    pub address: Address,
+    pub key: AccountKey,
+}
+
+/// The private key of a [`KeyedAccount`].
+pub enum AccountKey {
+    Secp256k1(SecretKey),
+    Bls(bls_signatures::PrivateKey),
+}
+
 /// Execution options
 #[derive(Clone, Debug, Default)]
 pub struct ExecutionOptions {
@@ -64,6 +84,10 @@ pub struct Tester<B: Blockstore + 'static, E: Externs + 'static> {
     code_cids: Vec<Cid>,
     // Executor used to interact with deployed actors.
     pub executor: Option<IntegrationExecutor<B, E>>,
+    // Context the current machine was instantiated with, to re-instantiate it at other epochs.
+    context: Option<MachineContext>,
+    // Engine pool shared by all the machines instantiated by the tester.
+    engine: Option<EnginePool>,
     // State tree constructed before instantiating the Machine
     pub state_tree: Option<StateTree<B>>,
 
@@ -72,6 +96,9 @@ pub struct Tester<B: Blockstore + 'static, E: Externs + 'static> {
 
     // ready if the machine has been instantiated
     pub ready: bool,
+
+    // Generates the keys of accounts created with `create_account`.
+    rng: ChaCha8Rng,
 }
 
 impl<B, E> Tester<B, E>
@@ -110,21 +137,22 @@ where
             nv,
             builtin_actors,
             executor: None,
+            context: None,
+            engine: None,
             code_cids: vec![],
             state_tree: Some(state_tree),
             accounts_code_cid,
             placeholder_code_cid,
             options: None,
             ready: false,
+            rng: ChaCha8Rng::seed_from_u64(0),
         })
     }
 
     /// Creates new accounts in the testing context
     /// Inserts the specified number of accounts in the state tree, all with 1000 FIL，returning their IDs and Addresses.
     pub fn create_accounts<const N: usize>(&mut self) -> Result<[Account; N]> {
-        use rand::SeedableRng;
-
-        let rng = &mut rand_chacha::ChaCha8Rng::seed_from_u64(8);
+        let rng = &mut ChaCha8Rng::seed_from_u64(8);
 
         let mut ret: [Account; N] = [(0, Address::default()); N];
         for account in ret.iter_mut().take(N) {
@@ -134,9 +162,74 @@ where
         Ok(ret)
     }
 
-    pub fn create_account(&mut self) -> Result<Account> {
-        let accounts: [Account; 1] = self.create_accounts()?;
-        Ok(accounts[0])
+    /// Creates a new account with a fresh key of the given type, funded with
+    /// [`INITIAL_ACCOUNT_BALANCE`], returning it along with its key.
+    ///
+    /// Accounts can be created both before and after the machine is instantiated.
+    pub fn create_account(&mut self, signature_type: SignatureType) -> Result<KeyedAccount> {
+        let (address, key) = match signature_type {
+            SignatureType::Secp256k1 => {
+                let key = SecretKey::random(&mut self.rng);
+                let pub_key = PublicKey::from_secret_key(&key);
+                let address = Address::new_secp256k1(&pub_key.serialize())?;
+                (address, AccountKey::Secp256k1(key))
+            }
+            SignatureType::BLS => {
+                let key = bls_signatures::PrivateKey::generate(&mut self.rng);
+                let address = Address::new_bls(&key.public_key().as_bytes())?;
+                (address, AccountKey::Bls(key))
+            }
+        };
+        let id = self.fund(&address, INITIAL_ACCOUNT_BALANCE.clone())?;
+        Ok(KeyedAccount { id, address, key })
+    }
+
+    /// Mints `amount` into the balance of the actor at `address`, like a faucet, returning its ID.
+    /// If there's no such actor and `address` is a secp256k1 or BLS address, an account is
+    /// created for it.
+    pub fn fund(&mut self, address: &Address, amount: TokenAmount) -> Result<ActorID> {
+        let accounts_code_cid = self.accounts_code_cid;
+        match (&mut self.executor, &mut self.state_tree) {
+            (Some(executor), _) => credit_actor(
+                executor.state_tree_mut(),
+                accounts_code_cid,
+                address,
+                amount,
+            ),
+            (None, Some(state_tree)) => {
+                credit_actor(state_tree, accounts_code_cid, address, amount)
+            }
+            (None, None) => Err(anyhow!("no state tree")),
+        }
+    }
+
+    /// Transfers `value` from `from` to `to` with an explicit message (at the sender's next
+    /// sequence number), returning its result.
+    pub fn transfer(
+        &mut self,
+        from: &Address,
+        to: &Address,
+        value: TokenAmount,
+    ) -> Result<ApplyRet> {
+        let executor = self
+            .executor
+            .as_mut()
+            .ok_or_else(|| anyhow!("machine not instantiated"))?;
+        let sequence = executor
+            .state_tree()
+            .get_actor_by_address(from)?
+            .ok_or_else(|| anyhow!("sender {from} doesn't exist"))?
+            .sequence;
+        let msg = Message {
+            from: *from,
+            to: *to,
+            value,
+            sequence,
+            gas_limit: BLOCK_GAS_LIMIT,
+            ..Message::default()
+        };
+        let raw_length = fvm_ipld_encoding::to_vec(&msg)?.len();
+        executor.execute_message(msg, ApplyKind::Explicit, raw_length)
     }
 
     pub fn set_account_sequence(&mut self, id: ActorID, new_sequence: u64) -> anyhow::Result<()> {
@@ -197,6 +290,12 @@ where
         Ok(state_cid)
     }
 
+    /// Put the code of a shared library module in the blockstore, returning its CID (see
+    /// [`NetworkConfig::add_shared_module`]).
+    pub fn put_shared_module(&mut self, wasm_bin: &[u8]) -> Result<Cid> {
+        put_wasm_code(self.state_tree.as_mut().unwrap().store(), wasm_bin)
+    }
+
     /// Set a new actor at a given address, provided with a given token balance
     /// and returns the CodeCID of the installed actor
     pub fn set_actor_from_bin(
@@ -292,8 +391,120 @@ where
         // Custom configuration.
         configure_mc(&mut mc);
 
-        let engine = EnginePool::new_default((&mc.network.clone()).into())?;
-        engine.acquire().preload(&blockstore, &self.code_cids)?;
+        // The engine depends on the network configuration.
+        self.engine = None;
+        self.build_machine(mc, blockstore, externs)
+    }
+
+    /// Re-instantiates the machine at the given epoch, keeping its state and configuration.
+    ///
+    /// The timestamp is moved accordingly. Network upgrades scheduled at or before the new epoch
+    /// take effect.
+    pub fn set_epoch(&mut self, epoch: ChainEpoch) -> Result<()>
+    where
+        E: Clone,
+    {
+        self.reinstantiate_machine(|mc| {
+            let elapsed = (epoch - mc.epoch) * mc.epoch_duration_seconds as i64;
+            mc.timestamp = mc.timestamp.saturating_add_signed(elapsed);
+            mc.epoch = epoch;
+        })
+    }
+
+    /// Advances the machine by `epochs` epochs, keeping its state and configuration. If
+    /// `run_cron` is set, the cron actor is ticked at the end of every epoch (and must therefore
+    /// have been deployed), as the chain would.
+    pub fn advance_epochs(&mut self, epochs: ChainEpoch, run_cron: bool) -> Result<()>
+    where
+        E: Clone,
+    {
+        let epoch = self.current_epoch()?;
+        for epoch in epoch..epoch + epochs {
+            if run_cron {
+                self.tick_cron()?;
+            }
+            self.set_epoch(epoch + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Re-instantiates the machine with the given base fee, keeping its state and configuration.
+    pub fn set_base_fee(&mut self, base_fee: TokenAmount) -> Result<()>
+    where
+        E: Clone,
+    {
+        self.reinstantiate_machine(|mc| {
+            mc.set_base_fee(base_fee);
+        })
+    }
+
+    /// Returns the epoch the machine is instantiated at.
+    pub fn current_epoch(&self) -> Result<ChainEpoch> {
+        self.context
+            .as_ref()
+            .map(|mc| mc.epoch)
+            .ok_or_else(|| anyhow!("machine not instantiated"))
+    }
+
+    /// Invokes the cron actor's epoch tick, as the system actor does at the end of every epoch.
+    fn tick_cron(&mut self) -> Result<()> {
+        let epoch = self.current_epoch()?;
+        let executor = self
+            .executor
+            .as_mut()
+            .ok_or_else(|| anyhow!("machine not instantiated"))?;
+        let tick = Message {
+            from: Address::new_id(SYSTEM_ACTOR_ID),
+            to: Address::new_id(CRON_ACTOR_ID),
+            method_num: EPOCH_TICK_METHOD,
+            gas_limit: BLOCK_GAS_LIMIT * 10000,
+            ..Message::default()
+        };
+        let ret = executor.execute_message(tick, ApplyKind::Implicit, 0)?;
+        if !ret.msg_receipt.exit_code.is_success() {
+            return Err(anyhow!(
+                "cron tick at epoch {} failed with {}",
+                epoch,
+                ret.msg_receipt.exit_code
+            ));
+        }
+        Ok(())
+    }
+
+    /// Flushes the machine's state and re-instantiates it on top of it, with the same externs and
+    /// its context adjusted by `configure_mc`.
+    fn reinstantiate_machine<G>(&mut self, configure_mc: G) -> Result<()>
+    where
+        E: Clone,
+        G: FnOnce(&mut MachineContext),
+    {
+        let mut executor = self
+            .executor
+            .take()
+            .ok_or_else(|| anyhow!("machine not instantiated"))?;
+        let state_root = executor.flush().context(FailedToFlushTree)?;
+        let machine = executor
+            .into_machine()
+            .ok_or_else(|| anyhow!("machine poisoned"))?;
+        let externs = machine.externs().clone();
+        let blockstore = machine.into_store().into_inner();
+
+        let mut mc = self.context.take().unwrap();
+        mc.initial_state_root = state_root;
+        configure_mc(&mut mc);
+
+        self.build_machine(mc, blockstore, externs)
+    }
+
+    fn build_machine(&mut self, mc: MachineContext, blockstore: B, externs: E) -> Result<()> {
+        let engine = match &self.engine {
+            Some(engine) => engine.clone(),
+            None => {
+                let engine = EnginePool::new_default((&mc.network.clone()).into())?;
+                engine.acquire().preload(&blockstore, &self.code_cids)?;
+                self.engine.insert(engine).clone()
+            }
+        };
 
         let machine = DefaultMachine::new(&mc, blockstore, externs)?;
 
@@ -302,11 +513,22 @@ where
         >::new(engine, machine)?;
 
         self.executor = Some(executor);
+        self.context = Some(mc);
         self.ready = true;
 
         Ok(())
     }
 
+    /// Reads the state of the actor at `address`, from the machine's state if it has been
+    /// instantiated, or from the genesis state otherwise.
+    pub fn read_actor_state<S: de::DeserializeOwned>(&self, address: &Address) -> Result<S> {
+        match (&self.executor, &self.state_tree) {
+            (Some(executor), _) => read_actor_state(executor.state_tree(), address),
+            (None, Some(state_tree)) => read_actor_state(state_tree, address),
+            (None, None) => Err(anyhow!("no state tree")),
+        }
+    }
+
     /// Get blockstore
     pub fn blockstore(&self) -> &dyn Blockstore {
         if self.executor.is_some() {
@@ -422,6 +644,49 @@ impl BasicTester {
     }
 }
 
+/// Credits `amount` to the actor at `address`, creating an account actor for it if it's a
+/// secp256k1 or BLS address that doesn't exist yet. Returns the actor's ID.
+fn credit_actor(
+    state_tree: &mut StateTree<impl Blockstore>,
+    accounts_code_cid: Cid,
+    address: &Address,
+    amount: TokenAmount,
+) -> Result<ActorID> {
+    if let Some(id) = state_tree.lookup_id(address)? {
+        let mut actor = state_tree
+            .get_actor(id)?
+            .ok_or_else(|| anyhow!("actor {id} doesn't exist"))?;
+        actor.deposit_funds(&amount)?;
+        state_tree.set_actor(id, actor);
+        return Ok(id);
+    }
+
+    if !matches!(address.protocol(), Protocol::Secp256k1 | Protocol::BLS) {
+        return Err(anyhow!("can't create an account for {address}"));
+    }
+    let id = state_tree.register_new_address(address)?;
+    let state = fvm::account_actor::State { address: *address };
+    let state = state_tree.store().put_cbor(&state, Code::Blake2b256)?;
+    state_tree.set_actor(
+        id,
+        ActorState::new(accounts_code_cid, state, amount, 0, None),
+    );
+    Ok(id)
+}
+
+fn read_actor_state<S: de::DeserializeOwned>(
+    state_tree: &StateTree<impl Blockstore>,
+    address: &Address,
+) -> Result<S> {
+    let actor = state_tree
+        .get_actor_by_address(address)?
+        .ok_or_else(|| anyhow!("actor {address} doesn't exist"))?;
+    state_tree
+        .store()
+        .get_cbor(&actor.state)?
+        .ok_or_else(|| anyhow!("state {} of actor {address} not found", actor.state))
+}
+
 /// Inserts the WASM code for the actor into the blockstore.
 fn put_wasm_code(blockstore: &impl Blockstore, wasm_binary: &[u8]) -> Result<Cid> {
     let cid = blockstore.put(
@@ -10,6 +10,7 @@ use fvm::machine::Machine;
 use fvm_integration_tests::dummy::DummyExterns;
 use fvm_ipld_blockstore::MemoryBlockstore;
 use fvm_shared::address::Address;
+use fvm_shared::crypto::signature::SignatureType;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::message::Message;
 use fvm_shared::state::StateTreeVersion;
@@ -25,7 +26,10 @@ fn basic_send() {
     )
     .unwrap();
 
-    let (_, sender) = tester.create_account().unwrap();
+    let sender = tester
+        .create_account(SignatureType::Secp256k1)
+        .unwrap()
+        .address;
 
     // Send to an f4 to create a placeholder. Otherwise, we end up invoking a constructor.
     let receiver = Address::new_delegated(10, b"foobar").expect("failed to construct f4 address");
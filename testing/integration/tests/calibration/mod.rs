@@ -28,6 +28,9 @@ use serde::Serialize;
 
 pub const ENOUGH_GAS: Gas = Gas::new(1_000_000_000);
 
+/// The expected execution speed: 10 gas (10,000 milligas) per nanosecond.
+pub const GAS_MILLIS_PER_NS: f64 = 10_000.0;
+
 #[derive(Serialize_tuple, Deserialize_tuple, Clone, Debug, Default)]
 pub struct State {
     pub count: u64,
@@ -103,6 +106,32 @@ pub struct RegressionResult {
     pub r_squared: f64,
 }
 
+/// A price proposed from a regression, in milligas, to be used with `Gas::from_milligas`.
+///
+/// These are only suggestions: always check the charts to decide whether, for example, the base
+/// cost is significant or should be ignored.
+#[derive(Serialize)]
+pub struct Proposal {
+    pub name: String,
+    pub label: String,
+    pub base_cost: i64,
+    pub variable_cost: i64,
+    pub r_squared: f64,
+}
+
+/// Convert regression results into proposed milligas prices.
+pub fn propose(name: &str, regs: &[RegressionResult]) -> Vec<Proposal> {
+    regs.iter()
+        .map(|reg| Proposal {
+            name: name.to_owned(),
+            label: reg.label.to_owned(),
+            base_cost: (reg.intercept * GAS_MILLIS_PER_NS).ceil() as i64,
+            variable_cost: (reg.slope * GAS_MILLIS_PER_NS).ceil() as i64,
+            r_squared: reg.r_squared,
+        })
+        .collect()
+}
+
 const NOP_ACTOR: &str = r#"
 (module
   (memory (export "memory") 1)
@@ -173,6 +202,10 @@ pub fn export(name: &str, obs: &Vec<Obs>, regs: &Vec<RegressionResult>) -> std::
     let file_name = format!("{name}.jsonline");
     export_json(&out.join("regressions").join(&file_name), regs)?;
     export_json(&out.join("observations").join(&file_name), obs)?;
+    export_json(
+        &out.join("proposals").join(&file_name),
+        &propose(name, regs),
+    )?;
     Ok(())
 }
 
@@ -2,8 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 mod bundles;
 use bundles::*;
-use fvm::executor::{ApplyKind, Executor};
-use fvm::machine::Machine;
+use std::sync::{Arc, Mutex};
+
+use fvm::engine::EnginePool;
+use fvm::executor::{ApplyKind, ApplyRet, Executor};
+use fvm::machine::{EventContext, EventSink, Machine};
+use fvm::system_actor::SYSTEM_ACTOR_ID;
+use fvm_integration_tests::assertions::{event_with_key, ApplyRetAssertions};
 use fvm_integration_tests::dummy::DummyExterns;
 use fvm_integration_tests::tester::IntegrationExecutor;
 use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
@@ -81,6 +86,10 @@ fn events_test() {
         ]
     );
 
+    let bar = res.expect_event(event_with_key(actor_id, "bar"));
+    assert_eq!(bar.event.entries.len(), 2);
+    res.assert_gas_between(gas_used, gas_used);
+
     // Check the events AMT.
     assert!(res.msg_receipt.events_root.is_some());
     // Check that we haven't inserted the events AMT in the blockstore.
@@ -157,6 +166,263 @@ fn events_test() {
     assert_eq!(0, res.events.len());
 }
 
+#[test]
+fn events_index_test() {
+    let (mut executor, sender_address, actor_address) = setup_with_event_index();
+
+    // Emits two events.
+    let message = Message {
+        from: sender_address,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 2,
+        sequence: 0,
+        ..Message::default()
+    };
+    let res = executor
+        .execute_message(message.clone(), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+
+    // Emits no events.
+    let message = Message {
+        method_num: 3,
+        sequence: 1,
+        ..message
+    };
+    let res = executor
+        .execute_message(message.clone(), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+
+    // Emits two events again.
+    let message = Message {
+        method_num: 2,
+        sequence: 2,
+        ..message
+    };
+    let res = executor
+        .execute_message(message.clone(), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+
+    let indexed = executor.take_tipset_events();
+    assert_eq!(2, indexed.len());
+    assert_eq!(0, indexed[0].message_index);
+    assert_eq!(2, indexed[1].message_index);
+    assert_ne!(indexed[0].message_cid, indexed[1].message_cid);
+    assert_eq!(Some(indexed[1].events_root), res.msg_receipt.events_root);
+    assert_eq!(indexed[1].events, res.events);
+
+    // Taking the events drains them.
+    assert!(executor.take_tipset_events().is_empty());
+}
+
+#[test]
+fn events_index_skips_implicit_messages() {
+    let (mut executor, sender_address, actor_address) = setup_with_event_index();
+
+    // Each message emits two events.
+    let message = Message {
+        from: sender_address,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 2,
+        sequence: 0,
+        ..Message::default()
+    };
+    let res = executor
+        .execute_message(message.clone(), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+
+    // Implicit messages aren't part of the tipset, so they don't take up an index.
+    let res = executor
+        .execute_message(message.clone(), ApplyKind::Implicit, 100)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+
+    let message = Message {
+        sequence: 1,
+        ..message
+    };
+    let res = executor
+        .execute_message(message.clone(), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+
+    let indexed = executor.take_tipset_events();
+    let indices: Vec<_> = indexed.iter().map(|e| e.message_index).collect();
+    assert_eq!(indices, [0, 1, 1]);
+
+    // Indices restart with the next tipset.
+    let message = Message {
+        sequence: 2,
+        ..message
+    };
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+    let indexed = executor.take_tipset_events();
+    assert_eq!(1, indexed.len());
+    assert_eq!(0, indexed[0].message_index);
+}
+
+#[test]
+fn events_index_after_rejected_tentative_message() {
+    let (mut executor, sender_address, actor_address) = setup_with_event_index();
+
+    // Emits two events, but its effects are dropped.
+    let message = Message {
+        from: sender_address,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 2,
+        sequence: 0,
+        ..Message::default()
+    };
+    let (res, committed) = executor
+        .execute_message_tentatively(message.clone(), ApplyKind::Explicit, 100, |_, _| false)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+    assert!(!committed);
+
+    // The dropped message isn't counted, so this is the first message.
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+
+    let indexed = executor.take_tipset_events();
+    assert_eq!(1, indexed.len());
+    assert_eq!(0, indexed[0].message_index);
+    assert_eq!(indexed[0].events, res.events);
+}
+
+#[derive(Clone, Default)]
+struct RecordingSink(Arc<Mutex<Vec<(EventContext, StampedEvent)>>>);
+
+impl EventSink for RecordingSink {
+    fn push_event(&self, context: &EventContext, event: &StampedEvent) {
+        self.0.lock().unwrap().push((*context, event.clone()));
+    }
+}
+
+#[test]
+fn event_sink_skips_dropped_messages() {
+    let (executor, sender_address, actor_address) = setup();
+
+    let sink = RecordingSink::default();
+    let machine = executor.into_machine().unwrap();
+    let engine = EnginePool::new_default((&machine.context().network).into()).unwrap();
+    let mut executor =
+        IntegrationExecutor::new(engine, machine.with_event_sink(sink.clone())).unwrap();
+
+    // Emits two events, but its effects are dropped.
+    let message = Message {
+        from: sender_address,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 2,
+        sequence: 0,
+        ..Message::default()
+    };
+    let (res, committed) = executor
+        .execute_message_tentatively(message.clone(), ApplyKind::Explicit, 100, |_, _| false)
+        .unwrap();
+    assert_eq!(2, res.events.len());
+    assert!(!committed);
+    assert!(sink.0.lock().unwrap().is_empty());
+
+    // Emits two events, and its effects are kept.
+    let (res, committed) = executor
+        .execute_message_tentatively(message, ApplyKind::Explicit, 100, |_, _| true)
+        .unwrap();
+    assert!(committed);
+
+    let pushed = sink.0.lock().unwrap();
+    assert_eq!(2, pushed.len());
+    assert!(pushed.iter().all(|(context, _)| context.message_index == 0));
+    let events: Vec<_> = pushed.iter().map(|(_, event)| event.clone()).collect();
+    assert_eq!(events, res.events);
+}
+
+/// Sends value to a new account (creating it), with system events enabled or disabled, and the
+/// given per-message event payload cap.
+fn send_to_new_account(system_events: bool, max_payload: usize) -> ApplyRet {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_sender_id, sender)] = tester.create_accounts().unwrap();
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |nc| {
+                if system_events {
+                    nc.enable_system_events();
+                }
+                nc.event_limits.max_message_payload = max_payload;
+            },
+            |_| (),
+        )
+        .unwrap();
+
+    let message = Message {
+        from: sender,
+        to: Address::new_secp256k1(&[1; 65]).unwrap(),
+        gas_limit: 1000000000,
+        value: TokenAmount::from_atto(1),
+        ..Message::default()
+    };
+    let res = tester
+        .executor
+        .as_mut()
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+    res
+}
+
+#[test]
+fn system_events() {
+    // No system events unless enabled.
+    let res = send_to_new_account(false, usize::MAX);
+    assert!(res.events.is_empty());
+    assert!(res.msg_receipt.events_root.is_none());
+
+    let res = send_to_new_account(true, usize::MAX);
+    assert_eq!(1, res.events.len());
+    let created = res.expect_event(event_with_key(SYSTEM_ACTOR_ID, "type"));
+    assert_eq!(created.event.entries[0].value, b"actor-created");
+    assert!(res.msg_receipt.events_root.is_some());
+
+    // System events don't count towards the per-message payload.
+    let res = send_to_new_account(true, 0);
+    assert_eq!(1, res.events.len());
+}
+
+#[test]
+#[should_panic(expected = "no matching event")]
+fn expect_event_without_match() {
+    let res = send_to_new_account(false, usize::MAX);
+    res.expect_event(event_with_key(SYSTEM_ACTOR_ID, "type"));
+}
+
+#[test]
+#[should_panic(expected = "gas to be used")]
+fn assert_gas_between_out_of_range() {
+    let res = send_to_new_account(false, usize::MAX);
+    let gas_used = res.msg_receipt.gas_used;
+    res.assert_gas_between(gas_used + 1, u64::MAX);
+}
+
 fn setup() -> (
     IntegrationExecutor<MemoryBlockstore, DummyExterns>,
     Address,
@@ -191,3 +457,37 @@ fn setup() -> (
     let executor = tester.executor.unwrap();
     (executor, sender, actor)
 }
+
+fn setup_with_event_index() -> (
+    IntegrationExecutor<MemoryBlockstore, DummyExterns>,
+    Address,
+    Address,
+) {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_sender_id, sender)] = tester.create_accounts().unwrap();
+
+    let state_cid = tester.set_state(&[(); 0]).unwrap();
+    let actor = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(EVENTS_ACTOR_BINARY, state_cid, actor, TokenAmount::zero())
+        .unwrap();
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                mc.enable_event_index();
+            },
+        )
+        .unwrap();
+
+    let executor = tester.executor.unwrap();
+    (executor, sender, actor)
+}
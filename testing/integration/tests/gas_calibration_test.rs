@@ -588,3 +588,33 @@ fn on_scan_cbor_links() {
         export(&name, &obs, &regs).unwrap();
     }
 }
+
+#[test]
+fn proposes_prices() {
+    use calibration::{least_squares, propose, Obs};
+
+    // Observations taking exactly 100ns plus 2ns per byte.
+    let obs: Vec<_> = [0usize, 10, 100, 1000]
+        .into_iter()
+        .map(|size| Obs {
+            charge: "OnTest".into(),
+            label: "n/a".into(),
+            elapsed_nanos: 100 + 2 * size as u128,
+            variables: vec![size],
+            compute_gas: 0,
+        })
+        .collect();
+    let regs = vec![least_squares("bytes".into(), &obs, 0)];
+
+    let proposals = propose("OnTest", &regs);
+    assert_eq!(proposals.len(), 1);
+    let proposal = &proposals[0];
+    assert_eq!(
+        (proposal.name.as_str(), proposal.label.as_str()),
+        ("OnTest", "bytes")
+    );
+    // At 10 gas per nanosecond, in milligas.
+    assert_eq!(proposal.base_cost, 1_000_000);
+    assert_eq!(proposal.variable_cost, 20_000);
+    assert!((proposal.r_squared - 1.0).abs() < 1e-9);
+}
@@ -0,0 +1,123 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+#![cfg(feature = "m2-native")]
+mod bundles;
+use bundles::*;
+use cid::Cid;
+use fvm::engine::EnginePool;
+use fvm::executor::{ApplyKind, Executor};
+use fvm::machine::{Machine, NetworkConfig};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::IPLD_RAW;
+use multihash::{Code, MultihashDigest};
+use num_traits::Zero;
+
+const NV_FOR_TEST: NetworkVersion = NetworkVersion::V21;
+
+const INSTALLED: &str = r#"(module
+  (memory (export "memory") 1)
+  (func (export "invoke") (param i32) (result i32)
+    (i32.const 0)))"#;
+
+/// Has an actor install the `INSTALLED` code (writing its CID into a buffer of `cid_len` bytes),
+/// flushes the machine, and returns the exit code, the code, and the resulting blockstore.
+fn install_and_flush(cid_len: u32) -> (ExitCode, Vec<u8>, MemoryBlockstore) {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender)] = tester.create_accounts().unwrap();
+
+    // The installer creates an IPLD_RAW block from the code in its data segment, installs it, and
+    // exits with the error (offset to a user exit code) if that fails.
+    let code = wat::parse_str(INSTALLED).unwrap();
+    let data: String = code.iter().map(|b| format!("\\{b:02x}")).collect();
+    let installer = format!(
+        r#"(module
+             (import "ipld" "block_create" (func $block_create (param i32 i64 i32 i32) (result i32)))
+             (import "actor" "install_actor_code" (func $install (param i32 i32 i32 i32) (result i32)))
+             (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+             (memory (export "memory") 1)
+             (data (i32.const 1024) "{data}")
+             (func (export "invoke") (param $x i32) (result i32)
+               (local $err i32)
+               (if (call $block_create (i32.const 0) (i64.const {IPLD_RAW}) (i32.const 1024) (i32.const {len}))
+                 (then unreachable))
+               (local.set $err
+                 (call $install (i32.const 4) (i32.load (i32.const 0)) (i32.const 8) (i32.const {cid_len})))
+               (if (local.get $err)
+                 (then (drop (call $exit
+                   (i32.add (local.get $err) (i32.const 32))
+                   (i32.const 0) (i32.const 0) (i32.const 0)))))
+               (i32.const 0)))"#,
+        len = code.len(),
+    );
+
+    let state_cid = tester.set_state(&[(); 0]).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            &wat::parse_str(installer).unwrap(),
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let mut executor = tester.executor.take().unwrap();
+    let message = Message {
+        from: sender,
+        to: actor_address,
+        gas_limit: 1_000_000_000,
+        method_num: 1,
+        ..Message::default()
+    };
+    let exit_code = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap()
+        .msg_receipt
+        .exit_code;
+
+    executor.flush().unwrap();
+    let blockstore = executor.into_machine().unwrap().into_store().into_inner();
+    (exit_code, code, blockstore)
+}
+
+#[test]
+fn installed_code_survives_flush() {
+    let (exit_code, code, blockstore) = install_and_flush(100);
+    assert_eq!(exit_code, ExitCode::OK);
+
+    // The code isn't reachable from the state root, but must still have been written back.
+    let k = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&code));
+    assert_eq!(blockstore.get(&k).unwrap(), Some(code));
+
+    // And a new machine (with a fresh engine) must be able to load it.
+    let engine = EnginePool::new_default((&NetworkConfig::new(NV_FOR_TEST)).into()).unwrap();
+    engine.acquire().preload(&blockstore, &[k]).unwrap();
+}
+
+#[test]
+fn install_checks_buffer_first() {
+    // A Blake2b256 IPLD_RAW CID takes 38 bytes. The syscall must fail before installing anything.
+    let (exit_code, code, blockstore) = install_and_flush(37);
+    assert_eq!(
+        exit_code,
+        ExitCode::new(32 + ErrorNumber::BufferTooSmall as u32)
+    );
+
+    let k = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&code));
+    assert_eq!(blockstore.get(&k).unwrap(), None);
+}
@@ -3,17 +3,27 @@
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::anyhow;
 use cid::Cid;
-use fvm::executor::{ApplyKind, Executor, ThreadedExecutor};
-use fvm::machine::Machine;
+use fvm::call_manager::backtrace::{Backtrace, Cause};
+use fvm::call_manager::DebugOutput;
+use fvm::engine::EnginePool;
+use fvm::executor::{
+    ApplyFailure, ApplyKind, DefaultExecutor, DifferentialExecutor, Executor, GasEstimationConfig,
+    GasSponsor, SenderChecks, ThreadedExecutor, ValueTransferPolicy, ValueTransferViolation,
+};
+use fvm::machine::{Machine, Metrics, NetworkConfig};
+use fvm::trace::ExecutionEvent;
 use fvm_integration_tests::dummy::DummyExterns;
 use fvm_integration_tests::tester::{Account, IntegrationExecutor, Tester};
 use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
 use fvm_ipld_encoding::tuple::*;
 use fvm_ipld_encoding::RawBytes;
-use fvm_shared::address::Address;
+use fvm_shared::address::{Address, Protocol};
+use fvm_shared::crypto::signature::SignatureType;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::message::Message;
@@ -30,7 +40,7 @@ use num_traits::Zero;
 mod bundles;
 use bundles::*;
 use fvm_shared::chainid::ChainID;
-use fvm_shared::ActorID;
+use fvm_shared::{ActorID, BLOCK_GAS_LIMIT};
 
 /// The state object.
 #[derive(Serialize_tuple, Deserialize_tuple, Clone, Debug, Default)]
@@ -64,10 +74,19 @@ fn hello_world() {
     tester
         .set_actor_from_bin(wasm_bin, state_cid, actor_address, TokenAmount::zero())
         .unwrap();
+    let state: State = tester.read_actor_state(&actor_address).unwrap();
+    assert_eq!(state.count, 0);
 
     // Instantiate machine
     tester.instantiate_machine(DummyExterns).unwrap();
 
+    // The state is now read from the machine.
+    let state: State = tester.read_actor_state(&actor_address).unwrap();
+    assert_eq!(state.count, 0);
+    assert!(tester
+        .read_actor_state::<State>(&Address::new_id(10001))
+        .is_err());
+
     // Send message
     let message = Message {
         from: sender[0].1,
@@ -86,6 +105,304 @@ fn hello_world() {
     assert_eq!(res.msg_receipt.exit_code.value(), 16)
 }
 
+#[test]
+fn message_bundle() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (_, receiver)]: [Account; 2] = tester.create_accounts().unwrap();
+
+    // The hello world actor always exits with code 16.
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            HELLO_WORLD_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let transfer = Message {
+        from: sender,
+        to: receiver,
+        gas_limit: 1000000000,
+        value: TokenAmount::from_atto(100),
+        ..Message::default()
+    };
+    let failing = Message {
+        from: sender,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 1,
+        sequence: 1,
+        ..Message::default()
+    };
+
+    // The second message fails, so the transfer is reverted too.
+    let report = executor
+        .execute_message_bundle([
+            (transfer.clone(), ApplyKind::Explicit, 100),
+            (failing, ApplyKind::Explicit, 100),
+        ])
+        .unwrap();
+    assert!(!report.committed());
+    assert_eq!(report.applied.len(), 2);
+    assert_eq!(report.failure.as_ref().unwrap().index, 1);
+    let balance_of = |executor: &IntegrationExecutor<_, _>, addr: Address| {
+        executor
+            .state_tree()
+            .get_actor_by_address(&addr)
+            .unwrap()
+            .unwrap()
+            .balance
+    };
+    assert_eq!(
+        balance_of(executor, receiver),
+        TokenAmount::from_atto(10000)
+    );
+    let sender_state = executor.state_tree().get_actor(sender_id).unwrap().unwrap();
+    assert_eq!(sender_state.sequence, 0);
+
+    // On its own, the transfer goes through.
+    let report = executor
+        .execute_message_bundle([(transfer, ApplyKind::Explicit, 100)])
+        .unwrap();
+    assert!(report.committed());
+    assert!(report.applied[0].msg_receipt.exit_code.is_success());
+    assert_eq!(
+        balance_of(executor, receiver),
+        TokenAmount::from_atto(10100)
+    );
+}
+
+#[test]
+fn differential_execution() {
+    let new_hello_world_tester = |base_fee: u64| {
+        let mut tester = new_tester(
+            NV_FOR_TEST,
+            StateTreeVersion::V5,
+            MemoryBlockstore::default(),
+        )
+        .unwrap();
+        let [(_, sender)]: [Account; 1] = tester.create_accounts().unwrap();
+        let state_cid = tester.set_state(&State::default()).unwrap();
+        tester
+            .set_actor_from_bin(
+                HELLO_WORLD_ACTOR_BINARY,
+                state_cid,
+                Address::new_id(10000),
+                TokenAmount::zero(),
+            )
+            .unwrap();
+        tester
+            .instantiate_machine_with_config(
+                DummyExterns,
+                |_| (),
+                |mc| {
+                    mc.set_base_fee(TokenAmount::from_atto(base_fee));
+                },
+            )
+            .unwrap();
+        (sender, tester.executor.unwrap())
+    };
+    let message = |from| Message {
+        from,
+        to: Address::new_id(10000),
+        gas_limit: 1000000000,
+        method_num: 1,
+        ..Message::default()
+    };
+
+    // Identical machines don't diverge.
+    let (sender, primary) = new_hello_world_tester(100);
+    let (_, secondary) = new_hello_world_tester(100);
+    let mut executor = DifferentialExecutor::new(primary, secondary);
+    let res = executor
+        .execute_message(message(sender), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(res.msg_receipt.exit_code.value(), 16);
+    executor.flush().unwrap();
+    assert!(executor.divergences().is_empty());
+
+    // Machines with different base fees charge different fees.
+    let (sender, primary) = new_hello_world_tester(100);
+    let (_, secondary) = new_hello_world_tester(200);
+    let mut executor = DifferentialExecutor::new(primary, secondary);
+    executor
+        .execute_message(message(sender), ApplyKind::Explicit, 100)
+        .unwrap();
+    let divergences = executor.take_divergences();
+    assert_eq!(divergences.len(), 1);
+    assert_eq!(divergences[0].message_index, Some(0));
+    assert_eq!(divergences[0].what, "fees");
+}
+
+#[test]
+fn time_travel() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+    let [(_, sender), (_, receiver)]: [Account; 2] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    assert_eq!(tester.current_epoch().unwrap(), 0);
+
+    let transfer = |sequence| Message {
+        from: sender,
+        to: receiver,
+        gas_limit: 1000000000,
+        value: TokenAmount::from_atto(100),
+        sequence,
+        ..Message::default()
+    };
+    let res = tester
+        .executor
+        .as_mut()
+        .unwrap()
+        .execute_message(transfer(0), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    let penalty = res.penalty;
+
+    // The state is kept across epochs.
+    tester.set_epoch(100).unwrap();
+    tester.advance_epochs(5, false).unwrap();
+    assert_eq!(tester.current_epoch().unwrap(), 105);
+    let executor = tester.executor.as_ref().unwrap();
+    assert_eq!(executor.context().epoch, 105);
+    assert_eq!(executor.context().timestamp, 105 * 30);
+    let balance = executor
+        .state_tree()
+        .get_actor_by_address(&receiver)
+        .unwrap()
+        .unwrap()
+        .balance;
+    assert_eq!(balance, TokenAmount::from_atto(10100));
+
+    // The sender can't cover the base fee, so the miner is penalized at the new base fee (twice
+    // the default).
+    tester.set_base_fee(TokenAmount::from_atto(200)).unwrap();
+    let res = tester
+        .executor
+        .as_mut()
+        .unwrap()
+        .execute_message(transfer(1), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    assert_eq!(res.penalty, penalty * 2);
+}
+
+#[test]
+fn accounts_and_faucet() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+    let alice = tester.create_account(SignatureType::BLS).unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    // Accounts can be created and funded once the machine is instantiated.
+    let bob = tester.create_account(SignatureType::Secp256k1).unwrap();
+    assert_eq!(alice.address.protocol(), Protocol::BLS);
+    assert_eq!(bob.address.protocol(), Protocol::Secp256k1);
+    assert_ne!(alice.id, bob.id);
+    tester
+        .fund(&alice.address, TokenAmount::from_atto(1000))
+        .unwrap();
+
+    let res = tester
+        .transfer(&alice.address, &bob.address, TokenAmount::from_atto(500))
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    let res = tester
+        .transfer(&alice.address, &bob.address, TokenAmount::from_atto(500))
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+
+    let balance_of = |addr: &Address| {
+        tester
+            .executor
+            .as_ref()
+            .unwrap()
+            .state_tree()
+            .get_actor_by_address(addr)
+            .unwrap()
+            .unwrap()
+            .balance
+    };
+    assert_eq!(balance_of(&alice.address), TokenAmount::from_atto(10000));
+    assert_eq!(balance_of(&bob.address), TokenAmount::from_atto(11000));
+}
+
+#[test]
+fn value_transfer_policy() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+    let [(alice, alice_addr), (_, bob_addr)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let executor = tester.executor.take().unwrap();
+    tester.executor = Some(executor.with_value_transfer_policy(
+        ValueTransferPolicy::default().cap_sender(alice, TokenAmount::from_atto(100)),
+    ));
+
+    let res = tester
+        .transfer(&alice_addr, &bob_addr, TokenAmount::from_atto(100))
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+
+    // Transfers over the cap are rejected with the reason, and the miner is penalized.
+    let res = tester
+        .transfer(&alice_addr, &bob_addr, TokenAmount::from_atto(101))
+        .unwrap();
+    assert_eq!(
+        res.msg_receipt.exit_code,
+        ExitCode::SYS_SENDER_STATE_INVALID
+    );
+    assert_eq!(res.msg_receipt.gas_used, 0);
+    let base_fee = &tester.executor.as_ref().unwrap().context().base_fee;
+    assert_eq!(res.miner_penalty, base_fee * BLOCK_GAS_LIMIT);
+    assert!(!res.miner_penalty.is_zero());
+    let failure = res.failure_info.unwrap();
+    assert_eq!(
+        failure.to_string(),
+        format!(
+            "value transfer denied: value {} exceeds the cap of {} for sender {alice}",
+            TokenAmount::from_atto(101),
+            TokenAmount::from_atto(100),
+        )
+    );
+    match failure {
+        ApplyFailure::ValueTransferDenied(ValueTransferViolation::SenderCapExceeded {
+            sender,
+            value,
+            cap,
+        }) => {
+            assert_eq!(sender, alice);
+            assert_eq!(value, TokenAmount::from_atto(101));
+            assert_eq!(cap, TokenAmount::from_atto(100));
+        }
+        other => panic!("unexpected failure: {other}"),
+    }
+}
+
 #[test]
 fn ipld() {
     // Instantiate tester
@@ -547,6 +864,14 @@ fn native_stack_overflow() {
 }
 
 fn test_exitcode(wat: &str, code: ExitCode) {
+    test_exitcode_with_config(wat, code, |_| {})
+}
+
+fn test_exitcode_with_config(
+    wat: &str,
+    code: ExitCode,
+    configure_nc: impl FnOnce(&mut NetworkConfig),
+) {
     // Instantiate tester
     let mut tester = new_tester(
         NV_FOR_TEST,
@@ -572,7 +897,9 @@ fn test_exitcode(wat: &str, code: ExitCode) {
         .unwrap();
 
     // Instantiate machine
-    tester.instantiate_machine(DummyExterns).unwrap();
+    tester
+        .instantiate_machine_with_config(DummyExterns, configure_nc, |_| {})
+        .unwrap();
 
     // Send message
     let message = Message {
@@ -603,6 +930,69 @@ fn out_of_gas() {
     )
 }
 
+#[test]
+fn execution_timeout() {
+    // Loops until it runs out of gas, which takes far longer than the timeout.
+    let wat = r#"(module
+                   (memory (export "memory") 1)
+                   (func (export "invoke") (param $x i32) (result i32)
+                     (loop (br 0))
+                     (i32.const 1)))"#;
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+    let [(_, sender)]: [Account; 1] = tester.create_accounts().unwrap();
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            &wat::parse_str(wat).unwrap(),
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |nc| {
+                nc.execution_timeout(Duration::from_millis(10));
+            },
+            |_| (),
+        )
+        .unwrap();
+
+    let message = Message {
+        from: sender,
+        to: actor_address,
+        gas_limit: BLOCK_GAS_LIMIT,
+        method_num: 1,
+        ..Message::default()
+    };
+    let res = tester
+        .executor
+        .as_mut()
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+
+    // The timeout isn't the actor's fault, so it doesn't get an exit code of its own (e.g., out of
+    // gas): the message fails with a fatal error.
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::SYS_ASSERTION_FAILED);
+    assert_eq!(res.msg_receipt.gas_used, BLOCK_GAS_LIMIT);
+    match res.failure_info {
+        Some(ApplyFailure::MessageBacktrace(Backtrace {
+            cause: Some(Cause::Fatal { error_msg, .. }),
+            ..
+        })) => assert!(error_msg.contains("timed out"), "{error_msg}"),
+        other => panic!("expected a fatal error, got {other:?}"),
+    }
+}
+
 #[test]
 fn unreachable() {
     test_exitcode(
@@ -663,68 +1053,752 @@ fn no_memory() {
 }
 
 #[test]
-fn backtraces() {
-    // Note: this test **does not actually assert anything**, but it's useful to
-    // let us peep into FVM backtrace generation under different scenarios.
-    const WAT_ABORT: &str = r#"
-    (module
-      ;; ipld::open
-      (type (;0;) (func (param i32 i32) (result i32)))
-      (import "ipld" "open" (func $fvm_sdk::sys::ipld::open::syscall (type 0)))
-      ;; vm::abort -> vm::exit
-      (type (;1;) (func (param i32 i32 i32 i32) (result i32)))
-      (import "vm" "exit" (func $fvm_sdk::sys::vm::exit::syscall (type 1)))
-      (memory (export "memory") 1)
-      (func (export "invoke") (param $x i32) (result i32)
-        (i32.const 123)
-        (i32.const 123)
-        (call $fvm_sdk::sys::ipld::open::syscall)
-        (i32.const 0)
-        (i32.const 0)
-        (i32.const 0)
-        (call $fvm_sdk::sys::vm::exit::syscall)
-        unreachable
-      )
-    )
-    "#;
+fn call_depth() {
+    // Exits with the call depth (offset to a user exit code).
+    let wat = r#"(module
+                   (import "vm" "call_depth" (func $call_depth (param i32) (result i32)))
+                   (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+                   (memory (export "memory") 1)
+                   (func (export "invoke") (param $x i32) (result i32)
+                     (if (call $call_depth (i32.const 0))
+                       (then unreachable))
+                     (call $exit
+                       (i32.add (i32.load (i32.const 0)) (i32.const 32))
+                       (i32.const 0) (i32.const 0) (i32.const 0))))"#;
 
-    const WAT_FAIL: &str = r#"
-    (module
-      ;; ipld::open
-      (type (;0;) (func (param i32 i32) (result i32)))
-      (import "ipld" "open" (func $fvm_sdk::sys::ipld::open::syscall (type 0)))
-      ;; vm::abort -> vm::exit
-      (type (;1;) (func (param i32 i32 i32 i32) (result i32)))
-      (import "vm" "exit" (func $fvm_sdk::sys::vm::exit::syscall (type 1)))
-      (memory (export "memory") 1)
-      (func (export "invoke") (param $x i32) (result i32)
-        (i32.const 128)
-        (memory.grow)
-        (i32.const 4)
-        (i32.const 25493505)
-        (i32.store)
-        (i32.const 8)
-        (i32.const 0)
-        (i32.store)
-        (i32.const 4)
-        (call $fvm_sdk::sys::ipld::open::syscall)
-        (i32.const 0)
-        (i32.const 0)
-        (i32.const 0)
-        (call $fvm_sdk::sys::vm::exit::syscall)
-        unreachable
-      )
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
     )
-    "#;
-
-    let blockstore = {
-        let b = FailingBlockstore::default();
-        b.add_fail(Cid::try_from("baeaikaia").unwrap());
-        Rc::new(b)
-    };
-
-    // Instantiate tester
-    let mut tester = new_tester(NV_FOR_TEST, StateTreeVersion::V5, blockstore.clone()).unwrap();
+    .unwrap();
+    let [(_, sender)]: [Account; 1] = tester.create_accounts().unwrap();
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            &wat::parse_str(wat).unwrap(),
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                mc.enable_tracing();
+            },
+        )
+        .unwrap();
+
+    let message = Message {
+        from: sender,
+        to: actor_address,
+        gas_limit: 10_000_000,
+        method_num: 1,
+        ..Message::default()
+    };
+    let res = tester
+        .executor
+        .as_mut()
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+
+    // The receiver of the top-level message is the first frame on the call stack.
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::new(33));
+
+    // Both syscalls (call_depth and exit) are charged as syscalls.
+    let syscall_charges = res
+        .exec_trace
+        .iter()
+        .filter(|e| matches!(e, ExecutionEvent::GasCharge(c) if c.name == "OnSyscall"))
+        .count();
+    assert_eq!(syscall_charges, 2);
+}
+
+#[test]
+fn forbid_reentrancy() {
+    // Method 2 returns immediately. Methods 1 and 4 forbid re-entry, then all other methods send to
+    // the actor itself (method 4 with a plain value transfer, the others invoking method 2) and
+    // exit with the send's error (offset to a user exit code), if any.
+    let wat = r#"(module
+                   (import "vm" "message_context" (func $message_context (param i32) (result i32)))
+                   (import "vm" "set_reentrancy" (func $set_reentrancy (param i32) (result i32)))
+                   (import "send" "send" (func $send (param i32 i32 i32 i64 i32 i64 i64 i64 i64) (result i32)))
+                   (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+                   (memory (export "memory") 1)
+                   ;; The actor's own address, f010000.
+                   (data (i32.const 256) "\00\90\4e")
+                   (func (export "invoke") (param $x i32) (result i32)
+                     (local $method i64)
+                     (local $err i32)
+                     (if (call $message_context (i32.const 0))
+                       (then unreachable))
+                     (local.set $method (i64.load (i32.const 32)))
+                     (if (i64.eq (local.get $method) (i64.const 2))
+                       (then (return (i32.const 0))))
+                     (if (i32.or (i64.eq (local.get $method) (i64.const 1))
+                                 (i64.eq (local.get $method) (i64.const 4)))
+                       (then (if (call $set_reentrancy (i32.const 1))
+                         (then unreachable))))
+                     (local.set $err
+                       (call $send (i32.const 128) (i32.const 256) (i32.const 3)
+                         (select (i64.const 0) (i64.const 2)
+                           (i64.eq (local.get $method) (i64.const 4)))
+                         (i32.const 0) (i64.const 0) (i64.const 0) (i64.const -1) (i64.const 0)))
+                     (call $exit
+                       (select (i32.add (local.get $err) (i32.const 32)) (i32.const 0)
+                         (local.get $err))
+                       (i32.const 0) (i32.const 0) (i32.const 0))))"#;
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+    let [(_, sender)]: [Account; 1] = tester.create_accounts().unwrap();
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            &wat::parse_str(wat).unwrap(),
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let mut call = |sequence, method_num| {
+        let message = Message {
+            from: sender,
+            to: actor_address,
+            gas_limit: 100_000_000,
+            method_num,
+            sequence,
+            ..Message::default()
+        };
+        executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap()
+            .msg_receipt
+            .exit_code
+    };
+
+    // Re-entry is allowed by default.
+    assert_eq!(call(0, 3), ExitCode::OK);
+    // Once forbidden, calls back into the actor fail...
+    assert_eq!(
+        call(1, 1),
+        ExitCode::new(32 + ErrorNumber::Forbidden as u32)
+    );
+    // ...but plain value transfers don't run its code, so they're still allowed.
+    assert_eq!(call(2, 4), ExitCode::OK);
+    // The policy only lasted for the invocation that set it.
+    assert_eq!(call(3, 3), ExitCode::OK);
+}
+
+#[test]
+fn debug_output() {
+    // Logs a message, stores an artifact, and aborts.
+    let wat = r#"(module
+                   (import "debug" "log" (func $log (param i32 i32) (result i32)))
+                   (import "debug" "store_artifact" (func $store_artifact (param i32 i32 i32 i32) (result i32)))
+                   (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+                   (memory (export "memory") 1)
+                   (data (i32.const 0) "hello")
+                   (data (i32.const 16) "out.txt")
+                   (data (i32.const 32) "data")
+                   (func (export "invoke") (param $x i32) (result i32)
+                     (if (call $log (i32.const 0) (i32.const 5))
+                       (then unreachable))
+                     (if (call $store_artifact (i32.const 16) (i32.const 7) (i32.const 32) (i32.const 4))
+                       (then unreachable))
+                     (call $exit (i32.const 16) (i32.const 0) (i32.const 0) (i32.const 0))))"#;
+
+    let run = |debugging: bool| {
+        let mut tester = new_tester(
+            NV_FOR_TEST,
+            StateTreeVersion::V5,
+            MemoryBlockstore::default(),
+        )
+        .unwrap();
+        let [(_, sender)]: [Account; 1] = tester.create_accounts().unwrap();
+        let state_cid = tester.set_state(&State::default()).unwrap();
+        let actor_address = Address::new_id(10000);
+        tester
+            .set_actor_from_bin(
+                &wat::parse_str(wat).unwrap(),
+                state_cid,
+                actor_address,
+                TokenAmount::zero(),
+            )
+            .unwrap();
+        tester
+            .instantiate_machine_with_config(
+                DummyExterns,
+                |nc| nc.actor_debugging = debugging,
+                |_| (),
+            )
+            .unwrap();
+
+        let message = Message {
+            from: sender,
+            to: actor_address,
+            gas_limit: 10_000_000,
+            method_num: 1,
+            ..Message::default()
+        };
+        tester
+            .executor
+            .as_mut()
+            .unwrap()
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap()
+    };
+
+    // Output is returned even though the actor aborted.
+    let res = run(true);
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::new(16));
+    assert_eq!(
+        res.debug_output,
+        vec![
+            DebugOutput::Log {
+                actor: 10000,
+                message: "hello".into(),
+            },
+            DebugOutput::Artifact {
+                actor: 10000,
+                name: "out.txt".into(),
+                data: b"data".to_vec(),
+            },
+        ]
+    );
+
+    // And nothing is collected unless actor debugging is enabled.
+    let res = run(false);
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::new(16));
+    assert!(res.debug_output.is_empty());
+}
+
+#[test]
+fn backtrace_actor_names() {
+    // Aborts with a message.
+    let wat = r#"(module
+                   (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+                   (memory (export "memory") 1)
+                   (data (i32.const 0) "boom")
+                   (func (export "invoke") (param $x i32) (result i32)
+                     (call $exit (i32.const 16) (i32.const 0) (i32.const 0) (i32.const 4))))"#;
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+    let [(_, sender)]: [Account; 1] = tester.create_accounts().unwrap();
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            &wat::parse_str(wat).unwrap(),
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    // Rebuild the executor with a machine naming every actor it's asked about.
+    let machine = tester.executor.take().unwrap().into_machine().unwrap();
+    let code_cid = machine.state_tree().get_actor(10000).unwrap().unwrap().code;
+    let machine = machine.with_actor_name_resolver(move |code: &Cid| {
+        (*code == code_cid).then(|| "boom-actor".to_owned())
+    });
+    let engine = EnginePool::new_default((&machine.context().network.clone()).into()).unwrap();
+    let mut executor: IntegrationExecutor<_, _> = DefaultExecutor::new(engine, machine).unwrap();
+
+    let message = Message {
+        from: sender,
+        to: actor_address,
+        gas_limit: 10_000_000,
+        method_num: 1,
+        ..Message::default()
+    };
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::new(16));
+
+    let Some(ApplyFailure::MessageBacktrace(backtrace)) = res.failure_info else {
+        panic!("expected a backtrace, got {:?}", res.failure_info);
+    };
+    assert_eq!(backtrace.frames.len(), 1);
+    let frame = &backtrace.frames[0];
+    assert_eq!(frame.source, 10000);
+    assert_eq!(frame.code_cid, code_cid);
+    assert_eq!(frame.actor_name.as_deref(), Some("boom-actor"));
+    assert_eq!(
+        frame.to_string(),
+        "f010000 [boom-actor] (method invoke(1)) -- boom (16)"
+    );
+}
+
+#[test]
+fn wasm_trap_backtrace() {
+    // Traps in a named function.
+    let wat = r#"(module
+                   (memory (export "memory") 1)
+                   (func $trap_here
+                     unreachable)
+                   (func (export "invoke") (param $x i32) (result i32)
+                     (call $trap_here)
+                     (i32.const 0)))"#;
+
+    let run = |debugging: bool| {
+        let mut tester = new_tester(
+            NV_FOR_TEST,
+            StateTreeVersion::V5,
+            MemoryBlockstore::default(),
+        )
+        .unwrap();
+        let [(_, sender)]: [Account; 1] = tester.create_accounts().unwrap();
+        let state_cid = tester.set_state(&State::default()).unwrap();
+        let actor_address = Address::new_id(10000);
+        tester
+            .set_actor_from_bin(
+                &wat::parse_str(wat).unwrap(),
+                state_cid,
+                actor_address,
+                TokenAmount::zero(),
+            )
+            .unwrap();
+        tester
+            .instantiate_machine_with_config(
+                DummyExterns,
+                |nc| nc.actor_debugging = debugging,
+                |_| (),
+            )
+            .unwrap();
+
+        let message = Message {
+            from: sender,
+            to: actor_address,
+            gas_limit: 10_000_000,
+            method_num: 1,
+            ..Message::default()
+        };
+        let res = tester
+            .executor
+            .as_mut()
+            .unwrap()
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert_eq!(res.msg_receipt.exit_code, ExitCode::SYS_ILLEGAL_INSTRUCTION);
+        let Some(ApplyFailure::MessageBacktrace(backtrace)) = res.failure_info else {
+            panic!("expected a backtrace, got {:?}", res.failure_info);
+        };
+        assert_eq!(backtrace.frames.len(), 1);
+        backtrace.frames[0].message.clone()
+    };
+
+    // When debugging, the trap message names the function (from the name section) that trapped.
+    let message = run(true);
+    assert!(message.contains("unreachable"), "{message}");
+    assert!(message.contains("trap_here"), "{message}");
+
+    // Otherwise, there's no stack trace.
+    let message = run(false);
+    assert!(message.contains("unreachable"), "{message}");
+    assert!(!message.contains("trap_here"), "{message}");
+}
+
+#[derive(Clone, Default)]
+struct RecordingMetrics(Arc<Mutex<Vec<String>>>);
+
+impl RecordingMetrics {
+    fn record(&self, metric: String) {
+        self.0.lock().unwrap().push(metric);
+    }
+}
+
+impl Metrics for RecordingMetrics {
+    fn message_applied(&self, kind: ApplyKind, exit_code: ExitCode, _gas_used: u64) {
+        self.record(format!("applied {kind:?} {}", exit_code.value()));
+    }
+
+    fn block_written(&self, size: usize) {
+        self.record(format!("written {size}"));
+    }
+
+    fn module_cache_lookup(&self, hit: bool) {
+        self.record(format!("lookup {hit}"));
+    }
+}
+
+#[test]
+fn execution_metrics() {
+    // Writes a 5 byte IPLD_RAW block.
+    let wat = r#"(module
+                   (import "ipld" "block_create" (func $block_create (param i32 i64 i32 i32) (result i32)))
+                   (import "ipld" "block_link" (func $block_link (param i32 i32 i64 i32 i32 i32) (result i32)))
+                   (memory (export "memory") 1)
+                   (data (i32.const 16) "hello")
+                   (func (export "invoke") (param $x i32) (result i32)
+                     (if (call $block_create (i32.const 0) (i64.const 0x55) (i32.const 16) (i32.const 5))
+                       (then unreachable))
+                     (if (call $block_link (i32.const 4) (i32.load (i32.const 0)) (i64.const 0xb220) (i32.const 32) (i32.const 64) (i32.const 100))
+                       (then unreachable))
+                     (i32.const 0)))"#;
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+    let [(_, sender)]: [Account; 1] = tester.create_accounts().unwrap();
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            &wat::parse_str(wat).unwrap(),
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let metrics = RecordingMetrics::default();
+    let machine = tester.executor.take().unwrap().into_machine().unwrap();
+    let engine = EnginePool::new_default((&machine.context().network.clone()).into()).unwrap();
+    let mut executor =
+        IntegrationExecutor::new(engine, machine.with_metrics(metrics.clone())).unwrap();
+
+    for sequence in 0..2 {
+        let message = Message {
+            from: sender,
+            to: actor_address,
+            gas_limit: 10_000_000,
+            method_num: 1,
+            sequence,
+            ..Message::default()
+        };
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert_eq!(res.msg_receipt.exit_code, ExitCode::OK);
+    }
+
+    // The second message finds the actor's code in the module cache.
+    let recorded = metrics.0.lock().unwrap();
+    assert_eq!(
+        &recorded[recorded.len() - 3..],
+        ["lookup true", "written 5", "applied Explicit 0"]
+    );
+    assert_eq!(
+        recorded.iter().filter(|m| m.starts_with("applied")).count(),
+        2
+    );
+}
+
+#[test]
+fn lazy_syscall_linking() {
+    // Calls a syscall this FVM doesn't have, and exits with the error it returns (offset to a
+    // user exit code).
+    test_exitcode_with_config(
+        r#"(module
+             (import "vm" "future_syscall" (func $future (result i32)))
+             (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+             (memory (export "memory") 1)
+             (func (export "invoke") (param $x i32) (result i32)
+               (call $exit
+                 (i32.add (call $future) (i32.const 32))
+                 (i32.const 0) (i32.const 0) (i32.const 0))))"#,
+        ExitCode::new(32 + ErrorNumber::Forbidden as u32),
+        |nc| {
+            nc.enable_lazy_syscall_linking();
+        },
+    );
+}
+
+#[test]
+fn lazy_syscall_linking_conflicting_signatures() {
+    // Two actors importing the same unsupported syscall with different signatures must both link,
+    // regardless of which one is loaded first.
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+    let state_cid = tester.set_state(&State { count: 0 }).unwrap();
+
+    let signatures = ["", "(param i64 i32)"];
+    for (i, params) in signatures.iter().enumerate() {
+        let args = if params.is_empty() {
+            ""
+        } else {
+            "(i64.const 0) (i32.const 0)"
+        };
+        let wat = format!(
+            r#"(module
+                 (import "vm" "future_syscall" (func $future {params} (result i32)))
+                 (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+                 (memory (export "memory") 1)
+                 (func (export "invoke") (param $x i32) (result i32)
+                   (call $exit
+                     (i32.add (call $future {args}) (i32.const 32))
+                     (i32.const 0) (i32.const 0) (i32.const 0))))"#
+        );
+        tester
+            .set_actor_from_bin(
+                &wat::parse_str(wat).unwrap(),
+                state_cid,
+                Address::new_id(10000 + i as u64),
+                TokenAmount::zero(),
+            )
+            .unwrap();
+    }
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |nc| {
+                nc.enable_lazy_syscall_linking();
+            },
+            |_| {},
+        )
+        .unwrap();
+
+    let mut executor = ThreadedExecutor(tester.executor.unwrap());
+    for i in 0..signatures.len() {
+        let message = Message {
+            from: sender[0].1,
+            to: Address::new_id(10000 + i as u64),
+            gas_limit: 10_000_000,
+            method_num: 1,
+            sequence: i as u64,
+            ..Message::default()
+        };
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert_eq!(
+            res.msg_receipt.exit_code,
+            ExitCode::new(32 + ErrorNumber::Forbidden as u32)
+        );
+    }
+}
+
+/// Has a factory actor create an actor with the given code via `actor::create_and_invoke`,
+/// passing the given actor ID, and exit with the constructor's exit code (or the syscall's error,
+/// offset to a user exit code). Returns the factory's exit code, and the code of the actor
+/// registered under the factory's next actor address afterwards, if any.
+fn create_with_factory(
+    child: &str,
+    actor_id: ActorID,
+    enable_factories: bool,
+) -> (ExitCode, Option<Cid>) {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender)] = tester.create_accounts().unwrap();
+    let state_cid = tester.set_state(&State { count: 0 }).unwrap();
+
+    // Deploy a "template" actor to get the child's code into the blockstore.
+    let child_code = tester
+        .set_actor_from_bin(
+            &wat::parse_str(child).unwrap(),
+            state_cid,
+            Address::new_id(10001),
+            TokenAmount::zero(),
+        )
+        .unwrap();
+    let cid: String = child_code
+        .to_bytes()
+        .iter()
+        .map(|b| format!("\\{b:02x}"))
+        .collect();
+    let factory = format!(
+        r#"(module
+             (import "actor" "create_and_invoke"
+               (func $create (param i32 i64 i32 i32 i32 i32 i64 i64) (result i32)))
+             (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+             (memory (export "memory") 1)
+             (data (i32.const 1024) "{cid}")
+             (func (export "invoke") (param $x i32) (result i32)
+               (local $err i32)
+               (local.set $err
+                 (call $create (i32.const 0) (i64.const {actor_id}) (i32.const 1024)
+                   (i32.const 0) (i32.const 0) (i32.const 0) (i64.const 0) (i64.const 0)))
+               (if (local.get $err)
+                 (then (drop (call $exit
+                   (i32.add (local.get $err) (i32.const 32))
+                   (i32.const 0) (i32.const 0) (i32.const 0)))))
+               (drop (call $exit (i32.load (i32.const 0)) (i32.const 0) (i32.const 0) (i32.const 0)))
+               unreachable))"#
+    );
+    tester
+        .set_actor_from_bin(
+            &wat::parse_str(factory).unwrap(),
+            state_cid,
+            Address::new_id(10000),
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |nc| {
+                if enable_factories {
+                    nc.enable_actor_factories();
+                }
+            },
+            |_| {},
+        )
+        .unwrap();
+
+    let message = Message {
+        from: sender,
+        to: Address::new_id(10000),
+        gas_limit: 1_000_000_000,
+        method_num: 1,
+        ..Message::default()
+    };
+    let mut executor = tester.executor.unwrap();
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+
+    // The first actor created by this message is assigned this address.
+    let mut seed = fvm_ipld_encoding::to_vec(&sender).unwrap();
+    seed.extend_from_slice(&0u64.to_be_bytes());
+    seed.extend_from_slice(&0u64.to_be_bytes());
+    let created = executor
+        .state_tree()
+        .lookup_id(&Address::new_actor(&seed))
+        .unwrap()
+        .map(|id| executor.state_tree().get_actor(id).unwrap().unwrap().code);
+    assert!(created.is_none() || created == Some(child_code));
+
+    (res.msg_receipt.exit_code, created)
+}
+
+const CHILD_OK: &str = r#"(module
+  (memory (export "memory") 1)
+  (func (export "invoke") (param $x i32) (result i32)
+    (i32.const 0)))"#;
+
+#[test]
+fn create_and_invoke_from_factory() {
+    let (exit_code, created) = create_with_factory(CHILD_OK, 0, true);
+    assert_eq!(exit_code, ExitCode::OK);
+    assert!(created.is_some());
+}
+
+#[test]
+fn create_and_invoke_reverts_on_abort() {
+    let child = r#"(module
+      (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+      (memory (export "memory") 1)
+      (func (export "invoke") (param $x i32) (result i32)
+        (call $exit (i32.const 17) (i32.const 0) (i32.const 0) (i32.const 0))))"#;
+    let (exit_code, created) = create_with_factory(child, 0, true);
+    assert_eq!(exit_code, ExitCode::new(17));
+    assert!(created.is_none());
+}
+
+#[test]
+fn create_and_invoke_unauthorized() {
+    // Only the init actor may choose the new actor's ID.
+    let (exit_code, created) = create_with_factory(CHILD_OK, 20000, true);
+    assert_eq!(exit_code, ExitCode::new(32 + ErrorNumber::Forbidden as u32));
+    assert!(created.is_none());
+
+    // The syscall isn't linked unless factories are enabled, so the factory can't be loaded.
+    let (exit_code, created) = create_with_factory(CHILD_OK, 0, false);
+    assert_eq!(exit_code, ExitCode::SYS_ASSERTION_FAILED);
+    assert!(created.is_none());
+}
+
+#[test]
+fn backtraces() {
+    // Note: this test **does not actually assert anything**, but it's useful to
+    // let us peep into FVM backtrace generation under different scenarios.
+    const WAT_ABORT: &str = r#"
+    (module
+      ;; ipld::open
+      (type (;0;) (func (param i32 i32) (result i32)))
+      (import "ipld" "open" (func $fvm_sdk::sys::ipld::open::syscall (type 0)))
+      ;; vm::abort -> vm::exit
+      (type (;1;) (func (param i32 i32 i32 i32) (result i32)))
+      (import "vm" "exit" (func $fvm_sdk::sys::vm::exit::syscall (type 1)))
+      (memory (export "memory") 1)
+      (func (export "invoke") (param $x i32) (result i32)
+        (i32.const 123)
+        (i32.const 123)
+        (call $fvm_sdk::sys::ipld::open::syscall)
+        (i32.const 0)
+        (i32.const 0)
+        (i32.const 0)
+        (call $fvm_sdk::sys::vm::exit::syscall)
+        unreachable
+      )
+    )
+    "#;
+
+    const WAT_FAIL: &str = r#"
+    (module
+      ;; ipld::open
+      (type (;0;) (func (param i32 i32) (result i32)))
+      (import "ipld" "open" (func $fvm_sdk::sys::ipld::open::syscall (type 0)))
+      ;; vm::abort -> vm::exit
+      (type (;1;) (func (param i32 i32 i32 i32) (result i32)))
+      (import "vm" "exit" (func $fvm_sdk::sys::vm::exit::syscall (type 1)))
+      (memory (export "memory") 1)
+      (func (export "invoke") (param $x i32) (result i32)
+        (i32.const 128)
+        (memory.grow)
+        (i32.const 4)
+        (i32.const 25493505)
+        (i32.store)
+        (i32.const 8)
+        (i32.const 0)
+        (i32.store)
+        (i32.const 4)
+        (call $fvm_sdk::sys::ipld::open::syscall)
+        (i32.const 0)
+        (i32.const 0)
+        (i32.const 0)
+        (call $fvm_sdk::sys::vm::exit::syscall)
+        unreachable
+      )
+    )
+    "#;
+
+    let blockstore = {
+        let b = FailingBlockstore::default();
+        b.add_fail(Cid::try_from("baeaikaia").unwrap());
+        Rc::new(b)
+    };
+
+    // Instantiate tester
+    let mut tester = new_tester(NV_FOR_TEST, StateTreeVersion::V5, blockstore.clone()).unwrap();
 
     let sender: [Account; 1] = tester.create_accounts().unwrap();
 
@@ -1241,6 +2315,17 @@ fn upgrade_actor_test() {
             res.failure_info
         );
 
+        // debug logs are returned, even from the upgrade that was rejected
+        if case.method_num == 2 {
+            assert_eq!(
+                res.debug_output.last(),
+                Some(&DebugOutput::Log {
+                    actor: 10000,
+                    message: "[upgrade] params:2, calling sdk::vm::exit()".into(),
+                })
+            );
+        }
+
         // if this test case should return some data, check that it did
         if let Some(return_data) = case.return_data {
             let val: i64 = res.msg_receipt.return_data.deserialize().unwrap();
@@ -1260,6 +2345,201 @@ fn upgrade_actor_test() {
     }
 }
 
+#[test]
+fn relaxed_sender_checks() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (_, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    // The sequence is wrong, and the sender can't cover the gas at this fee cap.
+    let message = Message {
+        from: sender,
+        to: receiver,
+        gas_limit: 1000000000,
+        gas_fee_cap: TokenAmount::from_whole(1_000_000_000),
+        sequence: 7,
+        ..Message::default()
+    };
+
+    let res = tester
+        .executor
+        .as_mut()
+        .unwrap()
+        .execute_message(message.clone(), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(
+        res.msg_receipt.exit_code,
+        ExitCode::SYS_SENDER_STATE_INVALID
+    );
+
+    let executor = tester.executor.take().unwrap();
+    let mut executor = executor.with_sender_checks(SenderChecks {
+        nonce: false,
+        balance: false,
+        ..SenderChecks::default()
+    });
+    let balance = executor
+        .state_tree()
+        .get_actor(sender_id)
+        .unwrap()
+        .unwrap()
+        .balance;
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::OK);
+
+    // The sender can't cover the gas, so it's free: nothing is burnt or refunded, and the sender's
+    // balance is left untouched.
+    assert!(res.base_fee_burn.is_zero());
+    assert!(res.refund.is_zero());
+    let sender_state = executor.state_tree().get_actor(sender_id).unwrap().unwrap();
+    assert_eq!(sender_state.balance, balance);
+}
+
+#[test]
+fn estimate_gas() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender), (_, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let message = Message {
+        from: sender,
+        to: receiver,
+        value: TokenAmount::from_atto(1),
+        ..Message::default()
+    };
+    let estimate = executor
+        .estimate_gas(message.clone(), 100, GasEstimationConfig::default())
+        .unwrap();
+    assert_eq!(estimate.ret.msg_receipt.exit_code, ExitCode::OK);
+    assert!(estimate.gas_limit > estimate.gas_used);
+
+    // Estimation doesn't change the state, and the estimate fits.
+    let res = executor
+        .execute_message(
+            Message {
+                gas_limit: estimate.gas_limit,
+                ..message
+            },
+            ApplyKind::Explicit,
+            100,
+        )
+        .unwrap();
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::OK);
+    assert_eq!(res.msg_receipt.gas_used, estimate.gas_used);
+}
+
+#[test]
+fn estimate_gas_with_fees() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (receiver_id, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    // The sender can cover the value, but not the gas at the estimation's gas limit.
+    let message = Message {
+        from: sender,
+        to: receiver,
+        value: TokenAmount::from_atto(100),
+        gas_fee_cap: TokenAmount::from_atto(1),
+        gas_premium: TokenAmount::from_atto(1),
+        ..Message::default()
+    };
+    let actors = |executor: &IntegrationExecutor<MemoryBlockstore, DummyExterns>| {
+        [sender_id, receiver_id].map(|id| executor.state_tree().get_actor(id).unwrap().unwrap())
+    };
+    let before = actors(executor);
+    assert!(
+        before[0].balance < TokenAmount::from_atto(GasEstimationConfig::default().max_gas_limit)
+    );
+
+    let estimate = executor
+        .estimate_gas(message, 100, GasEstimationConfig::default())
+        .unwrap();
+    assert_eq!(estimate.ret.msg_receipt.exit_code, ExitCode::OK);
+
+    // Neither the gas nor the value was deducted, and nothing was minted.
+    assert_eq!(actors(executor), before);
+}
+
+#[test]
+fn rejected_gas_sponsorship() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender)] = tester.create_accounts().unwrap();
+
+    // The exit data actor exits with a non-zero exit code from the authorization method, so it
+    // rejects every sponsorship.
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let payer = Address::new_id(10000);
+    let payer_balance = TokenAmount::from_whole(10);
+    tester
+        .set_actor_from_bin(
+            EXIT_DATA_ACTOR_BINARY,
+            state_cid,
+            payer,
+            payer_balance.clone(),
+        )
+        .unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let message = Message {
+        from: sender,
+        to: sender,
+        gas_limit: 1000000000,
+        gas_fee_cap: TokenAmount::from_atto(1),
+        ..Message::default()
+    };
+    let sponsor = GasSponsor {
+        payer,
+        approval: RawBytes::default(),
+    };
+    let res = executor
+        .execute_sponsored_message(message, sponsor, 100)
+        .unwrap();
+    assert_eq!(
+        res.msg_receipt.exit_code,
+        ExitCode::SYS_SENDER_STATE_INVALID
+    );
+    assert_eq!(res.gas_payer, None);
+
+    assert_eq!(res.msg_receipt.gas_used, 0);
+    assert_eq!(res.penalty, &executor.context().base_fee * 1000000000);
+
+    // The message failed validation, so neither the payer nor the sender were touched.
+    let payer_state = executor.state_tree().get_actor(10000).unwrap().unwrap();
+    assert_eq!(payer_state.balance, payer_balance);
+    let sender_state = executor.state_tree().get_actor(sender_id).unwrap().unwrap();
+    assert_eq!(sender_state.sequence, 0);
+}
+
 #[derive(Default)]
 pub struct FailingBlockstore {
     fail_for: RefCell<HashSet<Cid>>,
@@ -0,0 +1,110 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ApplyKind, Executor};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::IntegrationExecutor;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_ipld_encoding::RawBytes;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::message::Message;
+use fvm_shared::receipt::Receipt;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use fvm_test_actors::wasm_bin::ORACLE_ACTOR_BINARY;
+use num_traits::Zero;
+
+const REQUEST: u64 = 2;
+const FULFILL: u64 = 3;
+
+const EPOCH: ChainEpoch = 200;
+
+struct Oracle {
+    executor: IntegrationExecutor<MemoryBlockstore, DummyExterns>,
+    sender: Address,
+    actor: Address,
+    sequence: u64,
+}
+
+impl Oracle {
+    fn call(&mut self, method_num: u64, param: i64) -> Receipt {
+        let message = Message {
+            from: self.sender,
+            to: self.actor,
+            gas_limit: 1000000000,
+            method_num,
+            params: RawBytes::serialize(param).unwrap(),
+            sequence: self.sequence,
+            ..Message::default()
+        };
+        self.sequence += 1;
+        self.executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap()
+            .msg_receipt
+    }
+}
+
+#[test]
+fn oracle_test() {
+    // Requests (round, randomness) by ID: ready, too recent, and expired.
+    let requests: Vec<(ChainEpoch, Option<[u8; 32]>)> =
+        vec![(EPOCH - 10, None), (EPOCH - 1, None), (EPOCH - 150, None)];
+    let mut oracle = setup(&requests);
+
+    // Fulfilling a request returns its randomness, and fulfilling it again returns the same
+    // randomness (even though the dummy externs return different randomness on every call).
+    let res = oracle.call(FULFILL, 0);
+    assert_eq!(res.exit_code, ExitCode::OK);
+    assert_eq!(res.return_data.len(), 32);
+    let again = oracle.call(FULFILL, 0);
+    assert_eq!(again.exit_code, ExitCode::OK);
+    assert_eq!(again.return_data, res.return_data);
+
+    // Requests outside the window can't be fulfilled.
+    assert_eq!(oracle.call(FULFILL, 1).exit_code, ExitCode::USR_FORBIDDEN);
+    assert_eq!(oracle.call(FULFILL, 2).exit_code, ExitCode::USR_FORBIDDEN);
+    assert_eq!(oracle.call(FULFILL, 9).exit_code, ExitCode::USR_NOT_FOUND);
+
+    // New requests must be for a future round, and can't be fulfilled until it's final.
+    let res = oracle.call(REQUEST, 5);
+    assert_eq!(res.exit_code, ExitCode::OK);
+    assert_eq!(res.return_data.deserialize::<u64>().unwrap(), 3);
+    assert_eq!(oracle.call(FULFILL, 3).exit_code, ExitCode::USR_FORBIDDEN);
+    assert_eq!(
+        oracle.call(REQUEST, 0).exit_code,
+        ExitCode::USR_ILLEGAL_ARGUMENT
+    );
+}
+
+fn setup(requests: &[(ChainEpoch, Option<[u8; 32]>)]) -> Oracle {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_sender_id, sender)] = tester.create_accounts().unwrap();
+
+    let state_cid = tester.set_state(&requests).unwrap();
+    let actor = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(ORACLE_ACTOR_BINARY, state_cid, actor, TokenAmount::zero())
+        .unwrap();
+
+    tester
+        .instantiate_machine_with_config(DummyExterns, |_| (), |mc| mc.epoch = EPOCH)
+        .unwrap();
+
+    Oracle {
+        executor: tester.executor.unwrap(),
+        sender,
+        actor,
+        sequence: 0,
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::engine::MAX_SHARED_MODULES;
+use fvm::executor::{ApplyKind, Executor};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use num_traits::Zero;
+
+/// Registers the given shared modules (in order), then invokes an actor with the given code and
+/// returns its exit code.
+fn invoke_with_shared_modules(actor: &str, shared: &[(&str, &str)]) -> ExitCode {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender)] = tester.create_accounts().unwrap();
+
+    let shared: Vec<_> = shared
+        .iter()
+        .map(|(name, wat)| {
+            let code = tester
+                .put_shared_module(&wat::parse_str(wat).unwrap())
+                .unwrap();
+            (name.to_string(), code)
+        })
+        .collect();
+
+    let state_cid = tester.set_state(&[(); 0]).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            &wat::parse_str(actor).unwrap(),
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |nc| {
+                for (name, code) in shared {
+                    nc.add_shared_module(name, code).unwrap();
+                }
+            },
+            |_| {},
+        )
+        .unwrap();
+
+    let message = Message {
+        from: sender,
+        to: actor_address,
+        gas_limit: 10_000_000,
+        method_num: 1,
+        ..Message::default()
+    };
+    tester
+        .executor
+        .as_mut()
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap()
+        .msg_receipt
+        .exit_code
+}
+
+const MATH: &str = r#"(module
+  (memory (export "memory") 1)
+  (func (export "add") (param i32 i32) (result i32)
+    (i32.add (local.get 0) (local.get 1))))"#;
+
+#[test]
+fn import_shared_module() {
+    let actor = r#"(module
+      (import "math" "add" (func $add (param i32 i32) (result i32)))
+      (memory (export "memory") 1)
+      (func (export "invoke") (param i32) (result i32)
+        (if (i32.ne (call $add (i32.const 2) (i32.const 3)) (i32.const 5))
+          (then unreachable))
+        (i32.const 0)))"#;
+    assert_eq!(
+        invoke_with_shared_modules(actor, &[("math", MATH)]),
+        ExitCode::OK
+    );
+
+    // Actors can't import shared modules that aren't registered.
+    assert_eq!(
+        invoke_with_shared_modules(actor, &[]),
+        ExitCode::SYS_ASSERTION_FAILED
+    );
+}
+
+#[test]
+fn import_shared_modules_transitively() {
+    // The actor only imports "tens", which imports "math". Both must be instantiated, in
+    // registration order, skipping the unused module registered between them.
+    let tens = r#"(module
+      (import "math" "add" (func $add (param i32 i32) (result i32)))
+      (memory (export "memory") 1)
+      (func (export "add_ten") (param i32) (result i32)
+        (call $add (local.get 0) (i32.const 10))))"#;
+    let unused = r#"(module (memory (export "memory") 1))"#;
+    let actor = r#"(module
+      (import "tens" "add_ten" (func $add_ten (param i32) (result i32)))
+      (memory (export "memory") 1)
+      (func (export "invoke") (param i32) (result i32)
+        (if (i32.ne (call $add_ten (i32.const 5)) (i32.const 15))
+          (then unreachable))
+        (i32.const 0)))"#;
+    assert_eq!(
+        invoke_with_shared_modules(actor, &[("math", MATH), ("unused", unused), ("tens", tens)]),
+        ExitCode::OK
+    );
+
+    // Shared modules may only import shared modules registered before them.
+    assert_eq!(
+        invoke_with_shared_modules(actor, &[("tens", tens), ("math", MATH)]),
+        ExitCode::SYS_ASSERTION_FAILED
+    );
+}
+
+#[test]
+fn shared_modules_cannot_import_syscalls() {
+    // Syscalls would operate on the actor's memory, so shared modules can't import them.
+    let exit = r#"(module
+      (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+      (memory (export "memory") 1)
+      (func (export "exit") (param i32) (result i32)
+        (call $exit (local.get 0) (i32.const 0) (i32.const 0) (i32.const 0))))"#;
+    let actor = r#"(module
+      (import "exit" "exit" (func $exit (param i32) (result i32)))
+      (memory (export "memory") 1)
+      (func (export "invoke") (param i32) (result i32)
+        (call $exit (i32.const 42))))"#;
+    assert_eq!(
+        invoke_with_shared_modules(actor, &[("exit", exit)]),
+        ExitCode::SYS_ASSERTION_FAILED
+    );
+}
+
+#[test]
+fn import_all_shared_modules() {
+    // Each store holds an instance of the actor and of every shared module it imports, so the
+    // instance pool must be sized for the maximum number of shared modules.
+    let names: Vec<_> = (0..MAX_SHARED_MODULES).map(|i| format!("lib{i}")).collect();
+    let shared: Vec<_> = names.iter().map(|name| (name.as_str(), MATH)).collect();
+    let imports: String = names
+        .iter()
+        .map(|name| format!(r#"(import "{name}" "add" (func (param i32 i32) (result i32)))"#))
+        .collect();
+    let calls: String = (0..MAX_SHARED_MODULES)
+        .map(|i| format!("(drop (call {i} (i32.const 1) (i32.const 1)))"))
+        .collect();
+    let actor = format!(
+        r#"(module
+          {imports}
+          (memory (export "memory") 1)
+          (func (export "invoke") (param i32) (result i32)
+            {calls}
+            (i32.const 0)))"#
+    );
+    assert_eq!(invoke_with_shared_modules(&actor, &shared), ExitCode::OK);
+}
@@ -0,0 +1,155 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+#![cfg(feature = "tracing")]
+mod bundles;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use bundles::*;
+use fvm::executor::{ApplyKind, Executor};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::Account;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use num_traits::Zero;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// A span, with its recorded fields.
+#[derive(Debug)]
+struct Span {
+    name: &'static str,
+    fields: BTreeMap<&'static str, String>,
+}
+
+impl Span {
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+struct Fields<'a>(&'a mut BTreeMap<&'static str, String>);
+
+impl Visit for Fields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+/// Records every span created, in order (span IDs are indices into the list, plus one).
+#[derive(Clone, Default)]
+struct RecordingSubscriber(Arc<Mutex<Vec<Span>>>);
+
+impl Subscriber for RecordingSubscriber {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let mut spans = self.0.lock().unwrap();
+        let mut span = Span {
+            name: attrs.metadata().name(),
+            fields: BTreeMap::new(),
+        };
+        attrs.record(&mut Fields(&mut span.fields));
+        spans.push(span);
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, id: &Id, values: &Record<'_>) {
+        let mut spans = self.0.lock().unwrap();
+        let span = &mut spans[id.into_u64() as usize - 1];
+        values.record(&mut Fields(&mut span.fields));
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn message_spans() {
+    // Exits with 16.
+    let wat = r#"(module
+                   (import "vm" "exit" (func $exit (param i32 i32 i32 i32) (result i32)))
+                   (memory (export "memory") 1)
+                   (func (export "invoke") (param $x i32) (result i32)
+                     (call $exit (i32.const 16) (i32.const 0) (i32.const 0) (i32.const 0))))"#;
+
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+    let [(sender_id, sender)]: [Account; 1] = tester.create_accounts().unwrap();
+    let state_cid = tester.set_state(&[(); 0]).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            &wat::parse_str(wat).unwrap(),
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let message = Message {
+        from: sender,
+        to: actor_address,
+        gas_limit: 10_000_000,
+        method_num: 1,
+        ..Message::default()
+    };
+    let subscriber = RecordingSubscriber::default();
+    let res = tracing::subscriber::with_default(subscriber.clone(), || {
+        tester
+            .executor
+            .as_mut()
+            .unwrap()
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap()
+    });
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::new(16));
+
+    let spans = subscriber.0.lock().unwrap();
+    let span = |name: &str| {
+        spans
+            .iter()
+            .find(|s| s.name == name)
+            .unwrap_or_else(|| panic!("no {name} span in {spans:?}"))
+    };
+
+    let message = span("execute_message");
+    assert_eq!(message.field("from"), Some(sender.to_string().as_str()));
+    assert_eq!(message.field("to"), Some("f010000"));
+    assert_eq!(message.field("method"), Some("1"));
+    assert_eq!(message.field("exit_code"), Some("16"));
+    let gas_used = res.msg_receipt.gas_used.to_string();
+    assert_eq!(message.field("gas_used"), Some(gas_used.as_str()));
+
+    let call = span("call");
+    assert_eq!(call.field("from"), Some(sender_id.to_string().as_str()));
+    assert_eq!(call.field("to"), Some("10000"));
+    assert!(call.field("gas_used").is_some());
+
+    let syscall = span("syscall");
+    assert_eq!(syscall.field("module"), Some("vm"));
+    assert_eq!(syscall.field("name"), Some("exit"));
+}
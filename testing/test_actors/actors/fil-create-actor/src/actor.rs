@@ -51,6 +51,21 @@ pub fn invoke(_: u32) -> u32 {
                 sdk::actor::create_actor(1002, &placeholder_cid, None)
             );
 
+            // assigning an f4 address should fail unless we're called by the namespace's
+            // manager, and the namespace has one
+            //
+            let eth_addr = Address::new_delegated(10, &[1u8; 20]).unwrap();
+            assert_eq!(
+                Err(ErrorNumber::Forbidden),
+                sdk::actor::create_actor(1002, &acct_cid, Some(eth_addr))
+            );
+            let unmanaged_addr = Address::new_delegated(1919, &[1u8; 20]).unwrap();
+            assert_eq!(
+                Err(ErrorNumber::Forbidden),
+                sdk::actor::create_actor(1002, &acct_cid, Some(unmanaged_addr))
+            );
+            assert_eq!(None, sdk::actor::resolve_address(&unmanaged_addr));
+
             // verify that resolving address returns None if address cannot be resolved
             //
             let not_found_addresss = Address::new_actor(&[0u8; SECP_PUB_LEN]);
@@ -0,0 +1,106 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! An example randomness oracle: callers request randomness from a future beacon round, and
+//! anyone may fulfill the request once that round is final (but before it expires).
+use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::{from_slice, to_vec, CBOR, DAG_CBOR, IPLD_RAW};
+use fvm_sdk as sdk;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::error::ExitCode;
+use fvm_shared::randomness::RANDOMNESS_LENGTH;
+use sdk::error::RandomnessError;
+use sdk::rand::{RandomnessRequest, RandomnessWindow};
+use serde_tuple::{Deserialize_tuple, Serialize_tuple};
+
+const REQUEST: u64 = 2;
+const FULFILL: u64 = 3;
+
+/// Requests may be fulfilled from 2 epochs after the requested round, for 100 epochs.
+const WINDOW: RandomnessWindow = RandomnessWindow {
+    min_lookback: 2,
+    max_lookback: 100,
+};
+
+/// A randomness request, identified by its index in the actor's state.
+#[derive(Serialize_tuple, Deserialize_tuple)]
+struct Request {
+    round: ChainEpoch,
+    randomness: Option<[u8; RANDOMNESS_LENGTH]>,
+}
+
+#[no_mangle]
+pub fn invoke(params: u32) -> u32 {
+    sdk::initialize();
+
+    let params = sdk::message::params_raw(params).unwrap().unwrap();
+    let mut requests: Vec<Request> = load_state();
+
+    let ret = match sdk::message::method_number() {
+        // Takes the number of epochs to wait for, and returns the request ID.
+        REQUEST => {
+            let delay: ChainEpoch = from_slice(&params.data).unwrap();
+            if delay <= 0 {
+                sdk::vm::abort(
+                    ExitCode::USR_ILLEGAL_ARGUMENT.value(),
+                    Some("randomness must be requested from a future round"),
+                );
+            }
+            let request = RandomnessRequest::new(delay, WINDOW);
+            requests.push(Request {
+                round: request.round,
+                randomness: None,
+            });
+            IpldBlock {
+                codec: CBOR,
+                data: to_vec(&((requests.len() - 1) as u64)).unwrap(),
+            }
+        }
+        // Takes a request ID, and returns the request's randomness. Fulfilling a request twice
+        // returns the same randomness.
+        FULFILL => {
+            let id: u64 = from_slice(&params.data).unwrap();
+            let Some(request) = requests.get_mut(id as usize) else {
+                sdk::vm::abort(ExitCode::USR_NOT_FOUND.value(), Some("no such request"))
+            };
+            let randomness = match request.randomness {
+                Some(randomness) => randomness,
+                None => {
+                    let randomness = RandomnessRequest {
+                        round: request.round,
+                        window: WINDOW,
+                    }
+                    .fulfill(&id.to_be_bytes())
+                    .unwrap_or_else(|e| {
+                        let code = match e {
+                            RandomnessError::TooEarly { .. } | RandomnessError::Expired { .. } => {
+                                ExitCode::USR_FORBIDDEN
+                            }
+                            RandomnessError::Unavailable(_) => ExitCode::USR_ILLEGAL_STATE,
+                        };
+                        sdk::vm::abort(code.value(), Some(&e.to_string()))
+                    });
+                    request.randomness = Some(randomness);
+                    randomness
+                }
+            };
+            IpldBlock {
+                codec: IPLD_RAW,
+                data: randomness.to_vec(),
+            }
+        }
+        _ => sdk::vm::abort(ExitCode::USR_UNHANDLED_MESSAGE.value(), None),
+    };
+
+    save_state(&requests);
+    sdk::vm::exit(0, Some(ret), None)
+}
+
+fn load_state() -> Vec<Request> {
+    let root = sdk::sself::root().unwrap();
+    from_slice(&sdk::ipld::get(&root).unwrap()).unwrap()
+}
+
+fn save_state(requests: &[Request]) {
+    let root = sdk::ipld::put(0xb220, 32, DAG_CBOR, &to_vec(requests).unwrap()).unwrap();
+    sdk::sself::set_root(&root).unwrap();
+}
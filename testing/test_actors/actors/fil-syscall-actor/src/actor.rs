@@ -39,6 +39,10 @@ pub fn invoke(_: u32) -> u32 {
     test_network_context();
     test_message_context();
     test_balance();
+    test_send_metered();
+    test_reentrancy();
+    test_gas_price();
+    test_abi_version();
     test_unaligned();
 
     #[cfg(coverage)]
@@ -331,6 +335,8 @@ fn test_network_context() {
     assert_eq!(sdk::network::version(), NetworkVersion::V21);
     assert_eq!(sdk::network::tipset_timestamp(), 0);
     assert_eq!(sdk::network::base_fee(), TokenAmount::from_atto(100));
+    assert_eq!(sdk::network::epoch_duration_seconds(), 30);
+    assert_eq!(sdk::network::blocks_per_epoch(), 5);
 }
 
 fn test_message_context() {
@@ -341,6 +347,8 @@ fn test_message_context() {
     assert_eq!(sdk::message::method_number(), 1);
     assert!(sdk::message::value_received().is_zero());
     assert!(sdk::message::gas_premium().is_zero());
+    assert_eq!(sdk::vm::call_depth(), 1);
+    assert_eq!(sdk::vm::call_stack(), [10000]);
 }
 
 fn test_balance() {
@@ -354,6 +362,92 @@ fn test_balance() {
     );
 }
 
+fn test_send_metered() {
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::sys::SendFlags;
+
+    let before = sdk::gas::available();
+    let res = sdk::send::invoke(
+        &Address::new_id(sdk::message::caller()),
+        0,
+        None,
+        TokenAmount::default(),
+        None,
+        SendFlags::empty(),
+    )
+    .unwrap();
+    let observed = before - sdk::gas::available();
+    assert!(res.is_success());
+    // The reported gas excludes the syscall overhead charged to the caller.
+    assert!(res.gas_used <= observed);
+}
+
+fn test_abi_version() {
+    use fvm_shared::sys::SYSCALL_ABI_VERSION;
+
+    assert_eq!(sdk::vm::abi_version(), SYSCALL_ABI_VERSION);
+    // Whatever the FVM supports, the SDK must understand every capability it reports.
+    let caps = unsafe { sdk::sys::vm::capabilities().unwrap() };
+    assert_eq!(sdk::vm::capabilities().bits(), caps);
+}
+
+fn test_reentrancy() {
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::sys::{ReentrancyPolicy, SendFlags};
+
+    let me = Address::new_id(sdk::message::receiver());
+    let call_self = |method| {
+        sdk::send::send(
+            &me,
+            method,
+            None,
+            TokenAmount::default(),
+            None,
+            SendFlags::empty(),
+        )
+    };
+
+    sdk::vm::set_reentrancy(ReentrancyPolicy::Forbid);
+    // Calling back into ourselves is refused before any code runs.
+    assert_eq!(call_self(2).err(), Some(ErrorNumber::Forbidden));
+    // But plain sends don't run any code, so they're fine.
+    assert!(call_self(0).unwrap().exit_code.is_success());
+    sdk::vm::set_reentrancy(ReentrancyPolicy::Allow);
+
+    // Unknown policies are rejected.
+    assert_eq!(
+        unsafe { sdk::sys::vm::set_reentrancy(2) },
+        Err(ErrorNumber::IllegalArgument)
+    );
+}
+
+fn test_gas_price() {
+    use fvm_shared::sys::PricedOperation;
+
+    // Bigger blocks cost more.
+    let small = sdk::gas::price(PricedOperation::BlockCreate, 0, 0).unwrap();
+    let large = sdk::gas::price(PricedOperation::BlockCreate, 1 << 20, 0).unwrap();
+    assert!(small < large);
+
+    // Hashing is priced per hash function.
+    assert!(sdk::gas::price(
+        PricedOperation::Hash,
+        100,
+        SharedSupportedHashes::Sha2_256 as u64
+    )
+    .is_ok());
+    assert_eq!(
+        sdk::gas::price(PricedOperation::Hash, 100, 0xdead),
+        Err(ErrorNumber::IllegalArgument)
+    );
+
+    // Unknown operations are rejected.
+    assert_eq!(
+        unsafe { sdk::sys::gas::price(1000, 0, 0) },
+        Err(ErrorNumber::IllegalArgument)
+    );
+}
+
 /// Test to make sure we can return into unaligned pointers. Technically, we use repr-packed
 /// everywhere so this should always work, but we should test anyways.
 fn test_unaligned() {
@@ -33,6 +33,7 @@ const ACTORS: &[(&str, &str)] = &[
     ("SSELF_ACTOR_BINARY", "fil_sself_actor"),
     ("UPGRADE_ACTOR_BINARY", "fil_upgrade_actor"),
     ("UPGRADE_RECEIVE_ACTOR_BINARY", "fil_upgrade_receive_actor"),
+    ("ORACLE_ACTOR_BINARY", "fil_oracle_actor"),
 ];
 
 const WASM_TARGET: &str = "wasm32-unknown-unknown";